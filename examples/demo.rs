@@ -1,4 +1,4 @@
-use bevy::prelude::*;
+use bevy::{mesh::Indices, platform::collections::HashMap, prelude::*};
 use bevy_egui::*;
 use bevy_meshopt::*;
 
@@ -6,14 +6,51 @@ pub fn main() -> AppExit {
     App::new()
         .insert_resource(HelmetEntity(None))
         .insert_resource(Reset(true))
-        .insert_resource(Simplify(false))
-        .insert_resource(SimplifySettings(default()))
+        .insert_resource(Export(false))
+        .insert_resource(Projection::default())
+        .insert_resource(Recommendation::default())
+        .insert_resource(Sweep::default())
+        .insert_resource(LodGrid::default())
         .add_plugins(DefaultPlugins)
+        .add_plugins(MeshoptPlugin::default())
+        .add_plugins(MeshLodPlugin)
+        .add_plugins(GltfMeshoptPlugin {
+            // Keep every triangle for the simplification settings to experiment with, only
+            // optimize the helmet and generate the levels the LOD grid switches between.
+            settings: MeshProcessSettings {
+                simplify: None,
+                lods: vec![0.5.into(), 0.25.into(), 0.125.into()],
+                ..default()
+            },
+        })
+        // Also loads glTFs compressed with gltfpack.
+        .add_plugins(GltfMeshoptCompressionPlugin)
         .add_plugins(EguiPlugin::default())
         .add_plugins(bevy_inspector_egui::quick::WorldInspectorPlugin::default())
         .add_systems(Startup, setup)
         .add_systems(Startup, load_gltf)
-        .add_systems(Update, (reset_gltf_object, simplify_meshes).chain())
+        .add_systems(
+            Update,
+            (
+                (
+                    reset_gltf_object,
+                    keep_picking_meshes,
+                    spawn_lod_grid,
+                    add_mesh_lods,
+                )
+                    .in_set(MeshoptSet::Queue),
+                (
+                    pick_on_click,
+                    project_simplification,
+                    log_stats,
+                    log_mesh_stats,
+                    recommend_simplification,
+                    sweep_simplification,
+                    export_meshes,
+                )
+                    .after(MeshoptSet::Apply),
+            ),
+        )
         .add_systems(EguiPrimaryContextPass, simplify_settings_ui)
         .run()
 }
@@ -33,6 +70,9 @@ fn load_gltf(mut commands: Commands, asset_server: Res<AssetServer>) {
 #[derive(Resource)]
 pub struct Reset(bool);
 
+#[derive(Resource)]
+pub struct Export(bool);
+
 fn reset_gltf_object(
     mut reset: ResMut<Reset>,
     mut commands: Commands,
@@ -63,6 +103,102 @@ fn reset_gltf_object(
     reset.0 = false;
 }
 
+/// Saves the meshes of the helmet, simplified and optimized as they are, as `.glb` files to load
+/// back with the `AssetServer`.
+fn export_meshes(
+    mut export: ResMut<Export>,
+    helmet_entity: Res<HelmetEntity>,
+    children: Query<&Children>,
+    query: Query<(&Mesh3d, Option<&Name>)>,
+    meshes: Res<Assets<Mesh>>,
+) {
+    if !std::mem::take(&mut export.0) {
+        return;
+    }
+    let Some(helmet) = helmet_entity.0 else {
+        return;
+    };
+    for entity in children.iter_descendants(helmet) {
+        let Ok((mesh3d, name)) = query.get(entity) else {
+            continue;
+        };
+        let Some(mesh) = meshes.get(mesh3d) else {
+            continue;
+        };
+        let name = name.map_or_else(|| entity.to_string(), Name::to_string);
+        let path = format!("assets/exported/{name}.glb");
+        match save_mesh(&path, mesh) {
+            Ok(()) => info!("Saved {path}"),
+            Err(err) => warn!("Can't save {path}: {err}"),
+        }
+    }
+}
+
+/// Grid of helmets switching between levels of detail with their distance to the camera.
+#[derive(Resource, Default)]
+pub struct LodGrid {
+    enabled: bool,
+    roots: Vec<Entity>,
+}
+
+#[derive(Component)]
+struct LodGridHelmet;
+
+fn spawn_lod_grid(
+    mut commands: Commands,
+    mut grid: ResMut<LodGrid>,
+    helmet_scene: Res<HelmetScene>,
+    gltf_assets: Res<Assets<Gltf>>,
+) {
+    let spawned = !grid.roots.is_empty();
+    if grid.enabled == spawned {
+        return;
+    }
+
+    if !grid.enabled {
+        for root in grid.roots.drain(..) {
+            commands.entity(root).despawn();
+        }
+        return;
+    }
+    let Some(gltf) = gltf_assets.get(&helmet_scene.0) else {
+        return;
+    };
+    for x in -2..=2 {
+        for z in 1..=5 {
+            let root = commands
+                .spawn((
+                    SceneRoot(gltf.scenes[0].clone()),
+                    Transform::from_xyz(x as f32, 0.0, -(z * z) as f32),
+                    LodGridHelmet,
+                ))
+                .id();
+            grid.roots.push(root);
+        }
+    }
+}
+
+/// Switches the meshes of the grid helmets between the levels `GltfMeshoptPlugin` generated.
+fn add_mesh_lods(
+    mut commands: Commands,
+    query: Query<(Entity, &Mesh3d), Without<MeshLods>>,
+    parents: Query<&ChildOf>,
+    grid_helmets: Query<(), With<LodGridHelmet>>,
+    gltf_lods: Res<GltfLods>,
+) {
+    for (entity, mesh3d) in &query {
+        if !parents
+            .iter_ancestors(entity)
+            .any(|ancestor| grid_helmets.contains(ancestor))
+        {
+            continue;
+        }
+        if let Some(lods) = gltf_lods.mesh_lods(mesh3d) {
+            commands.entity(entity).insert(lods.clone());
+        }
+    }
+}
+
 pub fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
     commands.spawn((
         Camera3d::default(),
@@ -76,68 +212,361 @@ pub fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
     ));
 }
 
-#[derive(Resource)]
-pub struct Simplify(bool);
+/// Logs the analysis of every mesh entities switch to, i.e. the source once it loads and the
+/// processed mesh after every batch.
+fn log_mesh_stats(
+    query: Query<(Entity, &Mesh3d)>,
+    meshes: Res<Assets<Mesh>>,
+    mut logged: Local<HashMap<Entity, AssetId<Mesh>>>,
+) {
+    for (entity, mesh3d) in &query {
+        if logged.get(&entity) == Some(&mesh3d.id()) {
+            continue;
+        }
+        let Some(mesh) = meshes.get(mesh3d) else {
+            continue;
+        };
 
-fn simplify_meshes(
-    mut simplify: ResMut<Simplify>,
-    params: Res<SimplifySettings>,
-    mut query: Query<&mut Mesh3d>,
-    mut meshes: ResMut<Assets<Mesh>>,
+        match mesh.analyze(None) {
+            Ok(stats) => info!("{entity}: {stats}"),
+            Err(err) => warn!("{entity}: can't analyze mesh: {err}"),
+        }
+        logged.insert(entity, mesh3d.id());
+    }
+}
+
+/// Keeps the full resolution meshes around for [`pick_on_click`].
+fn keep_picking_meshes(
+    mut commands: Commands,
+    query: Query<Entity, (With<Mesh3d>, Without<KeepPickingMesh>)>,
 ) {
-    if !simplify.0 {
+    for entity in &query {
+        commands.entity(entity).insert(KeepPickingMesh);
+    }
+}
+
+/// Raycasts against the full resolution mesh of every entity rather than the rendered one.
+fn pick_on_click(
+    mouse: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    query: Query<(Entity, &Mesh3d, Option<&PickingMesh>, &GlobalTransform)>,
+    meshes: Res<Assets<Mesh>>,
+) {
+    use bevy::picking::mesh_picking::ray_cast::{Backfaces, ray_mesh_intersection};
+
+    if !mouse.just_pressed(MouseButton::Left) {
         return;
     }
-    info!("simplify params: {:?}", params.0);
-
-    let mut positions_before = 0;
-    let mut positions_after = 0;
-    let mut indices_before = 0;
-    let mut indices_after = 0;
-
-    for mut mesh3d in &mut query {
-        if let Some(original_mesh) = meshes.get(mesh3d.id()) {
-            let mut mesh = original_mesh.clone();
-            positions_before += mesh
-                .attribute(Mesh::ATTRIBUTE_POSITION)
-                .map_or(0, |a| a.len());
-            indices_before += mesh.indices().map_or(0, |indices| indices.len());
-
-            mesh.assert_indices_u32();
-            if let Err(err) = mesh.simplify(&params.0) {
-                error!("Mesh simplification failed: {}", err);
-            };
+    let Some(cursor) = windows.iter().find_map(Window::cursor_position) else {
+        return;
+    };
+    let Some(ray) = cameras
+        .iter()
+        .find(|(camera, _)| camera.is_active)
+        .and_then(|(camera, transform)| camera.viewport_to_world(transform, cursor).ok())
+    else {
+        return;
+    };
 
-            positions_after += mesh
-                .attribute(Mesh::ATTRIBUTE_POSITION)
-                .map_or(0, |a| a.len());
-            indices_after += mesh.indices().map_or(0, |indices| indices.len());
+    let closest = query
+        .iter()
+        .filter_map(|(entity, mesh3d, picking, transform)| {
+            let mesh = meshes.get(PickingMesh::resolve(mesh3d, picking))?;
+            let positions = mesh.attribute(Mesh::ATTRIBUTE_POSITION)?.as_float3()?;
+            let Some(Indices::U32(indices)) = mesh.indices() else {
+                return None;
+            };
+            let hit = ray_mesh_intersection(
+                ray,
+                &transform.affine(),
+                positions,
+                None,
+                Some(indices),
+                None,
+                Backfaces::Cull,
+            )?;
+            Some((entity, picking.is_some(), hit.distance))
+        })
+        .min_by(|a, b| a.2.total_cmp(&b.2));
+    if let Some((entity, full_resolution, distance)) = closest {
+        info!("Picked {entity} at distance {distance:.3}, full resolution: {full_resolution}");
+    }
+}
 
-            *mesh3d = Mesh3d(meshes.add(mesh));
-        }
+fn log_stats(stats: Res<SimplifyStats>) {
+    if !stats.is_changed() || stats.is_added() {
+        return;
     }
 
+    match stats.last_error {
+        Some(OptError::MismatchedAttributeLength {
+            attribute,
+            expected,
+            actual,
+        }) => error!(
+            "{} meshes failed to process, {attribute} has {actual} values for {expected} vertices",
+            stats.failed
+        ),
+        Some(err @ OptError::UnsupportedPrimitiveTopology(_)) => warn!(
+            "{} meshes were left as they are, lines and strips aren't simplified: {err}",
+            stats.failed
+        ),
+        Some(err) => error!("{} meshes failed to process: {}", stats.failed, err),
+        None => {}
+    }
+    if let Some(err) = stats.optimize_last_error {
+        error!(
+            "{} meshes failed to optimize: {}",
+            stats.optimize_failed, err
+        );
+    }
+    info!(
+        "Simplified {} meshes, indices: {} -> {}",
+        stats.simplified_meshes, stats.simplify.indices_before, stats.simplify.indices_after
+    );
     info!(
-        "Positions before: {}, after: {}",
-        positions_before, positions_after
+        "Keeping {} picking meshes, {} bytes",
+        stats.picking_meshes, stats.picking_mesh_memory
     );
     info!(
-        "Indices before: {}, after: {}",
-        indices_before, indices_after
+        "Optimized {} meshes ({} skipped), ACMR: {:.3} -> {:.3}, overdraw: {:.3} -> {:.3}, overfetch: {:.3} -> {:.3}",
+        stats.optimized_meshes,
+        stats.optimize_skipped,
+        stats.optimize.acmr_before(),
+        stats.optimize.acmr_after(),
+        stats.optimize.overdraw_before(),
+        stats.optimize.overdraw_after(),
+        stats.optimize.overfetch_before(),
+        stats.optimize.overfetch_after(),
     );
+}
+
+/// Projected outcome of simplifying with the current settings.
+#[derive(Resource, Default)]
+pub struct Projection {
+    params: Option<SimplifyParams>,
+    meshes: Vec<AssetId<Mesh>>,
+    triangles_before: usize,
+    triangles_after: usize,
+    max_error: f32,
+}
 
-    simplify.0 = false;
+fn project_simplification(
+    mut projection: ResMut<Projection>,
+    params: Res<SimplifySettings>,
+    query: Query<&Mesh3d>,
+    meshes: Res<Assets<Mesh>>,
+) {
+    let mesh_ids: Vec<AssetId<Mesh>> = query
+        .iter()
+        .map(|mesh3d| mesh3d.id())
+        .filter(|id| meshes.contains(*id))
+        .collect();
+    if projection.params.as_ref() == Some(&params.0) && projection.meshes == mesh_ids {
+        return;
+    }
+
+    let mut triangles_before = 0;
+    let mut triangles_after = 0;
+    let mut max_error: f32 = 0.0;
+    for id in &mesh_ids {
+        let Some(mesh) = meshes.get(*id) else {
+            continue;
+        };
+
+        let report = mesh.simplify_dry_run(&params.0);
+
+        if let Ok(report) = report {
+            triangles_before += report.triangles_before();
+            triangles_after += report.triangles_after();
+            max_error = max_error.max(report.result_error);
+        }
+    }
+
+    *projection = Projection {
+        params: Some(params.0.clone()),
+        meshes: mesh_ids,
+        triangles_before,
+        triangles_after,
+        max_error,
+    };
+}
+
+/// Recommended simplification of the visible meshes for the current camera.
+#[derive(Resource, Default)]
+pub struct Recommendation {
+    view: ViewParams,
+    triangles_before: usize,
+    triangles_after: usize,
+    max_error: f32,
+}
+
+fn recommend_simplification(
+    mut recommendation: ResMut<Recommendation>,
+    cameras: Query<(&Camera, &GlobalTransform, &bevy::camera::Projection)>,
+    query: Query<(&Mesh3d, &GlobalTransform)>,
+    meshes: Res<Assets<Mesh>>,
+) {
+    let Some((camera, camera_transform, bevy::camera::Projection::Perspective(perspective))) =
+        cameras.iter().find(|(camera, ..)| camera.is_active)
+    else {
+        return;
+    };
+    let viewport_height = camera
+        .physical_viewport_size()
+        .map_or(recommendation.view.viewport_height, |size| size.y as f32);
+
+    let mut triangles_before = 0;
+    let mut triangles_after = 0;
+    let mut max_error = f32::INFINITY;
+    let mut distance = f32::INFINITY;
+    for (mesh3d, transform) in &query {
+        let Some(mesh) = meshes.get(mesh3d) else {
+            continue;
+        };
+
+        // Distances are in mesh units, undo the mesh's scale.
+        let world_distance = camera_transform
+            .translation()
+            .distance(transform.translation());
+        let scale = transform.scale().abs().max_element().max(f32::EPSILON);
+        distance = distance.min(world_distance);
+        let view = ViewParams {
+            distance: world_distance / scale,
+            vertical_fov: perspective.fov,
+            viewport_height,
+            ..recommendation.view
+        };
+
+        if let (Some(indices), Ok(target)) = (mesh.indices(), recommend_target(mesh, &view)) {
+            triangles_before += indices.len() / 3;
+            triangles_after += target.index_count / 3;
+            max_error = max_error.min(target.max_error);
+        }
+    }
+
+    recommendation.view.distance = distance;
+    recommendation.view.viewport_height = viewport_height;
+    recommendation.triangles_before = triangles_before;
+    recommendation.triangles_after = triangles_after;
+    recommendation.max_error = if max_error.is_finite() {
+        max_error
+    } else {
+        0.0
+    };
 }
 
-#[derive(Resource, Deref, DerefMut)]
-pub struct SimplifySettings(SimplifyParams<'static>);
+/// Error/triangle count curve of the current meshes, computed when `run` is set.
+#[derive(Resource, Default)]
+pub struct Sweep {
+    run: bool,
+    points: Vec<SweepPoint>,
+    knee: Option<usize>,
+}
+
+fn sweep_simplification(
+    mut sweep: ResMut<Sweep>,
+    settings: Res<SimplifySettings>,
+    query: Query<&Mesh3d>,
+    meshes: Res<Assets<Mesh>>,
+) {
+    if !sweep.run {
+        return;
+    }
+    sweep.run = false;
+
+    // Log-spaced from 0.01% to 100% error.
+    let errors: Vec<f32> = (0..=24)
+        .map(|step| 10f32.powf(-4.0 + step as f32 / 6.0))
+        .collect();
+    let mut points: Vec<SweepPoint> = errors
+        .iter()
+        .map(|&max_error| SweepPoint {
+            max_error,
+            achieved_error: 0.0,
+            triangles: 0,
+            vertices: 0,
+        })
+        .collect();
+
+    let mut seen = Vec::new();
+    for mesh3d in &query {
+        if seen.contains(&mesh3d.id()) {
+            continue;
+        }
+        seen.push(mesh3d.id());
+        let Some(mesh) = meshes.get(mesh3d) else {
+            continue;
+        };
+        let Ok(curve) = simplify_sweep(mesh, &settings, &errors, true) else {
+            continue;
+        };
+        for (total, point) in points.iter_mut().zip(curve) {
+            total.achieved_error = total.achieved_error.max(point.achieved_error);
+            total.triangles += point.triangles;
+            total.vertices += point.vertices;
+        }
+    }
+
+    sweep.knee = knee_point(&points);
+    sweep.points = points;
+}
+
+/// Plots the triangle count of the sweep against the logarithm of the error.
+fn sweep_plot(ui: &mut egui::Ui, sweep: &Sweep) {
+    let (response, painter) = ui.allocate_painter(
+        egui::vec2(ui.available_width(), 120.0),
+        egui::Sense::hover(),
+    );
+    let rect = response.rect;
+    painter.rect_stroke(
+        rect,
+        0.0,
+        ui.visuals().widgets.noninteractive.bg_stroke,
+        egui::StrokeKind::Inside,
+    );
+
+    let (Some(first), Some(last)) = (sweep.points.first(), sweep.points.last()) else {
+        return;
+    };
+    let max_triangles = first.triangles.max(1) as f32;
+    let (min_log, max_log) = (first.max_error.log10(), last.max_error.log10());
+    let position = |point: &SweepPoint| {
+        let x = (point.max_error.log10() - min_log) / (max_log - min_log).max(f32::EPSILON);
+        let y = point.triangles as f32 / max_triangles;
+        rect.lerp_inside(egui::vec2(x, 1.0 - y))
+    };
+
+    let line: Vec<egui::Pos2> = sweep.points.iter().map(position).collect();
+    painter.add(egui::Shape::line(
+        line,
+        egui::Stroke::new(1.5, ui.visuals().text_color()),
+    ));
+    if let Some(knee) = sweep.knee {
+        painter.circle_filled(
+            position(&sweep.points[knee]),
+            4.0,
+            ui.visuals().selection.bg_fill,
+        );
+    }
+}
 
 // UI system
+#[allow(clippy::too_many_arguments)]
 pub fn simplify_settings_ui(
     mut contexts: EguiContexts,
     mut settings: ResMut<SimplifySettings>,
     mut reset: ResMut<Reset>,
+    mut export: ResMut<Export>,
     mut simplify: ResMut<Simplify>,
+    mut optimize_settings: ResMut<OptimizeSettings>,
+    mut optimize: ResMut<Optimize>,
+    projection: Res<Projection>,
+    mut recommendation: ResMut<Recommendation>,
+    mut sweep: ResMut<Sweep>,
+    mut lod_grid: ResMut<LodGrid>,
+    stats: Res<SimplifyStats>,
 ) {
     let Ok(ctx) = contexts.ctx_mut() else {
         return;
@@ -146,98 +575,10 @@ pub fn simplify_settings_ui(
     egui::Window::new("Simplify")
         .default_width(300.0)
         .show(ctx, |ui| {
-            // Max Error
-            egui::Grid::new("Property grid")
-                .num_columns(2)
-                .show(ui, |ui| {
-                    ui.label("Max Error:");
-                    ui.add(
-                        egui::Slider::new(&mut settings.max_error, 0.0..=1.0)
-                            .logarithmic(true)
-                            .text("error"),
-                    );
-
-                    ui.end_row();
-
-                    ui.label("Target Count:");
-                    let mut target_type = match settings.target_index_count {
-                        TargetIndices::Count(_) => "Count",
-                        TargetIndices::Multiplier(_) => "Multiplier",
-                    };
-
-                    egui::ComboBox::from_label("")
-                        .selected_text(target_type)
-                        .show_ui(ui, |ui| {
-                            if ui.selectable_value(&mut target_type, "Count", "Count").clicked() {
-                                if !matches!(settings.target_index_count, TargetIndices::Count(_)) {
-                                    settings.target_index_count = TargetIndices::Count(1000);
-                                }
-                            }
-
-                            if ui.selectable_value(&mut target_type, "Multiplier", "Multiplier").clicked() {
-                                if !matches!(settings.target_index_count, TargetIndices::Multiplier(_)) {
-                                    settings.target_index_count = TargetIndices::Multiplier(0.5);
-                                }
-                            }
-                        });
-
-                    ui.end_row();
-                    ui.label("");
-                    match &mut settings.target_index_count {
-                        TargetIndices::Count(count) => {
-                            ui.add(
-                                egui::Slider::new(count, 1..=100_000)
-                                    .logarithmic(true)
-                                    .text("triangles"),
-                            );
-                        }
-                        TargetIndices::Multiplier(multiplier) => {
-                            ui.add(egui::Slider::new(multiplier, 0.0..=1.0).text("%"));
-                        }
-                    }
-                });
-
-            ui.add_space(10.0);
-
-            // Simplify Options (bitset)
-            ui.label("Options:");
-            if ui.checkbox(
-                &mut settings.options.contains(SimplifyOptions::LockBorder),
-                "Lock Border",
-            )
-            .on_hover_text("Prevent border vertices from moving").clicked() {
-                settings.options.toggle(SimplifyOptions::LockBorder);
-            };
-
-            if ui.checkbox(
-                &mut settings.options.contains(SimplifyOptions::Sparse),
-                "Sparse",
-            )
-            .on_hover_text("Use sparse decimation").clicked() {
-                settings.options.toggle(SimplifyOptions::Sparse);
-            };
-
-            if ui.checkbox(
-                &mut settings.options.contains(SimplifyOptions::ErrorAbsolute),
-                "Error Absolute",
-            )
-            .on_hover_text("Use absolute error instead of relative").clicked() {
-                settings.options.toggle(SimplifyOptions::ErrorAbsolute);
-            };
-
-            if ui.checkbox(
-                &mut settings.options.contains(SimplifyOptions::Regularize),
-                "Regularize",
-            )
-            .on_hover_text("Produce more regular triangle sizes and shapes during simplification, at some cost to geometric quality")
-            .clicked() {
-                settings.options.toggle(SimplifyOptions::Regularize);
+            if simplify_params_ui(ui, settings.bypass_change_detection()).changed() {
+                settings.set_changed();
             }
 
-            // Sloppy
-            ui.checkbox(&mut settings.sloppy, "Sloppy")
-                .on_hover_text("Use faster but less accurate simplification");
-
             ui.add_space(10.0);
 
             // let mut is_percentage = matches!(settings.target_count, TargetCount::Percentage(_));
@@ -253,19 +594,149 @@ pub fn simplify_settings_ui(
 
             ui.add_space(10.0);
 
-            if ui.button("Reset").clicked() {
-                reset.0 = true;
+            ui.collapsing("Optimize", |ui| {
+                ui.checkbox(&mut optimize_settings.vertex_cache, "Vertex Cache");
+                if ui
+                    .checkbox(
+                        &mut matches!(optimize_settings.cache_model, CacheModel::Fifo { .. }),
+                        "FIFO Cache",
+                    )
+                    .on_hover_text("Optimize for a fixed size FIFO cache instead of an LRU one")
+                    .clicked()
+                {
+                    optimize_settings.cache_model = match optimize_settings.cache_model {
+                        CacheModel::Fifo { .. } => CacheModel::Lru,
+                        _ => CacheModel::Fifo { cache_size: 16 },
+                    };
+                }
+                ui.checkbox(&mut optimize_settings.overdraw, "Overdraw");
+                ui.add(
+                    egui::Slider::new(&mut optimize_settings.overdraw_threshold, 1.0..=3.0)
+                        .text("threshold"),
+                );
+                ui.checkbox(&mut optimize_settings.vertex_fetch, "Vertex Fetch");
+                ui.checkbox(
+                    &mut optimize_settings.strip_and_regenerate.normals,
+                    "Regenerate Normals",
+                );
+                ui.checkbox(
+                    &mut optimize_settings.strip_and_regenerate.tangents,
+                    "Regenerate Tangents",
+                );
+                let mut skip = optimize_settings.skip_if_optimized.is_some();
+                if ui
+                    .checkbox(&mut skip, "Skip Optimized")
+                    .on_hover_text("Leave meshes that are already near-optimal as is")
+                    .changed()
+                {
+                    optimize_settings.skip_if_optimized =
+                        skip.then(OptimizedThresholds::default);
+                }
+            });
+
+            ui.add_space(10.0);
+
+            ui.horizontal(|ui| {
+                if ui.button("Reset").clicked() {
+                    reset.0 = true;
+                }
+                if ui
+                    .button("Export")
+                    .on_hover_text("Save the helmet meshes as they are to assets/exported")
+                    .clicked()
+                {
+                    export.0 = true;
+                }
+            });
+            ui.horizontal(|ui| {
+                if ui.button("Simplify").clicked() {
+                    simplify.0 = true;
+                }
+                if ui.button("Optimize").clicked() {
+                    optimize.0 = true;
+                }
+                ui.label(format!(
+                    "{} -> {} triangles at {:.2}% error",
+                    projection.triangles_before,
+                    projection.triangles_after,
+                    projection.max_error * 100.0,
+                ));
+            });
+            if stats.simplified_meshes > 0 {
+                ui.label(format!(
+                    "Last batch: {} -> {} triangles at {:.2}% error ({:.4} units)",
+                    stats.simplify.triangles_before(),
+                    stats.simplify.triangles_after(),
+                    stats.simplify.result_error * 100.0,
+                    stats.simplify.result_error_absolute(),
+                ));
+            }
+            if stats.optimized_meshes > 0 {
+                ui.label(format!(
+                    "Last optimize: ACMR {:.3} -> {:.3}, overdraw {:.3} -> {:.3}, overfetch {:.3} -> {:.3}",
+                    stats.optimize.acmr_before(),
+                    stats.optimize.acmr_after(),
+                    stats.optimize.overdraw_before(),
+                    stats.optimize.overdraw_after(),
+                    stats.optimize.overfetch_before(),
+                    stats.optimize.overfetch_after(),
+                ));
+            }
+            if let Some(error) = stats.last_error {
+                ui.label(format!("{} meshes failed to simplify: {error}", stats.failed));
             }
-            if ui.button("Simplify").clicked() {
-                simplify.0 = true;
+            if let Some(error) = stats.optimize_last_error {
+                ui.label(format!(
+                    "{} meshes failed to optimize: {error}",
+                    stats.optimize_failed
+                ));
             }
+            ui.checkbox(&mut lod_grid.enabled, "LOD Grid")
+                .on_hover_text("Spawn a grid of helmets switching levels of detail with distance");
+
+            ui.collapsing("Recommendation", |ui| {
+                ui.add(
+                    egui::Slider::new(&mut recommendation.view.triangles_per_pixel, 0.001..=1.0)
+                        .logarithmic(true)
+                        .text("triangles per pixel"),
+                );
+                ui.add(
+                    egui::Slider::new(&mut recommendation.view.error_pixels, 0.1..=10.0)
+                        .logarithmic(true)
+                        .text("error pixels"),
+                );
+                ui.label(format!(
+                    "{} -> {} triangles at {:.2}% error from {:.2}m",
+                    recommendation.triangles_before,
+                    recommendation.triangles_after,
+                    recommendation.max_error * 100.0,
+                    recommendation.view.distance,
+                ));
+            });
+
+            ui.collapsing("Sweep", |ui| {
+                if ui.button("Run sweep").clicked() {
+                    sweep.run = true;
+                }
+                sweep_plot(ui, &sweep);
+                if let Some(knee) = sweep.knee.map(|knee| sweep.points[knee]) {
+                    ui.label(format!(
+                        "Knee: {} triangles at {:.2}% error",
+                        knee.triangles,
+                        knee.max_error * 100.0,
+                    ));
+                    if ui.button("Use knee error").clicked() {
+                        settings.max_error = knee.max_error;
+                    }
+                }
+            });
 
             // Display current settings
             ui.separator();
             ui.collapsing("Current Settings", |ui| {
                 ui.label(format!("Max Error: {:.4}", settings.max_error));
                 ui.label(format!("Options: {:?}", settings.options));
-                ui.label(format!("Sloppy: {}", settings.sloppy));
+                ui.label(format!("Mode: {:?}", settings.mode));
                 // ui.label(format!("Target: {:?}", settings.target_count));
             });
         });
@@ -0,0 +1,127 @@
+//! Simplifies `.meshopt` files offline, optionally writing the table remapping the vertices of
+//! each source mesh to the simplified one next to it:
+//!
+//! ```sh
+//! cargo run --example simplify --features export -- --ratio 0.25 --remap out/ rock.meshopt
+//! ```
+//!
+//! writes `out/rock.meshopt` and `out/rock.remap`, to load with `RemapTable::load` and remap
+//! per-vertex data kept outside of the mesh.
+
+use std::{
+    fs,
+    io::{self, BufWriter},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use bevy_meshopt::{
+    CompressedMeshFile, MeshExt, OptError, RemapTable, SimplifyParams, TargetIndices, save_mesh,
+};
+
+const USAGE: &str = "usage: simplify [--ratio <fraction>] [--max-error <error>] [--remap] <output directory> <input.meshopt>...";
+
+struct Args {
+    params: SimplifyParams,
+    remap: bool,
+    output: PathBuf,
+    inputs: Vec<PathBuf>,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut params = SimplifyParams::default();
+    let mut remap = false;
+    let mut paths = Vec::new();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| -> Result<f32, String> {
+            args.next()
+                .and_then(|value| value.parse().ok())
+                .ok_or_else(|| format!("{name} expects a number"))
+        };
+        match arg.as_str() {
+            "--ratio" => params.target_index_count = TargetIndices::Multiplier(value(&arg)?),
+            "--max-error" => params.max_error = value(&arg)?,
+            "--remap" => remap = true,
+            _ if arg.starts_with("--") => return Err(format!("unknown option {arg}")),
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+    if paths.len() < 2 {
+        return Err(USAGE.to_string());
+    }
+    let output = paths.remove(0);
+    Ok(Args {
+        params,
+        remap,
+        output,
+        inputs: paths,
+    })
+}
+
+/// Simplifies LOD0 of the `.meshopt` file at `input` into `output`, with its remap table next to
+/// it when `remap` is set.
+fn simplify_file(
+    input: &Path,
+    output: &Path,
+    params: &SimplifyParams,
+    remap: bool,
+) -> io::Result<()> {
+    let invalid = |err| io::Error::new(io::ErrorKind::InvalidData, err);
+    let file = CompressedMeshFile::from_bytes(&fs::read(input)?)?;
+    let source = file
+        .levels
+        .first()
+        .ok_or_else(|| invalid(OptError::MissingMesh))?
+        .decode()?;
+
+    let mut mesh = source.clone();
+    let report = mesh.simplify_with_report(params).map_err(invalid)?;
+    // Simplifying only replaces the indices, the remap table comes from dropping the vertices
+    // they no longer use.
+    let mut table = mesh.optimize_vertex_fetch_remap().map_err(invalid)?;
+    table.source_hash = RemapTable::content_hash(&source);
+
+    save_mesh(output, &mesh)?;
+    if remap {
+        table.save(BufWriter::new(fs::File::create(
+            output.with_extension("remap"),
+        )?))?;
+    }
+    println!(
+        "{}: {} -> {} triangles, {} -> {} vertices",
+        input.display(),
+        report.triangles_before(),
+        report.triangles_after(),
+        table.source_vertex_count,
+        table.destination_vertex_count,
+    );
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("{message}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut failed = false;
+    for input in &args.inputs {
+        let Some(name) = input.file_name() else {
+            continue;
+        };
+        let output = args.output.join(name).with_extension("meshopt");
+        if let Err(err) = simplify_file(input, &output, &args.params, args.remap) {
+            eprintln!("{}: {err}", input.display());
+            failed = true;
+        }
+    }
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
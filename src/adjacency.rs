@@ -0,0 +1,330 @@
+use bevy::math::Vec3;
+
+/// Connectivity of the triangles of a mesh, see [`MeshExt::build_adjacency`](crate::MeshExt::build_adjacency).
+///
+/// Vertices are compared by position, so triangles on either side of an attribute seam (UV or
+/// normal splits) are still neighbors. Edges are identified by the first vertex at each of their
+/// two positions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TriangleAdjacency {
+    /// Welded vertex of every vertex, `u32::MAX` for unused ones.
+    pub(crate) remap: Vec<u32>,
+    pub(crate) welded_vertex_count: usize,
+    /// Unit normal of every triangle, zero for degenerate triangles.
+    pub(crate) face_normals: Vec<Vec3>,
+    /// First vertex of every welded vertex.
+    representatives: Vec<u32>,
+    /// Distinct welded edges, sorted, and the triangles using each of them.
+    edges: Vec<(u32, u32)>,
+    edge_offsets: Vec<u32>,
+    edge_triangles: Vec<u32>,
+    neighbor_offsets: Vec<u32>,
+    neighbors: Vec<u32>,
+    incident_offsets: Vec<u32>,
+    incident: Vec<u32>,
+}
+
+/// Edge of a [`TriangleAdjacency`] along with the triangles using it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct AdjacentEdge<'a> {
+    /// First vertex at the position of each end of the edge.
+    pub vertices: [u32; 2],
+    pub triangles: &'a [u32],
+}
+
+impl AdjacentEdge<'_> {
+    /// Whether the edge is only used by one triangle, i.e. on an open boundary.
+    pub fn is_open(&self) -> bool {
+        self.triangles.len() == 1
+    }
+
+    /// Whether the edge is used by more than two triangles.
+    pub fn is_non_manifold(&self) -> bool {
+        self.triangles.len() > 2
+    }
+}
+
+impl TriangleAdjacency {
+    pub(crate) fn new(indices: &[u32], positions: &[[f32; 3]]) -> Self {
+        let (welded_vertex_count, remap) = meshopt::generate_vertex_remap(positions, Some(indices));
+        let position = |index: u32| Vec3::from_array(positions[index as usize]);
+        let face_normals: Vec<Vec3> = indices
+            .chunks_exact(3)
+            .map(|triangle| {
+                let [a, b, c] = [
+                    position(triangle[0]),
+                    position(triangle[1]),
+                    position(triangle[2]),
+                ];
+                (b - a).cross(c - a).normalize_or_zero()
+            })
+            .collect();
+
+        let mut representatives = vec![u32::MAX; welded_vertex_count];
+        for (vertex, &welded) in remap.iter().enumerate() {
+            if welded != u32::MAX && representatives[welded as usize] == u32::MAX {
+                representatives[welded as usize] = vertex as u32;
+            }
+        }
+
+        let mut pairs = Vec::with_capacity(indices.len());
+        for (triangle, corners) in indices.chunks_exact(3).enumerate() {
+            for corner in 0..3 {
+                let a = remap[corners[corner] as usize];
+                let b = remap[corners[(corner + 1) % 3] as usize];
+                if a != b {
+                    pairs.push(((a.min(b), a.max(b)), triangle as u32));
+                }
+            }
+        }
+        pairs.sort_unstable();
+        pairs.dedup();
+
+        let mut edges = Vec::new();
+        let mut edge_offsets = vec![0];
+        let mut edge_triangles = Vec::with_capacity(pairs.len());
+        for faces in pairs.chunk_by(|(a, _), (b, _)| a == b) {
+            edges.push(faces[0].0);
+            edge_triangles.extend(faces.iter().map(|&(_, triangle)| triangle));
+            edge_offsets.push(edge_triangles.len() as u32);
+        }
+
+        let triangle_count = face_normals.len();
+        let mut neighbor_lists = vec![Vec::new(); triangle_count];
+        for faces in edge_offsets.windows(2) {
+            let faces = &edge_triangles[faces[0] as usize..faces[1] as usize];
+            for (i, &a) in faces.iter().enumerate() {
+                for &b in &faces[i + 1..] {
+                    neighbor_lists[a as usize].push(b);
+                    neighbor_lists[b as usize].push(a);
+                }
+            }
+        }
+        let (neighbor_offsets, neighbors) = flatten(neighbor_lists);
+
+        let mut incident_lists = vec![Vec::new(); welded_vertex_count];
+        for (triangle, corners) in indices.chunks_exact(3).enumerate() {
+            for &vertex in corners {
+                incident_lists[remap[vertex as usize] as usize].push(triangle as u32);
+            }
+        }
+        let (incident_offsets, incident) = flatten(incident_lists);
+
+        TriangleAdjacency {
+            remap,
+            welded_vertex_count,
+            face_normals,
+            representatives,
+            edges,
+            edge_offsets,
+            edge_triangles,
+            neighbor_offsets,
+            neighbors,
+            incident_offsets,
+            incident,
+        }
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.face_normals.len()
+    }
+
+    /// Number of distinct vertex positions used by the triangles.
+    pub fn position_count(&self) -> usize {
+        self.welded_vertex_count
+    }
+
+    /// First vertex at the position of `vertex`, `None` for vertices no triangle uses.
+    pub fn representative(&self, vertex: u32) -> Option<u32> {
+        match self.remap.get(vertex as usize) {
+            Some(&welded) if welded != u32::MAX => Some(self.representatives[welded as usize]),
+            _ => None,
+        }
+    }
+
+    /// Unit normal of a triangle, zero for degenerate triangles.
+    pub fn face_normal(&self, triangle: u32) -> Vec3 {
+        self.face_normals[triangle as usize]
+    }
+
+    /// Triangles sharing an edge with `triangle`, sorted.
+    pub fn neighbors(&self, triangle: u32) -> &[u32] {
+        let range = &self.neighbor_offsets[triangle as usize..][..2];
+        &self.neighbors[range[0] as usize..range[1] as usize]
+    }
+
+    /// Triangles using a vertex at the position of `vertex`, sorted.
+    pub fn incident_triangles(&self, vertex: u32) -> &[u32] {
+        match self.remap.get(vertex as usize) {
+            Some(&welded) if welded != u32::MAX => {
+                let range = &self.incident_offsets[welded as usize..][..2];
+                &self.incident[range[0] as usize..range[1] as usize]
+            }
+            _ => &[],
+        }
+    }
+
+    /// Every edge along with the triangles using it, ordered by position.
+    pub fn edges(&self) -> impl Iterator<Item = AdjacentEdge<'_>> {
+        self.welded_edges().map(|((a, b), triangles)| AdjacentEdge {
+            vertices: [
+                self.representatives[a as usize],
+                self.representatives[b as usize],
+            ],
+            triangles,
+        })
+    }
+
+    /// Edges used by a single triangle.
+    pub fn open_edges(&self) -> impl Iterator<Item = AdjacentEdge<'_>> {
+        self.edges().filter(AdjacentEdge::is_open)
+    }
+
+    /// Edges used by more than two triangles.
+    pub fn non_manifold_edges(&self) -> impl Iterator<Item = AdjacentEdge<'_>> {
+        self.edges().filter(AdjacentEdge::is_non_manifold)
+    }
+
+    /// Every welded edge along with the triangles using it.
+    pub(crate) fn welded_edges(&self) -> impl Iterator<Item = ((u32, u32), &[u32])> {
+        self.edges
+            .iter()
+            .zip(self.edge_offsets.windows(2))
+            .map(|(&edge, range)| {
+                (
+                    edge,
+                    &self.edge_triangles[range[0] as usize..range[1] as usize],
+                )
+            })
+    }
+
+    /// Sets the flag of every vertex whose welded vertex is flagged in `welded_flags`.
+    pub(crate) fn flag_welded(&self, welded_flags: &[bool], flags: &mut [bool]) {
+        for (flag, &welded) in flags.iter_mut().zip(&self.remap) {
+            if welded != u32::MAX && welded_flags[welded as usize] {
+                *flag = true;
+            }
+        }
+    }
+}
+
+/// Concatenates `lists` after sorting and deduplicating each one, returning the offset of every
+/// list followed by the total length.
+fn flatten(lists: Vec<Vec<u32>>) -> (Vec<u32>, Vec<u32>) {
+    let mut offsets = Vec::with_capacity(lists.len() + 1);
+    let mut values = Vec::new();
+    offsets.push(0);
+    for mut list in lists {
+        list.sort_unstable();
+        list.dedup();
+        values.extend(list);
+        offsets.push(values.len() as u32);
+    }
+    (offsets, values)
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{
+        asset::RenderAssetUsages,
+        math::primitives::Cuboid,
+        mesh::{Indices, Mesh, Meshable, PrimitiveTopology},
+    };
+
+    use crate::{
+        MeshExt,
+        test_util::{grid, positions, with_u16_indices},
+    };
+
+    #[test]
+    fn grid_has_open_edges_along_its_border_only() {
+        let cells = 4;
+        let mesh = grid(cells);
+        let adjacency = mesh.build_adjacency().unwrap();
+        assert_eq!(
+            adjacency.position_count(),
+            ((cells + 1) * (cells + 1)) as usize
+        );
+        assert_eq!(adjacency.open_edges().count(), (4 * cells) as usize);
+        assert_eq!(adjacency.non_manifold_edges().count(), 0);
+
+        let positions = positions(&mesh);
+        let on_border = |vertex: u32| {
+            let [x, _, z] = positions[vertex as usize];
+            [x, z].iter().any(|&value| value == 0.0 || value == 1.0)
+        };
+        for edge in adjacency.open_edges() {
+            assert!(edge.vertices.into_iter().all(on_border));
+        }
+        for edge in adjacency.edges().filter(|edge| !edge.is_open()) {
+            assert_eq!(edge.triangles.len(), 2);
+        }
+    }
+
+    #[test]
+    fn fin_edge_is_non_manifold() {
+        // Three triangles sharing the edge from the origin to `+Y`.
+        let positions = vec![
+            [0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [-1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0],
+        ];
+        let mesh = Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::default(),
+        )
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+        .with_inserted_indices(Indices::U32(vec![0, 1, 2, 1, 0, 3, 0, 1, 4]));
+        let adjacency = mesh.build_adjacency().unwrap();
+
+        let non_manifold: Vec<_> = adjacency.non_manifold_edges().collect();
+        assert_eq!(non_manifold.len(), 1);
+        assert_eq!(non_manifold[0].vertices, [0, 1]);
+        assert_eq!(non_manifold[0].triangles, [0, 1, 2]);
+        assert_eq!(adjacency.open_edges().count(), 6);
+        for triangle in 0..3 {
+            assert_eq!(adjacency.neighbors(triangle).len(), 2);
+        }
+    }
+
+    #[test]
+    fn attribute_seams_keep_the_cube_closed() {
+        // Every corner of the cube is split into three vertices with different normals and UVs.
+        let cube: Mesh = Cuboid::default().mesh().into();
+        assert_eq!(cube.count_vertices(), 24);
+        let adjacency = cube.build_adjacency().unwrap();
+        assert_eq!(adjacency.position_count(), 8);
+        assert_eq!(adjacency.edges().count(), 18);
+        assert_eq!(adjacency.open_edges().count(), 0);
+        assert_eq!(adjacency.non_manifold_edges().count(), 0);
+        for triangle in 0..adjacency.triangle_count() as u32 {
+            assert_eq!(adjacency.neighbors(triangle).len(), 3);
+        }
+
+        let positions = positions(&cube);
+        for vertex in 0..24 {
+            let representative = adjacency.representative(vertex).unwrap();
+            assert_eq!(
+                positions[representative as usize],
+                positions[vertex as usize]
+            );
+            // Each corner is used by the two triangles of each of its three faces, or one of them.
+            assert!((3..=6).contains(&adjacency.incident_triangles(vertex).len()));
+        }
+    }
+
+    #[test]
+    fn u16_indices_build_the_same_adjacency() {
+        let cube: Mesh = Cuboid::default().mesh().into();
+        assert_eq!(
+            with_u16_indices(cube.clone()).build_adjacency().unwrap(),
+            cube.build_adjacency().unwrap()
+        );
+        assert_eq!(
+            with_u16_indices(grid(3)).build_adjacency().unwrap(),
+            grid(3).build_adjacency().unwrap()
+        );
+    }
+}
@@ -0,0 +1,284 @@
+use std::io;
+
+use bevy::{
+    app::{App, Plugin},
+    asset::{
+        Asset, AssetApp,
+        io::{AsyncWriteExt, Writer},
+        processor::LoadTransformAndSave,
+        saver::{AssetSaver, SavedAsset},
+        transformer::{AssetTransformer, TransformedAsset},
+    },
+    log::{info, warn},
+    mesh::{Mesh, PrimitiveTopology, VertexAttributeValues},
+    reflect::TypePath,
+};
+
+#[cfg(feature = "gltf")]
+use bevy::{
+    asset::{
+        meta::{AssetAction, AssetMeta},
+        processor::{Process, ProcessContext, ProcessError},
+    },
+    gltf::{GltfLoader, GltfLoaderSettings},
+};
+#[cfg(feature = "gltf")]
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "gltf")]
+use crate::process_gltf;
+use crate::{
+    CompressedMeshFile, CompressedMeshLoader, MeshExt, MeshProcessSettings, OptError,
+    OptimizeSkipReason, SimplifyParams,
+};
+
+/// [`AssetTransformer`] simplifying meshes while they are processed, with the [`SimplifyParams`]
+/// stored in the settings of their `.meta` file. Combine it with
+/// [`LoadTransformAndSave`](bevy::asset::processor::LoadTransformAndSave) and a loader and saver
+/// of the mesh format to register a processor:
+///
+/// ```ignore
+/// app.register_asset_processor::<LoadTransformAndSave<MyMeshLoader, SimplifyMeshProcess, MyMeshSaver>>(
+///     LoadTransformAndSave::new(SimplifyMeshProcess, MyMeshSaver),
+/// );
+/// ```
+///
+/// Every field of the settings is optional, missing ones take their [`SimplifyParams::default`]:
+///
+/// ```ron
+/// settings: (
+///     target_index_count: TriangleCount(2000),
+///     max_error: 0.02,
+///     options: ["LockBorder"],
+/// ),
+/// ```
+///
+/// Meshes that can't be simplified, i.e. that aren't a `TriangleList` or have no `Float32x3`
+/// positions, are passed through untouched with a warning rather than failing the asset. Other
+/// errors, e.g. indices past the vertices, fail it.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct SimplifyMeshProcess;
+
+impl AssetTransformer for SimplifyMeshProcess {
+    type AssetInput = Mesh;
+    type AssetOutput = Mesh;
+    type Settings = SimplifyParams;
+    type Error = OptError;
+
+    async fn transform<'a>(
+        &'a self,
+        mut asset: TransformedAsset<Mesh>,
+        settings: &'a SimplifyParams,
+    ) -> Result<TransformedAsset<Mesh>, OptError> {
+        let topology = asset.primitive_topology();
+        if topology != PrimitiveTopology::TriangleList {
+            warn!("Not simplifying mesh with {topology:?} topology, only triangle lists are");
+            return Ok(asset);
+        }
+        if !matches!(
+            asset.attribute(Mesh::ATTRIBUTE_POSITION),
+            Some(VertexAttributeValues::Float32x3(_))
+        ) {
+            warn!("Not simplifying mesh without Float32x3 positions");
+            return Ok(asset);
+        }
+
+        let report = asset.simplify_with_report(settings)?;
+        info!(
+            "Simplified mesh from {} to {} triangles, error {} ({} in mesh units)",
+            report.indices_before / 3,
+            report.indices_after / 3,
+            report.result_error,
+            report.result_error_absolute(),
+        );
+        Ok(asset)
+    }
+}
+
+/// Runs the stages of the [`MeshProcessSettings`] stored in the `.meta` file of a mesh while it is
+/// processed, turning it into a [`ProcessedMesh`] holding the LOD chain if any levels are listed.
+/// Registered by [`MeshoptProcessorPlugin`].
+///
+/// Like [`SimplifyMeshProcess`], meshes that can't be processed are passed through untouched with
+/// a warning.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct ProcessMesh;
+
+/// Output of [`ProcessMesh`], the processed mesh followed by its coarser levels of detail.
+#[derive(Asset, TypePath, Debug, Clone)]
+pub struct ProcessedMesh {
+    pub levels: Vec<Mesh>,
+}
+
+impl AssetTransformer for ProcessMesh {
+    type AssetInput = Mesh;
+    type AssetOutput = ProcessedMesh;
+    type Settings = MeshProcessSettings;
+    type Error = OptError;
+
+    async fn transform<'a>(
+        &'a self,
+        mut asset: TransformedAsset<Mesh>,
+        settings: &'a MeshProcessSettings,
+    ) -> Result<TransformedAsset<ProcessedMesh>, OptError> {
+        let topology = asset.primitive_topology();
+        let usage = asset.asset_usage;
+        let mut mesh = std::mem::replace(asset.get_mut(), Mesh::new(topology, usage));
+        if topology != PrimitiveTopology::TriangleList {
+            warn!("Not processing mesh with {topology:?} topology, only triangle lists are");
+            return Ok(asset.replace_asset(ProcessedMesh { levels: vec![mesh] }));
+        }
+        if !matches!(
+            mesh.attribute(Mesh::ATTRIBUTE_POSITION),
+            Some(VertexAttributeValues::Float32x3(_))
+        ) {
+            warn!("Not processing mesh without Float32x3 positions");
+            return Ok(asset.replace_asset(ProcessedMesh { levels: vec![mesh] }));
+        }
+
+        let triangles_before = mesh.indices().map_or(mesh.count_vertices(), |i| i.len()) / 3;
+        let (chain, report) = settings.process_with_report(&mut mesh)?;
+        let levels = match chain {
+            Some(chain) => chain.levels,
+            None => vec![mesh],
+        };
+        info!(
+            "Processed mesh from {} to {} triangles in {} levels{}",
+            triangles_before,
+            levels[0].indices().map_or(0, |indices| indices.len()) / 3,
+            levels.len(),
+            match report.optimize.skipped_reason {
+                Some(OptimizeSkipReason::AlreadyOptimized) => ", already optimized",
+                None => "",
+            },
+        );
+        Ok(asset.replace_asset(ProcessedMesh { levels }))
+    }
+}
+
+/// Saves [`ProcessedMesh`]es as `.meshopt` files, see [`CompressedMeshFile`]. Registered by
+/// [`MeshoptProcessorPlugin`].
+#[derive(Debug, Copy, Clone, Default)]
+pub struct CompressedMeshSaver;
+
+impl AssetSaver for CompressedMeshSaver {
+    type Asset = ProcessedMesh;
+    type Settings = ();
+    type OutputLoader = CompressedMeshLoader;
+    type Error = io::Error;
+
+    async fn save(
+        &self,
+        writer: &mut Writer,
+        asset: SavedAsset<'_, ProcessedMesh>,
+        _settings: &(),
+    ) -> io::Result<()> {
+        let file = CompressedMeshFile::from_meshes(&asset.levels)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error))?;
+        writer.write_all(&file.to_bytes()).await
+    }
+}
+
+/// Processor loading `.meshopt` files, running [`ProcessMesh`] on them and saving the result with
+/// [`CompressedMeshSaver`].
+pub type MeshoptProcessor =
+    LoadTransformAndSave<CompressedMeshLoader, ProcessMesh, CompressedMeshSaver>;
+
+/// Settings of [`GltfMeshoptProcessor`] in the `.meta` file of a glTF, both fields are optional:
+///
+/// ```ron
+/// settings: (
+///     meshes: (
+///         simplify: Some((target: Multiplier(0.5), max_error: 0.01)),
+///     ),
+/// ),
+/// ```
+#[cfg(feature = "gltf")]
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GltfProcessSettings {
+    /// Stages run on every primitive, without [`MeshProcessSettings::lods`], see [`process_gltf`].
+    pub meshes: MeshProcessSettings,
+    /// Settings Bevy's `GltfLoader` loads the processed file with, all of their fields have to be
+    /// given.
+    pub loader: GltfLoaderSettings,
+}
+
+/// Processor running [`process_gltf`] on `.gltf` and `.glb` files with the
+/// [`GltfProcessSettings`] of their `.meta` file, registered by [`MeshoptProcessorPlugin`]. The
+/// processed file is loaded by Bevy's `GltfLoader` with the same labels as the source.
+///
+/// Primitives that can't be processed are kept as they are with a warning, files whose buffers
+/// live in other files are copied untouched.
+#[cfg(feature = "gltf")]
+#[derive(Debug, Copy, Clone, Default)]
+pub struct GltfMeshoptProcessor;
+
+#[cfg(feature = "gltf")]
+impl Process for GltfMeshoptProcessor {
+    type Settings = GltfProcessSettings;
+    type OutputLoader = GltfLoader;
+
+    async fn process(
+        &self,
+        context: &mut ProcessContext<'_>,
+        meta: AssetMeta<(), Self>,
+        writer: &mut Writer,
+    ) -> Result<GltfLoaderSettings, ProcessError> {
+        let AssetAction::Process { settings, .. } = meta.asset else {
+            return Err(ProcessError::WrongMetaType);
+        };
+        let path = context.path().clone();
+        if !settings.meshes.lods.is_empty() {
+            warn!("Not generating the levels of detail of {path}, glTF files can't hold them");
+        }
+        let external = |uri: &str| {
+            Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("buffer {uri} is in another file"),
+            ))
+        };
+        let bytes = match process_gltf(context.asset_bytes(), external, &settings.meshes) {
+            Ok((bytes, report)) => {
+                for (label, reason) in &report.skipped {
+                    warn!("Not processing {path}#{label}: {reason}");
+                }
+                info!("Processed {} primitives of {path}", report.processed.len());
+                bytes
+            }
+            Err(error) => {
+                warn!("Not processing {path}: {error}");
+                context.asset_bytes().to_vec()
+            }
+        };
+        writer
+            .write_all(&bytes)
+            .await
+            .map_err(|error| ProcessError::AssetSaveError(error.into()))?;
+        Ok(settings.loader)
+    }
+}
+
+/// Processes every `.meshopt` file with [`MeshoptProcessor`] when the app runs the asset
+/// processor, so meshes are simplified, optimized and split into LODs once at import rather than
+/// at runtime. The settings of each asset live in its `.meta` file, see [`MeshProcessSettings`],
+/// which the processor writes with the default settings for files that don't have one yet.
+/// Needs [`MeshoptPlugin`](crate::MeshoptPlugin) for the [`CompressedMeshLoader`], and does
+/// nothing for apps that don't process assets.
+///
+/// With the `gltf` feature, `.gltf` and `.glb` files are processed as well with
+/// [`GltfMeshoptProcessor`], which needs Bevy's `GltfPlugin` to load them. Their primitives are
+/// processed without levels of detail, write `.meshopt` files with [`CompressedMeshFile::to_bytes`]
+/// for meshes that need them.
+pub struct MeshoptProcessorPlugin;
+
+impl Plugin for MeshoptProcessorPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_asset_processor(MeshoptProcessor::new(ProcessMesh, CompressedMeshSaver))
+            .set_default_asset_processor::<MeshoptProcessor>("meshopt");
+        #[cfg(feature = "gltf")]
+        app.register_asset_processor(GltfMeshoptProcessor)
+            .set_default_asset_processor::<GltfMeshoptProcessor>("gltf")
+            .set_default_asset_processor::<GltfMeshoptProcessor>("glb");
+    }
+}
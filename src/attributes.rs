@@ -0,0 +1,302 @@
+use bevy::{
+    image::Image,
+    math::{UVec2, Vec2},
+    mesh::{Mesh, MeshVertexAttribute, VertexAttributeValues},
+    reflect::Reflect,
+};
+
+use crate::SimplifyParams;
+
+/// How strongly UV coordinates are preserved during simplification.
+#[derive(Debug, Copy, Clone, PartialEq, Reflect)]
+#[reflect(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub enum UvWeighting {
+    /// Weight used for both UV components as is.
+    Weight(f32),
+    /// Derive the weight from the resolution of the texture sampled with the UVs, so that moving
+    /// the UVs by `tolerance_texels` texels costs as much as the simplifier's `max_error`.
+    Texels {
+        resolution: UVec2,
+        tolerance_texels: f32,
+    },
+}
+
+impl UvWeighting {
+    /// [`UvWeighting::Texels`] using the resolution of `image`, e.g. the base color texture of
+    /// the mesh's material.
+    pub fn texels_for_image(image: &Image, tolerance_texels: f32) -> Self {
+        UvWeighting::Texels {
+            resolution: image.size(),
+            tolerance_texels,
+        }
+    }
+
+    /// Weight of the U and V components fed to the simplifier for a given `max_error`.
+    pub fn effective_weight(&self, max_error: f32) -> Vec2 {
+        match *self {
+            UvWeighting::Weight(weight) => Vec2::splat(weight),
+            UvWeighting::Texels {
+                resolution,
+                tolerance_texels,
+            } => resolution.as_vec2() * max_error / tolerance_texels.max(f32::EPSILON),
+        }
+    }
+}
+
+/// UV attributes weighted by [`SimplifyParams::uv_weighting`], in order.
+const UV_ATTRIBUTES: [MeshVertexAttribute; 2] = [Mesh::ATTRIBUTE_UV_0, Mesh::ATTRIBUTE_UV_1];
+
+/// Effective weights of the UV sets present on the mesh, see [`UvWeighting::effective_weight`].
+pub(crate) fn uv_weights(mesh: &Mesh, params: &SimplifyParams) -> [Option<Vec2>; 2] {
+    let mut weights = [None; 2];
+    for ((weight, weighting), attribute) in weights
+        .iter_mut()
+        .zip(params.uv_weighting)
+        .zip(UV_ATTRIBUTES)
+    {
+        if let Some(weighting) = weighting
+            && uv_attribute(mesh, attribute).is_some()
+        {
+            *weight = Some(weighting.effective_weight(params.max_error * params.error_units()));
+        }
+    }
+    weights
+}
+
+fn uv_attribute(mesh: &Mesh, attribute: MeshVertexAttribute) -> Option<&[[f32; 2]]> {
+    match mesh.attribute(attribute) {
+        Some(VertexAttributeValues::Float32x2(values)) if values.len() == mesh.count_vertices() => {
+            Some(values)
+        }
+        _ => None,
+    }
+}
+
+/// Dimensions joint influences are projected onto for [`SimplifyParams::skinning_weight`].
+const SKIN_DIMENSIONS: usize = 4;
+/// Every dimension holds the weighted homogeneous position of the vertex.
+const SKIN_COMPONENTS: usize = SKIN_DIMENSIONS * 4;
+
+/// Interleaved per-vertex attributes fed to the attribute-aware simplifier, along with the weight
+/// of each component.
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct VertexAttributes<'a> {
+    pub values: &'a [f32],
+    pub weights: &'a [f32],
+}
+
+impl VertexAttributes<'_> {
+    /// Number of floats per vertex.
+    pub fn stride(&self) -> usize {
+        self.weights.len()
+    }
+}
+
+/// Collects the attributes `params` asks the simplifier to preserve into `values` and `weights`,
+/// returns `None` if there aren't any.
+///
+/// When `planar_interior` is set, the attributes of the vertices it flags are zeroed so they don't
+/// hold back collapses within planar regions.
+pub(crate) fn vertex_attributes<'a>(
+    mesh: &Mesh,
+    positions: &[[f32; 3]],
+    params: &SimplifyParams,
+    planar_interior: Option<&[bool]>,
+    values: &'a mut Vec<f32>,
+    weights: &'a mut Vec<f32>,
+) -> Option<VertexAttributes<'a>> {
+    values.clear();
+    weights.clear();
+
+    let vertex_count = positions.len();
+    let skin = (params.skinning_weight > 0.0)
+        .then(|| skin_influences(mesh, vertex_count))
+        .flatten();
+
+    if skin.is_some() {
+        weights.extend([params.skinning_weight; SKIN_COMPONENTS]);
+    }
+
+    let normals = (params.normal_weight > 0.0)
+        .then(|| match mesh.attribute(Mesh::ATTRIBUTE_NORMAL) {
+            Some(VertexAttributeValues::Float32x3(normals)) if normals.len() == vertex_count => {
+                Some(normals)
+            }
+            _ => None,
+        })
+        .flatten();
+    if normals.is_some() {
+        weights.extend([params.normal_weight; 3]);
+    }
+
+    let colors = (params.color_weight > 0.0)
+        .then(|| match mesh.attribute(Mesh::ATTRIBUTE_COLOR) {
+            Some(VertexAttributeValues::Float32x4(colors)) if colors.len() == vertex_count => {
+                Some(colors)
+            }
+            _ => None,
+        })
+        .flatten();
+    if colors.is_some() {
+        weights.extend([params.color_weight; 4]);
+    }
+
+    let mut uvs = [None; 2];
+    for ((uv, attribute), weight) in uvs
+        .iter_mut()
+        .zip(UV_ATTRIBUTES)
+        .zip(uv_weights(mesh, params))
+    {
+        if let Some(weight) = weight {
+            *uv = uv_attribute(mesh, attribute);
+            weights.extend(weight.to_array());
+        }
+    }
+
+    if weights.is_empty() {
+        return None;
+    }
+
+    let stride = weights.len();
+    values.resize(vertex_count * stride, 0.0);
+    let mut offset = 0;
+    if let Some((joints, joint_weights)) = skin {
+        let scale = meshopt::simplify_scale_decoder(positions).max(f32::EPSILON);
+        for (vertex, position) in positions.iter().enumerate() {
+            let value = &mut values[vertex * stride + offset..][..SKIN_COMPONENTS];
+            let position = [
+                position[0] / scale,
+                position[1] / scale,
+                position[2] / scale,
+                1.0,
+            ];
+            for (&joint, &weight) in joints[vertex].iter().zip(&joint_weights[vertex]) {
+                if weight == 0.0 {
+                    continue;
+                }
+
+                let direction = joint_direction(joint);
+                for (k, direction) in direction.iter().enumerate() {
+                    for (c, position) in position.iter().enumerate() {
+                        value[k * 4 + c] += weight * direction * position;
+                    }
+                }
+            }
+        }
+        offset += SKIN_COMPONENTS;
+    }
+
+    if let Some(normals) = normals {
+        for (vertex, normal) in normals.iter().enumerate() {
+            values[vertex * stride + offset..][..3].copy_from_slice(normal);
+        }
+        offset += 3;
+    }
+
+    if let Some(colors) = colors {
+        for (vertex, color) in colors.iter().enumerate() {
+            values[vertex * stride + offset..][..4].copy_from_slice(color);
+        }
+        offset += 4;
+    }
+
+    for uv in uvs.into_iter().flatten() {
+        for (vertex, uv) in uv.iter().enumerate() {
+            values[vertex * stride + offset..][..2].copy_from_slice(uv);
+        }
+        offset += 2;
+    }
+    debug_assert_eq!(offset, stride);
+
+    if let Some(interior) = planar_interior {
+        for (values, _) in values
+            .chunks_exact_mut(stride)
+            .zip(interior)
+            .filter(|(_, interior)| **interior)
+        {
+            values.fill(0.0);
+        }
+    }
+
+    Some(VertexAttributes { values, weights })
+}
+
+/// Joint indices and weights of every vertex.
+type SkinInfluences<'a> = (&'a [[u16; 4]], &'a [[f32; 4]]);
+
+fn skin_influences(mesh: &Mesh, vertex_count: usize) -> Option<SkinInfluences<'_>> {
+    let Some(VertexAttributeValues::Uint16x4(joints)) = mesh.attribute(Mesh::ATTRIBUTE_JOINT_INDEX)
+    else {
+        return None;
+    };
+    let Some(VertexAttributeValues::Float32x4(weights)) =
+        mesh.attribute(Mesh::ATTRIBUTE_JOINT_WEIGHT)
+    else {
+        return None;
+    };
+
+    (joints.len() == vertex_count && weights.len() == vertex_count)
+        .then_some((joints.as_slice(), weights.as_slice()))
+}
+
+/// Locks both vertices of every edge whose ends have a different dominant joint, see
+/// [`SimplifyParams::lock_joint_seams`].
+pub(crate) fn lock_joint_seam_vertices(mesh: &Mesh, indices: &[u32], locks: &mut [bool]) {
+    let Some((joints, weights)) = skin_influences(mesh, locks.len()) else {
+        return;
+    };
+    let dominant: Vec<u16> = joints
+        .iter()
+        .zip(weights)
+        .map(|(joints, weights)| {
+            let (corner, _) = weights.iter().enumerate().fold(
+                (0, f32::NEG_INFINITY),
+                |best, (corner, &weight)| {
+                    if weight > best.1 {
+                        (corner, weight)
+                    } else {
+                        best
+                    }
+                },
+            );
+            joints[corner]
+        })
+        .collect();
+
+    for triangle in indices.chunks_exact(3) {
+        for (a, b) in [(0, 1), (1, 2), (2, 0)] {
+            let (a, b) = (triangle[a] as usize, triangle[b] as usize);
+            if dominant[a] != dominant[b] {
+                locks[a] = true;
+                locks[b] = true;
+            }
+        }
+    }
+}
+
+/// Pseudo-random unit direction for a joint.
+///
+/// The skinned position of a vertex is `sum(weight * joint_matrix * position)`, which is linear in
+/// the `weight * position` products of every joint. Keeping the interpolation error of those
+/// products low therefore keeps the deformed surface close for any pose, and projecting the joints
+/// onto a few random directions keeps the attribute count independent of the skeleton size.
+fn joint_direction(joint: u16) -> [f32; SKIN_DIMENSIONS] {
+    let mut state = joint as u32 ^ 0x9e37_79b9;
+    let mut direction = [0.0; SKIN_DIMENSIONS];
+    for component in &mut direction {
+        // xorshift32
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        *component = state as f32 / u32::MAX as f32 * 2.0 - 1.0;
+    }
+
+    let length = direction.iter().map(|c| c * c).sum::<f32>().sqrt();
+    if length > 0.0 {
+        for component in &mut direction {
+            *component /= length;
+        }
+    }
+    direction
+}
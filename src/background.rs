@@ -0,0 +1,685 @@
+use std::time::Duration;
+
+use bevy::{
+    app::{App, Plugin, Update},
+    asset::{Assets, Handle},
+    ecs::{entity::Entities, prelude::*, schedule::ScheduleLabel, system::SystemParam},
+    mesh::{Mesh, Mesh3d},
+    platform::time::Instant,
+    reflect::Reflect,
+    tasks::{AsyncComputeTaskPool, Task, futures::check_ready},
+};
+
+use crate::{
+    LodChain, LodChainCompleted, LodChainParams, LodChainReport, MeshBoundsPlugin, MeshExt,
+    MeshLods, MeshModified, MeshoptPlugin, MeshoptSet, OptError, RequestTag,
+    SimplificationCompleted, SimplifyParams, SimplifyReport,
+};
+
+/// Simplifies meshes on the [`AsyncComputeTaskPool`] instead of in a batch on the main thread:
+/// insert [`SimplifyMesh`] on an entity with a [`Mesh3d`] to request it, or [`GenerateMeshLods`]
+/// for its levels of detail. [`MeshProcessRequest`] messages request either for a mesh asset
+/// without an entity.
+///
+/// Requests are taken off their entities in [`MeshoptSet::Queue`]. Once its mesh is loaded, a copy
+/// of it is simplified in the background from [`MeshoptSet::Process`] on, and the result is swapped
+/// in by [`MeshoptSet::Apply`] of the first frame after the task finishes according to
+/// [`SimplifiedMeshOutput`], followed by a [`MeshSimplified`] or [`MeshSimplifyFailed`] message.
+/// [`SimplifyExecution`] runs the requests on the main thread instead. Simplified meshes are also
+/// reported with [`MeshModified`] for the [`MeshBoundsPlugin`] it adds. The systems run in
+/// [`MeshoptPlugin::schedule`] when the [`MeshoptPlugin`] is added before this plugin, in
+/// [`Update`] otherwise.
+/// Inserting [`SimplifyMesh`] again before the result is in cancels the pending request in favor
+/// of the new one, and so does [`GenerateMeshLods`].
+///
+/// Every request is reported exactly once, in the order they complete, with a
+/// [`SimplificationCompleted`] or [`LodChainCompleted`] message carrying the [`RequestTag`] of
+/// its entity, whether it succeeded, failed or was cancelled. Requests whose entity was despawned
+/// meanwhile are dropped and reported as [`OptError::Cancelled`]. When the source mesh was removed
+/// from [`Assets<Mesh>`] or the entity's [`Mesh3d`] was replaced or removed, the result is dropped
+/// and reported as [`OptError::MissingMesh`].
+///
+/// ```no_run
+/// use bevy::prelude::*;
+/// use bevy_meshopt::{MeshSimplified, SimplifyMesh, SimplifyParams};
+///
+/// fn request(mut commands: Commands, query: Query<Entity, Added<Mesh3d>>) {
+///     for entity in &query {
+///         commands.entity(entity).insert(SimplifyMesh(SimplifyParams::default()));
+///     }
+/// }
+///
+/// fn done(mut simplified: MessageReader<MeshSimplified>) {
+///     for simplified in simplified.read() {
+///         info!("{} now has {} triangles", simplified.entity, simplified.report.triangles_after());
+///     }
+/// }
+/// ```
+pub struct MeshSimplifyPlugin;
+
+impl Plugin for MeshSimplifyPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<MeshBoundsPlugin>() {
+            app.add_plugins(MeshBoundsPlugin);
+        }
+        let schedule = app
+            .get_added_plugins::<MeshoptPlugin>()
+            .first()
+            .map_or(Update.intern(), |plugin| plugin.schedule);
+        app.add_message::<MeshSimplified>()
+            .add_message::<MeshSimplifyFailed>()
+            .add_message::<MeshProcessRequest>()
+            .add_message::<SimplificationCompleted>()
+            .add_message::<LodChainCompleted>()
+            .init_resource::<SimplifiedMeshOutput>()
+            .init_resource::<SimplifyExecution>()
+            .init_resource::<SimplifyRequests>()
+            .register_type::<SimplifyMesh>()
+            .register_type::<RequestTag>()
+            .add_systems(
+                schedule,
+                (
+                    queue_simplify_requests.in_set(MeshoptSet::Queue),
+                    start_simplify_tasks.in_set(MeshoptSet::Process),
+                    finish_simplify_tasks.in_set(MeshoptSet::Apply),
+                ),
+            );
+    }
+}
+
+/// Requests a background simplification of the entity's mesh, see [`MeshSimplifyPlugin`].
+/// Removed once the request is queued.
+#[derive(Component, Debug, Clone, Reflect)]
+#[reflect(Component, Debug, Clone)]
+pub struct SimplifyMesh(pub SimplifyParams);
+
+/// Requests the levels of detail of the entity's mesh, generated like [`SimplifyMesh`] and
+/// inserted as [`MeshLods`] with LOD0 as the entity's [`Mesh3d`], add the
+/// [`MeshLodPlugin`](crate::MeshLodPlugin) to switch between them. Removed once the request is
+/// queued.
+#[derive(Component, Debug, Clone)]
+pub struct GenerateMeshLods(pub LodChainParams);
+
+/// Requests the processing of a mesh asset that no entity has to use, see [`MeshSimplifyPlugin`].
+/// The result is stored according to [`SimplifiedMeshOutput`] and reported with an `entity` of
+/// `None`.
+#[derive(Message, Debug, Clone)]
+pub struct MeshProcessRequest {
+    pub request_tag: Option<RequestTag>,
+    pub mesh: Handle<Mesh>,
+    pub processing: MeshProcessing,
+}
+
+/// What a [`MeshProcessRequest`] does with its mesh.
+#[derive(Debug, Clone)]
+pub enum MeshProcessing {
+    /// Simplifies it like [`SimplifyMesh`].
+    Simplify(SimplifyParams),
+    /// Generates its levels of detail like [`GenerateMeshLods`].
+    LodChain(LodChainParams),
+}
+
+/// Where [`MeshSimplifyPlugin`] puts simplified meshes and LOD0 of the levels it generates.
+#[derive(Resource, Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum SimplifiedMeshOutput {
+    /// Adds the simplified mesh as a new asset and points the entity at it, the source is left
+    /// untouched.
+    #[default]
+    NewAsset,
+    /// Replaces the source asset, which also changes every other entity using it.
+    Overwrite,
+}
+
+/// How [`MeshSimplifyPlugin`] runs the requests.
+#[derive(Resource, Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum SimplifyExecution {
+    /// On the [`AsyncComputeTaskPool`], results are swapped in on the first frame after their
+    /// task finishes.
+    #[default]
+    Async,
+    /// One after the other on the main thread, in the frame their mesh is loaded.
+    Immediate,
+    /// One after the other on the main thread until the budget of the frame is spent, the rest
+    /// wait for the next frame. Every frame runs at least one request, so meshes taking longer
+    /// than the budget are still processed.
+    TimeSliced(Duration),
+}
+
+/// Sent by [`MeshSimplifyPlugin`] once the simplified mesh of `entity` is swapped in.
+#[derive(Message, Debug, Clone)]
+pub struct MeshSimplified {
+    pub entity: Entity,
+    /// Mesh the entity uses now.
+    pub mesh: Handle<Mesh>,
+    pub report: SimplifyReport,
+}
+
+/// Sent by [`MeshSimplifyPlugin`] when the mesh of `entity` failed to simplify or its result had
+/// to be dropped, the entity keeps its mesh. Cancelled requests are only reported by
+/// [`SimplificationCompleted`].
+#[derive(Message, Debug, Copy, Clone)]
+pub struct MeshSimplifyFailed {
+    pub entity: Entity,
+    pub error: OptError,
+}
+
+/// Requests of [`MeshSimplifyPlugin`], waiting for their mesh to load or being processed.
+/// Dropping a task cancels it.
+#[derive(Resource, Default)]
+struct SimplifyRequests {
+    queued: Vec<(Request, MeshProcessing)>,
+    running: Vec<(Request, Work)>,
+}
+
+#[derive(Clone)]
+struct Request {
+    entity: Option<Entity>,
+    request_tag: Option<RequestTag>,
+    source: Handle<Mesh>,
+    lods: bool,
+}
+
+impl Request {
+    fn new(
+        entity: Option<Entity>,
+        request_tag: Option<RequestTag>,
+        source: &Handle<Mesh>,
+        processing: &MeshProcessing,
+    ) -> Self {
+        Request {
+            entity,
+            request_tag,
+            source: source.clone(),
+            lods: matches!(processing, MeshProcessing::LodChain(_)),
+        }
+    }
+}
+
+enum Work {
+    Task(Task<Result<Processed, OptError>>),
+    /// Processed on the main thread, taken once reported.
+    Done(Option<Box<Result<Processed, OptError>>>),
+}
+
+enum Processed {
+    Simplified(Mesh, SimplifyReport),
+    Lods(LodChain),
+}
+
+/// Completion messages of the requests.
+#[derive(SystemParam)]
+struct Completions<'w> {
+    simplified: MessageWriter<'w, SimplificationCompleted>,
+    lods: MessageWriter<'w, LodChainCompleted>,
+}
+
+impl Completions<'_> {
+    /// Reports `request` as failed with `error`.
+    fn fail(&mut self, request: &Request, error: OptError) {
+        if request.lods {
+            self.lods.write(LodChainCompleted {
+                request_tag: request.request_tag,
+                entity: request.entity,
+                levels: Vec::new(),
+                result: Err(error),
+            });
+        } else {
+            self.simplified.write(SimplificationCompleted {
+                request_tag: request.request_tag,
+                entity: request.entity,
+                mesh: request.source.clone(),
+                result: Err(error),
+            });
+        }
+    }
+}
+
+impl SimplifyRequests {
+    /// Queues `request`, cancelling the pending request of the same kind for its entity.
+    fn queue(
+        &mut self,
+        request: Request,
+        processing: MeshProcessing,
+        completions: &mut Completions,
+    ) {
+        if request.entity.is_some() {
+            self.cancel(
+                |pending| pending.entity == request.entity && pending.lods == request.lods,
+                completions,
+            );
+        }
+        self.queued.push((request, processing));
+    }
+
+    /// Drops the requests `cancelled` holds for and reports them as [`OptError::Cancelled`].
+    fn cancel(&mut self, cancelled: impl Fn(&Request) -> bool, completions: &mut Completions) {
+        let mut keep = |request: &Request| {
+            if cancelled(request) {
+                completions.fail(request, OptError::Cancelled);
+                return false;
+            }
+            true
+        };
+        self.queued.retain(|(request, _)| keep(request));
+        self.running.retain(|(request, _)| keep(request));
+    }
+}
+
+fn queue_simplify_requests(
+    mut commands: Commands,
+    simplify: Query<(Entity, &Mesh3d, &SimplifyMesh, Option<&RequestTag>)>,
+    lods: Query<(Entity, &Mesh3d, &GenerateMeshLods, Option<&RequestTag>)>,
+    mut messages: MessageReader<MeshProcessRequest>,
+    mut requests: ResMut<SimplifyRequests>,
+    mut completions: Completions,
+) {
+    for (entity, mesh3d, request, request_tag) in &simplify {
+        let processing = MeshProcessing::Simplify(request.0.clone());
+        let request = Request::new(Some(entity), request_tag.copied(), &mesh3d.0, &processing);
+        requests.queue(request, processing, &mut completions);
+        commands.entity(entity).remove::<SimplifyMesh>();
+    }
+    for (entity, mesh3d, request, request_tag) in &lods {
+        let processing = MeshProcessing::LodChain(request.0.clone());
+        let request = Request::new(Some(entity), request_tag.copied(), &mesh3d.0, &processing);
+        requests.queue(request, processing, &mut completions);
+        commands.entity(entity).remove::<GenerateMeshLods>();
+    }
+    for message in messages.read() {
+        let request = Request::new(
+            None,
+            message.request_tag,
+            &message.mesh,
+            &message.processing,
+        );
+        requests.queue(request, message.processing.clone(), &mut completions);
+    }
+}
+
+fn start_simplify_tasks(
+    mut requests: ResMut<SimplifyRequests>,
+    meshes: Res<Assets<Mesh>>,
+    execution: Res<SimplifyExecution>,
+    entities: &Entities,
+    mut completions: Completions,
+) {
+    requests.cancel(
+        |request| {
+            request
+                .entity
+                .is_some_and(|entity| !entities.contains(entity))
+        },
+        &mut completions,
+    );
+
+    let start = Instant::now();
+    let mut processed_any = false;
+    let SimplifyRequests { queued, running } = &mut *requests;
+    queued.retain(|(request, processing)| {
+        if let SimplifyExecution::TimeSliced(budget) = *execution
+            && processed_any
+            && start.elapsed() >= budget
+        {
+            return true;
+        }
+        // Waits for the mesh to load.
+        let Some(mesh) = meshes.get(&request.source) else {
+            return true;
+        };
+
+        let mesh = mesh.clone();
+        let processing = processing.clone();
+        let work = match *execution {
+            SimplifyExecution::Async => Work::Task(
+                AsyncComputeTaskPool::get().spawn(async move { process(mesh, &processing) }),
+            ),
+            SimplifyExecution::Immediate | SimplifyExecution::TimeSliced(_) => {
+                processed_any = true;
+                Work::Done(Some(Box::new(process(mesh, &processing))))
+            }
+        };
+        running.push((request.clone(), work));
+        false
+    });
+}
+
+fn process(mut mesh: Mesh, processing: &MeshProcessing) -> Result<Processed, OptError> {
+    match processing {
+        MeshProcessing::Simplify(params) => {
+            let report = mesh.simplify_with_report(params)?;
+            Ok(Processed::Simplified(mesh, report))
+        }
+        MeshProcessing::LodChain(params) => mesh.generate_lod_chain(params).map(Processed::Lods),
+    }
+}
+
+/// Result of a request swapped in, with the mesh now in the source's place.
+enum Finished {
+    Simplified(SimplifyReport),
+    Lods(MeshLods, LodChainReport),
+}
+
+#[allow(clippy::too_many_arguments)]
+fn finish_simplify_tasks(
+    mut commands: Commands,
+    mut requests: ResMut<SimplifyRequests>,
+    mut query: Query<&mut Mesh3d>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    entities: &Entities,
+    output: Res<SimplifiedMeshOutput>,
+    mut simplified: MessageWriter<MeshSimplified>,
+    mut failed: MessageWriter<MeshSimplifyFailed>,
+    mut modified: MessageWriter<MeshModified>,
+    mut completions: Completions,
+) {
+    requests.running.retain_mut(|(request, work)| {
+        let result = match work {
+            Work::Task(task) => check_ready(task),
+            Work::Done(result) => result.take().map(|result| *result),
+        };
+        let Some(result) = result else {
+            return true;
+        };
+        if request
+            .entity
+            .is_some_and(|entity| !entities.contains(entity))
+        {
+            completions.fail(request, OptError::Cancelled);
+            return false;
+        }
+
+        let result = result.and_then(|processed| {
+            let mut mesh3d = match request.entity {
+                Some(entity) => Some(
+                    query
+                        .get_mut(entity)
+                        .ok()
+                        .filter(|mesh3d| mesh3d.id() == request.source.id())
+                        .ok_or(OptError::MissingMesh)?,
+                ),
+                None => None,
+            };
+            if !meshes.contains(&request.source) {
+                return Err(OptError::MissingMesh);
+            }
+            let handle = match *output {
+                SimplifiedMeshOutput::NewAsset => meshes.reserve_handle(),
+                SimplifiedMeshOutput::Overwrite => request.source.clone(),
+            };
+            let (mesh, finished) = match processed {
+                Processed::Simplified(mesh, report) => (Some(mesh), Finished::Simplified(report)),
+                Processed::Lods(chain) => {
+                    let report = chain.report.clone();
+                    let (lods, lod0) = MeshLods::from_chain(chain, &handle, &mut meshes);
+                    (lod0, Finished::Lods(lods, report))
+                }
+            };
+            meshes
+                .insert(&handle, mesh.ok_or(OptError::MissingMesh)?)
+                .map_err(|_| OptError::MissingMesh)?;
+            if let Some(mesh3d) = &mut mesh3d {
+                mesh3d.0 = handle.clone();
+            }
+            Ok((handle, finished))
+        });
+
+        let (request_tag, entity) = (request.request_tag, request.entity);
+        match result {
+            Ok((mesh, Finished::Simplified(report))) => {
+                modified.write(MeshModified(mesh.id()));
+                if let Some(entity) = entity {
+                    simplified.write(MeshSimplified {
+                        entity,
+                        mesh: mesh.clone(),
+                        report,
+                    });
+                }
+                completions.simplified.write(SimplificationCompleted {
+                    request_tag,
+                    entity,
+                    mesh,
+                    result: Ok(report),
+                });
+            }
+            Ok((mesh, Finished::Lods(lods, report))) => {
+                modified.write(MeshModified(mesh.id()));
+                let levels = lods.levels.iter().map(|(level, _)| level.clone()).collect();
+                if let Some(entity) = entity {
+                    commands.entity(entity).insert(lods);
+                }
+                completions.lods.write(LodChainCompleted {
+                    request_tag,
+                    entity,
+                    levels,
+                    result: Ok(report),
+                });
+            }
+            Err(error) => {
+                if let Some(entity) = entity.filter(|_| !request.lods) {
+                    failed.write(MeshSimplifyFailed { entity, error });
+                }
+                completions.fail(request, error);
+            }
+        }
+        false
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{
+        app::{PostUpdate, TaskPoolPlugin},
+        asset::{AssetApp, AssetPlugin, RenderAssetUsages},
+        mesh::PrimitiveTopology,
+    };
+
+    use super::*;
+    use crate::{
+        TargetIndices,
+        test_util::{indices, sphere},
+    };
+
+    /// App simplifying in `PostUpdate` through the schedule of the `MeshoptPlugin`.
+    fn app(execution: SimplifyExecution) -> App {
+        let mut app = App::new();
+        app.add_plugins((
+            TaskPoolPlugin::default(),
+            AssetPlugin::default(),
+            MeshoptPlugin::default().in_schedule(PostUpdate),
+            MeshSimplifyPlugin,
+        ))
+        .init_asset::<Mesh>()
+        .insert_resource(execution);
+        app
+    }
+
+    fn half() -> SimplifyParams {
+        SimplifyParams {
+            target_index_count: TargetIndices::Multiplier(0.5),
+            max_error: 1.0,
+            ..Default::default()
+        }
+    }
+
+    fn add_mesh(app: &mut App, mesh: Mesh) -> Handle<Mesh> {
+        app.world_mut().resource_mut::<Assets<Mesh>>().add(mesh)
+    }
+
+    /// Messages of type `M` sent by an update.
+    fn update<M: Message>(app: &mut App) -> Vec<M> {
+        app.update();
+        app.world_mut()
+            .resource_mut::<Messages<M>>()
+            .drain()
+            .collect()
+    }
+
+    /// Updates `app` until `done` holds for the `M` messages sent so far.
+    fn update_until<M: Message>(app: &mut App, done: impl Fn(&[M]) -> bool) -> Vec<M> {
+        let mut messages = Vec::new();
+        for _ in 0..1000 {
+            messages.extend(update(app));
+            if done(&messages) {
+                return messages;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        panic!("timed out after {} messages", messages.len());
+    }
+
+    #[test]
+    fn spawned_mesh_is_swapped_for_the_simplified_one() {
+        let mut app = app(SimplifyExecution::Async);
+        let source = add_mesh(&mut app, sphere(4));
+        let entity = app
+            .world_mut()
+            .spawn((Mesh3d(source.clone()), SimplifyMesh(half())))
+            .id();
+
+        let messages = update_until::<MeshSimplified>(&mut app, |messages| !messages.is_empty());
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].entity, entity);
+
+        let world = app.world();
+        assert!(!world.entity(entity).contains::<SimplifyMesh>());
+        let mesh3d = world.entity(entity).get::<Mesh3d>().unwrap();
+        assert_ne!(mesh3d.id(), source.id());
+        assert_eq!(mesh3d.0, messages[0].mesh);
+        let meshes = world.resource::<Assets<Mesh>>();
+        let simplified = indices(meshes.get(mesh3d).unwrap()).len();
+        assert_eq!(simplified, messages[0].report.triangles_after() * 3);
+        assert!(simplified <= indices(meshes.get(&source).unwrap()).len() / 2);
+    }
+
+    #[test]
+    fn every_request_completes_exactly_once() {
+        let mut app = app(SimplifyExecution::Async);
+        let sphere_mesh = add_mesh(&mut app, sphere(4));
+        let lines = add_mesh(
+            &mut app,
+            Mesh::new(PrimitiveTopology::LineList, RenderAssetUsages::default())
+                .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, vec![[0.0f32; 3]; 2]),
+        );
+        // Not loaded yet, keeps its requests queued.
+        let pending = app
+            .world_mut()
+            .resource_mut::<Assets<Mesh>>()
+            .reserve_handle();
+
+        let world = app.world_mut();
+        world.spawn((
+            Mesh3d(sphere_mesh.clone()),
+            SimplifyMesh(half()),
+            RequestTag(1),
+        ));
+        world.spawn((Mesh3d(lines), SimplifyMesh(half()), RequestTag(2)));
+        let lods = world
+            .spawn((
+                Mesh3d(sphere_mesh.clone()),
+                GenerateMeshLods(LodChainParams::default()),
+                RequestTag(3),
+            ))
+            .id();
+        world.write_message(MeshProcessRequest {
+            request_tag: Some(RequestTag(4)),
+            mesh: sphere_mesh.clone(),
+            processing: MeshProcessing::Simplify(half()),
+        });
+        let replaced = world
+            .spawn((Mesh3d(pending.clone()), SimplifyMesh(half()), RequestTag(5)))
+            .id();
+        let despawned = world
+            .spawn((Mesh3d(pending.clone()), SimplifyMesh(half()), RequestTag(7)))
+            .id();
+        app.update();
+
+        let world = app.world_mut();
+        world
+            .entity_mut(replaced)
+            .insert((SimplifyMesh(half()), RequestTag(6)));
+        world.despawn(despawned);
+        world
+            .resource_mut::<Assets<Mesh>>()
+            .insert(&pending, sphere(2))
+            .unwrap();
+
+        let mut chains = Vec::new();
+        let mut done = Vec::new();
+        for _ in 0..1000 {
+            done.extend(update::<SimplificationCompleted>(&mut app));
+            chains.extend(
+                app.world_mut()
+                    .resource_mut::<Messages<LodChainCompleted>>()
+                    .drain(),
+            );
+            if done.len() >= 6 && !chains.is_empty() {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        // Nothing is reported twice.
+        done.extend(update::<SimplificationCompleted>(&mut app));
+
+        let mut tags: Vec<u64> = done
+            .iter()
+            .map(|completed| completed.request_tag.unwrap().0)
+            .collect();
+        tags.sort();
+        assert_eq!(tags, [1, 2, 4, 5, 6, 7]);
+        let result = |tag| {
+            let completed = done
+                .iter()
+                .find(|completed| completed.request_tag == Some(RequestTag(tag)))
+                .unwrap();
+            (completed.entity, completed.result.as_ref().map(|_| ()))
+        };
+        assert!(matches!(result(1), (Some(_), Ok(()))));
+        assert!(matches!(
+            result(2),
+            (Some(_), Err(OptError::UnsupportedPrimitiveTopology(_)))
+        ));
+        assert!(matches!(result(4), (None, Ok(()))));
+        assert!(
+            matches!(result(5), (Some(entity), Err(OptError::Cancelled)) if entity == replaced)
+        );
+        assert!(matches!(result(6), (Some(entity), Ok(())) if entity == replaced));
+        assert!(
+            matches!(result(7), (Some(entity), Err(OptError::Cancelled)) if entity == despawned)
+        );
+
+        assert_eq!(chains.len(), 1);
+        assert_eq!(chains[0].request_tag, Some(RequestTag(3)));
+        assert_eq!(chains[0].entity, Some(lods));
+        let report = chains[0].result.as_ref().unwrap();
+        assert_eq!(report.levels.len(), chains[0].levels.len());
+        let world = app.world();
+        assert_eq!(
+            world.entity(lods).get::<Mesh3d>().unwrap().0,
+            chains[0].levels[0]
+        );
+        assert_eq!(
+            world.entity(lods).get::<MeshLods>().unwrap().levels.len(),
+            report.levels.len()
+        );
+    }
+
+    #[test]
+    fn time_sliced_requests_complete_one_per_frame() {
+        let mut app = app(SimplifyExecution::TimeSliced(Duration::ZERO));
+        let mesh = add_mesh(&mut app, sphere(3));
+        for tag in 0..3 {
+            app.world_mut()
+                .spawn((Mesh3d(mesh.clone()), SimplifyMesh(half()), RequestTag(tag)));
+        }
+        let mut tags = Vec::new();
+        for _ in 0..3 {
+            let done = update::<SimplificationCompleted>(&mut app);
+            assert_eq!(done.len(), 1);
+            assert!(done[0].result.is_ok());
+            tags.push(done[0].request_tag.unwrap().0);
+        }
+        assert!(update::<SimplificationCompleted>(&mut app).is_empty());
+        tags.sort();
+        assert_eq!(tags, [0, 1, 2]);
+    }
+}
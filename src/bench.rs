@@ -0,0 +1,201 @@
+use std::{fmt::Display, time::Duration};
+
+use bevy::{mesh::Mesh, platform::time::Instant};
+
+use crate::{MeshExt, MeshStats, OptError, OptimizeSettings, SimplifyParams, TargetIndices};
+
+/// Named combination of passes [`bench_presets`] runs on a copy of the mesh, simplification
+/// first. A preset without either pass measures the mesh as it is.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchPreset {
+    pub name: String,
+    pub simplify: Option<SimplifyParams>,
+    pub optimize: Option<OptimizeSettings>,
+}
+
+impl BenchPreset {
+    pub fn new(
+        name: impl Into<String>,
+        simplify: Option<SimplifyParams>,
+        optimize: Option<OptimizeSettings>,
+    ) -> Self {
+        BenchPreset {
+            name: name.into(),
+            simplify,
+            optimize,
+        }
+    }
+
+    /// Every combination of `max_errors` and `targets` applied to `params`, errors varying
+    /// slowest, named like `0.01/Multiplier(0.5)`. Each preset optimizes with `optimize`.
+    pub fn grid(
+        params: &SimplifyParams,
+        max_errors: &[f32],
+        targets: &[TargetIndices],
+        optimize: Option<OptimizeSettings>,
+    ) -> Vec<BenchPreset> {
+        max_errors
+            .iter()
+            .flat_map(|&max_error| {
+                targets.iter().map(move |&target_index_count| {
+                    BenchPreset::new(
+                        format!("{max_error}/{target_index_count:?}"),
+                        Some(SimplifyParams {
+                            max_error,
+                            target_index_count,
+                            ..params.clone()
+                        }),
+                        optimize,
+                    )
+                })
+            })
+            .collect()
+    }
+}
+
+/// What a [`BenchPreset`] turned the mesh into and how long it took.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PresetMeasurement {
+    /// Error reported by the simplifier, relative to the mesh extents unless
+    /// `SimplifyOptions::ErrorAbsolute` was used. `0.0` without simplification.
+    pub result_error: f32,
+    /// `result_error` in mesh units, see
+    /// [`SimplifyReport::result_error_absolute`](crate::SimplifyReport::result_error_absolute).
+    pub result_error_absolute: f32,
+    /// The resulting mesh as measured by [`MeshExt::analyze`], including its triangle count and
+    /// ACMR.
+    pub stats: MeshStats,
+    /// Fastest simplification of the runs, zero without simplification.
+    pub simplify_time: Duration,
+    /// Fastest optimization of the runs, zero without optimization.
+    pub optimize_time: Duration,
+}
+
+impl PresetMeasurement {
+    pub fn triangles(&self) -> usize {
+        self.stats.triangles
+    }
+
+    pub fn acmr(&self) -> f32 {
+        self.stats.acmr
+    }
+
+    /// Wall time of both passes.
+    pub fn time(&self) -> Duration {
+        self.simplify_time + self.optimize_time
+    }
+}
+
+/// Outcome of one preset of [`bench_presets`].
+#[derive(Debug, Clone)]
+pub struct PresetResult {
+    pub name: String,
+    pub result: Result<PresetMeasurement, OptError>,
+}
+
+/// Measurements of [`bench_presets`], printed as one line per preset.
+#[derive(Debug, Clone)]
+pub struct BenchReport {
+    /// The mesh before any preset ran.
+    pub source: MeshStats,
+    /// One result per preset, in the order they were given.
+    pub presets: Vec<PresetResult>,
+}
+
+impl BenchReport {
+    /// Preset with the fewest triangles whose `result_error` is at most `max_error`, e.g. to pick
+    /// the most aggressive setting that still looks right.
+    pub fn fewest_triangles_within(&self, max_error: f32) -> Option<&PresetResult> {
+        self.presets
+            .iter()
+            .filter(|preset| {
+                preset
+                    .result
+                    .is_ok_and(|measurement| measurement.result_error <= max_error)
+            })
+            .min_by_key(|preset| preset.result.map_or(usize::MAX, |m| m.triangles()))
+    }
+}
+
+impl Display for BenchReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "source: {}", self.source)?;
+        for preset in &self.presets {
+            match &preset.result {
+                Ok(measurement) => writeln!(
+                    f,
+                    "{}: {} triangles, error {:.4} ({:.4} mesh units), ACMR {:.3}, {:.2} ms",
+                    preset.name,
+                    measurement.triangles(),
+                    measurement.result_error,
+                    measurement.result_error_absolute,
+                    measurement.acmr(),
+                    measurement.time().as_secs_f64() * 1000.0,
+                )?,
+                Err(err) => writeln!(f, "{}: {err}", preset.name)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Runs every preset on a copy of `mesh` `runs` times, at least once, without modifying it, and
+/// measures the results, e.g. to pick production LOD settings empirically. Wall times are the
+/// fastest of the runs, which are otherwise identical. Presets that fail are reported with their
+/// error, while a mesh that can't be analyzed at all fails like [`MeshExt::analyze`].
+pub fn bench_presets(
+    mesh: &Mesh,
+    presets: &[BenchPreset],
+    runs: usize,
+) -> Result<BenchReport, OptError> {
+    let source = mesh.analyze(None)?;
+    let presets = presets
+        .iter()
+        .map(|preset| PresetResult {
+            name: preset.name.clone(),
+            result: bench_preset(mesh, preset, runs.max(1)),
+        })
+        .collect();
+    Ok(BenchReport { source, presets })
+}
+
+fn bench_preset(
+    mesh: &Mesh,
+    preset: &BenchPreset,
+    runs: usize,
+) -> Result<PresetMeasurement, OptError> {
+    let mut simplify_time = Duration::MAX;
+    let mut optimize_time = Duration::MAX;
+    let mut measured = None;
+    for _ in 0..runs {
+        let mut copy = mesh.clone();
+        let start = Instant::now();
+        let report = preset
+            .simplify
+            .as_ref()
+            .map(|params| copy.simplify_with_report(params))
+            .transpose()?;
+        let simplified = Instant::now();
+        if let Some(settings) = &preset.optimize {
+            copy.optimize(settings)?;
+        }
+        simplify_time = simplify_time.min(simplified - start);
+        optimize_time = optimize_time.min(simplified.elapsed());
+        measured = Some((copy, report));
+    }
+
+    let (result, report) = measured.expect("at least one run");
+    Ok(PresetMeasurement {
+        result_error: report.map_or(0.0, |report| report.result_error),
+        result_error_absolute: report.map_or(0.0, |report| report.result_error_absolute()),
+        stats: result.analyze(None)?,
+        simplify_time: preset
+            .simplify
+            .as_ref()
+            .map_or(Duration::ZERO, |_| simplify_time),
+        optimize_time: preset
+            .optimize
+            .as_ref()
+            .map_or(Duration::ZERO, |_| optimize_time),
+    })
+}
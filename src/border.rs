@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+
+use bevy::{
+    math::Vec3,
+    mesh::{Indices, Mesh, VertexAttributeValues},
+};
+
+use crate::{
+    OptError, adjacency::TriangleAdjacency, mesh_indices, mesh_positions, vertex::append_vertices,
+};
+
+/// Which open boundary edges of a mesh to select.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum BorderSelection {
+    /// Every open boundary edge.
+    #[default]
+    All,
+    /// Only boundary edges lying on a face of the mesh's bounding box, e.g. the seams of a
+    /// terrain chunk, leaving holes in the interior alone.
+    BoundingBox { epsilon: f32 },
+}
+
+/// Open boundary edges of the mesh, i.e. edges used by a single triangle, oriented the same way as
+/// in that triangle.
+///
+/// Vertices are compared by position, so attribute seams (e.g. UV seams) aren't treated as
+/// borders.
+pub(crate) fn border_edges(
+    mesh: &Mesh,
+    selection: BorderSelection,
+) -> Result<Vec<[u32; 2]>, OptError> {
+    let indices = mesh_indices(mesh)?;
+    let positions = mesh_positions(mesh)?;
+    let adjacency = TriangleAdjacency::new(indices, positions);
+
+    let mut border: Vec<[u32; 2]> = adjacency
+        .welded_edges()
+        .filter(|(_, triangles)| triangles.len() == 1)
+        .map(|((a, b), triangles)| {
+            // The edge as walked by its triangle.
+            let corners = &indices[triangles[0] as usize * 3..][..3];
+            (0..3)
+                .map(|corner| [corners[corner], corners[(corner + 1) % 3]])
+                .find(|edge| {
+                    let welded = edge.map(|vertex| adjacency.remap[vertex as usize]);
+                    welded == [a, b] || welded == [b, a]
+                })
+                .expect("every welded edge comes from one of its triangles")
+        })
+        .collect();
+
+    if let BorderSelection::BoundingBox { epsilon } = selection {
+        let (min, max) = positions.iter().fold(
+            (Vec3::splat(f32::INFINITY), Vec3::splat(f32::NEG_INFINITY)),
+            |(min, max), position| {
+                let position = Vec3::from_array(*position);
+                (min.min(position), max.max(position))
+            },
+        );
+
+        border.retain(|edge| {
+            let a = Vec3::from_array(positions[edge[0] as usize]);
+            let b = Vec3::from_array(positions[edge[1] as usize]);
+            (0..3).any(|axis| {
+                let on = |bound: f32| {
+                    (a[axis] - bound).abs() <= epsilon && (b[axis] - bound).abs() <= epsilon
+                };
+                on(min[axis]) || on(max[axis])
+            })
+        });
+    }
+
+    border.sort_unstable();
+    Ok(border)
+}
+
+/// Extrudes the selected border edges by `depth` along `direction`, see
+/// [`MeshExt::generate_skirt`](crate::MeshExt::generate_skirt).
+pub(crate) fn generate_skirt(
+    mesh: &mut Mesh,
+    direction: Vec3,
+    depth: f32,
+    selection: BorderSelection,
+) -> Result<usize, OptError> {
+    let edges = border_edges(mesh, selection)?;
+    if edges.is_empty() {
+        return Ok(0);
+    }
+
+    let vertex_count = mesh.count_vertices() as u32;
+    let mut sources = Vec::new();
+    let mut skirt_vertex = HashMap::new();
+    for &vertex in edges.iter().flatten() {
+        skirt_vertex.entry(vertex).or_insert_with(|| {
+            sources.push(vertex);
+            vertex_count + sources.len() as u32 - 1
+        });
+    }
+
+    append_vertices(mesh, &sources);
+    let offset = direction.normalize_or_zero() * depth;
+    if let Some(VertexAttributeValues::Float32x3(positions)) =
+        mesh.attribute_mut(Mesh::ATTRIBUTE_POSITION)
+    {
+        for position in &mut positions[vertex_count as usize..] {
+            *position = (Vec3::from_array(*position) + offset).to_array();
+        }
+    }
+
+    let Some(Indices::U32(indices)) = mesh.indices_mut() else {
+        unreachable!("indices were validated by `border_edges`");
+    };
+    indices.reserve(edges.len() * 6);
+    for &[a, b] in &edges {
+        let (skirt_a, skirt_b) = (skirt_vertex[&a], skirt_vertex[&b]);
+        // The parent triangle walks the edge from `a` to `b`, a neighbor across it would walk it
+        // from `b` to `a`.
+        indices.extend_from_slice(&[b, a, skirt_a, b, skirt_a, skirt_b]);
+    }
+
+    Ok(edges.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::asset::Handle;
+
+    use super::*;
+    use crate::{
+        MeshExt, SimplifyParams, TargetIndices,
+        test_util::{grid, indices, positions, sphere},
+    };
+
+    #[test]
+    fn skirt_refuses_morph_targets() {
+        let mut mesh = sphere(2);
+        mesh.set_morph_targets(Handle::default());
+        let result = mesh.generate_skirt(Vec3::NEG_Y, 0.1, BorderSelection::All);
+        assert!(matches!(result, Err(OptError::MorphTargetsUnsupported)));
+    }
+
+    #[test]
+    fn skirt_extrudes_every_border_edge_with_consistent_winding() {
+        let mut chunk = grid(8);
+        chunk
+            .simplify_with_report(&SimplifyParams {
+                target_index_count: TargetIndices::Multiplier(0.5),
+                max_error: 1.0,
+                ..Default::default()
+            })
+            .unwrap();
+        let triangles = indices(&chunk).len() / 3;
+        let borders = chunk.border_edges(BorderSelection::All).unwrap();
+        assert!(!borders.is_empty());
+
+        let extruded = chunk
+            .generate_skirt(Vec3::NEG_Y, 0.25, BorderSelection::All)
+            .unwrap();
+        assert_eq!(extruded, borders.len());
+        let skirted = indices(&chunk);
+        assert_eq!(skirted.len() / 3, triangles + 2 * borders.len());
+
+        // Consistently wound triangles never walk an edge in the same direction twice.
+        let mut directed: Vec<[u32; 2]> = skirted
+            .chunks_exact(3)
+            .flat_map(|t| [[t[0], t[1]], [t[1], t[2]], [t[2], t[0]]])
+            .collect();
+        directed.sort_unstable();
+        let len = directed.len();
+        directed.dedup();
+        assert_eq!(directed.len(), len);
+
+        // The skirt walls face out of the chunk, like the chunk faces up.
+        let positions = positions(&chunk);
+        let center = Vec3::new(0.5, 0.0, 0.5);
+        for t in skirted[triangles * 3..].chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| Vec3::from_array(positions[t[i] as usize]));
+            let normal = (b - a).cross(c - a);
+            let outward = ((a + b + c) / 3.0 - center).with_y(0.0);
+            assert!(normal.dot(outward) > 0.0);
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy::{
+    app::{App, Plugin, PostUpdate},
+    asset::{AssetId, Assets},
+    camera::{
+        primitives::{Aabb, MeshAabb},
+        visibility::{NoFrustumCulling, VisibilitySystems},
+    },
+    ecs::prelude::*,
+    mesh::{Mesh, Mesh3d},
+};
+
+/// Keeps the [`Aabb`] of entities in step with the meshes they use when those are modified,
+/// Bevy only computes it once for entities that don't have one yet. Added by
+/// [`MeshoptPlugin`](crate::MeshoptPlugin) and [`MeshSimplifyPlugin`](crate::MeshSimplifyPlugin),
+/// which send [`MeshModified`] for every mesh they produce.
+pub struct MeshBoundsPlugin;
+
+impl Plugin for MeshBoundsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<MeshModified>().add_systems(
+            PostUpdate,
+            update_modified_mesh_aabbs.before(VisibilitySystems::CalculateBounds),
+        );
+    }
+}
+
+/// Sent when the mesh asset `0` changed shape, e.g. after simplifying it in place with
+/// [`MeshExt::simplify`](crate::MeshExt::simplify), so [`update_modified_mesh_aabbs`] refreshes
+/// the bounds of every entity using it.
+#[derive(Message, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct MeshModified(pub AssetId<Mesh>);
+
+/// Recomputes the [`Aabb`] of every entity whose mesh was reported by a [`MeshModified`] message
+/// this frame. Runs before Bevy computes the bounds of new entities, so entities spawned with a
+/// modified mesh later in the same frame get fresh bounds either way. Entities whose mesh isn't
+/// loaded lose their [`Aabb`] until it is.
+pub fn update_modified_mesh_aabbs(
+    mut commands: Commands,
+    mut modified: MessageReader<MeshModified>,
+    meshes: Res<Assets<Mesh>>,
+    query: Query<(Entity, &Mesh3d), Without<NoFrustumCulling>>,
+) {
+    let modified: HashSet<AssetId<Mesh>> = modified.read().map(|modified| modified.0).collect();
+    if modified.is_empty() {
+        return;
+    }
+
+    // Computed once for every mesh, however many entities share it.
+    let mut aabbs = HashMap::new();
+    for (entity, mesh3d) in &query {
+        let id = mesh3d.id();
+        if !modified.contains(&id) {
+            continue;
+        }
+
+        let aabb = *aabbs
+            .entry(id)
+            .or_insert_with(|| meshes.get(id).and_then(MeshAabb::compute_aabb));
+        match aabb {
+            Some(aabb) => commands.entity(entity).try_insert(aabb),
+            None => commands.entity(entity).try_remove::<Aabb>(),
+        };
+    }
+}
@@ -0,0 +1,455 @@
+use std::{
+    fs::{self, File},
+    io::{self, Read, Write},
+    path::PathBuf,
+    time::SystemTime,
+};
+
+use bevy::{ecs::resource::Resource, mesh::Mesh, tasks::IoTaskPool};
+
+use crate::{
+    FallbackPolicy, HardEdges, OptError, SeamLocks, SilhouetteLocks, SimplifyParams, SimplifyPath,
+    SymmetryPlane, TargetIndices, UvWeighting, mesh_positions,
+    remap::{FNV_OFFSET, fnv1a, mesh_content_hash},
+    validate_indices,
+    vertex_lock::user_vertex_locks,
+};
+
+/// Identifies a cache entry, followed by the format version.
+const MAGIC: [u8; 4] = *b"MSCE";
+const VERSION: u32 = 2;
+const EXTENSION: &str = "msce";
+
+/// Where [`SimplifyCache`] keeps its entries, see [`MeshoptPlugin::cache`](crate::MeshoptPlugin::cache).
+#[derive(Debug, Clone, PartialEq)]
+pub struct CacheSettings {
+    /// Directory holding the entries, created if it doesn't exist.
+    pub directory: PathBuf,
+    /// Total size of the entries in bytes, the least recently used ones are evicted beyond it.
+    pub max_size: u64,
+}
+
+impl CacheSettings {
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        CacheSettings {
+            directory: directory.into(),
+            max_size: 256 * 1024 * 1024,
+        }
+    }
+}
+
+/// Simplification result read from the cache.
+pub(crate) struct CachedSimplify {
+    pub indices: Vec<u32>,
+    pub error: f32,
+    pub path: SimplifyPath,
+}
+
+/// On-disk cache of the index buffers produced by
+/// [`simplify_meshes`](crate::simplify_meshes), keyed by the content of the mesh and the
+/// simplification params.
+///
+/// Entries are stored with meshoptimizer's index buffer encoding and written in the background.
+/// Entries that fail to load are treated as misses and deleted.
+#[derive(Resource, Debug, Clone)]
+pub struct SimplifyCache {
+    settings: CacheSettings,
+}
+
+impl SimplifyCache {
+    pub fn new(settings: CacheSettings) -> Self {
+        SimplifyCache { settings }
+    }
+
+    pub fn settings(&self) -> &CacheSettings {
+        &self.settings
+    }
+
+    /// Key of the result of simplifying `mesh` with `params`. The locks are resolved for `mesh`
+    /// first, so predicates are keyed by the vertices they lock, failing where simplifying would.
+    pub(crate) fn key(mesh: &Mesh, params: &SimplifyParams) -> Result<u64, OptError> {
+        let locks = user_vertex_locks(mesh, mesh_positions(mesh)?, params)?;
+        let mut key = KeyHasher(FNV_OFFSET);
+        key.u64(mesh_content_hash(mesh));
+        key.params(params, locks.as_deref());
+        Ok(key.0)
+    }
+
+    fn path(&self, key: u64) -> PathBuf {
+        self.settings
+            .directory
+            .join(format!("{key:016x}.{EXTENSION}"))
+    }
+
+    /// Loads the entry for `key`, deleting it if it can't be read.
+    pub(crate) fn load(&self, key: u64, vertex_count: usize) -> Option<CachedSimplify> {
+        let path = self.path(key);
+        let file = File::options().read(true).write(true).open(&path).ok()?;
+        match read_entry(&file, key, vertex_count) {
+            Ok(entry) => {
+                // Keeps recently used entries from being evicted.
+                let _ = file.set_modified(SystemTime::now());
+                Some(entry)
+            }
+            Err(_) => {
+                drop(file);
+                let _ = fs::remove_file(&path);
+                None
+            }
+        }
+    }
+
+    /// Writes the entry for `key` in the background, evicting old entries afterwards.
+    pub(crate) fn store(
+        &self,
+        key: u64,
+        indices: &[u32],
+        vertex_count: usize,
+        error: f32,
+        path: SimplifyPath,
+    ) {
+        let Ok(encoded) = meshopt::encode_index_buffer(indices, vertex_count) else {
+            return;
+        };
+        let (path, attempts, max_error) = match path {
+            SimplifyPath::Standard => (0u32, 0, 0.0f32),
+            SimplifyPath::Sloppy => (1, 0, 0.0),
+            SimplifyPath::RelaxedError {
+                attempts,
+                max_error,
+            } => (2, attempts, max_error),
+        };
+        let mut bytes = Vec::with_capacity(encoded.len() + 52);
+        bytes.extend_from_slice(&MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&key.to_le_bytes());
+        bytes.extend_from_slice(&(indices.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(&(vertex_count as u64).to_le_bytes());
+        bytes.extend_from_slice(&error.to_le_bytes());
+        bytes.extend_from_slice(&path.to_le_bytes());
+        bytes.extend_from_slice(&attempts.to_le_bytes());
+        bytes.extend_from_slice(&max_error.to_le_bytes());
+        bytes.extend_from_slice(&encoded);
+        bytes.extend_from_slice(&fnv1a(FNV_OFFSET, &bytes).to_le_bytes());
+
+        let cache = self.clone();
+        let path = self.path(key);
+        let write = move || {
+            if write_entry(&cache.settings.directory, &path, &bytes).is_ok() {
+                let _ = cache.evict();
+            }
+        };
+        match IoTaskPool::try_get() {
+            Some(pool) => pool.spawn(async move { write() }).detach(),
+            None => write(),
+        }
+    }
+
+    /// Deletes the least recently used entries until the cache fits in `max_size`.
+    fn evict(&self) -> io::Result<()> {
+        let mut entries = Vec::new();
+        for entry in fs::read_dir(&self.settings.directory)? {
+            let entry = entry?;
+            let path = entry.path();
+            if path
+                .extension()
+                .is_none_or(|extension| extension != EXTENSION)
+            {
+                continue;
+            }
+            let metadata = entry.metadata()?;
+            entries.push((metadata.modified()?, metadata.len(), path));
+        }
+
+        let mut size: u64 = entries.iter().map(|(_, len, _)| len).sum();
+        entries.sort_by_key(|(modified, ..)| *modified);
+        for (_, len, path) in entries {
+            if size <= self.settings.max_size {
+                break;
+            }
+            fs::remove_file(path)?;
+            size -= len;
+        }
+        Ok(())
+    }
+}
+
+/// FNV-1a over the fields of the params, stable across runs and builds unlike `Debug` output or
+/// the std hashers.
+struct KeyHasher(u64);
+
+impl KeyHasher {
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.0 = fnv1a(self.0, bytes);
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    fn f32(&mut self, value: f32) {
+        self.u32(value.to_bits());
+    }
+
+    fn f32s(&mut self, values: &[f32]) {
+        values.iter().for_each(|&value| self.f32(value));
+    }
+
+    fn bool(&mut self, value: bool) {
+        self.bytes(&[value as u8]);
+    }
+
+    /// Hashes whether `value` is set, then what `hash` hashes of it.
+    fn option<T>(&mut self, value: Option<T>, hash: impl FnOnce(&mut Self, T)) {
+        self.bool(value.is_some());
+        if let Some(value) = value {
+            hash(self, value);
+        }
+    }
+
+    fn params(&mut self, params: &SimplifyParams, locks: Option<&[bool]>) {
+        // Destructured so that new fields can't be forgotten.
+        let SimplifyParams {
+            max_error,
+            target_index_count,
+            min_target_index_count,
+            options,
+            mode,
+            // Both resolved into `locks`.
+            vertex_locks: _,
+            locked_vertices: _,
+            symmetry,
+            skinning_weight,
+            uv_weighting,
+            normal_weight,
+            color_weight,
+            silhouette_locks,
+            hard_edges,
+            seam_locks,
+            planarity_tolerance,
+            lock_non_manifold,
+            lock_joint_seams,
+            merge_double_sided,
+            strip_degenerates,
+            strategy,
+            expand_generated_indices,
+            shrink_indices,
+            fallback,
+            fallback_tolerance,
+            canonical_order,
+            world_scale,
+        } = params;
+
+        self.f32(*max_error);
+        match *target_index_count {
+            TargetIndices::Count(count) => {
+                self.u32(0);
+                self.u64(count as u64);
+            }
+            TargetIndices::Multiplier(multiplier) => {
+                self.u32(1);
+                self.f32(multiplier);
+            }
+            TargetIndices::TriangleCount(triangles) => {
+                self.u32(2);
+                self.u64(triangles as u64);
+            }
+            TargetIndices::VertexCount(vertices) => {
+                self.u32(3);
+                self.u64(vertices as u64);
+            }
+            TargetIndices::ErrorOnly => self.u32(4),
+        }
+        self.u64(*min_target_index_count as u64);
+        self.u32(options.bits());
+        self.u32(*mode as u32);
+        self.option(locks, |key, locks| {
+            key.u64(locks.len() as u64);
+            for chunk in locks.chunks(64) {
+                let word = chunk
+                    .iter()
+                    .enumerate()
+                    .fold(0u64, |word, (bit, &lock)| word | (lock as u64) << bit);
+                key.u64(word);
+            }
+        });
+        self.option(symmetry.as_ref(), |key, symmetry| {
+            let SymmetryPlane {
+                normal,
+                distance,
+                epsilon,
+                mode,
+            } = symmetry;
+            key.f32s(&normal.to_array());
+            key.f32s(&[*distance, *epsilon]);
+            key.u32(*mode as u32);
+        });
+        self.f32(*skinning_weight);
+        for weighting in uv_weighting {
+            self.option(weighting.as_ref(), |key, weighting| match *weighting {
+                UvWeighting::Weight(weight) => {
+                    key.u32(0);
+                    key.f32(weight);
+                }
+                UvWeighting::Texels {
+                    resolution,
+                    tolerance_texels,
+                } => {
+                    key.u32(1);
+                    key.u32(resolution.x);
+                    key.u32(resolution.y);
+                    key.f32(tolerance_texels);
+                }
+            });
+        }
+        self.f32s(&[*normal_weight, *color_weight]);
+        self.option(silhouette_locks.as_ref(), |key, locks| {
+            let SilhouetteLocks {
+                directions,
+                angle_tolerance,
+            } = locks;
+            key.u64(directions.len() as u64);
+            directions
+                .iter()
+                .for_each(|direction| key.f32s(&direction.to_array()));
+            key.f32(*angle_tolerance);
+        });
+        self.option(hard_edges.as_ref(), |key, hard_edges| {
+            let HardEdges {
+                angle_threshold,
+                detection,
+            } = hard_edges;
+            key.f32(*angle_threshold);
+            key.u32(*detection as u32);
+        });
+        self.option(seam_locks.as_ref(), |key, seam_locks| {
+            let SeamLocks {
+                uv_0,
+                uv_1,
+                uv_epsilon,
+                normal_angle,
+            } = seam_locks;
+            key.bool(*uv_0);
+            key.bool(*uv_1);
+            key.f32(*uv_epsilon);
+            key.option(*normal_angle, Self::f32);
+        });
+        self.option(*planarity_tolerance, Self::f32);
+        for flag in [
+            *lock_non_manifold,
+            *lock_joint_seams,
+            *merge_double_sided,
+            *strip_degenerates,
+            *expand_generated_indices,
+            *shrink_indices,
+            *canonical_order,
+        ] {
+            self.bool(flag);
+        }
+        self.u32(*strategy as u32);
+        match *fallback {
+            FallbackPolicy::None => self.u32(0),
+            FallbackPolicy::Sloppy { max_error } => {
+                self.u32(1);
+                self.f32(max_error);
+            }
+            FallbackPolicy::RelaxError {
+                factor,
+                max_attempts,
+            } => {
+                self.u32(2);
+                self.f32(factor);
+                self.u32(max_attempts);
+            }
+        }
+        self.f32(*fallback_tolerance);
+        self.option(*world_scale, Self::f32);
+    }
+}
+
+fn read_entry(mut file: &File, key: u64, vertex_count: usize) -> io::Result<CachedSimplify> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message);
+
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+    let Some((payload, checksum)) = bytes.split_last_chunk::<8>() else {
+        return Err(invalid("truncated cache entry"));
+    };
+    if fnv1a(FNV_OFFSET, payload) != u64::from_le_bytes(*checksum) {
+        return Err(invalid("cache entry checksum mismatch"));
+    }
+
+    let (header, encoded) = payload
+        .split_at_checked(48)
+        .ok_or_else(|| invalid("truncated cache entry"))?;
+    let u32_at = |offset: usize| u32::from_le_bytes(header[offset..offset + 4].try_into().unwrap());
+    let u64_at = |offset: usize| u64::from_le_bytes(header[offset..offset + 8].try_into().unwrap());
+    if header[..4] != MAGIC || u32_at(4) != VERSION {
+        return Err(invalid("not a cache entry"));
+    }
+    if u64_at(8) != key || u64_at(24) != vertex_count as u64 {
+        return Err(invalid("cache entry of a different mesh"));
+    }
+
+    // meshoptimizer asserts on index counts that aren't a multiple of 3.
+    let index_count = u64_at(16) as usize;
+    if !index_count.is_multiple_of(3) {
+        return Err(invalid("cache entry has an invalid index count"));
+    }
+    let indices = meshopt::decode_index_buffer(encoded, index_count)
+        .map_err(|_| invalid("undecodable cache entry"))?;
+    validate_indices(&indices, vertex_count).map_err(|_| invalid("cache entry out of bounds"))?;
+    let path = match u32_at(36) {
+        0 => SimplifyPath::Standard,
+        1 => SimplifyPath::Sloppy,
+        2 => SimplifyPath::RelaxedError {
+            attempts: u32_at(40),
+            max_error: f32::from_bits(u32_at(44)),
+        },
+        _ => return Err(invalid("cache entry has an unknown simplify path")),
+    };
+    Ok(CachedSimplify {
+        indices,
+        error: f32::from_bits(u32_at(32)),
+        path,
+    })
+}
+
+/// Writes to a temporary file first so readers never see partial entries.
+fn write_entry(directory: &PathBuf, path: &PathBuf, bytes: &[u8]) -> io::Result<()> {
+    fs::create_dir_all(directory)?;
+    let temporary = path.with_extension("tmp");
+    File::create(&temporary)?.write_all(bytes)?;
+    fs::rename(temporary, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::math::Vec3;
+
+    use crate::{VertexLocks, test_util::sphere};
+
+    fn locking(predicate: impl Fn(Vec3) -> bool + Send + Sync + 'static) -> SimplifyParams {
+        SimplifyParams {
+            locked_vertices: Some(VertexLocks::predicate(predicate)),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn predicates_are_keyed_by_the_vertices_they_lock() {
+        let mesh = sphere(4);
+        let above = SimplifyCache::key(&mesh, &locking(|position| position.y > 0.0)).unwrap();
+        let below = SimplifyCache::key(&mesh, &locking(|position| position.y < 0.0)).unwrap();
+        let also_above = SimplifyCache::key(&mesh, &locking(|position| position.y > 0.0)).unwrap();
+        let unlocked = SimplifyCache::key(&mesh, &SimplifyParams::default()).unwrap();
+        assert_ne!(above, below);
+        assert_ne!(above, unlocked);
+        assert_eq!(above, also_above);
+    }
+}
@@ -0,0 +1,408 @@
+use std::io;
+
+use bevy::{
+    asset::RenderAssetUsages,
+    mesh::{Indices, Mesh, MeshVertexAttribute, PrimitiveTopology},
+};
+
+use crate::{OptError, vertex::values_from_bytes};
+
+/// meshoptimizer encodes at least one byte for every 1024 bytes of vertices, whatever they hold.
+/// Checked before decoding so corrupted counts can't allocate more than that.
+const MAX_VERTEX_COMPRESSION: usize = 1024;
+
+/// Mesh compressed with meshoptimizer's vertex and index codecs, see
+/// [`MeshExt::encode_compressed`](crate::MeshExt::encode_compressed). The streams compress
+/// further with a general purpose compressor like zstd.
+///
+/// Decoding with [`CompressedMesh::decode`] gives back the exact attribute values and index
+/// order that were encoded. Stored on disk as the levels of a
+/// [`CompressedMeshFile`](crate::CompressedMeshFile), which
+/// [`CompressedMeshLoader`](crate::CompressedMeshLoader) decodes into meshes at load time and the
+/// processor of `MeshoptProcessorPlugin` writes.
+#[doc(alias = "EncodedMesh")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct CompressedMesh {
+    /// [`PrimitiveTopology`] as 0 to 4, from `PointList` to `TriangleStrip`.
+    pub topology: u8,
+    /// Bits of the [`RenderAssetUsages`].
+    pub asset_usage: u8,
+    pub vertex_count: u32,
+    pub indices: Option<CompressedIndices>,
+    /// One stream per attribute, in the order of [`Mesh::attributes`].
+    pub attributes: Vec<CompressedAttribute>,
+}
+
+/// Index stream of a [`CompressedMesh`], encoded as a sequence so any topology keeps its order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct CompressedIndices {
+    /// Whether the indices are `u32` rather than `u16`.
+    pub wide: bool,
+    pub count: u32,
+    pub data: Vec<u8>,
+}
+
+/// Vertex stream of a [`CompressedMesh`]. Values are padded to a multiple of 4 bytes before
+/// encoding, as meshoptimizer requires.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct CompressedAttribute {
+    /// [`MeshVertexAttribute::name`], which identifies the attribute when decoding.
+    pub name: String,
+    /// Name of the [`VertexFormat`](bevy::mesh::VertexFormat), e.g. `Float32x3`.
+    pub format: String,
+    pub data: Vec<u8>,
+}
+
+/// Attributes [`CompressedMesh::decode`] recognizes without being told about them.
+const BUILTIN_ATTRIBUTES: [MeshVertexAttribute; 8] = [
+    Mesh::ATTRIBUTE_POSITION,
+    Mesh::ATTRIBUTE_NORMAL,
+    Mesh::ATTRIBUTE_UV_0,
+    Mesh::ATTRIBUTE_UV_1,
+    Mesh::ATTRIBUTE_TANGENT,
+    Mesh::ATTRIBUTE_COLOR,
+    Mesh::ATTRIBUTE_JOINT_WEIGHT,
+    Mesh::ATTRIBUTE_JOINT_INDEX,
+];
+
+const TOPOLOGIES: [PrimitiveTopology; 5] = [
+    PrimitiveTopology::PointList,
+    PrimitiveTopology::LineList,
+    PrimitiveTopology::LineStrip,
+    PrimitiveTopology::TriangleList,
+    PrimitiveTopology::TriangleStrip,
+];
+
+/// Encodes every attribute and the indices of `mesh`, in any topology.
+pub(crate) fn encode_compressed(mesh: &Mesh) -> Result<CompressedMesh, OptError> {
+    let vertex_count = mesh.count_vertices();
+    let vertex_count_u32 =
+        u32::try_from(vertex_count).map_err(|_| OptError::InvalidVertexCount(vertex_count))?;
+
+    let indices = match mesh.indices() {
+        Some(indices) => {
+            let widened: Vec<u32> = indices.iter().map(|index| index as u32).collect();
+            if let Some(&index) = widened
+                .iter()
+                .find(|&&index| index as usize >= vertex_count)
+            {
+                return Err(OptError::IndexOutOfBounds(index));
+            }
+            let count = u32::try_from(widened.len())
+                .map_err(|_| OptError::InvalidIndexCount(widened.len()))?;
+            Some(CompressedIndices {
+                wide: matches!(indices, Indices::U32(_)),
+                count,
+                data: encode_index_sequence(&widened, vertex_count),
+            })
+        }
+        None => None,
+    };
+
+    let mut attributes = Vec::with_capacity(mesh.attributes().count());
+    for (attribute, values) in mesh.attributes() {
+        if values.len() != vertex_count {
+            return Err(OptError::InvalidVertexCount(values.len()));
+        }
+        let size = attribute.format.size() as usize;
+        let stride = padded_stride(size);
+        let bytes = values.get_bytes();
+        let data = if stride == size {
+            encode_vertex_buffer(bytes, vertex_count, stride)
+        } else {
+            let mut padded = vec![0; vertex_count * stride];
+            for (padded, value) in padded
+                .chunks_exact_mut(stride)
+                .zip(bytes.chunks_exact(size))
+            {
+                padded[..size].copy_from_slice(value);
+            }
+            encode_vertex_buffer(&padded, vertex_count, stride)
+        };
+        attributes.push(CompressedAttribute {
+            name: attribute.name.to_string(),
+            format: values.enum_variant_name().to_string(),
+            data,
+        });
+    }
+
+    let topology = mesh.primitive_topology();
+    Ok(CompressedMesh {
+        topology: TOPOLOGIES
+            .iter()
+            .position(|&known| known == topology)
+            .ok_or(OptError::UnsupportedPrimitiveTopology(topology))? as u8,
+        asset_usage: mesh.asset_usage.bits(),
+        vertex_count: vertex_count_u32,
+        indices,
+        attributes,
+    })
+}
+
+impl CompressedMesh {
+    /// Decodes the mesh, recognizing the attributes Bevy defines on [`Mesh`].
+    ///
+    /// Fails with [`io::ErrorKind::InvalidData`] on corrupted streams, unknown attributes,
+    /// attributes whose format differs from the one Bevy expects and indices past the vertices.
+    pub fn decode(&self) -> io::Result<Mesh> {
+        self.decode_with_attributes(&[])
+    }
+
+    /// Like [`CompressedMesh::decode`], also recognizing the custom attributes in `custom`.
+    pub fn decode_with_attributes(&self, custom: &[MeshVertexAttribute]) -> io::Result<Mesh> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+
+        let topology = *TOPOLOGIES
+            .get(self.topology as usize)
+            .ok_or_else(|| invalid(format!("unknown topology {}", self.topology)))?;
+        let asset_usage = RenderAssetUsages::from_bits(self.asset_usage)
+            .ok_or_else(|| invalid(format!("unknown asset usage {}", self.asset_usage)))?;
+        let vertex_count = self.vertex_count as usize;
+        let mut mesh = Mesh::new(topology, asset_usage);
+
+        for (i, compressed) in self.attributes.iter().enumerate() {
+            if self.attributes[..i]
+                .iter()
+                .any(|other| other.name == compressed.name)
+            {
+                return Err(invalid(format!(
+                    "attribute {} listed twice",
+                    compressed.name
+                )));
+            }
+            let attribute = *custom
+                .iter()
+                .chain(&BUILTIN_ATTRIBUTES)
+                .find(|attribute| attribute.name == compressed.name)
+                .ok_or_else(|| invalid(format!("unknown attribute {}", compressed.name)))?;
+
+            let stride = padded_stride(attribute.format.size() as usize);
+            let len = vertex_count
+                .checked_mul(stride)
+                .filter(|&len| len <= compressed.data.len().saturating_mul(MAX_VERTEX_COMPRESSION))
+                .ok_or_else(|| invalid(format!("attribute {} is truncated", compressed.name)))?;
+            let mut bytes = vec![0u8; len];
+            // SAFETY: `bytes` holds `vertex_count` values of `stride` bytes, a multiple of 4 of at
+            // most 256 bytes as the decoder requires.
+            let result = unsafe {
+                meshopt::ffi::meshopt_decodeVertexBuffer(
+                    bytes.as_mut_ptr().cast(),
+                    vertex_count,
+                    stride,
+                    compressed.data.as_ptr(),
+                    compressed.data.len(),
+                )
+            };
+            if result != 0 {
+                return Err(invalid(format!(
+                    "attribute {} is corrupted",
+                    compressed.name
+                )));
+            }
+
+            let values = values_from_bytes(attribute.format, &bytes, stride)
+                .filter(|values| values.enum_variant_name() == compressed.format)
+                .ok_or_else(|| {
+                    invalid(format!(
+                        "attribute {} is {}, expected {:?}",
+                        compressed.name, compressed.format, attribute.format
+                    ))
+                })?;
+            mesh.insert_attribute(attribute, values);
+        }
+
+        if let Some(indices) = &self.indices {
+            let count = indices.count as usize;
+            // Every index takes at least a byte, after the header byte.
+            if indices.data.len() <= count {
+                return Err(invalid("indices are truncated".to_string()));
+            }
+            let decoded = if indices.wide {
+                decode_index_sequence(&indices.data, count).map(Indices::U32)
+            } else {
+                decode_index_sequence(&indices.data, count).map(Indices::U16)
+            }
+            .ok_or_else(|| invalid("indices are corrupted".to_string()))?;
+            if decoded.iter().any(|index| index >= vertex_count) {
+                return Err(invalid("index past the vertices".to_string()));
+            }
+            mesh.insert_indices(decoded);
+        }
+
+        Ok(mesh)
+    }
+}
+
+/// meshoptimizer only encodes vertices whose size is a multiple of 4.
+fn padded_stride(size: usize) -> usize {
+    size.next_multiple_of(4)
+}
+
+fn encode_vertex_buffer(bytes: &[u8], vertex_count: usize, stride: usize) -> Vec<u8> {
+    // SAFETY: `bytes` holds `vertex_count` values of `stride` bytes, a multiple of 4 of at most
+    // 256 bytes as the encoder requires.
+    unsafe {
+        let bound = meshopt::ffi::meshopt_encodeVertexBufferBound(vertex_count, stride);
+        let mut data = vec![0; bound];
+        let size = meshopt::ffi::meshopt_encodeVertexBuffer(
+            data.as_mut_ptr(),
+            data.len(),
+            bytes.as_ptr().cast(),
+            vertex_count,
+            stride,
+        );
+        data.truncate(size);
+        data
+    }
+}
+
+pub(crate) fn encode_index_sequence(indices: &[u32], vertex_count: usize) -> Vec<u8> {
+    // SAFETY: the buffer is as large as the bound meshoptimizer computes for the indices.
+    unsafe {
+        let bound = meshopt::ffi::meshopt_encodeIndexSequenceBound(indices.len(), vertex_count);
+        let mut data = vec![0; bound];
+        let size = meshopt::ffi::meshopt_encodeIndexSequence(
+            data.as_mut_ptr(),
+            data.len(),
+            indices.as_ptr(),
+            indices.len(),
+        );
+        data.truncate(size);
+        data
+    }
+}
+
+/// `None` if meshoptimizer rejects `data`, the indices aren't known to be in range.
+fn decode_index_sequence<T: Copy + Default>(data: &[u8], count: usize) -> Option<Vec<T>> {
+    let mut indices = vec![T::default(); count];
+    // SAFETY: `indices` holds `count` indices of 2 or 4 bytes, `T` is only ever `u16` or `u32`.
+    let result = unsafe {
+        meshopt::ffi::meshopt_decodeIndexSequence(
+            indices.as_mut_ptr().cast(),
+            count,
+            size_of::<T>(),
+            data.as_ptr(),
+            data.len(),
+        )
+    };
+    (result == 0).then_some(indices)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::mesh::VertexAttributeValues;
+
+    use crate::{
+        MeshExt,
+        test_util::{attribute_bytes, indices, sphere, with_u16_indices},
+    };
+
+    /// [`sphere`] with tangents and a second UV set.
+    fn textured_sphere() -> Mesh {
+        let mut mesh = sphere(4).with_generated_tangents().unwrap();
+        let Some(VertexAttributeValues::Float32x2(uvs)) = mesh.attribute(Mesh::ATTRIBUTE_UV_0)
+        else {
+            unreachable!()
+        };
+        let lightmap: Vec<[f32; 2]> = uvs.iter().map(|&[u, v]| [v * 0.5, u * 0.25]).collect();
+        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_1, lightmap);
+        mesh
+    }
+
+    fn assert_round_trips(mesh: &Mesh) {
+        let decoded = mesh.encode_compressed().unwrap().decode().unwrap();
+        assert_eq!(attribute_bytes(&decoded), attribute_bytes(mesh));
+        assert_eq!(indices(&decoded), indices(mesh));
+        assert_eq!(
+            matches!(decoded.indices(), Some(Indices::U16(_))),
+            matches!(mesh.indices(), Some(Indices::U16(_)))
+        );
+        assert_eq!(decoded.primitive_topology(), mesh.primitive_topology());
+    }
+
+    #[test]
+    fn decode_gives_back_the_encoded_mesh() {
+        let mesh = textured_sphere();
+        assert_eq!(mesh.attributes().count(), 5);
+        assert_round_trips(&mesh);
+        assert_round_trips(&with_u16_indices(mesh));
+    }
+
+    /// Every stream of `compressed` with `corrupt` applied to it in turn.
+    fn corrupted_streams(
+        compressed: &CompressedMesh,
+        corrupt: impl Fn(&mut Vec<u8>) -> bool,
+    ) -> Vec<CompressedMesh> {
+        let mut corrupted = Vec::new();
+        for stream in 0..=compressed.attributes.len() {
+            let mut copy = compressed.clone();
+            let data = match copy.attributes.get_mut(stream) {
+                Some(attribute) => &mut attribute.data,
+                None => &mut copy.indices.as_mut().unwrap().data,
+            };
+            if corrupt(data) {
+                corrupted.push(copy);
+            }
+        }
+        corrupted
+    }
+
+    #[test]
+    fn truncated_streams_fail_to_decode() {
+        let compressed = textured_sphere().encode_compressed().unwrap();
+        let longest = compressed
+            .attributes
+            .iter()
+            .map(|attribute| attribute.data.len())
+            .max()
+            .unwrap();
+        for len in 0..longest {
+            for truncated in corrupted_streams(&compressed, |data| {
+                let shorter = len < data.len();
+                data.truncate(len);
+                shorter
+            }) {
+                assert!(truncated.decode().is_err(), "decoded at {len} bytes");
+            }
+        }
+    }
+
+    #[test]
+    fn flipped_bytes_never_panic_or_index_past_the_vertices() {
+        let compressed = textured_sphere().encode_compressed().unwrap();
+        let vertex_count = compressed.vertex_count as usize;
+        for offset in (0..compressed.attributes[0].data.len()).step_by(7) {
+            for flipped in corrupted_streams(&compressed, |data| {
+                data.get_mut(offset).map(|byte| *byte ^= 0x5a).is_some()
+            }) {
+                if let Ok(mesh) = flipped.decode() {
+                    assert_eq!(mesh.count_vertices(), vertex_count);
+                    assert!(indices(&mesh).iter().all(|&i| (i as usize) < vertex_count));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn corrupted_header_fields_fail_to_decode() {
+        let compressed = textured_sphere().encode_compressed().unwrap();
+        let corruptions: [fn(&mut CompressedMesh); 7] = [
+            |mesh| mesh.topology = 5,
+            |mesh| mesh.asset_usage = u8::MAX,
+            |mesh| mesh.vertex_count = u32::MAX,
+            |mesh| mesh.indices.as_mut().unwrap().count += 1,
+            |mesh| mesh.attributes[1].name = "Vertex_Unknown".to_string(),
+            |mesh| mesh.attributes[1].format = "Float32x4".to_string(),
+            |mesh| mesh.attributes.push(mesh.attributes[0].clone()),
+        ];
+        for (i, corrupt) in corruptions.into_iter().enumerate() {
+            let mut corrupted = compressed.clone();
+            corrupt(&mut corrupted);
+            assert!(corrupted.decode().is_err(), "corruption {i} decoded");
+        }
+    }
+}
@@ -0,0 +1,194 @@
+use bevy::{
+    math::Vec3,
+    mesh::{Indices, Mesh},
+};
+
+use crate::{
+    OptError, adjacency::TriangleAdjacency, mesh_indices, mesh_positions, vertex::gather_vertices,
+};
+
+/// Triangle count of every connected component, largest first.
+pub(crate) fn component_sizes(mesh: &Mesh) -> Result<Vec<usize>, OptError> {
+    let components = Components::new(mesh)?;
+    Ok(components
+        .order
+        .iter()
+        .map(|&component| components.sizes[component as usize] as usize)
+        .collect())
+}
+
+/// Indices of `mesh` without the components whose bounding sphere, around the average of their
+/// corners, has a radius of at most `min_error` times the extent of the mesh, the measure of
+/// `SimplifyOptions::Prune`. Returns them with the number of removed triangles.
+pub(crate) fn prune_components(mesh: &Mesh, min_error: f32) -> Result<(Vec<u32>, usize), OptError> {
+    let indices = mesh_indices(mesh)?;
+    let positions = mesh_positions(mesh)?;
+    let components = Components::new(mesh)?;
+    let corners = || {
+        indices
+            .chunks_exact(3)
+            .zip(&components.triangle_components)
+            .flat_map(|(triangle, &component)| {
+                triangle
+                    .iter()
+                    .map(move |&vertex| (component, Vec3::from(positions[vertex as usize])))
+            })
+    };
+
+    let mut centers = vec![Vec3::ZERO; components.sizes.len()];
+    for (component, position) in corners() {
+        centers[component as usize] += position;
+    }
+    for (center, &size) in centers.iter_mut().zip(&components.sizes) {
+        *center /= (size * 3) as f32;
+    }
+    let mut radii = vec![0f32; components.sizes.len()];
+    for (component, position) in corners() {
+        let radius = &mut radii[component as usize];
+        *radius = radius.max(position.distance(centers[component as usize]));
+    }
+
+    let cutoff = min_error * meshopt::simplify_scale_decoder(positions);
+    let mut kept = Vec::with_capacity(indices.len());
+    for (triangle, &component) in indices.chunks_exact(3).zip(&components.triangle_components) {
+        if radii[component as usize] > cutoff {
+            kept.extend_from_slice(triangle);
+        }
+    }
+    let removed = (indices.len() - kept.len()) / 3;
+    Ok((kept, removed))
+}
+
+/// Every connected component as its own mesh, largest first.
+pub(crate) fn split_by_connectivity(mesh: &Mesh) -> Result<Vec<Mesh>, OptError> {
+    let indices = mesh_indices(mesh)?;
+    let components = Components::new(mesh)?;
+
+    // Triangles of every component, grouped with a counting sort so each pass stays linear.
+    let mut offsets = vec![0; components.sizes.len() + 1];
+    for (component, &size) in components.sizes.iter().enumerate() {
+        offsets[component + 1] = offsets[component] + size as usize;
+    }
+    let mut triangles = vec![0; components.triangle_components.len()];
+    let mut next = offsets.clone();
+    for (triangle, &component) in components.triangle_components.iter().enumerate() {
+        triangles[next[component as usize]] = triangle as u32;
+        next[component as usize] += 1;
+    }
+
+    let mut remap = vec![u32::MAX; mesh.count_vertices()];
+    let split = components
+        .order
+        .iter()
+        .map(|&component| {
+            let component = component as usize;
+            let mut sources = Vec::new();
+            let mut component_indices = Vec::new();
+            for &triangle in &triangles[offsets[component]..offsets[component + 1]] {
+                for &vertex in &indices[triangle as usize * 3..][..3] {
+                    let new_index = &mut remap[vertex as usize];
+                    if *new_index == u32::MAX {
+                        *new_index = sources.len() as u32;
+                        sources.push(vertex);
+                    }
+                    component_indices.push(*new_index);
+                }
+            }
+            for &vertex in &sources {
+                remap[vertex as usize] = u32::MAX;
+            }
+
+            let mut part = gather_vertices(mesh, &sources);
+            part.insert_indices(Indices::U32(component_indices));
+            part
+        })
+        .collect();
+    Ok(split)
+}
+
+struct Components {
+    /// Component of every triangle, numbered in the order of their first triangle.
+    triangle_components: Vec<u32>,
+    /// Triangle count of every component.
+    sizes: Vec<u32>,
+    /// Components by descending triangle count, ties in the order of their first triangle.
+    order: Vec<u32>,
+}
+
+impl Components {
+    /// Joins the triangles sharing an edge, vertices sharing a position are treated as one so
+    /// attribute seams don't split components.
+    fn new(mesh: &Mesh) -> Result<Self, OptError> {
+        let indices = mesh_indices(mesh)?;
+        let positions = mesh_positions(mesh)?;
+        let adjacency = TriangleAdjacency::new(indices, positions);
+
+        let mut sets = DisjointSets::new(adjacency.triangle_count());
+        for (_, triangles) in adjacency.welded_edges() {
+            for pair in triangles.windows(2) {
+                sets.union(pair[0], pair[1]);
+            }
+        }
+
+        let mut numbers = vec![u32::MAX; adjacency.triangle_count()];
+        let mut sizes = Vec::new();
+        let triangle_components = (0..adjacency.triangle_count() as u32)
+            .map(|triangle| {
+                let number = &mut numbers[sets.find(triangle) as usize];
+                if *number == u32::MAX {
+                    *number = sizes.len() as u32;
+                    sizes.push(0);
+                }
+                sizes[*number as usize] += 1;
+                *number
+            })
+            .collect();
+
+        let mut order: Vec<u32> = (0..sizes.len() as u32).collect();
+        order.sort_by_key(|&component| std::cmp::Reverse(sizes[component as usize]));
+        Ok(Components {
+            triangle_components,
+            sizes,
+            order,
+        })
+    }
+}
+
+/// Union-find with path halving and union by size, close to linear even when every triangle is
+/// its own component.
+struct DisjointSets {
+    parents: Vec<u32>,
+    sizes: Vec<u32>,
+}
+
+impl DisjointSets {
+    fn new(count: usize) -> Self {
+        DisjointSets {
+            parents: (0..count as u32).collect(),
+            sizes: vec![1; count],
+        }
+    }
+
+    fn find(&mut self, mut element: u32) -> u32 {
+        while self.parents[element as usize] != element {
+            let grandparent = self.parents[self.parents[element as usize] as usize];
+            self.parents[element as usize] = grandparent;
+            element = grandparent;
+        }
+        element
+    }
+
+    fn union(&mut self, a: u32, b: u32) {
+        let (a, b) = (self.find(a), self.find(b));
+        if a == b {
+            return;
+        }
+        let (large, small) = if self.sizes[a as usize] >= self.sizes[b as usize] {
+            (a, b)
+        } else {
+            (b, a)
+        };
+        self.parents[small as usize] = large;
+        self.sizes[large as usize] += self.sizes[small as usize];
+    }
+}
@@ -0,0 +1,155 @@
+use bevy::{
+    math::{Vec2, Vec3},
+    mesh::{Mesh, VertexAttributeValues},
+};
+
+use crate::{OptError, mesh_indices, mesh_positions, metrics::SurfaceIndex};
+
+/// Point on the simplified mesh along with the closest point on the original mesh.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct CorrespondenceSample {
+    /// Vertex of the simplified mesh the sample was generated for.
+    pub simplified_vertex: u32,
+    /// Triangle of the simplified mesh the sample lies on.
+    pub simplified_triangle: u32,
+    /// Barycentric coordinates of the sample within `simplified_triangle`.
+    pub simplified_barycentric: [f32; 3],
+    /// Position of the sample on the simplified mesh.
+    pub position: [f32; 3],
+    /// Closest triangle of the original mesh.
+    pub original_triangle: u32,
+    /// Barycentric coordinates of the closest point within `original_triangle`.
+    pub original_barycentric: [f32; 3],
+    /// Closest point on the original mesh.
+    pub original_position: [f32; 3],
+    /// Normal of the original mesh at the closest point, interpolated from `ATTRIBUTE_NORMAL` or
+    /// the face normal when the mesh has none.
+    pub original_normal: [f32; 3],
+    /// `ATTRIBUTE_UV_0` of the original mesh at the closest point.
+    pub original_uv: Option<[f32; 2]>,
+    pub distance: f32,
+}
+
+/// Mapping from points on a simplified mesh to the original surface, e.g. for baking normal maps
+/// that make a LOD look like LOD0.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct CorrespondenceMap {
+    pub samples: Vec<CorrespondenceSample>,
+    /// Largest distance between a sample and its closest point on the original mesh.
+    pub max_distance: f32,
+}
+
+/// Finds the closest point on `original` for `samples_per_vertex` sample points around every
+/// vertex of `simplified`. The first sample of a vertex is the vertex itself, the rest are spread
+/// over its triangles.
+///
+/// Both meshes need u32 indices and `TriangleList` topology, they don't have to share a vertex
+/// buffer.
+pub fn compute_correspondence(
+    original: &Mesh,
+    simplified: &Mesh,
+    samples_per_vertex: u32,
+) -> Result<CorrespondenceMap, OptError> {
+    let original_indices = mesh_indices(original)?;
+    let original_positions = mesh_positions(original)?;
+    let simplified_indices = mesh_indices(simplified)?;
+    let simplified_positions = mesh_positions(simplified)?;
+
+    let normals = match original.attribute(Mesh::ATTRIBUTE_NORMAL) {
+        Some(VertexAttributeValues::Float32x3(normals))
+            if normals.len() == original_positions.len() =>
+        {
+            Some(normals)
+        }
+        _ => None,
+    };
+    let uvs = match original.attribute(Mesh::ATTRIBUTE_UV_0) {
+        Some(VertexAttributeValues::Float32x2(uvs)) if uvs.len() == original_positions.len() => {
+            Some(uvs)
+        }
+        _ => None,
+    };
+
+    // Triangles of the simplified mesh around each of its vertices.
+    let mut incident = vec![Vec::new(); simplified_positions.len()];
+    for (triangle, corners) in simplified_indices.chunks_exact(3).enumerate() {
+        for &vertex in corners {
+            incident[vertex as usize].push(triangle as u32);
+        }
+    }
+
+    let surface = SurfaceIndex::new(original_indices, original_positions);
+    let samples_per_vertex = samples_per_vertex.max(1) as usize;
+    let mut map = CorrespondenceMap::default();
+    for (vertex, triangles) in incident.iter().enumerate() {
+        if triangles.is_empty() {
+            continue;
+        }
+
+        // Later samples move from the vertex towards the centroids of its triangles, going
+        // around the vertex before stepping further out.
+        let rings = (samples_per_vertex - 1).div_ceil(triangles.len()) + 1;
+        for sample in 0..samples_per_vertex {
+            let triangle = triangles[sample.saturating_sub(1) % triangles.len()];
+            let corners = &simplified_indices[triangle as usize * 3..][..3];
+            let ring = if sample == 0 {
+                0
+            } else {
+                (sample - 1) / triangles.len() + 1
+            };
+            let t = ring as f32 / rings as f32;
+
+            let mut barycentric = Vec3::splat(t / 3.0);
+            let corner = corners.iter().position(|&c| c == vertex as u32).unwrap();
+            barycentric[corner] += 1.0 - t;
+
+            let position = corners
+                .iter()
+                .zip(barycentric.to_array())
+                .map(|(&c, weight)| Vec3::from_array(simplified_positions[c as usize]) * weight)
+                .sum::<Vec3>();
+
+            let Some(hit) = surface.closest_point(position) else {
+                continue;
+            };
+
+            let original_corners = &original_indices[hit.triangle as usize * 3..][..3];
+            let original_normal = match normals {
+                Some(normals) => original_corners
+                    .iter()
+                    .zip(hit.barycentric.to_array())
+                    .map(|(&c, weight)| Vec3::from_array(normals[c as usize]) * weight)
+                    .sum::<Vec3>()
+                    .normalize_or_zero(),
+                None => surface.triangle_normal(hit.triangle),
+            };
+            let original_uv = uvs.map(|uvs| {
+                original_corners
+                    .iter()
+                    .zip(hit.barycentric.to_array())
+                    .map(|(&c, weight)| Vec2::from_array(uvs[c as usize]) * weight)
+                    .sum::<Vec2>()
+                    .to_array()
+            });
+
+            let distance = hit.distance_squared.sqrt();
+            map.max_distance = map.max_distance.max(distance);
+            map.samples.push(CorrespondenceSample {
+                simplified_vertex: vertex as u32,
+                simplified_triangle: triangle,
+                simplified_barycentric: barycentric.to_array(),
+                position: position.to_array(),
+                original_triangle: hit.triangle,
+                original_barycentric: hit.barycentric.to_array(),
+                original_position: hit.point.to_array(),
+                original_normal: original_normal.to_array(),
+                original_uv,
+                distance,
+            });
+        }
+    }
+
+    Ok(map)
+}
@@ -0,0 +1,288 @@
+use std::collections::HashMap;
+
+use bevy::{
+    app::{App, Plugin, PostUpdate},
+    asset::{AssetEvent, AssetId, Assets},
+    color::{Color, Mix, palettes::css},
+    ecs::prelude::*,
+    gizmos::gizmos::Gizmos,
+    math::{Isometry3d, Vec3},
+    mesh::{Mesh, Mesh3d},
+    transform::{TransformSystems, components::GlobalTransform},
+};
+use meshopt::SimplifyOptions;
+
+use crate::{
+    BorderSelection, OptError, PickingMesh, SimplifyParams, SimplifySettings, border::border_edges,
+    mesh_indices, mesh_positions, metrics::SurfaceIndex, simplify::resolve_vertex_locks,
+    u32_indexed,
+};
+
+/// Draws how the simplifier sees the meshes of entities carrying a [`MeshoptDebug`], to see where
+/// [`SimplifySettings`] lock the mesh down and where the error of a simplified mesh concentrates
+/// while tuning `max_error`.
+///
+/// What is drawn is computed once per mesh and kept until the mesh is modified or the settings
+/// change.
+pub struct MeshoptDebugPlugin;
+
+impl Plugin for MeshoptDebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MeshoptDebugSettings>().add_systems(
+            PostUpdate,
+            draw_meshopt_debug.after(TransformSystems::Propagate),
+        );
+    }
+}
+
+/// Selects what [`MeshoptDebugPlugin`] draws for the mesh of an entity, everything by default.
+#[derive(Component, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct MeshoptDebug {
+    /// Open boundary edges, which `SimplifyOptions::LockBorder` keeps in place.
+    pub border_edges: bool,
+    /// Vertices locked by the [`SimplifySettings`], or the default [`SimplifyParams`] without the
+    /// resource: vertex locks, symmetry, silhouettes, hard edges and so on, plus the borders with
+    /// `SimplifyOptions::LockBorder`.
+    pub locked_vertices: bool,
+    /// Triangles without an area, which [`SimplifyParams::strip_degenerates`] drops.
+    pub degenerate_triangles: bool,
+    /// Triangles colored by how far they are from the mesh the entity had before it was
+    /// processed, its [`PickingMesh`]. Needs a [`KeepPickingMesh`](crate::KeepPickingMesh) on
+    /// the entity when it is simplified, entities without a [`PickingMesh`] draw no heat.
+    pub error_heat: bool,
+}
+
+impl Default for MeshoptDebug {
+    fn default() -> Self {
+        MeshoptDebug {
+            border_edges: true,
+            locked_vertices: true,
+            degenerate_triangles: true,
+            error_heat: true,
+        }
+    }
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct MeshoptDebugSettings {
+    pub enabled: bool,
+    /// Error in mesh units drawn in `heat_color`, `None` scales the heat of every mesh to its
+    /// largest error.
+    pub max_heat_error: Option<f32>,
+    pub border_color: Color,
+    pub locked_color: Color,
+    pub degenerate_color: Color,
+    /// Color of triangles that lie on the original surface.
+    pub cool_color: Color,
+    /// Color of triangles at `max_heat_error`.
+    pub heat_color: Color,
+}
+
+impl Default for MeshoptDebugSettings {
+    fn default() -> Self {
+        MeshoptDebugSettings {
+            enabled: true,
+            max_heat_error: None,
+            border_color: css::YELLOW.into(),
+            locked_color: css::AQUA.into(),
+            degenerate_color: css::FUCHSIA.into(),
+            cool_color: css::LIME.into(),
+            heat_color: css::RED.into(),
+        }
+    }
+}
+
+/// Size of the markers of locked vertices and degenerate triangles relative to the extent of the
+/// mesh.
+const MARKER_SIZE: f32 = 0.005;
+
+/// What is drawn for a mesh, in mesh space.
+#[derive(Debug, Default)]
+struct DebugGeometry {
+    border_edges: Vec<[Vec3; 2]>,
+    locked_vertices: Vec<Vec3>,
+    degenerate_triangles: Vec<Vec3>,
+    /// Triangles with their largest distance to the original surface.
+    heat: Vec<([Vec3; 3], f32)>,
+    max_error: f32,
+    marker_size: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct DebugGeometryKey {
+    mesh: AssetId<Mesh>,
+    /// Mesh the heat is measured against.
+    source: Option<AssetId<Mesh>>,
+    debug: MeshoptDebug,
+}
+
+fn draw_meshopt_debug(
+    settings: Res<MeshoptDebugSettings>,
+    simplify: Option<Res<SimplifySettings>>,
+    meshes: Res<Assets<Mesh>>,
+    mut events: MessageReader<AssetEvent<Mesh>>,
+    mut cache: Local<HashMap<DebugGeometryKey, DebugGeometry>>,
+    query: Query<(
+        &MeshoptDebug,
+        &Mesh3d,
+        &GlobalTransform,
+        Option<&PickingMesh>,
+    )>,
+    mut gizmos: Gizmos,
+) {
+    for event in events.read() {
+        if let AssetEvent::Modified { id }
+        | AssetEvent::Removed { id }
+        | AssetEvent::Unused { id } = event
+        {
+            cache.retain(|key, _| key.mesh != *id && key.source != Some(*id));
+        }
+    }
+    // The locks depend on the settings.
+    if simplify
+        .as_ref()
+        .is_some_and(|simplify| simplify.is_changed())
+    {
+        cache.clear();
+    }
+    if !settings.enabled {
+        return;
+    }
+    let default_params = SimplifyParams::default();
+    let params = simplify
+        .as_deref()
+        .map_or(&default_params, |simplify| &simplify.0);
+
+    for (debug, mesh3d, transform, picking_mesh) in &query {
+        let key = DebugGeometryKey {
+            mesh: mesh3d.id(),
+            source: picking_mesh
+                .filter(|_| debug.error_heat)
+                .map(|picking_mesh| picking_mesh.id()),
+            debug: *debug,
+        };
+        let geometry = match cache.get(&key) {
+            Some(geometry) => geometry,
+            None => {
+                let Some(mesh) = meshes.get(key.mesh) else {
+                    continue;
+                };
+                let source = key.source.and_then(|source| meshes.get(source));
+                // Meshes the simplifier can't read draw nothing.
+                let geometry = debug_geometry(mesh, source, params, debug).unwrap_or_default();
+                cache.entry(key).or_insert(geometry)
+            }
+        };
+
+        let affine = transform.affine();
+        let at = |point: Vec3| affine.transform_point3(point);
+        let marker_size = geometry.marker_size * transform.scale().abs().max_element();
+        for &[a, b] in &geometry.border_edges {
+            gizmos.line(at(a), at(b), settings.border_color);
+        }
+        for &vertex in &geometry.locked_vertices {
+            gizmos.cross(
+                Isometry3d::from_translation(at(vertex)),
+                marker_size,
+                settings.locked_color,
+            );
+        }
+        for &centroid in &geometry.degenerate_triangles {
+            gizmos.sphere(
+                Isometry3d::from_translation(at(centroid)),
+                marker_size,
+                settings.degenerate_color,
+            );
+        }
+        let max_error = settings.max_heat_error.unwrap_or(geometry.max_error);
+        for &([a, b, c], error) in &geometry.heat {
+            let heat = if max_error > 0.0 {
+                (error / max_error).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            let color = settings.cool_color.mix(&settings.heat_color, heat);
+            gizmos.linestrip([at(a), at(b), at(c), at(a)], color);
+        }
+    }
+}
+
+fn debug_geometry(
+    mesh: &Mesh,
+    source: Option<&Mesh>,
+    params: &SimplifyParams,
+    debug: &MeshoptDebug,
+) -> Result<DebugGeometry, OptError> {
+    let mesh = u32_indexed(mesh)?;
+    let indices = mesh_indices(&mesh)?;
+    let positions = mesh_positions(&mesh)?;
+    let at = |vertex: u32| Vec3::from(positions[vertex as usize]);
+
+    let (min, max) = positions.iter().fold(
+        (Vec3::splat(f32::INFINITY), Vec3::splat(f32::NEG_INFINITY)),
+        |(min, max), &position| (min.min(position.into()), max.max(position.into())),
+    );
+    let mut geometry = DebugGeometry {
+        marker_size: (max - min).max_element().max(0.0) * MARKER_SIZE,
+        ..Default::default()
+    };
+
+    if debug.border_edges {
+        geometry.border_edges = border_edges(&mesh, BorderSelection::All)?
+            .into_iter()
+            .map(|edge| edge.map(at))
+            .collect();
+    }
+
+    if debug.locked_vertices {
+        let mut buffer = Vec::new();
+        let mut locked = resolve_vertex_locks(&mesh, indices, positions, params, &mut buffer)?
+            .map_or_else(|| vec![false; positions.len()], <[bool]>::to_vec);
+        if params.options.contains(SimplifyOptions::LockBorder) {
+            for vertex in border_edges(&mesh, BorderSelection::All)?
+                .into_iter()
+                .flatten()
+            {
+                locked[vertex as usize] = true;
+            }
+        }
+        // Only the vertices the triangles use, once each.
+        let mut used = vec![false; positions.len()];
+        for &vertex in indices {
+            if locked[vertex as usize] && !used[vertex as usize] {
+                used[vertex as usize] = true;
+                geometry.locked_vertices.push(at(vertex));
+            }
+        }
+    }
+
+    if debug.degenerate_triangles {
+        geometry.degenerate_triangles = indices
+            .chunks_exact(3)
+            .map(|triangle| [at(triangle[0]), at(triangle[1]), at(triangle[2])])
+            .filter(|[a, b, c]| (*b - *a).cross(*c - *a) == Vec3::ZERO)
+            .map(|[a, b, c]| (a + b + c) / 3.0)
+            .collect();
+    }
+
+    if debug.error_heat
+        && let Some(source) = source
+    {
+        let source = u32_indexed(source)?;
+        let surface = SurfaceIndex::new(mesh_indices(&source)?, mesh_positions(&source)?);
+        for triangle in indices.chunks_exact(3) {
+            let corners = [at(triangle[0]), at(triangle[1]), at(triangle[2])];
+            let centroid = (corners[0] + corners[1] + corners[2]) / 3.0;
+            let error = corners
+                .into_iter()
+                .chain([centroid])
+                .filter_map(|point| surface.closest_point(point))
+                .map(|hit| hit.distance_squared.sqrt())
+                .fold(0.0, f32::max);
+            geometry.max_error = geometry.max_error.max(error);
+            geometry.heat.push((corners, error));
+        }
+    }
+
+    Ok(geometry)
+}
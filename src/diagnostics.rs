@@ -0,0 +1,65 @@
+use bevy::{
+    app::{App, Last, Plugin},
+    asset::Assets,
+    diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic},
+    ecs::prelude::*,
+    mesh::{Mesh, Mesh3d},
+};
+
+use crate::scene::triangle_count;
+
+/// Registers diagnostics for the work of the batches of [`MeshoptPlugin`](crate::MeshoptPlugin)
+/// in addition to the ones it always registers on [`SimplifyStats`](crate::SimplifyStats): the
+/// triangles removed by and the time spent on every batch, the average error the meshes were
+/// simplified with and the triangles of the scene, e.g. for `LogDiagnosticsPlugin` or a
+/// diagnostics overlay.
+///
+/// Batch diagnostics get a measurement whenever a batch runs, [`Self::SCENE_TRIANGLES`] every
+/// frame.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MeshoptDiagnosticsPlugin;
+
+impl MeshoptDiagnosticsPlugin {
+    /// Triangles removed by the last simplify batch, over all of its meshes.
+    pub const SIMPLIFY_TRIANGLES_REMOVED: DiagnosticPath =
+        DiagnosticPath::const_new("meshopt/simplify/triangles_removed");
+    /// Milliseconds the last simplify batch took.
+    pub const SIMPLIFY_TIME: DiagnosticPath = DiagnosticPath::const_new("meshopt/simplify/time");
+    /// Average of the errors the meshes of the last simplify batch were simplified with, as
+    /// opposed to the largest one of [`SimplifyStats::SIMPLIFY_ERROR`](crate::SimplifyStats).
+    pub const SIMPLIFY_AVERAGE_ERROR: DiagnosticPath =
+        DiagnosticPath::const_new("meshopt/simplify/average_error");
+    /// Milliseconds the last optimize batch took.
+    pub const OPTIMIZE_TIME: DiagnosticPath = DiagnosticPath::const_new("meshopt/optimize/time");
+    /// Triangles of the meshes of every entity with a [`Mesh3d`], visible or not, counting shared
+    /// meshes once per entity.
+    pub const SCENE_TRIANGLES: DiagnosticPath =
+        DiagnosticPath::const_new("meshopt/scene/triangles");
+}
+
+impl Plugin for MeshoptDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_diagnostic(Diagnostic::new(Self::SIMPLIFY_TRIANGLES_REMOVED))
+            .register_diagnostic(Diagnostic::new(Self::SIMPLIFY_TIME).with_suffix("ms"))
+            .register_diagnostic(Diagnostic::new(Self::SIMPLIFY_AVERAGE_ERROR))
+            .register_diagnostic(Diagnostic::new(Self::OPTIMIZE_TIME).with_suffix("ms"))
+            .register_diagnostic(Diagnostic::new(Self::SCENE_TRIANGLES))
+            .add_systems(Last, measure_scene_triangles);
+    }
+}
+
+/// Measures [`MeshoptDiagnosticsPlugin::SCENE_TRIANGLES`], meshes that aren't loaded count as
+/// empty.
+pub fn measure_scene_triangles(
+    mut diagnostics: Diagnostics,
+    meshes: Res<Assets<Mesh>>,
+    query: Query<&Mesh3d>,
+) {
+    diagnostics.add_measurement(&MeshoptDiagnosticsPlugin::SCENE_TRIANGLES, || {
+        query
+            .iter()
+            .filter_map(|mesh3d| meshes.get(mesh3d))
+            .map(triangle_count)
+            .sum::<usize>() as f64
+    });
+}
@@ -0,0 +1,377 @@
+use bevy::{
+    math::Vec3,
+    mesh::{Indices, Mesh, VertexAttributeValues},
+};
+
+use crate::{
+    OptError, mesh_indices, mesh_positions, metrics::SurfaceIndex, vertex::append_vertices,
+};
+
+/// How [`mesh_diff`] samples and visualizes the deviation between two meshes.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct MeshDiffSettings {
+    /// Random points sampled over each surface in addition to its vertices, spread by area.
+    pub samples: u32,
+    /// Seed of the sampling, the same seed gives the same diff on every machine.
+    pub seed: u64,
+    /// Deviation shown as the hottest color, `None` uses the largest measured deviation.
+    pub max_deviation: Option<f32>,
+    /// Number of worst locations marked with a spike, `0` disables them.
+    pub flags: usize,
+    /// Height of the spikes relative to the diagonal of the bounds of the second mesh. Worst
+    /// locations closer than that to a flagged one aren't flagged again.
+    pub flag_height: f32,
+}
+
+impl Default for MeshDiffSettings {
+    fn default() -> Self {
+        MeshDiffSettings {
+            samples: 10_000,
+            seed: 0,
+            max_deviation: None,
+            flags: 0,
+            flag_height: 0.1,
+        }
+    }
+}
+
+/// Surface difference between two meshes, see [`mesh_diff`].
+#[derive(Debug, Clone)]
+pub struct MeshDiff {
+    /// Copy of the second mesh colored by deviation in `ATTRIBUTE_COLOR`, from blue where the
+    /// surfaces match to red at [`MeshDiffSettings::max_deviation`], followed by the flag spikes.
+    pub mesh: Mesh,
+    /// Largest distance between a sample and the other surface, in either direction.
+    pub max_deviation: f32,
+    /// Average distance over all samples.
+    pub mean_deviation: f32,
+    /// Position of every flag on the second mesh, worst first.
+    pub flags: Vec<Vec3>,
+}
+
+/// Sample on `b` and the distance from it to the other surface.
+#[derive(Debug, Copy, Clone)]
+struct Deviation {
+    /// Closest triangle of `b` and the barycentric coordinates of the sample within it.
+    triangle: u32,
+    barycentric: Vec3,
+    position: Vec3,
+    distance: f32,
+}
+
+/// Measures how far the surfaces of `a` and `b` are from each other and visualizes the result on
+/// a copy of `b`. Deviation is sampled both ways, from points on `b` to `a` and from points on `a`
+/// to `b`, so missing or extra geometry on either side shows up. Every sample colors the vertices
+/// of the triangle of `b` it is on, weighted by its barycentric coordinates, and each vertex keeps
+/// the worst deviation it received.
+///
+/// Unlike the reports of the simplifier, the meshes don't have to share a vertex buffer, so this
+/// works for any two versions of an asset. Both need u32 indices and `TriangleList` topology.
+pub fn mesh_diff(a: &Mesh, b: &Mesh, settings: &MeshDiffSettings) -> Result<MeshDiff, OptError> {
+    let a_indices = mesh_indices(a)?;
+    let a_positions = mesh_positions(a)?;
+    let b_indices = mesh_indices(b)?;
+    let b_positions = mesh_positions(b)?;
+    let a_surface = SurfaceIndex::new(a_indices, a_positions);
+    let b_surface = SurfaceIndex::new(b_indices, b_positions);
+    let mut random = Random::new(settings.seed);
+
+    let mut deviations = Vec::new();
+    let b_triangles = triangle_of_vertices(b_indices, b_positions.len());
+    for sample in samples(b_indices, b_positions, settings.samples, &mut random) {
+        let (triangle, barycentric) = match sample {
+            // Vertex samples are attributed to the first triangle using them.
+            Sample::Vertex(vertex) => match b_triangles[vertex as usize] {
+                Some((triangle, corner)) => (triangle, Vec3::AXES[corner]),
+                None => continue,
+            },
+            Sample::Surface {
+                triangle,
+                barycentric,
+            } => (triangle, barycentric),
+        };
+        let position = b_surface.point_at(triangle, barycentric);
+        let Some(hit) = a_surface.closest_point(position) else {
+            continue;
+        };
+        deviations.push(Deviation {
+            triangle,
+            barycentric,
+            position,
+            distance: hit.distance_squared.sqrt(),
+        });
+    }
+    for sample in samples(a_indices, a_positions, settings.samples, &mut random) {
+        let position = match sample {
+            Sample::Vertex(vertex) => Vec3::from(a_positions[vertex as usize]),
+            Sample::Surface {
+                triangle,
+                barycentric,
+            } => a_surface.point_at(triangle, barycentric),
+        };
+        let Some(hit) = b_surface.closest_point(position) else {
+            continue;
+        };
+        deviations.push(Deviation {
+            triangle: hit.triangle,
+            barycentric: hit.barycentric,
+            position: hit.point,
+            distance: hit.distance_squared.sqrt(),
+        });
+    }
+
+    let measured_max = deviations
+        .iter()
+        .map(|deviation| deviation.distance)
+        .fold(0.0, f32::max);
+    let mean_deviation = deviations
+        .iter()
+        .map(|deviation| deviation.distance)
+        .sum::<f32>()
+        / deviations.len().max(1) as f32;
+    let max_deviation = settings.max_deviation.unwrap_or(measured_max);
+
+    let mut vertex_deviations = vec![0.0f32; b_positions.len()];
+    for deviation in &deviations {
+        let corners = &b_indices[deviation.triangle as usize * 3..][..3];
+        for (&vertex, weight) in corners.iter().zip(deviation.barycentric.to_array()) {
+            let value = &mut vertex_deviations[vertex as usize];
+            *value = value.max(deviation.distance * weight);
+        }
+    }
+
+    let mut mesh = b.clone();
+    let mut colors: Vec<[f32; 4]> = vertex_deviations
+        .iter()
+        .map(|&deviation| ramp(deviation / max_deviation.max(f32::EPSILON)))
+        .collect();
+    let flags = flag_locations(&mut deviations, b_positions, settings);
+    if !flags.is_empty() {
+        add_flags(
+            &mut mesh,
+            &flags,
+            b_positions,
+            &b_surface,
+            settings,
+            &mut colors,
+        );
+    }
+    mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+
+    Ok(MeshDiff {
+        mesh,
+        max_deviation: measured_max,
+        mean_deviation,
+        flags: flags.iter().map(|deviation| deviation.position).collect(),
+    })
+}
+
+/// Blue, cyan, green, yellow then red as `t` goes from 0 to 1.
+fn ramp(t: f32) -> [f32; 4] {
+    const STOPS: [[f32; 3]; 5] = [
+        [0.0, 0.0, 1.0],
+        [0.0, 1.0, 1.0],
+        [0.0, 1.0, 0.0],
+        [1.0, 1.0, 0.0],
+        [1.0, 0.0, 0.0],
+    ];
+    let scaled = t.clamp(0.0, 1.0) * (STOPS.len() - 1) as f32;
+    let low = (scaled as usize).min(STOPS.len() - 2);
+    let blend = scaled - low as f32;
+    let [r, g, b] = [0, 1, 2].map(|c| STOPS[low][c] * (1.0 - blend) + STOPS[low + 1][c] * blend);
+    [r, g, b, 1.0]
+}
+
+/// Deviations below this fraction of the diagonal of the bounds are rounding noise and never
+/// flagged.
+const FLAG_NOISE: f32 = 1e-5;
+
+/// Worst deviations at least a flag height apart, worst first.
+fn flag_locations(
+    deviations: &mut [Deviation],
+    positions: &[[f32; 3]],
+    settings: &MeshDiffSettings,
+) -> Vec<Deviation> {
+    if settings.flags == 0 {
+        return Vec::new();
+    }
+
+    let diagonal = bounds_diagonal(positions);
+    let height = diagonal * settings.flag_height;
+    deviations.sort_by(|a, b| b.distance.total_cmp(&a.distance));
+    let mut flags: Vec<Deviation> = Vec::with_capacity(settings.flags);
+    for deviation in deviations
+        .iter()
+        .filter(|deviation| deviation.distance > diagonal * FLAG_NOISE)
+    {
+        if flags.len() == settings.flags {
+            break;
+        }
+        if flags
+            .iter()
+            .all(|flag| flag.position.distance(deviation.position) >= height)
+        {
+            flags.push(*deviation);
+        }
+    }
+    flags
+}
+
+fn bounds_diagonal(positions: &[[f32; 3]]) -> f32 {
+    let (min, max) = positions.iter().fold(
+        (Vec3::splat(f32::INFINITY), Vec3::splat(f32::NEG_INFINITY)),
+        |(min, max), &position| (min.min(position.into()), max.max(position.into())),
+    );
+    (max - min).length()
+}
+
+/// Appends a three-sided spike pointing away from the surface at every flag, colored like the
+/// deviation it marks. The spike vertices copy the other attributes of the closest corner of the
+/// flagged triangle.
+fn add_flags(
+    mesh: &mut Mesh,
+    flags: &[Deviation],
+    positions: &[[f32; 3]],
+    surface: &SurfaceIndex,
+    settings: &MeshDiffSettings,
+    colors: &mut Vec<[f32; 4]>,
+) {
+    let height = bounds_diagonal(positions) * settings.flag_height;
+    let max_deviation = settings
+        .max_deviation
+        .unwrap_or(flags[0].distance)
+        .max(f32::EPSILON);
+    let Some(Indices::U32(indices)) = mesh.indices() else {
+        return;
+    };
+    let sources: Vec<u32> = flags
+        .iter()
+        .flat_map(|flag| {
+            let corner = flag.barycentric.max_position();
+            [indices[flag.triangle as usize * 3 + corner]; 4]
+        })
+        .collect();
+
+    let base = mesh.count_vertices() as u32;
+    append_vertices(mesh, &sources);
+
+    let mut flag_positions = Vec::with_capacity(sources.len());
+    let mut flag_normals = Vec::with_capacity(sources.len());
+    let mut flag_indices = Vec::with_capacity(flags.len() * 9);
+    for (flag_index, flag) in flags.iter().enumerate() {
+        let normal = match surface.triangle_normal(flag.triangle) {
+            Vec3::ZERO => Vec3::Y,
+            normal => normal,
+        };
+        let (tangent, bitangent) = normal.any_orthonormal_pair();
+        let radius = height * 0.1;
+        let first = base + flag_index as u32 * 4;
+        for angle in [0.0f32, 120.0, 240.0] {
+            let (sin, cos) = angle.to_radians().sin_cos();
+            flag_positions.push(flag.position + (tangent * cos + bitangent * sin) * radius);
+        }
+        flag_positions.push(flag.position + normal * height);
+        flag_normals.extend([normal; 4]);
+        for side in 0..3 {
+            flag_indices.extend([first + side, first + (side + 1) % 3, first + 3]);
+        }
+        colors.extend([ramp(flag.distance / max_deviation); 4]);
+    }
+
+    if let Some(VertexAttributeValues::Float32x3(positions)) =
+        mesh.attribute_mut(Mesh::ATTRIBUTE_POSITION)
+    {
+        let start = positions.len() - flag_positions.len();
+        for (position, flag_position) in positions[start..].iter_mut().zip(&flag_positions) {
+            *position = flag_position.to_array();
+        }
+    }
+    if let Some(VertexAttributeValues::Float32x3(normals)) =
+        mesh.attribute_mut(Mesh::ATTRIBUTE_NORMAL)
+    {
+        let start = normals.len() - flag_normals.len();
+        for (normal, flag_normal) in normals[start..].iter_mut().zip(&flag_normals) {
+            *normal = flag_normal.to_array();
+        }
+    }
+    if let Some(Indices::U32(indices)) = mesh.indices_mut() {
+        indices.extend(flag_indices);
+    }
+}
+
+/// First triangle using every vertex, along with the corner of the vertex in it.
+fn triangle_of_vertices(indices: &[u32], vertex_count: usize) -> Vec<Option<(u32, usize)>> {
+    let mut triangles = vec![None; vertex_count];
+    for (index, &vertex) in indices.iter().enumerate() {
+        triangles[vertex as usize].get_or_insert(((index / 3) as u32, index % 3));
+    }
+    triangles
+}
+
+#[derive(Debug, Copy, Clone)]
+enum Sample {
+    Vertex(u32),
+    Surface { triangle: u32, barycentric: Vec3 },
+}
+
+/// Every used vertex of the surface, followed by `count` random points spread by area.
+fn samples(
+    indices: &[u32],
+    positions: &[[f32; 3]],
+    count: u32,
+    random: &mut Random,
+) -> Vec<Sample> {
+    let mut used = vec![false; positions.len()];
+    for &vertex in indices {
+        used[vertex as usize] = true;
+    }
+    let mut samples: Vec<Sample> = (0..positions.len() as u32)
+        .filter(|&vertex| used[vertex as usize])
+        .map(Sample::Vertex)
+        .collect();
+
+    let mut cumulative_area = Vec::with_capacity(indices.len() / 3);
+    let mut total = 0.0;
+    for corners in indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|corner| Vec3::from(positions[corners[corner] as usize]));
+        total += (b - a).cross(c - a).length();
+        cumulative_area.push(total);
+    }
+    if total <= 0.0 {
+        return samples;
+    }
+
+    samples.reserve(count as usize);
+    for _ in 0..count {
+        let target = random.next() * total;
+        let triangle = cumulative_area
+            .partition_point(|&area| area < target)
+            .min(cumulative_area.len() - 1);
+        // Uniform point in the triangle.
+        let (r1, r2) = (random.next().sqrt(), random.next());
+        samples.push(Sample::Surface {
+            triangle: triangle as u32,
+            barycentric: Vec3::new(1.0 - r1, r1 * (1.0 - r2), r1 * r2),
+        });
+    }
+    samples
+}
+
+/// splitmix64, so the samples only depend on the seed.
+struct Random(u64);
+
+impl Random {
+    fn new(seed: u64) -> Self {
+        Random(seed)
+    }
+
+    /// Uniform value in `0.0..1.0`.
+    fn next(&mut self) -> f32 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        (z >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+
+/// Writes `indices` into `out` without the second triangle of every pair of coincident triangles
+/// with opposite winding, i.e. double-sided faces baked as two triangles. Vertices are compared
+/// by position. Returns the number of removed triangles.
+pub(crate) fn merge_double_sided(
+    indices: &[u32],
+    positions: &[[f32; 3]],
+    out: &mut Vec<u32>,
+) -> usize {
+    let (_, remap) = meshopt::generate_vertex_remap(positions, Some(indices));
+
+    // Oriented triangle, rotated to start at its smallest welded vertex -> unpaired triangles.
+    let mut unpaired: HashMap<[u32; 3], Vec<u32>> = HashMap::new();
+    let mut removed = vec![false; indices.len() / 3];
+    for (triangle, corners) in indices.chunks_exact(3).enumerate() {
+        let [a, b, c] = [0, 1, 2].map(|corner| remap[corners[corner] as usize]);
+        if a == b || b == c || c == a {
+            continue;
+        }
+
+        let key = match a.min(b).min(c) {
+            min if min == a => [a, b, c],
+            min if min == b => [b, c, a],
+            _ => [c, a, b],
+        };
+        let flipped = [key[0], key[2], key[1]];
+        match unpaired.get_mut(&flipped).and_then(Vec::pop) {
+            Some(_) => removed[triangle] = true,
+            None => unpaired.entry(key).or_default().push(triangle as u32),
+        }
+    }
+
+    out.clear();
+    for (corners, removed) in indices.chunks_exact(3).zip(&removed) {
+        if !removed {
+            out.extend_from_slice(corners);
+        }
+    }
+    removed.iter().filter(|removed| **removed).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{
+        asset::Handle,
+        mesh::{Indices, Mesh, VertexAttributeValues},
+    };
+
+    use crate::{
+        MeshExt, OptError, SimplifyParams, TargetIndices,
+        test_util::{grid, indices, sphere, triangle_set, with_u16_indices},
+    };
+
+    /// [`grid`] followed by a copy of its vertices facing down and its triangles wound the other
+    /// way, the back faces a double-sided export bakes.
+    fn double_sided_grid(cells: u32) -> Mesh {
+        let mut mesh = grid(cells);
+        let vertex_count = mesh.count_vertices() as u32;
+        for (_, values) in mesh.attributes_mut() {
+            match values {
+                VertexAttributeValues::Float32x3(values) => values.extend_from_within(..),
+                VertexAttributeValues::Float32x2(values) => values.extend_from_within(..),
+                _ => unreachable!(),
+            }
+        }
+        let Some(VertexAttributeValues::Float32x3(normals)) =
+            mesh.attribute_mut(Mesh::ATTRIBUTE_NORMAL)
+        else {
+            unreachable!()
+        };
+        for normal in &mut normals[vertex_count as usize..] {
+            normal[1] = -normal[1];
+        }
+
+        let mut indices = indices(&mesh);
+        let back: Vec<u32> = indices
+            .chunks_exact(3)
+            .flat_map(|triangle| [triangle[0], triangle[2], triangle[1]].map(|i| i + vertex_count))
+            .collect();
+        indices.extend(back);
+        mesh.insert_indices(Indices::U32(indices));
+        mesh
+    }
+
+    #[test]
+    fn double_sided_grid_collapses_to_its_front() {
+        let cells = 4;
+        let front = grid(cells);
+        let mut mesh = double_sided_grid(cells);
+        assert_eq!(mesh.count_vertices(), 2 * front.count_vertices());
+
+        let removed = mesh.merge_double_sided().unwrap();
+        assert_eq!(removed, (2 * cells * cells) as usize);
+        assert_eq!(mesh.count_vertices(), front.count_vertices());
+        assert_eq!(triangle_set(&mesh), triangle_set(&front));
+        assert_eq!(mesh.merge_double_sided().unwrap(), 0);
+    }
+
+    #[test]
+    fn u16_double_sided_grid_keeps_its_index_format() {
+        let mut mesh = with_u16_indices(double_sided_grid(4));
+        assert_eq!(mesh.merge_double_sided().unwrap(), 32);
+        assert!(matches!(mesh.indices(), Some(Indices::U16(_))));
+        assert_eq!(triangle_set(&mesh), triangle_set(&grid(4)));
+    }
+
+    #[test]
+    fn report_counts_the_merged_triangles() {
+        let params = SimplifyParams {
+            merge_double_sided: true,
+            target_index_count: TargetIndices::Multiplier(1.0),
+            ..Default::default()
+        };
+        for mut mesh in [double_sided_grid(4), with_u16_indices(double_sided_grid(4))] {
+            let report = mesh.simplify_with_report(&params).unwrap();
+            assert_eq!(report.double_sided_triangles, 32);
+            assert_eq!(report.triangles_after(), 32);
+        }
+    }
+
+    #[test]
+    fn merge_refuses_morph_targets() {
+        let mut mesh = sphere(2);
+        mesh.set_morph_targets(Handle::default());
+        let result = mesh.merge_double_sided();
+        assert!(matches!(result, Err(OptError::MorphTargetsUnsupported)));
+    }
+}
@@ -0,0 +1,294 @@
+use std::{fs, io, path::Path};
+
+use bevy::mesh::{
+    Indices, Mesh, MeshVertexAttribute, MeshVertexAttributeId, PrimitiveTopology,
+    VertexAttributeValues, VertexFormat,
+};
+use serde_json::{Value, json};
+
+use crate::{CompressedMeshFile, OptError};
+
+pub(crate) const GLB_MAGIC: &[u8; 4] = b"glTF";
+pub(crate) const GLB_HEADER_SIZE: usize = 12;
+pub(crate) const JSON_CHUNK: u32 = 0x4E4F534A;
+pub(crate) const BIN_CHUNK: u32 = 0x004E4942;
+
+pub(crate) const ARRAY_BUFFER: u32 = 34962;
+pub(crate) const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+/// Bevy attributes with a glTF counterpart, the ones Bevy's glTF loader maps.
+pub(crate) const SEMANTICS: [(MeshVertexAttribute, &str); 8] = [
+    (Mesh::ATTRIBUTE_POSITION, "POSITION"),
+    (Mesh::ATTRIBUTE_NORMAL, "NORMAL"),
+    (Mesh::ATTRIBUTE_TANGENT, "TANGENT"),
+    (Mesh::ATTRIBUTE_UV_0, "TEXCOORD_0"),
+    (Mesh::ATTRIBUTE_UV_1, "TEXCOORD_1"),
+    (Mesh::ATTRIBUTE_COLOR, "COLOR_0"),
+    (Mesh::ATTRIBUTE_JOINT_INDEX, "JOINTS_0"),
+    (Mesh::ATTRIBUTE_JOINT_WEIGHT, "WEIGHTS_0"),
+];
+
+/// Writes `mesh` to `path` in the format of its extension, creating the missing directories:
+/// - `.glb`, binary glTF as written by [`meshes_to_glb`], loaded back as the `Mesh0/Primitive0`
+///   label of the glTF or by any other tool.
+/// - `.meshopt`, the compressed [`CompressedMeshFile`] the
+///   [`CompressedMeshLoader`](crate::CompressedMeshLoader) of `MeshoptPlugin` loads, smaller and
+///   keeping every attribute as it is.
+///
+/// Fails with [`io::ErrorKind::InvalidInput`] for other extensions and for meshes the format
+/// can't store.
+pub fn save_mesh(path: impl AsRef<Path>, mesh: &Mesh) -> io::Result<()> {
+    let path = path.as_ref();
+    let invalid_input = |err: OptError| io::Error::new(io::ErrorKind::InvalidInput, err);
+    let bytes = match path.extension().and_then(|extension| extension.to_str()) {
+        Some("glb") => meshes_to_glb([mesh]).map_err(invalid_input)?,
+        Some("meshopt") => CompressedMeshFile::from_meshes([mesh])
+            .map_err(invalid_input)?
+            .to_bytes(),
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("can't save meshes as {}", path.display()),
+            ));
+        }
+    };
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, bytes)
+}
+
+/// Binary glTF holding every mesh as a glTF mesh of one primitive, `Mesh0/Primitive0` onwards,
+/// each in a node of the default scene. Materials and morph targets aren't written.
+///
+/// The attributes Bevy's glTF loader maps, like `ATTRIBUTE_NORMAL` or `ATTRIBUTE_UV_0`, are
+/// written as their glTF counterparts, others as application-specific attributes named after the
+/// attribute with a leading underscore, e.g. `_Vertex_AO`, to register with
+/// `GltfPlugin::add_custom_vertex_attribute`. Fails with
+/// [`OptError::UnsupportedExportFormat`] for attributes whose format glTF can't store, like
+/// `Float16x2` or `Sint32`.
+pub fn meshes_to_glb<'a>(meshes: impl IntoIterator<Item = &'a Mesh>) -> Result<Vec<u8>, OptError> {
+    let mut bin = Vec::new();
+    let mut views = Vec::new();
+    let mut accessors = Vec::new();
+    let mut gltf_meshes = Vec::new();
+    let mut add_accessor = |bytes: &[u8], target: u32, mut accessor: Value, size: usize| {
+        let (data, mut view) = view_data(bytes, target, size);
+        view["buffer"] = json!(0);
+        view["byteOffset"] = json!(bin.len());
+        bin.extend_from_slice(&data);
+        bin.resize(bin.len().next_multiple_of(4), 0);
+
+        accessor["bufferView"] = json!(views.len());
+        views.push(view);
+        accessors.push(accessor);
+        accessors.len() - 1
+    };
+
+    for mesh in meshes {
+        let mode = match mesh.primitive_topology() {
+            PrimitiveTopology::PointList => 0,
+            PrimitiveTopology::LineList => 1,
+            PrimitiveTopology::LineStrip => 3,
+            PrimitiveTopology::TriangleList => 4,
+            PrimitiveTopology::TriangleStrip => 5,
+        };
+
+        let mut attributes = serde_json::Map::new();
+        for (attribute, values) in mesh.attributes() {
+            let accessor = attribute_accessor(attribute, values)?;
+            let size = attribute.format.size() as usize;
+            let index = add_accessor(values.get_bytes(), ARRAY_BUFFER, accessor, size);
+            attributes.insert(semantic(attribute.name, attribute.id), json!(index));
+        }
+
+        let mut primitive = json!({ "attributes": attributes, "mode": mode });
+        if let Some(indices) = mesh.indices() {
+            let (accessor, bytes, size) = index_accessor(indices);
+            primitive["indices"] =
+                json!(add_accessor(&bytes, ELEMENT_ARRAY_BUFFER, accessor, size));
+        }
+        gltf_meshes.push(json!({ "primitives": [primitive] }));
+    }
+
+    let nodes: Vec<Value> = (0..gltf_meshes.len())
+        .map(|mesh| json!({ "mesh": mesh }))
+        .collect();
+    let mut json = json!({
+        "asset": { "version": "2.0", "generator": "bevy_meshopt" },
+        "scene": 0,
+        "scenes": [{ "nodes": (0..nodes.len()).collect::<Vec<_>>() }],
+        "nodes": nodes,
+        "meshes": gltf_meshes,
+        "accessors": accessors,
+        "bufferViews": views,
+    });
+    if !bin.is_empty() {
+        json["buffers"] = json!([{ "byteLength": bin.len() }]);
+    }
+    Ok(glb(&json, &bin))
+}
+
+/// glTF accessor of the `values` of `attribute`, without its buffer view.
+pub(crate) fn attribute_accessor(
+    attribute: &MeshVertexAttribute,
+    values: &VertexAttributeValues,
+) -> Result<Value, OptError> {
+    let (component_type, normalized) =
+        component_type(attribute.format).ok_or(OptError::UnsupportedExportFormat {
+            attribute: attribute.name,
+            format: attribute.format,
+        })?;
+    let size = attribute.format.size() as usize;
+    let mut accessor = json!({
+        "componentType": component_type,
+        "count": values.len(),
+        "type": accessor_type(size / component_size(component_type)),
+    });
+    if normalized {
+        accessor["normalized"] = json!(true);
+    }
+    if let VertexAttributeValues::Float32x3(positions) = values
+        && attribute.id == Mesh::ATTRIBUTE_POSITION.id
+    {
+        insert_bounds(&mut accessor, positions);
+    }
+    Ok(accessor)
+}
+
+/// glTF accessor of `indices` without its buffer view, their bytes and the size of one index.
+pub(crate) fn index_accessor(indices: &Indices) -> (Value, Vec<u8>, usize) {
+    let (bytes, component_type, size): (Vec<u8>, _, _) = match indices {
+        Indices::U16(indices) => (
+            indices
+                .iter()
+                .flat_map(|index| index.to_le_bytes())
+                .collect(),
+            5123,
+            2,
+        ),
+        Indices::U32(indices) => (
+            indices
+                .iter()
+                .flat_map(|index| index.to_le_bytes())
+                .collect(),
+            5125,
+            4,
+        ),
+    };
+    let accessor = json!({
+        "componentType": component_type,
+        "count": indices.len(),
+        "type": "SCALAR",
+    });
+    (accessor, bytes, size)
+}
+
+/// Data of a buffer view for `target` holding `bytes`, values of `size` bytes each, and the view
+/// without its buffer and offset.
+pub(crate) fn view_data(bytes: &[u8], target: u32, size: usize) -> (Vec<u8>, Value) {
+    // Vertex attributes have to be aligned to 4 bytes.
+    let stride = match target {
+        ARRAY_BUFFER => size.next_multiple_of(4),
+        _ => size,
+    };
+    let mut view = json!({
+        "byteLength": bytes.len() / size * stride,
+        "target": target,
+    });
+    if stride == size {
+        return (bytes.to_vec(), view);
+    }
+    view["byteStride"] = json!(stride);
+    let mut data = Vec::with_capacity(bytes.len() / size * stride);
+    for value in bytes.chunks_exact(size) {
+        data.extend_from_slice(value);
+        data.resize(data.len() + stride - size, 0);
+    }
+    (data, view)
+}
+
+/// Sets the `min` and `max` glTF requires of position accessors.
+fn insert_bounds(accessor: &mut Value, positions: &[[f32; 3]]) {
+    let (min, max) =
+        positions
+            .iter()
+            .fold(([f32::MAX; 3], [f32::MIN; 3]), |(min, max), position| {
+                (
+                    [0, 1, 2].map(|axis| min[axis].min(position[axis])),
+                    [0, 1, 2].map(|axis| max[axis].max(position[axis])),
+                )
+            });
+    if !positions.is_empty() {
+        accessor["min"] = json!(min);
+        accessor["max"] = json!(max);
+    }
+}
+
+/// glTF attribute name of a mesh attribute, see [`meshes_to_glb`].
+pub(crate) fn semantic(name: &str, id: MeshVertexAttributeId) -> String {
+    match SEMANTICS.iter().find(|(attribute, _)| attribute.id == id) {
+        Some((_, semantic)) => semantic.to_string(),
+        None => format!("_{name}"),
+    }
+}
+
+/// glTF component type of a vertex format and whether it is normalized, `None` for formats
+/// glTF doesn't have.
+fn component_type(format: VertexFormat) -> Option<(u32, bool)> {
+    use VertexFormat::*;
+    Some(match format {
+        Sint8 | Sint8x2 | Sint8x4 => (5120, false),
+        Snorm8 | Snorm8x2 | Snorm8x4 => (5120, true),
+        Uint8 | Uint8x2 | Uint8x4 => (5121, false),
+        Unorm8 | Unorm8x2 | Unorm8x4 => (5121, true),
+        Sint16 | Sint16x2 | Sint16x4 => (5122, false),
+        Snorm16 | Snorm16x2 | Snorm16x4 => (5122, true),
+        Uint16 | Uint16x2 | Uint16x4 => (5123, false),
+        Unorm16 | Unorm16x2 | Unorm16x4 => (5123, true),
+        Uint32 | Uint32x2 | Uint32x3 | Uint32x4 => (5125, false),
+        Float32 | Float32x2 | Float32x3 | Float32x4 => (5126, false),
+        _ => return None,
+    })
+}
+
+fn component_size(component_type: u32) -> usize {
+    match component_type {
+        5120 | 5121 => 1,
+        5122 | 5123 => 2,
+        _ => 4,
+    }
+}
+
+fn accessor_type(components: usize) -> &'static str {
+    match components {
+        1 => "SCALAR",
+        2 => "VEC2",
+        3 => "VEC3",
+        _ => "VEC4",
+    }
+}
+
+/// `.glb` file made of `json` and the binary chunk `bin`, padded to 4 bytes.
+pub(crate) fn glb(json: &Value, bin: &[u8]) -> Vec<u8> {
+    let mut json = json.to_string().into_bytes();
+    json.resize(json.len().next_multiple_of(4), b' ');
+    let bin_length = bin.len().next_multiple_of(4);
+    let bin_chunk = bin_length > 0;
+    let length = GLB_HEADER_SIZE + 8 + json.len() + if bin_chunk { 8 + bin_length } else { 0 };
+
+    let mut glb = Vec::with_capacity(length);
+    glb.extend_from_slice(GLB_MAGIC);
+    glb.extend_from_slice(&2u32.to_le_bytes());
+    glb.extend_from_slice(&(length as u32).to_le_bytes());
+    glb.extend_from_slice(&(json.len() as u32).to_le_bytes());
+    glb.extend_from_slice(&JSON_CHUNK.to_le_bytes());
+    glb.extend_from_slice(&json);
+    if bin_chunk {
+        glb.extend_from_slice(&(bin_length as u32).to_le_bytes());
+        glb.extend_from_slice(&BIN_CHUNK.to_le_bytes());
+        glb.extend_from_slice(bin);
+        glb.resize(length, 0);
+    }
+    glb
+}
@@ -0,0 +1,175 @@
+use bevy::reflect::{Reflect, std_traits::ReflectDefault};
+use meshopt::SimplifyOptions;
+
+use crate::{
+    SimplifyMode, SimplifyParams, TargetIndices,
+    simplify::{SimplifyInput, run_simplifier},
+};
+
+/// What to do when the simplifier stops above [`SimplifyParams::target_index_count`] by more than
+/// [`SimplifyParams::fallback_tolerance`], typically because no further collapse fits in
+/// `max_error` (long thin ribbons, heavily non-manifold scans).
+#[derive(Debug, Copy, Clone, PartialEq, Default, Reflect)]
+#[reflect(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub enum FallbackPolicy {
+    /// Keeps the result of the simplifier.
+    #[default]
+    None,
+    /// Reruns with the sloppy simplifier, which reaches the target regardless of topology, within
+    /// `max_error`. This is the usual way to get aggressive LODs out of meshes the regular
+    /// simplifier stalls on, e.g. ones with locked borders everywhere. Attributes, planar regions
+    /// and `SimplifyOptions` are ignored by it, locks are kept.
+    Sloppy { max_error: f32 },
+    /// Retries with `max_error` multiplied by `factor` on every attempt, until the target is met
+    /// or `max_attempts` retries were made. The last retry is kept even if it misses the target.
+    /// Has no effect with a `max_error` of zero.
+    RelaxError { factor: f32, max_attempts: u32 },
+}
+
+/// Run that produced a simplification result, see [`SimplifyReport::path`](crate::SimplifyReport::path).
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum SimplifyPath {
+    /// The simplifier selected by [`SimplifyParams::mode`], no fallback was needed.
+    #[default]
+    Standard,
+    /// [`FallbackPolicy::Sloppy`] engaged.
+    Sloppy,
+    /// [`FallbackPolicy::RelaxError`] engaged, the result is from retry number `attempts` with
+    /// `max_error`.
+    RelaxedError { attempts: u32, max_error: f32 },
+}
+
+impl SimplifyPath {
+    /// Whether a [`FallbackPolicy`] produced the result.
+    pub fn is_fallback(&self) -> bool {
+        *self != SimplifyPath::Standard
+    }
+}
+
+/// Applies `params.fallback` if the result in `out`, simplified from `input` with a resulting
+/// `error`, is too far above `target_index_count`.
+pub(crate) fn apply_fallback(
+    out: &mut Vec<u32>,
+    input: SimplifyInput,
+    target_index_count: usize,
+    params: &SimplifyParams,
+    error: f32,
+) -> (f32, SimplifyPath) {
+    if params.target_index_count == TargetIndices::ErrorOnly {
+        return (error, SimplifyPath::Standard);
+    }
+    let tolerance = params.fallback_tolerance.max(0.0);
+    let limit = target_index_count.min(input.indices.len()) as f32 * (1.0 + tolerance);
+    let missed = |out: &[u32]| out.len() as f32 > limit;
+    if !missed(out) {
+        return (error, SimplifyPath::Standard);
+    }
+
+    match params.fallback {
+        FallbackPolicy::None => (error, SimplifyPath::Standard),
+        FallbackPolicy::Sloppy { max_error } => {
+            let sloppy = SimplifyParams {
+                mode: SimplifyMode::Sloppy,
+                max_error,
+                options: SimplifyOptions::None,
+                ..params.clone()
+            };
+            // Protected vertices would read as locked to the sloppy simplifier.
+            let input = SimplifyInput {
+                attributes: None,
+                protect: None,
+                ..input
+            };
+            let error = run_simplifier(out, input, target_index_count, &sloppy);
+            (error, SimplifyPath::Sloppy)
+        }
+        FallbackPolicy::RelaxError {
+            factor,
+            max_attempts,
+        } => {
+            let mut result = (error, SimplifyPath::Standard);
+            let mut max_error = params.max_error;
+            for attempts in 1..=max_attempts {
+                max_error *= factor;
+                let relaxed = SimplifyParams {
+                    max_error,
+                    ..params.clone()
+                };
+                let error = run_simplifier(out, input, target_index_count, &relaxed);
+                result = (
+                    error,
+                    SimplifyPath::RelaxedError {
+                        attempts,
+                        max_error,
+                    },
+                );
+                if !missed(out) {
+                    break;
+                }
+            }
+            result
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MeshExt, SimplifyReport, test_util::ribbon};
+
+    /// Triangles the ribbon is simplified to, within the default `fallback_tolerance`.
+    const TARGET: usize = 200;
+
+    /// Report of simplifying a coiled ribbon of 800 triangles to a quarter of them within an error
+    /// too tight for the regular simplifier to get there.
+    fn simplify_ribbon(fallback: FallbackPolicy) -> SimplifyReport {
+        let mut mesh = ribbon(400, 10.0);
+        mesh.simplify_with_report(&SimplifyParams {
+            target_index_count: TargetIndices::Multiplier(0.25),
+            max_error: 0.001,
+            fallback,
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    fn within_tolerance(report: &SimplifyReport) -> bool {
+        report.triangles_after() as f32 <= TARGET as f32 * 1.1
+    }
+
+    #[test]
+    fn ribbon_stalls_without_fallback() {
+        let report = simplify_ribbon(FallbackPolicy::None);
+        assert_eq!(report.triangles_before(), 4 * TARGET);
+        assert!(!within_tolerance(&report), "{report:?}");
+        assert_eq!(report.path, SimplifyPath::Standard);
+    }
+
+    #[test]
+    fn sloppy_fallback_reaches_the_target() {
+        let report = simplify_ribbon(FallbackPolicy::Sloppy { max_error: 1.0 });
+        assert!(within_tolerance(&report), "{report:?}");
+        assert_eq!(report.path, SimplifyPath::Sloppy);
+        assert!(report.path.is_fallback());
+    }
+
+    #[test]
+    fn relaxed_error_reaches_the_target() {
+        let report = simplify_ribbon(FallbackPolicy::RelaxError {
+            factor: 4.0,
+            max_attempts: 6,
+        });
+        assert!(within_tolerance(&report), "{report:?}");
+        let SimplifyPath::RelaxedError {
+            attempts,
+            max_error,
+        } = report.path
+        else {
+            panic!("{:?}", report.path);
+        };
+        assert!(attempts < 6);
+        assert!((max_error - 0.001 * 4f32.powi(attempts as i32)).abs() < 1e-6);
+        assert!(report.result_error <= max_error);
+    }
+}
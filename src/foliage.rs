@@ -0,0 +1,226 @@
+use bevy::{
+    math::Vec3,
+    reflect::{Reflect, std_traits::ReflectDefault},
+};
+
+/// How the simplifier reduces the triangle count.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Reflect)]
+#[reflect(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub enum SimplifyStrategy {
+    /// Collapses edges until the target or the error bound is reached.
+    #[default]
+    EdgeCollapse,
+    /// Meant for foliage made of alpha-tested cards: small disconnected planar components are
+    /// never collapsed, whole cards are removed instead, those with the smallest area first,
+    /// until they are reduced by the same ratio as the rest of the mesh. The rest (trunks,
+    /// branches) is edge-collapsed as usual. Removing cards doesn't count towards the error.
+    CardRemoval,
+}
+
+/// Most triangles a connected component can have to be treated as a card, enough for a quad
+/// split in a few segments, double-sided.
+const MAX_CARD_TRIANGLES: usize = 16;
+/// Cosine of the angle within which the faces of a card have to stay to the first one, in either
+/// direction so double-sided cards count.
+const CARD_PLANARITY: f32 = 0.94;
+
+/// Removes the smallest cards of `indices` until the card triangles are reduced by the same
+/// ratio as `target_index_count`, writing the kept cards into `cards` and every triangle that
+/// isn't part of a card into `rest`. Returns the index count left for `rest`.
+pub(crate) fn remove_cards(
+    indices: &[u32],
+    positions: &[[f32; 3]],
+    target_index_count: usize,
+    cards: &mut Vec<u32>,
+    rest: &mut Vec<u32>,
+) -> usize {
+    cards.clear();
+    rest.clear();
+
+    let (_, remap) = meshopt::generate_vertex_remap(positions, Some(indices));
+    let components = connected_components(indices, &remap);
+    let position = |index: u32| Vec3::from_array(positions[index as usize]);
+
+    // Triangles of every component, ordered by component.
+    let mut order: Vec<u32> = (0..(indices.len() / 3) as u32).collect();
+    order.sort_by_key(|&triangle| components[triangle as usize]);
+
+    let mut candidates: Vec<(f32, &[u32])> = Vec::new();
+    for triangles in order.chunk_by(|a, b| components[*a as usize] == components[*b as usize]) {
+        let mut area = 0.0;
+        let mut reference = Vec3::ZERO;
+        let mut planar = triangles.len() <= MAX_CARD_TRIANGLES;
+        for &triangle in triangles {
+            let corners = &indices[triangle as usize * 3..][..3];
+            let [a, b, c] = [0, 1, 2].map(|corner| position(corners[corner]));
+            let cross = (b - a).cross(c - a);
+            area += cross.length() * 0.5;
+            let normal = cross.normalize_or_zero();
+            if reference == Vec3::ZERO {
+                reference = normal;
+            } else if normal != Vec3::ZERO && normal.dot(reference).abs() < CARD_PLANARITY {
+                planar = false;
+            }
+        }
+
+        if planar {
+            candidates.push((area, triangles));
+        } else {
+            for &triangle in triangles {
+                rest.extend_from_slice(&indices[triangle as usize * 3..][..3]);
+            }
+        }
+    }
+
+    let card_index_count: usize = candidates
+        .iter()
+        .map(|(_, triangles)| triangles.len() * 3)
+        .sum();
+    let ratio = (target_index_count as f32 / indices.len().max(1) as f32).min(1.0);
+    let card_budget = (card_index_count as f32 * ratio) as usize;
+
+    // Largest cards first, ties keep their order so results are deterministic.
+    candidates.sort_by(|a, b| b.0.total_cmp(&a.0));
+    for (_, triangles) in candidates {
+        if cards.len() + triangles.len() * 3 > card_budget {
+            continue;
+        }
+        for &triangle in triangles {
+            cards.extend_from_slice(&indices[triangle as usize * 3..][..3]);
+        }
+    }
+
+    target_index_count.saturating_sub(cards.len())
+}
+
+/// Component of every triangle, triangles sharing a welded vertex are connected.
+fn connected_components(indices: &[u32], remap: &[u32]) -> Vec<u32> {
+    let mut parents: Vec<u32> = (0..remap.len() as u32).collect();
+    fn find(parents: &mut [u32], mut vertex: u32) -> u32 {
+        while parents[vertex as usize] != vertex {
+            let parent = parents[vertex as usize];
+            parents[vertex as usize] = parents[parent as usize];
+            vertex = parent;
+        }
+        vertex
+    }
+
+    for corners in indices.chunks_exact(3) {
+        let root = find(&mut parents, remap[corners[0] as usize]);
+        for &corner in &corners[1..] {
+            let other = find(&mut parents, remap[corner as usize]);
+            parents[other as usize] = root;
+        }
+    }
+
+    indices
+        .chunks_exact(3)
+        .map(|corners| find(&mut parents, remap[corners[0] as usize]))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use bevy::{
+        asset::RenderAssetUsages,
+        mesh::{Indices, Mesh, PrimitiveTopology},
+    };
+
+    use super::*;
+    use crate::{
+        MeshExt, SimplifyParams, TargetIndices,
+        test_util::{indices, positions, sphere, triangle_set},
+    };
+
+    const CARDS: u32 = 500;
+
+    /// Trunk made of a sphere and `CARDS` square leaf cards of distinct sizes around it, facing
+    /// every way around Y. Positions only, with the size of every card.
+    fn tree() -> (Mesh, Vec<f32>) {
+        let trunk = sphere(2);
+        let mut positions = positions(&trunk).to_vec();
+        let mut indices = indices(&trunk);
+        let mut sizes = Vec::new();
+        for card in 0..CARDS {
+            let size = 0.05 + 0.001 * ((card * 7919) % CARDS) as f32;
+            let angle = card as f32 * 2.399;
+            let center = Vec3::new(angle.cos() * 2.0, card as f32 * 0.01, angle.sin() * 2.0);
+            let [right, up] =
+                [Vec3::new(angle.sin(), 0.0, -angle.cos()), Vec3::Y].map(|axis| axis * size * 0.5);
+            let first = positions.len() as u32;
+            positions.extend(
+                [-right - up, right - up, right + up, -right + up]
+                    .map(|corner| (center + corner).to_array()),
+            );
+            indices.extend([0, 1, 2, 0, 2, 3].map(|corner| first + corner));
+            sizes.push(size);
+        }
+        let mesh = Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::default(),
+        )
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+        .with_inserted_indices(Indices::U32(indices));
+        (mesh, sizes)
+    }
+
+    fn card_removal(multiplier: f32) -> SimplifyParams {
+        SimplifyParams {
+            strategy: SimplifyStrategy::CardRemoval,
+            target_index_count: TargetIndices::Multiplier(multiplier),
+            max_error: 1.0,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn cards_are_kept_whole_largest_first() {
+        let (tree, sizes) = tree();
+        let trunk_triangles = indices(&sphere(2)).len() / 3;
+        // The triangles of every card, as `triangle_set` gives them.
+        let cards: Vec<Vec<[[u32; 3]; 3]>> = (0..CARDS as usize)
+            .map(|card| {
+                let first = trunk_triangles + card * 2;
+                let mut card_mesh = tree.clone();
+                card_mesh.insert_indices(Indices::U32(indices(&tree)[first * 3..][..6].to_vec()));
+                triangle_set(&card_mesh)
+            })
+            .collect();
+
+        for multiplier in [0.5, 0.1] {
+            let mut simplified = tree.clone();
+            simplified
+                .simplify_with_report(&card_removal(multiplier))
+                .unwrap();
+            let triangles: HashSet<_> = triangle_set(&simplified).into_iter().collect();
+
+            let mut kept = Vec::new();
+            let mut removed = Vec::new();
+            for (card, size) in cards.iter().zip(&sizes) {
+                let present = card.iter().filter(|t| triangles.contains(*t)).count();
+                assert!(
+                    present == 0 || present == card.len(),
+                    "card partly collapsed"
+                );
+                if present == 0 {
+                    removed.push(*size);
+                } else {
+                    kept.push(*size);
+                }
+            }
+
+            let expected = (CARDS as f32 * multiplier) as usize;
+            assert!(
+                kept.len().abs_diff(expected) <= 1,
+                "{} cards kept",
+                kept.len()
+            );
+            let smallest_kept = kept.iter().copied().fold(f32::INFINITY, f32::min);
+            let largest_removed = removed.iter().copied().fold(0.0, f32::max);
+            assert!(smallest_kept > largest_removed);
+        }
+    }
+}
@@ -0,0 +1,277 @@
+use bevy::mesh::{Mesh, MeshVertexAttribute, VertexAttributeValues, VertexFormat};
+
+use crate::OptError;
+
+/// Attributes widened by [`MeshExt::with_widened_attributes`](crate::MeshExt::with_widened_attributes)
+/// and the ones that didn't get their original format back.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct FormatReport {
+    /// Number of attributes that were converted to `f32` for processing.
+    pub widened: usize,
+    /// Attributes now stored in another format than the one they came in.
+    pub changes: Vec<FormatChange>,
+}
+
+impl FormatReport {
+    /// Whether every attribute has the format it came in.
+    pub fn formats_preserved(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FormatChange {
+    pub attribute: &'static str,
+    pub original: VertexFormat,
+    pub processed: VertexFormat,
+    pub reason: FormatChangeReason,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FormatChangeReason {
+    /// Widening was requested, the attribute is left as `f32`.
+    KeptWidened,
+    /// The processing replaced the attribute with values of another format, they are left as is.
+    Replaced,
+}
+
+/// Converts the normalized attributes to `f32`, runs `f` and encodes them back into their original
+/// format, rounding to the nearest representable value. Attributes are restored even if `f` fails.
+pub(crate) fn with_widened_attributes<R>(
+    mesh: &mut Mesh,
+    keep_widened: bool,
+    f: impl FnOnce(&mut Mesh) -> Result<R, OptError>,
+) -> Result<(R, FormatReport), OptError> {
+    let originals: Vec<MeshVertexAttribute> = mesh
+        .attributes()
+        .map(|(attribute, _)| *attribute)
+        .filter(|attribute| widened_format(attribute.format).is_some())
+        .collect();
+    for original in &originals {
+        if let Some(values) = mesh.remove_attribute(original.id) {
+            let values = widen(&values).unwrap_or(values);
+            let attribute = MeshVertexAttribute {
+                format: VertexFormat::from(&values),
+                ..*original
+            };
+            mesh.insert_attribute(attribute, values);
+        }
+    }
+
+    let result = f(mesh);
+
+    let mut report = FormatReport {
+        widened: originals.len(),
+        changes: Vec::new(),
+    };
+    for original in originals {
+        // Attributes removed by `f` stay removed.
+        let Some(processed) = mesh.attribute(original.id).map(VertexFormat::from) else {
+            continue;
+        };
+        let reason = if Some(processed) != widened_format(original.format) {
+            FormatChangeReason::Replaced
+        } else if keep_widened {
+            FormatChangeReason::KeptWidened
+        } else {
+            let values = mesh.remove_attribute(original.id).unwrap();
+            mesh.insert_attribute(original, narrow(&values, original.format));
+            continue;
+        };
+        report.changes.push(FormatChange {
+            attribute: original.name,
+            original: original.format,
+            processed,
+            reason,
+        });
+    }
+    Ok((result?, report))
+}
+
+/// `f32` format normalized attributes of `format` are widened to, `None` for formats that are
+/// already `f32` or aren't normalized.
+fn widened_format(format: VertexFormat) -> Option<VertexFormat> {
+    match format {
+        VertexFormat::Snorm16x2
+        | VertexFormat::Unorm16x2
+        | VertexFormat::Snorm8x2
+        | VertexFormat::Unorm8x2 => Some(VertexFormat::Float32x2),
+        VertexFormat::Snorm16x4
+        | VertexFormat::Unorm16x4
+        | VertexFormat::Snorm8x4
+        | VertexFormat::Unorm8x4 => Some(VertexFormat::Float32x4),
+        _ => None,
+    }
+}
+
+pub(crate) fn widen(values: &VertexAttributeValues) -> Option<VertexAttributeValues> {
+    use VertexAttributeValues as V;
+    Some(match values {
+        V::Snorm16x2(values) => V::Float32x2(map(values, |v| (v as f32 / 32767.0).max(-1.0))),
+        V::Snorm16x4(values) => V::Float32x4(map(values, |v| (v as f32 / 32767.0).max(-1.0))),
+        V::Unorm16x2(values) => V::Float32x2(map(values, |v| v as f32 / 65535.0)),
+        V::Unorm16x4(values) => V::Float32x4(map(values, |v| v as f32 / 65535.0)),
+        V::Snorm8x2(values) => V::Float32x2(map(values, |v| (v as f32 / 127.0).max(-1.0))),
+        V::Snorm8x4(values) => V::Float32x4(map(values, |v| (v as f32 / 127.0).max(-1.0))),
+        V::Unorm8x2(values) => V::Float32x2(map(values, |v| v as f32 / 255.0)),
+        V::Unorm8x4(values) => V::Float32x4(map(values, |v| v as f32 / 255.0)),
+        _ => return None,
+    })
+}
+
+/// Encodes values widened from `format` back into it, clamping values outside of its range.
+pub(crate) fn narrow(
+    values: &VertexAttributeValues,
+    format: VertexFormat,
+) -> VertexAttributeValues {
+    use VertexAttributeValues as V;
+    let snorm = |v: f32, max: f32| (v.clamp(-1.0, 1.0) * max).round();
+    let unorm = |v: f32, max: f32| (v.clamp(0.0, 1.0) * max).round();
+    match (values, format) {
+        (V::Float32x2(values), VertexFormat::Snorm16x2) => {
+            V::Snorm16x2(map(values, |v| snorm(v, 32767.0) as i16))
+        }
+        (V::Float32x4(values), VertexFormat::Snorm16x4) => {
+            V::Snorm16x4(map(values, |v| snorm(v, 32767.0) as i16))
+        }
+        (V::Float32x2(values), VertexFormat::Unorm16x2) => {
+            V::Unorm16x2(map(values, |v| unorm(v, 65535.0) as u16))
+        }
+        (V::Float32x4(values), VertexFormat::Unorm16x4) => {
+            V::Unorm16x4(map(values, |v| unorm(v, 65535.0) as u16))
+        }
+        (V::Float32x2(values), VertexFormat::Snorm8x2) => {
+            V::Snorm8x2(map(values, |v| snorm(v, 127.0) as i8))
+        }
+        (V::Float32x4(values), VertexFormat::Snorm8x4) => {
+            V::Snorm8x4(map(values, |v| snorm(v, 127.0) as i8))
+        }
+        (V::Float32x2(values), VertexFormat::Unorm8x2) => {
+            V::Unorm8x2(map(values, |v| unorm(v, 255.0) as u8))
+        }
+        (V::Float32x4(values), VertexFormat::Unorm8x4) => {
+            V::Unorm8x4(map(values, |v| unorm(v, 255.0) as u8))
+        }
+        _ => values.clone(),
+    }
+}
+
+fn map<T: Copy, U, const N: usize>(values: &[[T; N]], f: impl Fn(T) -> U) -> Vec<[U; N]> {
+    values.iter().map(|value| value.map(&f)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{asset::RenderAssetUsages, mesh::PrimitiveTopology};
+
+    use super::*;
+    use crate::MeshExt;
+
+    const FORMATS: [(VertexFormat, f32); 8] = [
+        (VertexFormat::Snorm16x2, 1.0 / 32767.0),
+        (VertexFormat::Snorm16x4, 1.0 / 32767.0),
+        (VertexFormat::Unorm16x2, 1.0 / 65535.0),
+        (VertexFormat::Unorm16x4, 1.0 / 65535.0),
+        (VertexFormat::Snorm8x2, 1.0 / 127.0),
+        (VertexFormat::Snorm8x4, 1.0 / 127.0),
+        (VertexFormat::Unorm8x2, 1.0 / 255.0),
+        (VertexFormat::Unorm8x4, 1.0 / 255.0),
+    ];
+
+    /// Every component of `values`, which have to be `f32`.
+    fn floats(values: &VertexAttributeValues) -> Vec<f32> {
+        match values {
+            VertexAttributeValues::Float32x2(values) => values.iter().flatten().copied().collect(),
+            VertexAttributeValues::Float32x4(values) => values.iter().flatten().copied().collect(),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Mesh with only `attribute`, its values spanning the whole range of its format.
+    fn mesh(attribute: MeshVertexAttribute) -> Mesh {
+        let values: Vec<[f32; 4]> = (0..=32)
+            .map(|i| {
+                let v = i as f32 / 16.0 - 1.0;
+                [v, -v, v * 0.37, 1.0 - v.abs()]
+            })
+            .collect();
+        let values = match widened_format(attribute.format) {
+            Some(VertexFormat::Float32x2) => {
+                VertexAttributeValues::Float32x2(values.iter().map(|v| [v[0], v[1]]).collect())
+            }
+            _ => VertexAttributeValues::Float32x4(values),
+        };
+        Mesh::new(PrimitiveTopology::PointList, RenderAssetUsages::default())
+            .with_inserted_attribute(attribute, narrow(&values, attribute.format))
+    }
+
+    #[test]
+    fn processed_attributes_get_their_format_back() {
+        for (i, (format, step)) in FORMATS.into_iter().enumerate() {
+            let attribute = MeshVertexAttribute::new("Vertex_Packed", 1000 + i as u64, format);
+            let source = mesh(attribute);
+            let mut processed = source.clone();
+            let ((), report) = processed
+                .with_widened_attributes(false, |mesh| {
+                    let widened = mesh.attribute_mut(attribute.id).unwrap();
+                    assert_eq!(
+                        VertexFormat::from(&*widened),
+                        widened_format(format).unwrap()
+                    );
+                    // Processing moves the values by less than a step.
+                    match widened {
+                        VertexAttributeValues::Float32x2(values) => {
+                            values.iter_mut().flatten().for_each(|v| *v += step * 0.4)
+                        }
+                        VertexAttributeValues::Float32x4(values) => {
+                            values.iter_mut().flatten().for_each(|v| *v += step * 0.4)
+                        }
+                        _ => unreachable!(),
+                    }
+                    Ok(())
+                })
+                .unwrap();
+            assert_eq!(report.widened, 1);
+            assert!(report.formats_preserved());
+
+            let values = processed.attribute(attribute.id).unwrap();
+            assert_eq!(VertexFormat::from(values), format);
+            let before = floats(&widen(source.attribute(attribute.id).unwrap()).unwrap());
+            let after = floats(&widen(values).unwrap());
+            for (before, after) in before.iter().zip(&after) {
+                assert!(
+                    (after - before).abs() <= step,
+                    "{format:?}: {before} became {after}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn kept_and_replaced_attributes_are_reported() {
+        let attribute = MeshVertexAttribute::new("Vertex_Packed", 1000, VertexFormat::Unorm8x4);
+        let mut kept = mesh(attribute);
+        let ((), report) = kept.with_widened_attributes(true, |_| Ok(())).unwrap();
+        assert_eq!(report.changes[0].reason, FormatChangeReason::KeptWidened);
+        assert_eq!(report.changes[0].processed, VertexFormat::Float32x4);
+        assert_eq!(
+            kept.attribute(attribute.id).map(VertexFormat::from),
+            Some(VertexFormat::Float32x4)
+        );
+
+        let mut replaced = mesh(attribute);
+        let ((), report) = replaced
+            .with_widened_attributes(false, |mesh| {
+                let values = vec![[0u32; 4]; mesh.count_vertices()];
+                let replacement = MeshVertexAttribute {
+                    format: VertexFormat::Uint32x4,
+                    ..attribute
+                };
+                mesh.insert_attribute(replacement, values);
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(report.changes[0].reason, FormatChangeReason::Replaced);
+        assert_eq!(report.changes[0].original, VertexFormat::Unorm8x4);
+    }
+}
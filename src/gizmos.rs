@@ -0,0 +1,125 @@
+use bevy::{
+    app::{App, Plugin, PostUpdate},
+    camera::Camera,
+    color::{Color, palettes::css},
+    ecs::prelude::*,
+    gizmos::gizmos::Gizmos,
+    math::{Isometry3d, Quat, Vec3},
+    transform::{TransformSystems, components::GlobalTransform},
+};
+
+use crate::Meshlets;
+
+/// Draws the bounding sphere and normal cone of every meshlet of entities carrying [`Meshlets`],
+/// colored by whether the meshlet passes the CPU cone test against the active camera.
+pub struct MeshletGizmoPlugin;
+
+impl Plugin for MeshletGizmoPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MeshletGizmoSettings>().add_systems(
+            PostUpdate,
+            draw_meshlet_gizmos.after(TransformSystems::Propagate),
+        );
+    }
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct MeshletGizmoSettings {
+    pub enabled: bool,
+    /// Only draw the meshlets of this entity.
+    pub selected: Option<Entity>,
+    /// Upper bound on the meshlets drawn per frame.
+    pub max_meshlets: usize,
+    pub visible_color: Color,
+    /// Color of meshlets rejected by the cone test.
+    pub culled_color: Color,
+}
+
+impl Default for MeshletGizmoSettings {
+    fn default() -> Self {
+        MeshletGizmoSettings {
+            enabled: true,
+            selected: None,
+            max_meshlets: 4096,
+            visible_color: css::LIME.into(),
+            culled_color: css::RED.into(),
+        }
+    }
+}
+
+/// Segments of the circle and cone arc drawn per meshlet, kept low so a few thousand meshlets stay
+/// cheap to draw.
+const RESOLUTION: u32 = 12;
+
+pub fn draw_meshlet_gizmos(
+    settings: Res<MeshletGizmoSettings>,
+    meshlets: Query<(Entity, &Meshlets, &GlobalTransform)>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    mut gizmos: Gizmos,
+) {
+    if !settings.enabled {
+        return;
+    }
+
+    let Some((_, camera_transform)) = cameras.iter().find(|(camera, _)| camera.is_active) else {
+        return;
+    };
+    let camera_position = camera_transform.translation();
+
+    let mut budget = settings.max_meshlets;
+    for (entity, meshlets, transform) in &meshlets {
+        if settings.selected.is_some_and(|selected| selected != entity) {
+            continue;
+        }
+
+        let affine = transform.affine();
+        let local_camera = affine.inverse().transform_point3(camera_position);
+        let scale = transform.scale().abs().max_element();
+        for bounds in meshlets.bounds.iter().take(budget) {
+            let color = if bounds.is_backfacing(local_camera) {
+                settings.culled_color
+            } else {
+                settings.visible_color
+            };
+
+            // Sphere outline facing the camera.
+            let center = affine.transform_point3(bounds.center);
+            let facing =
+                Quat::from_rotation_arc(Vec3::Z, (camera_position - center).normalize_or(Vec3::Z));
+            gizmos
+                .circle(
+                    Isometry3d::new(center, facing),
+                    bounds.radius * scale,
+                    color,
+                )
+                .resolution(RESOLUTION);
+
+            if bounds.cone_cutoff >= 1.0 {
+                continue;
+            }
+
+            let apex = affine.transform_point3(bounds.cone_apex);
+            let axis = affine
+                .transform_vector3(bounds.cone_axis)
+                .normalize_or_zero();
+            let length = bounds.radius * scale;
+            gizmos.arrow(apex, apex + axis * length, color);
+
+            // Arc spanning the cone's half-angle on either side of the axis.
+            let angle = bounds.cone_cutoff.clamp(-1.0, 1.0).acos();
+            let side = axis.any_orthonormal_vector();
+            gizmos.linestrip(
+                (0..=RESOLUTION).map(|i| {
+                    let t = -angle + 2.0 * angle * i as f32 / RESOLUTION as f32;
+                    apex + (axis * t.cos() + side * t.sin()) * length
+                }),
+                color,
+            );
+        }
+
+        budget = budget.saturating_sub(meshlets.bounds.len());
+        if budget == 0 {
+            break;
+        }
+    }
+}
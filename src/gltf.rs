@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+
+use bevy::{
+    app::{App, Plugin, Update},
+    asset::{AssetEvent, AssetId, Assets, Handle},
+    ecs::prelude::*,
+    gltf::{Gltf, GltfAssetLabel, GltfMesh},
+    log::warn,
+    mesh::Mesh,
+    prelude::{Deref, DerefMut},
+};
+
+use crate::{
+    MeshBoundsPlugin, MeshLods, MeshModified, MeshProcessSettings, MeshoptSet, OptError,
+    parallel::par_map,
+};
+
+/// Processes the meshes of every glTF once it is loaded along with its dependencies, running the
+/// stages of the [`GltfMeshoptSettings`] on every primitive in place, so scenes spawned from the
+/// glTF, before or after, use the processed meshes without any system waiting for the asset.
+///
+/// Levels of detail, if the settings list any, are added as new mesh assets and kept in
+/// [`GltfLods`]: Bevy only registers labeled sub-assets while an asset loads, so they are looked up
+/// there by label instead, e.g. `Mesh0/Primitive0/lod1`. A [`GltfMeshesProcessed`] message is sent
+/// for every processed glTF. Primitives that can't be processed, e.g. with morph targets or
+/// another topology, are left as they are with a warning.
+#[derive(Debug, Clone, Default)]
+pub struct GltfMeshoptPlugin {
+    pub settings: MeshProcessSettings,
+}
+
+impl Plugin for GltfMeshoptPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<MeshBoundsPlugin>() {
+            app.add_plugins(MeshBoundsPlugin);
+        }
+
+        app.insert_resource(GltfMeshoptSettings(self.settings.clone()))
+            .init_resource::<GltfLods>()
+            .add_message::<GltfMeshesProcessed>()
+            .add_systems(Update, process_gltf_meshes.before(MeshoptSet::Queue));
+    }
+}
+
+/// Stages [`GltfMeshoptPlugin`] runs on the primitives of glTFs loaded from now on.
+#[derive(Resource, Deref, DerefMut, Debug, Clone, Default)]
+pub struct GltfMeshoptSettings(pub MeshProcessSettings);
+
+/// Levels of detail [`GltfMeshoptPlugin`] generated for the primitives of processed glTFs, LOD0
+/// being the primitive itself.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct GltfLods {
+    labeled: HashMap<(AssetId<Gltf>, String), Handle<Mesh>>,
+    lods: HashMap<AssetId<Mesh>, MeshLods>,
+}
+
+impl GltfLods {
+    /// Level labeled like `Mesh0/Primitive0/lod1` of a glTF, the label of the primitive followed
+    /// by the level. `lod0` is the primitive.
+    pub fn get(&self, gltf: impl Into<AssetId<Gltf>>, label: &str) -> Option<&Handle<Mesh>> {
+        self.labeled.get(&(gltf.into(), label.to_owned()))
+    }
+
+    /// Levels of the primitive `mesh`, switching on their accumulated error like
+    /// [`MeshLods::from_mesh`], to insert on the entities of spawned scenes using it.
+    pub fn mesh_lods(&self, mesh: impl Into<AssetId<Mesh>>) -> Option<&MeshLods> {
+        self.lods.get(&mesh.into())
+    }
+}
+
+/// Sent by [`GltfMeshoptPlugin`] once the primitives of a glTF are processed.
+#[derive(Message, Debug, Clone)]
+pub struct GltfMeshesProcessed {
+    pub gltf: AssetId<Gltf>,
+    /// Number of processed primitives.
+    pub processed: usize,
+    /// Labels of the primitives left as they were, with the reason.
+    pub skipped: Vec<(String, OptError)>,
+}
+
+#[allow(clippy::too_many_arguments)]
+fn process_gltf_meshes(
+    settings: Res<GltfMeshoptSettings>,
+    mut events: MessageReader<AssetEvent<Gltf>>,
+    gltfs: Res<Assets<Gltf>>,
+    gltf_meshes: Res<Assets<GltfMesh>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut lods: ResMut<GltfLods>,
+    mut modified: MessageWriter<MeshModified>,
+    mut processed: MessageWriter<GltfMeshesProcessed>,
+) {
+    for event in events.read() {
+        let AssetEvent::LoadedWithDependencies { id } = *event else {
+            continue;
+        };
+        let Some(gltf) = gltfs.get(id) else {
+            continue;
+        };
+
+        let primitives: Vec<(String, Handle<Mesh>, Mesh)> = gltf
+            .meshes
+            .iter()
+            .filter_map(|handle| gltf_meshes.get(handle))
+            .flat_map(|gltf_mesh| &gltf_mesh.primitives)
+            .filter_map(|primitive| {
+                let label = GltfAssetLabel::Primitive {
+                    mesh: primitive.parent_mesh_index,
+                    primitive: primitive.index,
+                };
+                let mesh = meshes.get(&primitive.mesh)?.clone();
+                Some((label.to_string(), primitive.mesh.clone(), mesh))
+            })
+            .collect();
+        let results = par_map(primitives, |(label, handle, mut mesh)| {
+            let chain = settings.process(&mut mesh);
+            (label, handle, chain.map(|chain| (mesh, chain)))
+        });
+
+        let mut message = GltfMeshesProcessed {
+            gltf: id,
+            processed: 0,
+            skipped: Vec::new(),
+        };
+        for (label, handle, result) in results {
+            let (mesh, chain) = match result {
+                Ok(processed) => processed,
+                Err(err) => {
+                    warn!("Not processing glTF primitive {label}: {err}");
+                    message.skipped.push((label, err));
+                    continue;
+                }
+            };
+            let mesh = match chain {
+                Some(chain) => {
+                    let (levels, lod0) = MeshLods::from_chain(chain, &handle, &mut meshes);
+                    for (level, (level_handle, _)) in levels.levels.iter().enumerate() {
+                        lods.labeled
+                            .insert((id, format!("{label}/lod{level}")), level_handle.clone());
+                    }
+                    lods.lods.insert(handle.id(), levels);
+                    lod0.unwrap_or(mesh)
+                }
+                None => mesh,
+            };
+            if let Some(source) = meshes.get_mut(&handle) {
+                *source = mesh;
+                modified.write(MeshModified(handle.id()));
+            }
+            message.processed += 1;
+        }
+        processed.write(message);
+    }
+}
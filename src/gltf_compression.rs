@@ -0,0 +1,654 @@
+use std::{collections::HashMap, io};
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use bevy::{
+    app::{App, Plugin},
+    asset::{AssetApp, AssetLoader, LoadContext, io::Reader},
+    gltf::{DefaultGltfImageSampler, Gltf, GltfError, GltfLoader, GltfLoaderSettings, GltfPlugin},
+    image::{CompressedImageFormatSupport, CompressedImageFormats},
+};
+use serde_json::{Map, Value, json};
+
+use crate::export::{BIN_CHUNK, GLB_HEADER_SIZE, GLB_MAGIC, JSON_CHUNK, glb};
+
+/// Name of the glTF extension storing buffer views with meshoptimizer's vertex and index codecs.
+pub const EXT_MESHOPT_COMPRESSION: &str = "EXT_meshopt_compression";
+
+/// Loads glTF files using `EXT_meshopt_compression`, like those written by gltfpack or
+/// [`compress_gltf`], which Bevy's loader rejects. Requires Bevy's `GltfPlugin` to be added first,
+/// its settings are used for the loader replacing Bevy's for `.gltf` and `.glb` files.
+///
+/// Compressed buffer views are decoded with [`decompress_gltf`] before the file is handed to
+/// Bevy's loader, other files go to it untouched, so assets load with the same labels either way.
+pub struct GltfMeshoptCompressionPlugin;
+
+impl Plugin for GltfMeshoptCompressionPlugin {
+    fn build(&self, _app: &mut App) {}
+
+    fn finish(&self, app: &mut App) {
+        // Mirrors the loader `GltfPlugin` registers in its `finish`, which ran before this one.
+        let loader = {
+            let Some(gltf_plugin) = app.get_added_plugins::<GltfPlugin>().first().copied() else {
+                panic!("GltfMeshoptCompressionPlugin requires GltfPlugin to be added before it");
+            };
+            let world = app.world();
+            let default_sampler = match world.get_resource::<DefaultGltfImageSampler>() {
+                Some(sampler) => sampler.get_internal(),
+                None => DefaultGltfImageSampler::new(&gltf_plugin.default_sampler).get_internal(),
+            };
+            GltfLoader {
+                supported_compressed_formats: world
+                    .get_resource::<CompressedImageFormatSupport>()
+                    .map_or(CompressedImageFormats::NONE, |support| support.0),
+                custom_vertex_attributes: gltf_plugin.custom_vertex_attributes.clone(),
+                default_sampler,
+                default_use_model_forward_direction: gltf_plugin.use_model_forward_direction,
+            }
+        };
+        app.register_asset_loader(MeshoptGltfLoader(loader));
+    }
+}
+
+/// Bevy's [`GltfLoader`] decoding `EXT_meshopt_compression` buffer views first, registered by
+/// [`GltfMeshoptCompressionPlugin`].
+pub struct MeshoptGltfLoader(pub GltfLoader);
+
+impl AssetLoader for MeshoptGltfLoader {
+    type Asset = Gltf;
+    type Settings = GltfLoaderSettings;
+    type Error = GltfError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        settings: &GltfLoaderSettings,
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Gltf, GltfError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+
+        let file = GltfFile::parse(&bytes)?;
+        if !file.uses_extension(EXT_MESHOPT_COMPRESSION) {
+            return GltfLoader::load_gltf(&self.0, &bytes, load_context, settings).await;
+        }
+        let mut external = HashMap::new();
+        for uri in file.external_buffer_uris() {
+            let path = load_context
+                .asset_path()
+                .resolve_embed(&uri)
+                .map_err(|err| GltfError::InvalidBufferUri(uri.clone(), err))?;
+            external.insert(uri, load_context.read_asset_bytes(path).await?);
+        }
+        let decompressed = file.decompress(|uri| {
+            external
+                .remove(uri)
+                .ok_or_else(|| invalid(format!("buffer {uri} wasn't read")))
+        })?;
+        GltfLoader::load_gltf(&self.0, &decompressed, load_context, settings).await
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["gltf", "glb"]
+    }
+}
+
+/// Decodes the `EXT_meshopt_compression` buffer views of a `.gltf` or `.glb` file, returning a
+/// `.glb` file without the extension that any glTF loader reads. Buffers other than the binary
+/// chunk and data URIs are read with `read_buffer` from their URI, e.g.
+/// `|uri| std::fs::read(directory.join(uri))`. Files without the extension are returned as they
+/// are.
+pub fn decompress_gltf(
+    bytes: &[u8],
+    read_buffer: impl FnMut(&str) -> io::Result<Vec<u8>>,
+) -> io::Result<Vec<u8>> {
+    let file = GltfFile::parse(bytes)?;
+    if !file.uses_extension(EXT_MESHOPT_COMPRESSION) {
+        return Ok(bytes.to_vec());
+    }
+    file.decompress(read_buffer)
+}
+
+/// Encodes the vertex attributes, morph targets, animation data and indices of a `.gltf` or
+/// `.glb` file with `EXT_meshopt_compression`, the format gltfpack writes, returning a `.glb`
+/// file. Usually a fraction of the size, more so after meshoptimizer's optimizations and
+/// quantization, e.g. with [`MeshExt::optimize`](crate::MeshExt::optimize) and
+/// [`MeshExt::quantize_attributes`](crate::MeshExt::quantize_attributes) before exporting.
+///
+/// Buffer views are encoded without filters and only if it makes them smaller: attributes whose
+/// stride is a multiple of 4 bytes up to 256 bytes and `u16` or `u32` indices, triangle lists
+/// with the triangle codec. Everything else, images included, is kept as it is. Buffers are read
+/// like in [`decompress_gltf`], whose output can be compressed again.
+pub fn compress_gltf(
+    bytes: &[u8],
+    read_buffer: impl FnMut(&str) -> io::Result<Vec<u8>>,
+) -> io::Result<Vec<u8>> {
+    let file = GltfFile::parse(bytes)?;
+    if file.uses_extension(EXT_MESHOPT_COMPRESSION) {
+        return Err(invalid(format!("already uses {EXT_MESHOPT_COMPRESSION}")));
+    }
+    file.compress(read_buffer)
+}
+
+/// JSON and binary chunk of a `.gltf` or `.glb` file.
+pub(crate) struct GltfFile {
+    pub(crate) json: Value,
+    bin: Option<Vec<u8>>,
+}
+
+/// How a buffer view is encoded, the stride of its elements along with the codec.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum ViewCodec {
+    Attributes(usize),
+    Triangles(usize),
+    Indices(usize),
+}
+
+impl GltfFile {
+    pub(crate) fn parse(bytes: &[u8]) -> io::Result<Self> {
+        if !bytes.starts_with(GLB_MAGIC) {
+            return GltfFile::new(serde_json::from_slice(bytes)?, None);
+        }
+
+        let read_u32 = |offset: usize| {
+            bytes
+                .get(offset..offset + 4)
+                .map(|word| u32::from_le_bytes(word.try_into().unwrap()))
+                .ok_or_else(|| invalid("truncated glb".to_string()))
+        };
+        if read_u32(4)? != 2 {
+            return Err(invalid("unsupported glb version".to_string()));
+        }
+        let length = (read_u32(8)? as usize).min(bytes.len());
+        let mut json = None;
+        let mut bin = None;
+        let mut offset = GLB_HEADER_SIZE;
+        while offset + 8 <= length {
+            let chunk_length = read_u32(offset)? as usize;
+            let chunk_type = read_u32(offset + 4)?;
+            let chunk = bytes
+                .get(offset + 8..offset + 8 + chunk_length)
+                .ok_or_else(|| invalid("truncated glb chunk".to_string()))?;
+            match chunk_type {
+                JSON_CHUNK => json = Some(serde_json::from_slice(chunk)?),
+                BIN_CHUNK if bin.is_none() => bin = Some(chunk.to_vec()),
+                _ => {}
+            }
+            offset += 8 + chunk_length.next_multiple_of(4);
+        }
+        let json = json.ok_or_else(|| invalid("glb without a JSON chunk".to_string()))?;
+        GltfFile::new(json, bin)
+    }
+
+    fn new(json: Value, bin: Option<Vec<u8>>) -> io::Result<Self> {
+        if !json.is_object() {
+            return Err(invalid("glTF JSON isn't an object".to_string()));
+        }
+        Ok(GltfFile { json, bin })
+    }
+
+    pub(crate) fn uses_extension(&self, extension: &str) -> bool {
+        array(&self.json, "extensionsUsed")
+            .iter()
+            .any(|used| used.as_str() == Some(extension))
+    }
+
+    /// URIs of the buffers stored in other files.
+    fn external_buffer_uris(&self) -> Vec<String> {
+        array(&self.json, "buffers")
+            .iter()
+            .filter(|buffer| !is_fallback(buffer))
+            .filter_map(|buffer| buffer["uri"].as_str())
+            .filter(|uri| !uri.starts_with("data:"))
+            .map(str::to_owned)
+            .collect()
+    }
+
+    /// Data of every buffer, empty for `EXT_meshopt_compression` fallback buffers.
+    pub(crate) fn buffers(
+        &mut self,
+        mut read_buffer: impl FnMut(&str) -> io::Result<Vec<u8>>,
+    ) -> io::Result<Vec<Vec<u8>>> {
+        let mut bin = self.bin.take();
+        array(&self.json, "buffers")
+            .iter()
+            .enumerate()
+            .map(|(index, buffer)| match buffer["uri"].as_str() {
+                _ if is_fallback(buffer) => Ok(Vec::new()),
+                Some(uri) if uri.starts_with("data:") => {
+                    let (_, data) = uri
+                        .split_once(";base64,")
+                        .ok_or_else(|| invalid(format!("buffer {index} isn't base64")))?;
+                    STANDARD
+                        .decode(data)
+                        .map_err(|_| invalid(format!("buffer {index} isn't base64")))
+                }
+                Some(uri) => read_buffer(uri),
+                None if index == 0 => bin
+                    .take()
+                    .ok_or_else(|| invalid("glb without a binary chunk".to_string())),
+                None => Err(invalid(format!("buffer {index} has no data"))),
+            })
+            .collect()
+    }
+
+    pub(crate) fn decompress(
+        mut self,
+        read_buffer: impl FnMut(&str) -> io::Result<Vec<u8>>,
+    ) -> io::Result<Vec<u8>> {
+        let buffers = self.buffers(read_buffer)?;
+        let views = array(&self.json, "bufferViews")
+            .iter()
+            .enumerate()
+            .map(|(index, view)| {
+                let viewed = match view["extensions"].get(EXT_MESHOPT_COMPRESSION) {
+                    Some(compression) => decode_view(compression, &buffers),
+                    None => view_bytes(view, &buffers).map(<[u8]>::to_vec),
+                };
+                viewed.map_err(|error| invalid(format!("buffer view {index}: {error}")))
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let bin = self.relayout_views(views.iter().map(|view| (view.as_slice(), None)));
+        remove_extension(&mut self.json, EXT_MESHOPT_COMPRESSION);
+        Ok(glb(&self.json, &bin))
+    }
+
+    fn compress(
+        mut self,
+        read_buffer: impl FnMut(&str) -> io::Result<Vec<u8>>,
+    ) -> io::Result<Vec<u8>> {
+        let buffers = self.buffers(read_buffer)?;
+        let codecs = view_codecs(&self.json);
+        let views = array(&self.json, "bufferViews")
+            .iter()
+            .enumerate()
+            .map(|(index, view)| {
+                let bytes = view_bytes(view, &buffers)
+                    .map_err(|error| invalid(format!("buffer view {index}: {error}")))?;
+                let encoded = codecs
+                    .get(&index)
+                    .and_then(|&codec| encode_view(bytes, codec))
+                    .filter(|(encoded, _)| encoded.len() < bytes.len());
+                Ok((bytes, encoded))
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let bin = self.relayout_views(
+            views
+                .iter()
+                .map(|(bytes, encoded)| (*bytes, encoded.as_ref())),
+        );
+        for key in ["extensionsUsed", "extensionsRequired"] {
+            match &mut self.json[key] {
+                Value::Array(extensions) => extensions.push(json!(EXT_MESHOPT_COMPRESSION)),
+                extensions => *extensions = json!([EXT_MESHOPT_COMPRESSION]),
+            }
+        }
+        Ok(glb(&self.json, &bin))
+    }
+
+    /// Lays the buffer views out one after the other in a single buffer, the binary chunk of the
+    /// `.glb`, and returns it. Views given an encoding with its codec keep their bytes in it and
+    /// refer to a fallback buffer without data for the decoded ones.
+    pub(crate) fn relayout_views<'a>(
+        &mut self,
+        views: impl Iterator<Item = (&'a [u8], Option<&'a (Vec<u8>, ViewCodec)>)>,
+    ) -> Vec<u8> {
+        let mut bin = Vec::new();
+        let mut fallback_length = 0;
+        let json_views = self.json["bufferViews"].as_array_mut();
+        for ((bytes, encoded), view) in views.zip(json_views.into_iter().flatten()) {
+            let Some(view) = view.as_object_mut() else {
+                continue;
+            };
+            if let Some(Value::Object(extensions)) = view.get_mut("extensions") {
+                extensions.remove(EXT_MESHOPT_COMPRESSION);
+                if extensions.is_empty() {
+                    view.remove("extensions");
+                }
+            }
+            match encoded {
+                Some((encoded, codec)) => {
+                    view.insert("buffer".into(), json!(1));
+                    view.insert("byteOffset".into(), json!(fallback_length));
+                    fallback_length += bytes.len().next_multiple_of(4);
+
+                    let (mode, stride) = match *codec {
+                        ViewCodec::Attributes(stride) => ("ATTRIBUTES", stride),
+                        ViewCodec::Triangles(stride) => ("TRIANGLES", stride),
+                        ViewCodec::Indices(stride) => ("INDICES", stride),
+                    };
+                    let mut compression = Map::new();
+                    compression.insert("buffer".into(), json!(0));
+                    compression.insert("byteOffset".into(), json!(bin.len()));
+                    compression.insert("byteLength".into(), json!(encoded.len()));
+                    compression.insert("byteStride".into(), json!(stride));
+                    compression.insert("count".into(), json!(bytes.len() / stride));
+                    compression.insert("mode".into(), json!(mode));
+                    match view.entry("extensions").or_insert_with(|| json!({})) {
+                        Value::Object(extensions) => {
+                            extensions.insert(EXT_MESHOPT_COMPRESSION.into(), compression.into());
+                        }
+                        extensions => {
+                            *extensions = json!({ EXT_MESHOPT_COMPRESSION: compression });
+                        }
+                    }
+                    bin.extend_from_slice(encoded);
+                }
+                None => {
+                    view.insert("buffer".into(), json!(0));
+                    view.insert("byteOffset".into(), json!(bin.len()));
+                    bin.extend_from_slice(bytes);
+                }
+            }
+            view.insert("byteLength".into(), json!(bytes.len()));
+            bin.resize(bin.len().next_multiple_of(4), 0);
+        }
+
+        let mut buffers = vec![json!({ "byteLength": bin.len() })];
+        if fallback_length > 0 {
+            buffers.push(json!({
+                "byteLength": fallback_length,
+                "extensions": { EXT_MESHOPT_COMPRESSION: { "fallback": true } },
+            }));
+        }
+        self.json["buffers"] = Value::Array(buffers);
+        bin
+    }
+}
+
+/// Elements of `key` in `json`, empty if it isn't an array.
+pub(crate) fn array<'a>(json: &'a Value, key: &str) -> &'a [Value] {
+    json[key].as_array().map_or(&[], Vec::as_slice)
+}
+
+/// Whether `buffer` only stands in for the decoded views, without data.
+fn is_fallback(buffer: &Value) -> bool {
+    buffer["extensions"][EXT_MESHOPT_COMPRESSION]["fallback"] == true
+}
+
+pub(crate) fn read_usize(json: &Value, key: &str) -> Option<usize> {
+    json[key].as_u64().map(|value| value as usize)
+}
+
+pub(crate) fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Bytes a buffer view without the extension refers to.
+pub(crate) fn view_bytes<'a>(view: &Value, buffers: &'a [Vec<u8>]) -> io::Result<&'a [u8]> {
+    let buffer = read_usize(view, "buffer").unwrap_or(usize::MAX);
+    let offset = read_usize(view, "byteOffset").unwrap_or(0);
+    let length = read_usize(view, "byteLength").unwrap_or(0);
+    buffers
+        .get(buffer)
+        .and_then(|buffer| buffer.get(offset..offset.checked_add(length)?))
+        .ok_or_else(|| invalid("out of the bounds of its buffer".to_string()))
+}
+
+/// Decodes the bytes of a buffer view from its `EXT_meshopt_compression` object, checking what
+/// meshoptimizer's decoders assert on.
+fn decode_view(compression: &Value, buffers: &[Vec<u8>]) -> io::Result<Vec<u8>> {
+    let encoded = view_bytes(compression, buffers)?;
+    let stride = read_usize(compression, "byteStride").unwrap_or(0);
+    let count = read_usize(compression, "count").unwrap_or(0);
+    let mode = compression["mode"].as_str().unwrap_or_default();
+    let filter = compression["filter"].as_str().unwrap_or("NONE");
+
+    let valid = match mode {
+        "ATTRIBUTES" => stride > 0 && stride <= 256 && stride.is_multiple_of(4),
+        "TRIANGLES" => matches!(stride, 2 | 4) && count.is_multiple_of(3),
+        "INDICES" => matches!(stride, 2 | 4),
+        _ => false,
+    };
+    let valid_filter = match filter {
+        "NONE" => true,
+        "OCTAHEDRAL" => mode == "ATTRIBUTES" && matches!(stride, 4 | 8),
+        "QUATERNION" => mode == "ATTRIBUTES" && stride == 8,
+        "EXPONENTIAL" => mode == "ATTRIBUTES",
+        _ => false,
+    };
+    if !valid || !valid_filter {
+        return Err(invalid(format!(
+            "unsupported {mode} with {filter} filter and a stride of {stride}"
+        )));
+    }
+    let length = count
+        .checked_mul(stride)
+        .ok_or_else(|| invalid("too many elements".to_string()))?;
+    let mut bytes = vec![0u8; length];
+
+    // SAFETY: `bytes` holds `count` elements of `stride` bytes, which the mode and filter accept
+    // as checked above.
+    let result = unsafe {
+        let destination = bytes.as_mut_ptr().cast();
+        let (source, source_length) = (encoded.as_ptr(), encoded.len());
+        match mode {
+            "ATTRIBUTES" => meshopt::ffi::meshopt_decodeVertexBuffer(
+                destination,
+                count,
+                stride,
+                source,
+                source_length,
+            ),
+            "TRIANGLES" => meshopt::ffi::meshopt_decodeIndexBuffer(
+                destination,
+                count,
+                stride,
+                source,
+                source_length,
+            ),
+            _ => meshopt::ffi::meshopt_decodeIndexSequence(
+                destination,
+                count,
+                stride,
+                source,
+                source_length,
+            ),
+        }
+    };
+    if result != 0 {
+        return Err(invalid("corrupted".to_string()));
+    }
+    // SAFETY: as above, the filters only rewrite the elements in place.
+    unsafe {
+        let destination = bytes.as_mut_ptr().cast();
+        match filter {
+            "OCTAHEDRAL" => meshopt::ffi::meshopt_decodeFilterOct(destination, count, stride),
+            "QUATERNION" => meshopt::ffi::meshopt_decodeFilterQuat(destination, count, stride),
+            "EXPONENTIAL" => meshopt::ffi::meshopt_decodeFilterExp(destination, count, stride),
+            _ => {}
+        }
+    }
+    Ok(bytes)
+}
+
+/// Encodes the bytes of a buffer view with `codec`, `None` if they don't fit it.
+fn encode_view(bytes: &[u8], codec: ViewCodec) -> Option<(Vec<u8>, ViewCodec)> {
+    let encoded = match codec {
+        ViewCodec::Attributes(stride) => {
+            if !bytes.len().is_multiple_of(stride) {
+                return None;
+            }
+            let count = bytes.len() / stride;
+            // SAFETY: `bytes` holds `count` elements of `stride` bytes, a multiple of 4 of at most
+            // 256 bytes as the encoder requires, and the buffer is as large as its bound.
+            unsafe {
+                let bound = meshopt::ffi::meshopt_encodeVertexBufferBound(count, stride);
+                let mut data = vec![0; bound];
+                // Version 0, the only one the extension allows.
+                let size = meshopt::ffi::meshopt_encodeVertexBufferLevel(
+                    data.as_mut_ptr(),
+                    data.len(),
+                    bytes.as_ptr().cast(),
+                    count,
+                    stride,
+                    2,
+                    0,
+                );
+                data.truncate(size);
+                data
+            }
+        }
+        ViewCodec::Triangles(stride) | ViewCodec::Indices(stride) => {
+            if !bytes.len().is_multiple_of(stride) {
+                return None;
+            }
+            let indices: Vec<u32> = match stride {
+                2 => bytes
+                    .chunks_exact(2)
+                    .map(|index| u16::from_le_bytes([index[0], index[1]]) as u32)
+                    .collect(),
+                _ => bytes
+                    .chunks_exact(4)
+                    .map(|index| u32::from_le_bytes(index.try_into().unwrap()))
+                    .collect(),
+            };
+            let vertex_count = indices.iter().max().map_or(0, |&max| max as usize + 1);
+            match codec {
+                ViewCodec::Triangles(_) if indices.len().is_multiple_of(3) => {
+                    // SAFETY: the buffer is as large as the bound meshoptimizer computes for the
+                    // indices, whole triangles as the encoder requires.
+                    unsafe {
+                        let bound = meshopt::ffi::meshopt_encodeIndexBufferBound(
+                            indices.len(),
+                            vertex_count,
+                        );
+                        let mut data = vec![0; bound];
+                        let size = meshopt::ffi::meshopt_encodeIndexBuffer(
+                            data.as_mut_ptr(),
+                            data.len(),
+                            indices.as_ptr(),
+                            indices.len(),
+                        );
+                        data.truncate(size);
+                        data
+                    }
+                }
+                ViewCodec::Triangles(_) => return encode_view(bytes, ViewCodec::Indices(stride)),
+                _ => crate::compress::encode_index_sequence(&indices, vertex_count),
+            }
+        }
+    };
+    Some((encoded, codec))
+}
+
+/// Codec of every buffer view that only holds vertex attributes, animation data or indices,
+/// going by the accessors referring to it. Views of images, which aren't referred to by
+/// accessors, sparse accessors and views accessors disagree on are left out.
+fn view_codecs(json: &Value) -> HashMap<usize, ViewCodec> {
+    let accessors = array(json, "accessors");
+    let views = array(json, "bufferViews");
+    let mut codecs: HashMap<usize, Option<ViewCodec>> = HashMap::new();
+    let mut refer = |accessor: Option<usize>, index_mode: Option<u64>| {
+        let Some(accessor) = accessor.and_then(|accessor| accessors.get(accessor)) else {
+            return;
+        };
+        let Some(view) = read_usize(accessor, "bufferView") else {
+            return;
+        };
+        let codec = accessor_codec(accessor, views.get(view), index_mode);
+        let entry = codecs.entry(view).or_insert(codec);
+        if *entry != codec {
+            // Triangle lists sharing a view with other indices are encoded as a sequence.
+            *entry = match (*entry, codec) {
+                (Some(ViewCodec::Triangles(a)), Some(ViewCodec::Indices(b)))
+                | (Some(ViewCodec::Indices(a)), Some(ViewCodec::Triangles(b)))
+                    if a == b =>
+                {
+                    Some(ViewCodec::Indices(a))
+                }
+                _ => None,
+            };
+        }
+    };
+
+    for primitive in array(json, "meshes")
+        .iter()
+        .flat_map(|mesh| array(mesh, "primitives"))
+    {
+        let attributes = primitive["attributes"].as_object().into_iter().flatten();
+        let targets = array(primitive, "targets")
+            .iter()
+            .filter_map(Value::as_object)
+            .flatten();
+        for (_, accessor) in attributes.chain(targets) {
+            refer(accessor.as_u64().map(|index| index as usize), None);
+        }
+        let mode = primitive["mode"].as_u64().unwrap_or(4);
+        refer(read_usize(primitive, "indices"), Some(mode));
+    }
+    for sampler in array(json, "animations")
+        .iter()
+        .flat_map(|animation| array(animation, "samplers"))
+    {
+        refer(read_usize(sampler, "input"), None);
+        refer(read_usize(sampler, "output"), None);
+    }
+    for skin in array(json, "skins") {
+        refer(read_usize(skin, "inverseBindMatrices"), None);
+    }
+
+    let sparse_views: Vec<usize> = accessors
+        .iter()
+        .flat_map(|accessor| ["indices", "values"].map(|part| &accessor["sparse"][part]))
+        .filter_map(|part| read_usize(part, "bufferView"))
+        .collect();
+    codecs
+        .into_iter()
+        .filter(|(view, _)| !sparse_views.contains(view))
+        .filter_map(|(view, codec)| Some((view, codec?)))
+        .collect()
+}
+
+/// Codec of the view of `accessor`, the indices of a primitive drawn with `index_mode` if given.
+fn accessor_codec(
+    accessor: &Value,
+    view: Option<&Value>,
+    index_mode: Option<u64>,
+) -> Option<ViewCodec> {
+    let component_size = match accessor["componentType"].as_u64()? {
+        5120 | 5121 => 1,
+        5122 | 5123 => 2,
+        5125 | 5126 => 4,
+        _ => return None,
+    };
+    if let Some(mode) = index_mode {
+        return match (component_size, mode) {
+            (1, _) => None,
+            // Triangle lists.
+            (_, 4) => Some(ViewCodec::Triangles(component_size)),
+            _ => Some(ViewCodec::Indices(component_size)),
+        };
+    }
+
+    let components = match accessor["type"].as_str()? {
+        "SCALAR" => 1,
+        "VEC2" => 2,
+        "VEC3" => 3,
+        "VEC4" | "MAT2" => 4,
+        "MAT3" => 9,
+        "MAT4" => 16,
+        _ => return None,
+    };
+    // Matrices of smaller components have padded columns.
+    if accessor["type"].as_str()?.starts_with("MAT") && component_size != 4 {
+        return None;
+    }
+    let stride = view
+        .and_then(|view| read_usize(view, "byteStride"))
+        .unwrap_or(component_size * components);
+    (stride.is_multiple_of(4) && stride <= 256).then_some(ViewCodec::Attributes(stride))
+}
+
+/// Removes `extension` from the lists of used and required extensions.
+fn remove_extension(json: &mut Value, extension: &str) {
+    for key in ["extensionsUsed", "extensionsRequired"] {
+        if let Some(extensions) = json[key].as_array_mut() {
+            extensions.retain(|used| used.as_str() != Some(extension));
+            if extensions.is_empty() {
+                json.as_object_mut().map(|json| json.remove(key));
+            }
+        }
+    }
+}
@@ -0,0 +1,578 @@
+use std::io;
+
+use bevy::{
+    asset::RenderAssetUsages,
+    mesh::{
+        Indices, Mesh, MeshVertexAttribute, MeshVertexAttributeId, PrimitiveTopology, VertexFormat,
+    },
+};
+use serde_json::{Map, Value, json};
+
+use crate::{
+    MeshProcessSettings, OptError,
+    export::{
+        ARRAY_BUFFER, ELEMENT_ARRAY_BUFFER, SEMANTICS, attribute_accessor, glb, index_accessor,
+        semantic, view_data,
+    },
+    gltf_compression::{EXT_MESHOPT_COMPRESSION, GltfFile, array, invalid, read_usize, view_bytes},
+    vertex::values_from_bytes,
+};
+
+/// Id of the first attribute read from a glTF without a Bevy counterpart, the others follow.
+const GLTF_ATTRIBUTE_ID: u64 = 0x676c_5446_0000_0000;
+
+/// Outcome of [`process_gltf`]. Primitives are named by the label Bevy's glTF loader gives their
+/// mesh, e.g. `Mesh0/Primitive1`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GltfProcessReport {
+    pub processed: Vec<String>,
+    /// Primitives left as they are, with the reason.
+    pub skipped: Vec<(String, String)>,
+}
+
+/// Runs the stages of `settings` on every triangle list primitive of a `.gltf` or `.glb` file,
+/// returning a `.glb` file with the processed primitives and everything else as it was. Accessors
+/// of a primitive are rewritten in place, those it shares with other primitives are left to them.
+///
+/// glTF has no place for levels of detail, [`MeshProcessSettings::lods`] are ignored: generate
+/// them once the glTF is loaded with [`GltfMeshoptPlugin`](crate::GltfMeshoptPlugin), or from
+/// `.meshopt` files. Primitives that aren't triangle lists, have morph targets, use an extension
+/// like Draco or attributes meshes can't hold, e.g. sparse accessors, are left as they are and
+/// listed in the report, as are those processing fails for. Files using `EXT_meshopt_compression`
+/// are decoded and written without it, buffers are read like in
+/// [`decompress_gltf`](crate::decompress_gltf).
+pub fn process_gltf(
+    bytes: &[u8],
+    mut read_buffer: impl FnMut(&str) -> io::Result<Vec<u8>>,
+    settings: &MeshProcessSettings,
+) -> io::Result<(Vec<u8>, GltfProcessReport)> {
+    let mut file = GltfFile::parse(bytes)?;
+    if file.uses_extension(EXT_MESHOPT_COMPRESSION) {
+        file = GltfFile::parse(&file.decompress(&mut read_buffer)?)?;
+    }
+    let buffers = file.buffers(read_buffer)?;
+    let mut views = array(&file.json, "bufferViews")
+        .iter()
+        .enumerate()
+        .map(|(index, view)| {
+            view_bytes(view, &buffers)
+                .map(<[u8]>::to_vec)
+                .map_err(|error| invalid(format!("buffer view {index}: {error}")))
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+
+    let settings = MeshProcessSettings {
+        lods: Vec::new(),
+        ..settings.clone()
+    };
+    let mut uses = accessor_uses(&file.json);
+    let mut report = GltfProcessReport::default();
+    for mesh in 0..array(&file.json, "meshes").len() {
+        for primitive in 0..array(&file.json["meshes"][mesh], "primitives").len() {
+            let label = format!("Mesh{mesh}/Primitive{primitive}");
+            let json = &file.json["meshes"][mesh]["primitives"][primitive];
+            let processed =
+                read_primitive(&file.json, &views, json).and_then(|(mut mesh, semantics)| {
+                    settings.process(&mut mesh).map_err(|err| err.to_string())?;
+                    primitive_accessors(&mesh, &semantics).map_err(|err| err.to_string())
+                });
+            match processed {
+                Ok(accessors) => {
+                    write_primitive(
+                        &mut file.json,
+                        &mut views,
+                        &mut uses,
+                        (mesh, primitive),
+                        accessors,
+                    );
+                    report.processed.push(label);
+                }
+                Err(reason) => report.skipped.push((label, reason)),
+            }
+        }
+    }
+
+    drop_unused_views(&mut file.json, &mut views);
+    let bin = file.relayout_views(views.iter().map(|view| (view.as_slice(), None)));
+    Ok((glb(&file.json, &bin), report))
+}
+
+/// Accessor of a primitive waiting to be written: the attribute it is for, `None` for the
+/// indices, the accessor without its buffer view, the view and its data.
+type NewAccessor = (Option<String>, Value, Value, Vec<u8>);
+
+/// Mesh of a triangle list primitive, with the glTF name of each of its attributes.
+fn read_primitive(
+    json: &Value,
+    views: &[Vec<u8>],
+    primitive: &Value,
+) -> Result<(Mesh, Vec<(MeshVertexAttributeId, String)>), String> {
+    let mode = primitive["mode"].as_u64().unwrap_or(4);
+    if mode != 4 {
+        return Err(format!("mode {mode} isn't a triangle list"));
+    }
+    if !array(primitive, "targets").is_empty() {
+        return Err(OptError::MorphTargetsUnsupported.to_string());
+    }
+    if let Some(extension) = primitive["extensions"]
+        .as_object()
+        .and_then(|extensions| extensions.keys().next())
+    {
+        return Err(format!("uses {extension}"));
+    }
+
+    let mut mesh = Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    );
+    let mut semantics = Vec::new();
+    let attributes = primitive["attributes"].as_object().into_iter().flatten();
+    for (i, (name, accessor)) in attributes.enumerate() {
+        let accessor = accessor
+            .as_u64()
+            .ok_or_else(|| format!("attribute {name} has no accessor"))?;
+        let (format, bytes) = accessor_bytes(json, views, accessor as usize)?;
+        let values = values_from_bytes(format, &bytes, format.size() as usize)
+            .ok_or_else(|| format!("attribute {name} is {format:?}, which meshes can't hold"))?;
+        // Attributes in another format than Bevy's are kept as they are, like custom ones.
+        let attribute = match SEMANTICS
+            .iter()
+            .find(|(attribute, semantic)| semantic == name && attribute.format == format)
+        {
+            Some(&(attribute, _)) => attribute,
+            None => MeshVertexAttribute::new("Vertex_Gltf", GLTF_ATTRIBUTE_ID + i as u64, format),
+        };
+        semantics.push((attribute.id, name.clone()));
+        mesh.insert_attribute(attribute, values);
+    }
+    if let Some(accessor) = read_usize(primitive, "indices") {
+        let (format, bytes) = accessor_bytes(json, views, accessor)?;
+        mesh.insert_indices(match format {
+            VertexFormat::Uint8 => Indices::U16(bytes.iter().map(|&index| index as u16).collect()),
+            VertexFormat::Uint16 => Indices::U16(
+                bytes
+                    .chunks_exact(2)
+                    .map(|index| u16::from_le_bytes([index[0], index[1]]))
+                    .collect(),
+            ),
+            VertexFormat::Uint32 => Indices::U32(
+                bytes
+                    .chunks_exact(4)
+                    .map(|index| u32::from_le_bytes(index.try_into().unwrap()))
+                    .collect(),
+            ),
+            _ => return Err(format!("indices are {format:?}")),
+        });
+    }
+    Ok((mesh, semantics))
+}
+
+/// Format of the accessor `index` and its values packed one after the other.
+fn accessor_bytes(
+    json: &Value,
+    views: &[Vec<u8>],
+    index: usize,
+) -> Result<(VertexFormat, Vec<u8>), String> {
+    let accessor = array(json, "accessors")
+        .get(index)
+        .ok_or_else(|| format!("accessor {index} doesn't exist"))?;
+    if !accessor["sparse"].is_null() {
+        return Err(format!("accessor {index} is sparse"));
+    }
+    let format = accessor_format(accessor)
+        .ok_or_else(|| format!("accessor {index} has a format meshes can't hold"))?;
+    let view = read_usize(accessor, "bufferView")
+        .ok_or_else(|| format!("accessor {index} has no buffer view"))?;
+    let bytes = views
+        .get(view)
+        .ok_or_else(|| format!("buffer view {view} doesn't exist"))?;
+    let size = format.size() as usize;
+    let stride = read_usize(&json["bufferViews"][view], "byteStride").unwrap_or(size);
+    if stride < size {
+        return Err(format!(
+            "buffer view {view} has a stride below {size} bytes"
+        ));
+    }
+
+    let offset = read_usize(accessor, "byteOffset").unwrap_or(0);
+    let mut packed = Vec::new();
+    for i in 0..read_usize(accessor, "count").unwrap_or(0) {
+        let start = offset + i * stride;
+        let value = bytes
+            .get(start..start + size)
+            .ok_or_else(|| format!("accessor {index} is past the end of its buffer view"))?;
+        packed.extend_from_slice(value);
+    }
+    Ok((format, packed))
+}
+
+/// Vertex format of the values of a glTF accessor, `None` for those vertex buffers can't hold.
+fn accessor_format(accessor: &Value) -> Option<VertexFormat> {
+    use VertexFormat::*;
+    let formats = match (
+        accessor["componentType"].as_u64()?,
+        accessor["normalized"] == true,
+    ) {
+        (5120, false) => [Some(Sint8), Some(Sint8x2), None, Some(Sint8x4)],
+        (5120, true) => [Some(Snorm8), Some(Snorm8x2), None, Some(Snorm8x4)],
+        (5121, false) => [Some(Uint8), Some(Uint8x2), None, Some(Uint8x4)],
+        (5121, true) => [Some(Unorm8), Some(Unorm8x2), None, Some(Unorm8x4)],
+        (5122, false) => [Some(Sint16), Some(Sint16x2), None, Some(Sint16x4)],
+        (5122, true) => [Some(Snorm16), Some(Snorm16x2), None, Some(Snorm16x4)],
+        (5123, false) => [Some(Uint16), Some(Uint16x2), None, Some(Uint16x4)],
+        (5123, true) => [Some(Unorm16), Some(Unorm16x2), None, Some(Unorm16x4)],
+        (5125, false) => [Some(Uint32), Some(Uint32x2), Some(Uint32x3), Some(Uint32x4)],
+        (5126, false) => [
+            Some(Float32),
+            Some(Float32x2),
+            Some(Float32x3),
+            Some(Float32x4),
+        ],
+        _ => return None,
+    };
+    let components = match accessor["type"].as_str()? {
+        "SCALAR" => 1,
+        "VEC2" => 2,
+        "VEC3" => 3,
+        "VEC4" => 4,
+        _ => return None,
+    };
+    formats[components - 1]
+}
+
+/// Accessors holding the attributes and indices of a processed mesh, attributes named like in
+/// `semantics` when they were read from the glTF.
+fn primitive_accessors(
+    mesh: &Mesh,
+    semantics: &[(MeshVertexAttributeId, String)],
+) -> Result<Vec<NewAccessor>, OptError> {
+    let mut accessors = Vec::new();
+    for (attribute, values) in mesh.attributes() {
+        let name = match semantics.iter().find(|(id, _)| *id == attribute.id) {
+            Some((_, name)) => name.clone(),
+            None => semantic(attribute.name, attribute.id),
+        };
+        let accessor = attribute_accessor(attribute, values)?;
+        let size = attribute.format.size() as usize;
+        let (data, view) = view_data(values.get_bytes(), ARRAY_BUFFER, size);
+        accessors.push((Some(name), accessor, view, data));
+    }
+    if let Some(indices) = mesh.indices() {
+        let (accessor, bytes, size) = index_accessor(indices);
+        let (data, view) = view_data(&bytes, ELEMENT_ARRAY_BUFFER, size);
+        accessors.push((None, accessor, view, data));
+    }
+    Ok(accessors)
+}
+
+/// Points a primitive, given by the index of its mesh and its index in the mesh, at `accessors`.
+/// Accessors only the primitive used are replaced, those of other primitives stay and new ones
+/// are added. Accessors nothing uses anymore lose their data.
+fn write_primitive(
+    json: &mut Value,
+    views: &mut Vec<Vec<u8>>,
+    uses: &mut [usize],
+    (mesh, primitive): (usize, usize),
+    accessors: Vec<NewAccessor>,
+) {
+    let old = &json["meshes"][mesh]["primitives"][primitive];
+    let mut replaced: Vec<(Option<String>, usize)> = old["attributes"]
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter_map(|(name, accessor)| Some((Some(name.clone()), accessor.as_u64()? as usize)))
+        .chain(read_usize(old, "indices").map(|accessor| (None, accessor)))
+        .collect();
+
+    let mut attributes = Map::new();
+    let mut indices = None;
+    for (name, mut accessor, view, data) in accessors {
+        accessor["bufferView"] = json!(push(json, "bufferViews", view));
+        views.push(data);
+        let reused = replaced
+            .iter()
+            .position(|(replaced, _)| *replaced == name)
+            .map(|i| replaced.swap_remove(i).1);
+        let index = match reused {
+            Some(old) if uses[old] == 1 => {
+                json["accessors"][old] = accessor;
+                old
+            }
+            _ => {
+                reused.inspect(|&old| uses[old] -= 1);
+                push(json, "accessors", accessor)
+            }
+        };
+        match name {
+            Some(name) => {
+                attributes.insert(name, json!(index));
+            }
+            None => indices = Some(index),
+        }
+    }
+    for (_, old) in replaced {
+        uses[old] -= 1;
+        if uses[old] == 0
+            && let Some(accessor) = json["accessors"][old].as_object_mut()
+        {
+            // Accessors without a buffer view are zeros, which glTF allows.
+            accessor.remove("bufferView");
+            accessor.remove("byteOffset");
+        }
+    }
+
+    let primitive = &mut json["meshes"][mesh]["primitives"][primitive];
+    primitive["attributes"] = Value::Object(attributes);
+    match (indices, primitive.as_object_mut()) {
+        (Some(indices), _) => primitive["indices"] = json!(indices),
+        (None, Some(primitive)) => {
+            primitive.remove("indices");
+        }
+        (None, None) => {}
+    }
+}
+
+/// Appends `value` to the array `key` of `json`, creating it if needed, and returns its index.
+fn push(json: &mut Value, key: &str, value: Value) -> usize {
+    match &mut json[key] {
+        Value::Array(values) => {
+            values.push(value);
+            values.len() - 1
+        }
+        values => {
+            *values = json!([value]);
+            0
+        }
+    }
+}
+
+/// Number of references to every accessor from primitives, animations, skins and instanced
+/// nodes.
+fn accessor_uses(json: &Value) -> Vec<usize> {
+    let mut uses = vec![0; array(json, "accessors").len()];
+    let mut refer = |accessor: Option<u64>| {
+        if let Some(uses) = accessor.and_then(|accessor| uses.get_mut(accessor as usize)) {
+            *uses += 1;
+        }
+    };
+    for primitive in array(json, "meshes")
+        .iter()
+        .flat_map(|mesh| array(mesh, "primitives"))
+    {
+        let attributes = primitive["attributes"].as_object().into_iter().flatten();
+        let targets = array(primitive, "targets")
+            .iter()
+            .filter_map(Value::as_object)
+            .flatten();
+        for (_, accessor) in attributes.chain(targets) {
+            refer(accessor.as_u64());
+        }
+        refer(primitive["indices"].as_u64());
+    }
+    for sampler in array(json, "animations")
+        .iter()
+        .flat_map(|animation| array(animation, "samplers"))
+    {
+        refer(sampler["input"].as_u64());
+        refer(sampler["output"].as_u64());
+    }
+    for skin in array(json, "skins") {
+        refer(skin["inverseBindMatrices"].as_u64());
+    }
+    for node in array(json, "nodes") {
+        let instancing = &node["extensions"]["EXT_mesh_gpu_instancing"]["attributes"];
+        for (_, accessor) in instancing.as_object().into_iter().flatten() {
+            refer(accessor.as_u64());
+        }
+    }
+    uses
+}
+
+/// Removes the buffer views nothing refers to and renumbers the references to the others.
+fn drop_unused_views(json: &mut Value, views: &mut Vec<Vec<u8>>) {
+    let mut used = vec![false; views.len()];
+    for_each_view_reference(json, &mut |view| {
+        if let Some(used) = used.get_mut(view) {
+            *used = true;
+        }
+        view
+    });
+    let mut kept = 0;
+    let renumbered: Vec<usize> = used
+        .iter()
+        .map(|&used| {
+            kept += used as usize;
+            kept - used as usize
+        })
+        .collect();
+    for_each_view_reference(json, &mut |view| {
+        renumbered.get(view).copied().unwrap_or(view)
+    });
+
+    let mut used_views = used.iter();
+    views.retain(|_| *used_views.next().unwrap());
+    if let Some(json_views) = json["bufferViews"].as_array_mut() {
+        let mut used_views = used.iter();
+        json_views.retain(|_| *used_views.next().unwrap_or(&true));
+    }
+}
+
+/// Replaces every `bufferView` index in `json`, e.g. of accessors, images or extensions, with
+/// what `f` returns for it.
+fn for_each_view_reference(json: &mut Value, f: &mut impl FnMut(usize) -> usize) {
+    match json {
+        Value::Object(object) => {
+            for (key, value) in object {
+                match value.as_u64() {
+                    Some(view) if key == "bufferView" => *value = json!(f(view as usize)),
+                    _ => for_each_view_reference(value, f),
+                }
+            }
+        }
+        Value::Array(values) => {
+            for value in values {
+                for_each_view_reference(value, f);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        LevelTarget, ProcessSimplify, meshes_to_glb,
+        test_util::{grid, sphere},
+    };
+
+    fn quarter() -> MeshProcessSettings {
+        MeshProcessSettings {
+            simplify: Some(ProcessSimplify {
+                target: LevelTarget::Multiplier(0.25),
+                max_error: 1.0,
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn no_buffers(uri: &str) -> io::Result<Vec<u8>> {
+        Err(invalid(format!("no buffer {uri}")))
+    }
+
+    /// JSON of a `.glb` and the mesh of every triangle list, checking that every buffer view is
+    /// used.
+    fn read_back(bytes: &[u8]) -> (Value, Vec<Mesh>) {
+        let mut file = GltfFile::parse(bytes).unwrap();
+        let buffers = file.buffers(no_buffers).unwrap();
+        let views: Vec<Vec<u8>> = array(&file.json, "bufferViews")
+            .iter()
+            .map(|view| view_bytes(view, &buffers).unwrap().to_vec())
+            .collect();
+        let mut used = vec![false; views.len()];
+        for_each_view_reference(&mut file.json, &mut |view| {
+            used[view] = true;
+            view
+        });
+        assert!(used.iter().all(|&used| used));
+
+        let meshes = array(&file.json, "meshes")
+            .iter()
+            .flat_map(|mesh| array(mesh, "primitives"))
+            .filter_map(|primitive| read_primitive(&file.json, &views, primitive).ok())
+            .map(|(mesh, _)| mesh)
+            .collect();
+        (file.json, meshes)
+    }
+
+    fn triangles(mesh: &Mesh) -> usize {
+        mesh.indices().unwrap().len() / 3
+    }
+
+    #[test]
+    fn processes_every_primitive() {
+        let source = [sphere(8), grid(16)];
+        let glb = meshes_to_glb(&source).unwrap();
+        let (processed, report) = process_gltf(&glb, no_buffers, &quarter()).unwrap();
+        assert_eq!(report.processed, ["Mesh0/Primitive0", "Mesh1/Primitive0"]);
+        assert!(report.skipped.is_empty());
+        assert!(processed.len() < glb.len());
+
+        let (json, meshes) = read_back(&processed);
+        for (mesh, source) in meshes.iter().zip(&source) {
+            assert!(triangles(mesh) <= triangles(source) / 4);
+            assert_eq!(
+                mesh.attributes()
+                    .map(|(attribute, _)| attribute.id)
+                    .collect::<Vec<_>>(),
+                source
+                    .attributes()
+                    .map(|(attribute, _)| attribute.id)
+                    .collect::<Vec<_>>(),
+            );
+        }
+        let positions = &json["accessors"]
+            [json["meshes"][0]["primitives"][0]["attributes"]["POSITION"]
+                .as_u64()
+                .unwrap() as usize];
+        assert_eq!(positions["count"], meshes[0].count_vertices());
+        assert!(positions["min"].is_array() && positions["max"].is_array());
+    }
+
+    /// `.glb` of a sphere whose primitive is repeated with `modes`, all using the same
+    /// accessors.
+    fn shared_primitives(modes: &[u64]) -> Vec<u8> {
+        let source = meshes_to_glb([&sphere(8)]).unwrap();
+        let mut file = GltfFile::parse(&source).unwrap();
+        let primitive = file.json["meshes"][0]["primitives"][0].clone();
+        let primitives: Vec<Value> = modes
+            .iter()
+            .map(|&mode| {
+                let mut primitive = primitive.clone();
+                primitive["mode"] = json!(mode);
+                primitive
+            })
+            .collect();
+        file.json["meshes"][0]["primitives"] = json!(primitives);
+        let bin = file.buffers(no_buffers).unwrap().remove(0);
+        glb(&file.json, &bin)
+    }
+
+    #[test]
+    fn shared_accessors_are_split() {
+        let source = shared_primitives(&[4, 4, 1]);
+        let (processed, report) = process_gltf(&source, no_buffers, &quarter()).unwrap();
+        assert_eq!(report.processed, ["Mesh0/Primitive0", "Mesh0/Primitive1"]);
+        assert_eq!(report.skipped.len(), 1);
+        assert_eq!(report.skipped[0].0, "Mesh0/Primitive2");
+
+        let (json, meshes) = read_back(&processed);
+        let [first, second, line] = [0, 1, 2].map(|i| &json["meshes"][0]["primitives"][i]);
+        // The line list still uses the source accessors.
+        let source_json = GltfFile::parse(&source).unwrap().json;
+        assert_eq!(line, &source_json["meshes"][0]["primitives"][2]);
+        assert_ne!(first["attributes"], second["attributes"]);
+        assert_ne!(first["attributes"], line["attributes"]);
+        assert_eq!(triangles(&meshes[0]), triangles(&meshes[1]));
+    }
+
+    #[test]
+    fn last_user_replaces_shared_accessors() {
+        let source = shared_primitives(&[4, 4]);
+        let (processed, _) = process_gltf(&source, no_buffers, &quarter()).unwrap();
+        let (json, _) = read_back(&processed);
+        let source_json = GltfFile::parse(&source).unwrap().json;
+        let accessors = array(&json, "accessors");
+        // The second primitive replaced the accessors the first one had left to it.
+        assert_eq!(accessors.len(), array(&source_json, "accessors").len() * 2);
+        let attributes = source_json["meshes"][0]["primitives"][0]["attributes"]
+            .as_object()
+            .unwrap();
+        for accessor in attributes.values() {
+            let accessor = &accessors[accessor.as_u64().unwrap() as usize];
+            assert!(accessor["bufferView"].is_u64());
+        }
+        assert_eq!(array(&json, "bufferViews").len(), accessors.len());
+    }
+}
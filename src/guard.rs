@@ -0,0 +1,198 @@
+use bevy::{
+    math::Vec3,
+    mesh::{Indices, Mesh},
+};
+use meshopt::SimplifyOptions;
+
+use crate::{
+    ManifoldStatus, OptError, SimplifyParams, SimplifyReport, mesh_indices, mesh_positions,
+    metrics::{SurfaceIndex, surface_samples},
+    simplify::simplify_with_report,
+};
+
+/// Limits a simplified mesh has to stay within to be accepted by
+/// [`MeshExt::simplify_guarded`](crate::MeshExt::simplify_guarded).
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct QualityGuard {
+    /// Largest angle in radians between a simplified triangle and the closest source triangle.
+    pub max_normal_deviation: Option<f32>,
+    /// Largest distance between the simplified and the source surface, measured both ways.
+    /// Relative to the mesh extents unless `SimplifyOptions::ErrorAbsolute` is set, in world units
+    /// with `SimplifyParams::world_scale`.
+    pub max_geometric_deviation: Option<f32>,
+    /// Rejects results that aren't manifold or watertight when the source mesh was, see
+    /// [`ManifoldStatus::preserves`].
+    pub require_manifold: bool,
+    /// Number of simplifications tried before giving up, `max_error` is halved after every
+    /// rejected attempt.
+    pub max_attempts: u32,
+}
+
+impl Default for QualityGuard {
+    fn default() -> Self {
+        QualityGuard {
+            max_normal_deviation: Some(45f32.to_radians()),
+            max_geometric_deviation: None,
+            require_manifold: false,
+            max_attempts: 4,
+        }
+    }
+}
+
+/// Deviations measured by a [`QualityGuard`], `None` for the ones it doesn't check.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct GuardMeasurement {
+    /// In radians.
+    pub normal_deviation: Option<f32>,
+    /// In mesh units.
+    pub geometric_deviation: Option<f32>,
+    /// Edges used by a single triangle.
+    pub boundary_edges: Option<usize>,
+    /// Edges used by more than two triangles.
+    pub non_manifold_edges: Option<usize>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct GuardAttempt {
+    pub max_error: f32,
+    pub simplify: SimplifyReport,
+    pub measurement: GuardMeasurement,
+    pub passed: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GuardedSimplifyReport {
+    /// Report of the accepted attempt.
+    pub simplify: SimplifyReport,
+    /// Every attempt in order, the last one is the accepted one.
+    pub attempts: Vec<GuardAttempt>,
+    /// Manifold status of the accepted result, when [`QualityGuard::require_manifold`] is set.
+    pub manifold: Option<ManifoldStatus>,
+}
+
+pub(crate) fn simplify_guarded(
+    mesh: &mut Mesh,
+    params: &SimplifyParams,
+    guard: &QualityGuard,
+) -> Result<GuardedSimplifyReport, OptError> {
+    let original_indices = mesh_indices(mesh)?.clone();
+    let positions = mesh_positions(mesh)?;
+    let source = SurfaceIndex::new(&original_indices, positions);
+    let scale = if params.options.contains(SimplifyOptions::ErrorAbsolute) {
+        params.error_units()
+    } else {
+        meshopt::simplify_scale_decoder(positions)
+    };
+    let max_geometric_deviation = guard
+        .max_geometric_deviation
+        .map(|deviation| deviation * scale);
+    let source_manifold = guard
+        .require_manifold
+        .then(|| ManifoldStatus::new(&original_indices, positions));
+
+    let mut params = params.clone();
+    let mut attempts = Vec::new();
+    let mut measurement = GuardMeasurement::default();
+    for _ in 0..guard.max_attempts.max(1) {
+        let simplify = simplify_with_report(mesh, &params)?;
+        measurement = measure(mesh, &source, &original_indices, guard)?;
+        let manifold = match &source_manifold {
+            Some(_) => Some(ManifoldStatus::new(
+                mesh_indices(mesh)?,
+                mesh_positions(mesh)?,
+            )),
+            None => None,
+        };
+        if let Some(manifold) = &manifold {
+            measurement.boundary_edges = Some(manifold.boundary_edges.len());
+            measurement.non_manifold_edges = Some(manifold.non_manifold_edges.len());
+        }
+        let passed = manifold
+            .as_ref()
+            .zip(source_manifold.as_ref())
+            .is_none_or(|(manifold, source)| manifold.preserves(source))
+            && measurement
+                .normal_deviation
+                .zip(guard.max_normal_deviation)
+                .is_none_or(|(measured, max)| measured <= max)
+            && measurement
+                .geometric_deviation
+                .zip(max_geometric_deviation)
+                .is_none_or(|(measured, max)| measured <= max);
+        attempts.push(GuardAttempt {
+            max_error: params.max_error,
+            simplify,
+            measurement,
+            passed,
+        });
+        if passed {
+            return Ok(GuardedSimplifyReport {
+                simplify,
+                attempts,
+                manifold,
+            });
+        }
+
+        mesh.insert_indices(Indices::U32(original_indices.clone()));
+        params.max_error *= 0.5;
+    }
+
+    Err(OptError::QualityGuardFailed(measurement))
+}
+
+fn measure(
+    mesh: &Mesh,
+    source: &SurfaceIndex,
+    source_indices: &[u32],
+    guard: &QualityGuard,
+) -> Result<GuardMeasurement, OptError> {
+    let indices = mesh_indices(mesh)?;
+    let positions = mesh_positions(mesh)?;
+    let position = |index: u32| Vec3::from_array(positions[index as usize]);
+
+    let normal_deviation = guard.max_normal_deviation.map(|_| {
+        let mut deviation: f32 = 0.0;
+        for triangle in indices.chunks_exact(3) {
+            let [a, b, c] = [
+                position(triangle[0]),
+                position(triangle[1]),
+                position(triangle[2]),
+            ];
+            let normal = (b - a).cross(c - a).normalize_or_zero();
+            let Some(hit) = source.closest_point((a + b + c) / 3.0) else {
+                continue;
+            };
+            let source_normal = source.triangle_normal(hit.triangle);
+            if normal != Vec3::ZERO && source_normal != Vec3::ZERO {
+                deviation = deviation.max(normal.dot(source_normal).clamp(-1.0, 1.0).acos());
+            }
+        }
+        deviation
+    });
+
+    let geometric_deviation = guard.max_geometric_deviation.map(|_| {
+        let simplified = SurfaceIndex::new(indices, positions);
+        let distance = |surface: &SurfaceIndex, point: Vec3| {
+            surface
+                .closest_point(point)
+                .map_or(0.0, |hit| hit.distance_squared.sqrt())
+        };
+        let outward = surface_samples(indices, positions)
+            .into_iter()
+            .map(|sample| distance(source, sample))
+            .fold(0.0, f32::max);
+        // Vertices dropped by the simplifier still sit on the source surface.
+        let mut seen = vec![false; positions.len()];
+        source_indices
+            .iter()
+            .filter(|&&index| !std::mem::replace(&mut seen[index as usize], true))
+            .map(|&index| distance(&simplified, position(index)))
+            .fold(outward, f32::max)
+    });
+
+    Ok(GuardMeasurement {
+        normal_deviation,
+        geometric_deviation,
+        ..Default::default()
+    })
+}
@@ -0,0 +1,160 @@
+use bevy::{
+    math::Vec3,
+    mesh::{Mesh, VertexAttributeValues},
+    reflect::{Reflect, std_traits::ReflectDefault},
+};
+
+use crate::adjacency::TriangleAdjacency;
+
+/// How hard edges are found, see [`HardEdges`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Reflect)]
+#[reflect(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub enum HardEdgeDetection {
+    /// Vertices duplicated at the same position with normals further apart than the threshold,
+    /// i.e. the split normals exporters produce for flat shaded or beveled edges. Falls back to
+    /// [`HardEdgeDetection::FaceAngle`] for meshes without `ATTRIBUTE_NORMAL`.
+    #[default]
+    SplitNormals,
+    /// Edges between faces meeting at more than the threshold, regardless of the vertex normals.
+    FaceAngle,
+}
+
+/// Locks the vertices along hard edges so that bevels and creases keep their shape.
+///
+/// Locked vertices can't collapse along the edge either, so long straight hard edges keep all of
+/// their vertices. The regular simplifier already avoids collapsing across sharp features unless
+/// the error bound allows it, this mostly matters for sloppy simplification and large errors.
+#[derive(Debug, Copy, Clone, PartialEq, Reflect)]
+#[reflect(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct HardEdges {
+    /// Angle in radians above which an edge is considered hard.
+    pub angle_threshold: f32,
+    pub detection: HardEdgeDetection,
+}
+
+impl Default for HardEdges {
+    fn default() -> Self {
+        HardEdges {
+            angle_threshold: 40f32.to_radians(),
+            detection: HardEdgeDetection::default(),
+        }
+    }
+}
+
+impl HardEdges {
+    pub(crate) fn lock_hard_edge_vertices(
+        &self,
+        mesh: &Mesh,
+        indices: &[u32],
+        positions: &[[f32; 3]],
+        locks: &mut [bool],
+    ) {
+        let welded = TriangleAdjacency::new(indices, positions);
+        let cos_threshold = self.angle_threshold.cos();
+        let mut welded_locks = vec![false; welded.welded_vertex_count];
+
+        let normals = match mesh.attribute(Mesh::ATTRIBUTE_NORMAL) {
+            Some(VertexAttributeValues::Float32x3(normals)) if normals.len() == positions.len() => {
+                Some(normals)
+            }
+            _ => None,
+        };
+        match (self.detection, normals) {
+            (HardEdgeDetection::SplitNormals, Some(normals)) => {
+                // First normal seen at every welded vertex.
+                let mut first = vec![None; welded.welded_vertex_count];
+                for (normal, &welded_vertex) in normals.iter().zip(&welded.remap) {
+                    if welded_vertex == u32::MAX {
+                        continue;
+                    }
+
+                    let normal = Vec3::from_array(*normal).normalize_or_zero();
+                    match first[welded_vertex as usize] {
+                        None => first[welded_vertex as usize] = Some(normal),
+                        Some(first) if first.dot(normal) < cos_threshold => {
+                            welded_locks[welded_vertex as usize] = true;
+                        }
+                        Some(_) => {}
+                    }
+                }
+            }
+            _ => {
+                for ((a, b), faces) in welded.welded_edges() {
+                    let faces: Vec<Vec3> = faces
+                        .iter()
+                        .map(|&triangle| welded.face_normals[triangle as usize])
+                        .filter(|normal| *normal != Vec3::ZERO)
+                        .collect();
+                    let hard = faces.iter().enumerate().any(|(i, normal)| {
+                        faces[i + 1..]
+                            .iter()
+                            .any(|other| normal.dot(*other) < cos_threshold)
+                    });
+                    if hard {
+                        welded_locks[a as usize] = true;
+                        welded_locks[b as usize] = true;
+                    }
+                }
+            }
+        }
+
+        welded.flag_welded(&welded_locks, locks);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        MeshExt, SimplifyParams, TargetIndices,
+        test_util::{beveled_cube, indices, positions},
+    };
+
+    /// Distinct positions used by the triangles where a face of [`beveled_cube`] meets a bevel.
+    fn bevel_loop_vertices(mesh: &Mesh) -> usize {
+        let positions = positions(mesh);
+        let mut used: Vec<[i32; 3]> = indices(mesh)
+            .into_iter()
+            .map(|vertex| positions[vertex as usize])
+            .filter(|position| {
+                let on = |distance: f32| {
+                    position
+                        .iter()
+                        .filter(|value| (value.abs() - distance).abs() < 1e-4)
+                        .count()
+                };
+                on(1.0) == 1 && on(0.8) >= 1
+            })
+            .map(|position| position.map(|value| (value * 1000.0).round() as i32))
+            .collect();
+        used.sort_unstable();
+        used.dedup();
+        used.len()
+    }
+
+    fn simplified(hard_edges: Option<HardEdges>) -> Mesh {
+        let mut mesh = beveled_cube(0.2, 8);
+        let report = mesh
+            .simplify_with_report(&SimplifyParams {
+                target_index_count: TargetIndices::Multiplier(0.3),
+                max_error: 1.0,
+                hard_edges,
+                ..Default::default()
+            })
+            .unwrap();
+        assert!(report.triangles_after() as f32 <= report.triangles_before() as f32 * 0.3);
+        mesh
+    }
+
+    #[test]
+    fn hard_edges_keep_the_bevel_loops() {
+        let loops = bevel_loop_vertices(&beveled_cube(0.2, 8));
+        assert_eq!(
+            bevel_loop_vertices(&simplified(Some(HardEdges::default()))),
+            loops
+        );
+        assert!(bevel_loop_vertices(&simplified(None)) < loops);
+    }
+}
@@ -0,0 +1,260 @@
+use bevy::mesh::{Mesh, MeshVertexAttribute, MeshVertexAttributeId};
+
+use crate::{OptError, validate_indices};
+
+/// Vertex attributes of a mesh interleaved into a single vertex buffer, see
+/// [`MeshExt::to_interleaved`](crate::MeshExt::to_interleaved).
+///
+/// Every attribute keeps its format. Offsets are aligned to the size of their format, up to 4
+/// bytes, and the stride to 4 bytes as required for vertex buffers, the padding is zeroed. For
+/// example positions (`Float32x3`), a `Unorm8x2` attribute and normals (`Float32x3`) are at the
+/// offsets 0, 12 and 16 with a stride of 28.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InterleavedVertexBuffer {
+    pub vertices: Vec<u8>,
+    pub vertex_count: usize,
+    /// Bytes between the starts of consecutive vertices.
+    pub stride: usize,
+    /// Attributes in the order they are laid out in every vertex.
+    pub attributes: Vec<InterleavedAttribute>,
+    pub indices: Vec<u32>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct InterleavedAttribute {
+    pub attribute: MeshVertexAttribute,
+    /// Offset of the attribute from the start of a vertex, in bytes.
+    pub offset: usize,
+}
+
+/// Vertex fetch of an [`InterleavedVertexBuffer`] before and after
+/// [`InterleavedVertexBuffer::optimize_vertex_fetch`], measured with its stride.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct InterleavedFetchReport {
+    pub vertices_before: usize,
+    pub vertices_after: usize,
+    pub bytes_fetched_before: usize,
+    pub bytes_fetched_after: usize,
+}
+
+impl InterleavedVertexBuffer {
+    /// Bytes of vertex `index`.
+    pub fn vertex(&self, index: usize) -> &[u8] {
+        &self.vertices[index * self.stride..][..self.stride]
+    }
+
+    /// Offset of `id` in every vertex, `None` if it isn't part of the layout.
+    pub fn offset(&self, id: impl Into<MeshVertexAttributeId>) -> Option<usize> {
+        let id = id.into();
+        self.attributes
+            .iter()
+            .find(|attribute| attribute.attribute.id == id)
+            .map(|attribute| attribute.offset)
+    }
+
+    /// Reorders the vertices for locality of reference and drops unused ones, like
+    /// [`MeshExt::optimize_vertex_fetch`](crate::MeshExt::optimize_vertex_fetch) but moving whole
+    /// interleaved vertices and measuring the fetch with this buffer's stride. meshoptimizer only
+    /// measures strides up to 256 bytes, the fetched bytes are zero for larger ones.
+    pub fn optimize_vertex_fetch(&mut self) -> Result<InterleavedFetchReport, OptError> {
+        validate_indices(&self.indices, self.vertex_count)?;
+        let bytes_fetched_before = self.bytes_fetched();
+
+        let mut remap = vec![u32::MAX; self.vertex_count];
+        // SAFETY: `remap` has one entry per vertex and every index is below `vertex_count`.
+        let vertex_count = unsafe {
+            meshopt::ffi::meshopt_optimizeVertexFetchRemap(
+                remap.as_mut_ptr(),
+                self.indices.as_ptr(),
+                self.indices.len(),
+                self.vertex_count,
+            )
+        };
+        for index in &mut self.indices {
+            *index = remap[*index as usize];
+        }
+        let mut vertices = vec![0; vertex_count * self.stride];
+        for (vertex, &new) in remap.iter().enumerate() {
+            if new != u32::MAX {
+                vertices[new as usize * self.stride..][..self.stride]
+                    .copy_from_slice(self.vertex(vertex));
+            }
+        }
+
+        let vertices_before = self.vertex_count;
+        self.vertices = vertices;
+        self.vertex_count = vertex_count;
+        Ok(InterleavedFetchReport {
+            vertices_before,
+            vertices_after: vertex_count,
+            bytes_fetched_before,
+            bytes_fetched_after: self.bytes_fetched(),
+        })
+    }
+
+    fn bytes_fetched(&self) -> usize {
+        if !(1..=256).contains(&self.stride) {
+            return 0;
+        }
+        meshopt::analyze_vertex_fetch(&self.indices, self.vertex_count, self.stride).bytes_fetched
+            as usize
+    }
+}
+
+/// Interleaves the attributes listed in `layout`, or every attribute in the order Bevy lays them
+/// out, into one vertex buffer.
+pub(crate) fn to_interleaved(
+    mesh: &Mesh,
+    layout: Option<&[MeshVertexAttributeId]>,
+) -> Result<InterleavedVertexBuffer, OptError> {
+    let indices: Vec<u32> = mesh
+        .indices()
+        .ok_or(OptError::MissingIndices)?
+        .iter()
+        .map(|index| index as u32)
+        .collect();
+    let vertex_count = mesh.count_vertices();
+    validate_indices(&indices, vertex_count)?;
+
+    let attributes: Vec<_> = match layout {
+        Some(layout) => {
+            let mut attributes = Vec::with_capacity(layout.len());
+            for (position, &id) in layout.iter().enumerate() {
+                let attribute = mesh
+                    .attributes()
+                    .find(|(attribute, _)| attribute.id == id)
+                    .filter(|_| !layout[..position].contains(&id))
+                    .ok_or(OptError::InvalidVertexLayout(id))?;
+                attributes.push(attribute);
+            }
+            attributes
+        }
+        None => mesh.attributes().collect(),
+    };
+
+    let mut stride = 0usize;
+    let mut layout = Vec::with_capacity(attributes.len());
+    for (attribute, _) in &attributes {
+        let size = attribute.format.size() as usize;
+        let offset = stride.next_multiple_of(size.min(4));
+        layout.push(InterleavedAttribute {
+            attribute: **attribute,
+            offset,
+        });
+        stride = offset + size;
+    }
+    let stride = stride.next_multiple_of(4);
+
+    let mut vertices = vec![0; vertex_count * stride];
+    for (interleaved, (attribute, values)) in layout.iter().zip(&attributes) {
+        let size = attribute.format.size() as usize;
+        for (vertex, value) in vertices
+            .chunks_exact_mut(stride)
+            .zip(values.get_bytes().chunks_exact(size))
+        {
+            vertex[interleaved.offset..][..size].copy_from_slice(value);
+        }
+    }
+
+    Ok(InterleavedVertexBuffer {
+        vertices,
+        vertex_count,
+        stride,
+        attributes: layout,
+        indices,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::mesh::{Indices, VertexAttributeValues, VertexFormat};
+
+    use super::*;
+    use crate::{MeshExt, test_util::grid};
+
+    const PACKED: MeshVertexAttribute =
+        MeshVertexAttribute::new("Vertex_Packed", 1000, VertexFormat::Unorm8x2);
+
+    /// [`grid`] with a `Unorm8x2` attribute holding the vertex index.
+    fn packed_grid() -> Mesh {
+        let mut mesh = grid(2);
+        let packed: Vec<[u8; 2]> = (0..mesh.count_vertices() as u8).map(|i| [i, !i]).collect();
+        mesh.insert_attribute(PACKED, VertexAttributeValues::Unorm8x2(packed));
+        mesh
+    }
+
+    fn offsets(interleaved: &InterleavedVertexBuffer) -> Vec<usize> {
+        interleaved.attributes.iter().map(|a| a.offset).collect()
+    }
+
+    #[test]
+    fn offsets_and_stride_match_the_layout() {
+        let mesh = packed_grid();
+        let layout = [
+            Mesh::ATTRIBUTE_POSITION.id,
+            PACKED.id,
+            Mesh::ATTRIBUTE_NORMAL.id,
+        ];
+        let interleaved = mesh.to_interleaved(Some(&layout)).unwrap();
+        // 12 bytes of position, 2 of the packed attribute padded to 4, 12 of normal.
+        assert_eq!(offsets(&interleaved), [0, 12, 16]);
+        assert_eq!(interleaved.stride, 28);
+        assert_eq!(interleaved.vertices.len(), 28 * mesh.count_vertices());
+
+        let layout = [PACKED.id, Mesh::ATTRIBUTE_UV_0.id, PACKED.id];
+        assert!(matches!(
+            mesh.to_interleaved(Some(&layout)),
+            Err(OptError::InvalidVertexLayout(id)) if id == PACKED.id
+        ));
+        let interleaved = mesh
+            .to_interleaved(Some(&[PACKED.id, Mesh::ATTRIBUTE_UV_0.id]))
+            .unwrap();
+        // The UVs are aligned to 4 bytes after the packed attribute, the padding is zeroed.
+        assert_eq!(offsets(&interleaved), [0, 4]);
+        assert_eq!(interleaved.stride, 12);
+        for (vertex, packed) in (0..interleaved.vertex_count).zip(0u8..) {
+            assert_eq!(interleaved.vertex(vertex)[..4], [packed, !packed, 0, 0]);
+        }
+
+        // Sorted by id: position, normal, UV, packed.
+        let interleaved = mesh.to_interleaved(None).unwrap();
+        assert_eq!(offsets(&interleaved), [0, 12, 24, 32]);
+        assert_eq!(interleaved.stride, 36);
+        assert_eq!(interleaved.offset(PACKED.id), Some(32));
+    }
+
+    #[test]
+    fn fetch_optimization_keeps_every_triangle() {
+        let mut mesh = packed_grid();
+        let mut indices = mesh
+            .indices()
+            .unwrap()
+            .iter()
+            .map(|i| i as u32)
+            .collect::<Vec<_>>();
+        // Reversed triangles fetch the vertices back to front.
+        indices.reverse();
+        mesh.insert_indices(Indices::U32(indices));
+        let source = mesh.to_interleaved(None).unwrap();
+        let triangles = |interleaved: &InterleavedVertexBuffer| {
+            let mut triangles: Vec<Vec<u8>> = interleaved
+                .indices
+                .chunks_exact(3)
+                .map(|t| {
+                    t.iter()
+                        .flat_map(|&i| interleaved.vertex(i as usize).to_vec())
+                        .collect()
+                })
+                .collect();
+            triangles.sort();
+            triangles
+        };
+
+        let mut optimized = source.clone();
+        let report = optimized.optimize_vertex_fetch().unwrap();
+        assert_eq!(report.vertices_after, source.vertex_count);
+        assert!(report.bytes_fetched_after <= report.bytes_fetched_before);
+        assert_eq!(triangles(&optimized), triangles(&source));
+        assert_ne!(optimized.vertices, source.vertices);
+    }
+}
@@ -1,29 +1,557 @@
-use std::{error::Error, fmt::Display};
+use std::{borrow::Cow, error::Error, fmt::Display, ops::ControlFlow};
 
-use bevy::mesh::{Indices, Mesh, PrimitiveTopology, VertexAttributeValues};
+#[cfg(feature = "serialize")]
+use bevy::reflect::{ReflectDeserialize, ReflectSerialize};
+use bevy::{
+    math::Vec3,
+    mesh::{
+        Indices, Mesh, MeshVertexAttribute, MeshVertexAttributeId, PrimitiveTopology,
+        VertexAttributeValues, VertexFormat,
+    },
+    reflect::{FromReflect, PartialReflect, Reflect, std_traits::ReflectDefault},
+    transform::components::GlobalTransform,
+};
 
+mod adjacency;
+#[cfg(feature = "asset_processor")]
+mod asset_processor;
+mod attributes;
+mod background;
+mod bench;
+mod border;
+mod bounds;
+mod cache;
+mod compress;
+mod connectivity;
+mod correspondence;
+#[cfg(feature = "gizmos")]
+mod debug_gizmos;
+mod diagnostics;
+mod diff;
+mod double_sided;
+#[cfg(feature = "export")]
+mod export;
+mod fallback;
+mod foliage;
+mod formats;
+#[cfg(feature = "gizmos")]
+mod gizmos;
+#[cfg(feature = "gltf")]
+mod gltf;
+#[cfg(feature = "gltf")]
+mod gltf_compression;
+#[cfg(feature = "gltf")]
+mod gltf_process;
+mod guard;
+mod hard_edge;
+#[cfg(feature = "render")]
+mod index_upload;
+mod interleave;
+mod lod;
+mod lod_switch;
+mod manifold;
+mod mesh_asset;
+mod meshlet;
+mod meshlet_asset;
+#[cfg(feature = "meshlet_mesh")]
+mod meshlet_mesh;
+#[cfg(feature = "render")]
+mod meshlet_render;
+mod metrics;
+mod navmesh;
+mod occluder;
+mod optimize;
+#[cfg(feature = "debug_overlay")]
+mod overlay;
+mod parallel;
+#[cfg(feature = "egui")]
+mod params_ui;
+mod planar;
+mod plugin;
+mod points;
+#[cfg(feature = "serialize")]
+mod process;
+mod provenance;
+mod quantize;
+mod recommend;
+mod regenerate;
+mod reload;
+mod remap;
+mod report;
+mod scene;
+mod seam;
+mod silhouette;
+mod simplify;
+mod split;
+mod stats;
+mod strip;
+mod sweep;
+mod symmetry;
+#[cfg(test)]
+mod test_util;
+mod vertex;
+mod vertex_lock;
+
+pub use adjacency::{AdjacentEdge, TriangleAdjacency};
+#[cfg(feature = "asset_processor")]
+pub use asset_processor::{
+    CompressedMeshSaver, MeshoptProcessor, MeshoptProcessorPlugin, ProcessMesh, ProcessedMesh,
+    SimplifyMeshProcess,
+};
+#[cfg(all(feature = "asset_processor", feature = "gltf"))]
+pub use asset_processor::{GltfMeshoptProcessor, GltfProcessSettings};
+pub use attributes::UvWeighting;
+pub use background::{
+    GenerateMeshLods, MeshProcessRequest, MeshProcessing, MeshSimplified, MeshSimplifyFailed,
+    MeshSimplifyPlugin, SimplifiedMeshOutput, SimplifyExecution, SimplifyMesh,
+};
+pub use bench::{BenchPreset, BenchReport, PresetMeasurement, PresetResult, bench_presets};
+pub use border::BorderSelection;
+pub use bounds::{MeshBoundsPlugin, MeshModified, update_modified_mesh_aabbs};
+pub use cache::{CacheSettings, SimplifyCache};
+pub use compress::{CompressedAttribute, CompressedIndices, CompressedMesh};
+pub use correspondence::{CorrespondenceMap, CorrespondenceSample, compute_correspondence};
+#[cfg(feature = "gizmos")]
+pub use debug_gizmos::{MeshoptDebug, MeshoptDebugPlugin, MeshoptDebugSettings};
+pub use diagnostics::{MeshoptDiagnosticsPlugin, measure_scene_triangles};
+pub use diff::{MeshDiff, MeshDiffSettings, mesh_diff};
+#[cfg(feature = "export")]
+pub use export::{meshes_to_glb, save_mesh};
+pub use fallback::{FallbackPolicy, SimplifyPath};
+pub use foliage::SimplifyStrategy;
+pub use formats::{FormatChange, FormatChangeReason, FormatReport};
+#[cfg(feature = "gizmos")]
+pub use gizmos::{MeshletGizmoPlugin, MeshletGizmoSettings, draw_meshlet_gizmos};
+#[cfg(feature = "gltf")]
+pub use gltf::{GltfLods, GltfMeshesProcessed, GltfMeshoptPlugin, GltfMeshoptSettings};
+#[cfg(feature = "gltf")]
+pub use gltf_compression::{
+    EXT_MESHOPT_COMPRESSION, GltfMeshoptCompressionPlugin, MeshoptGltfLoader, compress_gltf,
+    decompress_gltf,
+};
+#[cfg(feature = "gltf")]
+pub use gltf_process::{GltfProcessReport, process_gltf};
+pub use guard::{GuardAttempt, GuardMeasurement, GuardedSimplifyReport, QualityGuard};
+pub use hard_edge::{HardEdgeDetection, HardEdges};
+#[cfg(feature = "render")]
+pub use index_upload::{IndexBufferUpdates, IndexUploadPlugin, IndexUploadStats};
+pub use interleave::{InterleavedAttribute, InterleavedFetchReport, InterleavedVertexBuffer};
+pub use lod::{
+    ConcatenatedLods, LevelSpec, LevelTarget, LodChain, LodChainParams, LodChainReport,
+    LodLevelReport, LodLevels, LodMemoryBudget, LodMemoryReport, LodMorph, LodStopReason,
+    LodStrategy, LodVertexBuffers, MemoryBudgetPolicy, MinTrianglesPolicy,
+};
+pub use lod_switch::{
+    LodBudgetStatus, LodHysteresis, LodMetric, LodTriangleBudget, MeshLodPlugin, MeshLods,
+    switch_mesh_lods,
+};
+pub use manifold::ManifoldStatus;
+pub use mesh_asset::{CompressedMeshFile, CompressedMeshLoader};
+pub use meshlet::{Meshlet, MeshletBounds, MeshletCullingBounds, MeshletParams, Meshlets};
+pub use meshlet_asset::{MeshletsAsset, MeshletsLoader};
+#[cfg(feature = "render")]
+pub use meshlet_render::{
+    GpuMeshlet, GpuMeshletBounds, MeshletBoundsBuffer, MeshletBuffers, MeshletRenderPlugin,
+    PackedMeshlets,
+};
 pub use meshopt::SimplifyOptions;
+pub use navmesh::{NavmeshParams, NavmeshReport};
+pub use occluder::{OccluderParams, OccluderReport};
+pub use optimize::{
+    CacheModel, OptimizeReport, OptimizeSettings, OptimizeSkipReason, OptimizedThresholds,
+};
+#[cfg(feature = "debug_overlay")]
+pub use overlay::{MeshoptOverlay, MeshoptOverlayPlugin};
+#[cfg(feature = "egui")]
+pub use params_ui::simplify_params_ui;
+pub use plugin::{
+    CurrentLod, KeepPickingMesh, LodChainCompleted, MeshoptPlugin, MeshoptSet, Optimize,
+    PickingMesh, ReclaimOutcome, RequestTag, SimplificationCompleted, Simplify, SimplifyOverride,
+    SimplifySettings, SimplifyStats, SourceMeshReclaimed, SourceReclaim, StrippedMeshes,
+    optimize_meshes, simplify_meshes, update_picking_meshes,
+};
+pub use points::{PointSimplifyParams, PointTarget};
+#[cfg(feature = "serialize")]
+pub use process::{MeshProcessReport, MeshProcessSettings, ProcessOptimize, ProcessSimplify};
+pub use provenance::{ProvenanceConfidence, TriangleProvenance};
+pub use quantize::{
+    DirectionQuantization, PositionQuantization, QuantizeConfig, QuantizeReport,
+    QuantizedPositions, UvQuantization,
+};
+pub use recommend::{TargetRecommendation, ViewParams, recommend_target};
+pub use regenerate::{AttributeSet, StripReport};
+pub use reload::{
+    DerivedMesh, DerivedMeshRefreshed, DerivedMeshes, MeshPass, refresh_derived_meshes,
+};
+pub use remap::RemapTable;
+pub use report::{SimplifyReport, WeldReport};
+pub use scene::{
+    BudgetWeighting, SceneBudget, SceneMeshReport, SceneSimplifyReport, simplify_hierarchy,
+    simplify_scene,
+};
+pub use seam::SeamLocks;
+pub use silhouette::SilhouetteLocks;
+pub use simplify::StepParams;
+pub use split::{
+    MeshletPartition, MeshletSplit, MeshletSplitParams, merge_meshlet_entities,
+    split_into_meshlet_entities,
+};
+pub use stats::MeshStats;
+pub use sweep::{SweepPoint, knee_point, simplify_sweep};
+pub use symmetry::{SymmetryMode, SymmetryPlane};
+pub use vertex_lock::{LockPredicate, VertexLocks};
 
+/// Operations on Bevy meshes. Those that merge, drop, reorder or duplicate vertices do so in every
+/// attribute of the mesh, whatever its format, so colors, custom attributes of other plugins and
+/// the like stay with their vertices. Meshes with an attribute that doesn't have a value per
+/// vertex fail with [`OptError::MismatchedAttributeLength`] instead of being truncated.
 pub trait MeshExt {
     /// Assert that the mesh has u32 indices, replaces if it is u16.
     fn assert_indices_u32(&mut self);
-    /// [`meshopt::simplify`] but returns the new indices and error.
-    #[must_use]
+    /// [`meshopt::simplify`] but returns the new indices and error. Needs `u32` indices since the
+    /// new ones refer to the vertices of the mesh as it is, [`MeshExt::simplify`] takes any.
+    #[must_use = "the mesh is left as is, the new indices are only returned"]
     fn simplify_new_indices(&self, params: &SimplifyParams) -> Result<(Vec<u32>, f32), OptError>;
-    /// [`meshopt::simplify`]
+    /// [`meshopt::simplify`]. Works on `u16`, `u32` and non-indexed triangle lists, `u16` indices
+    /// are kept and non-indexed meshes end up indexed unless
+    /// [`SimplifyParams::expand_generated_indices`] is set. Replaces the indices of the mesh, use
+    /// [`MeshExt::simplified`] to keep the source around, e.g. for LOD chains or undo.
+    ///
+    /// Only the indices change, so morph targets stay valid. Non-indexed meshes and
+    /// [`SimplifyMode::Points`] would merge or drop vertices and fail with
+    /// [`OptError::MorphTargetsUnsupported`] when the mesh has morph targets.
+    ///
+    /// 2D meshes work as well, with `Float32x3` positions or `Float32x2` ones, which are
+    /// simplified on the `z = 0` plane and stay `Float32x2`. `PointList` meshes are decimated by
+    /// the point simplifier like with [`SimplifyMode::Points`], one point kept per triangle the
+    /// target resolves to when counting a triangle per point. Other topologies fail with
+    /// [`OptError::UnsupportedPrimitiveTopology`].
     fn simplify(&mut self, params: &SimplifyParams) -> Result<f32, OptError>;
-    /// [`meshopt::optimize_vertex_fetch`]
+    /// [`MeshExt::simplify`] but reports the outcome, including the error in mesh units, e.g. to
+    /// decide whether to keep the result. The simplifier makes no collapses on a mesh already at
+    /// or below the target and reports an error of `0.0`, only the triangles removed by
+    /// `SimplifyOptions::Prune`, [`SimplifyParams::strip_degenerates`] or
+    /// [`SimplifyParams::merge_double_sided`] are gone and [`SimplifyParams::canonical_order`]
+    /// still sorts them.
+    fn simplify_with_report(&mut self, params: &SimplifyParams)
+    -> Result<SimplifyReport, OptError>;
+    /// Simplified copy of the mesh, leaving it untouched. Goes through [`MeshExt::simplify`] on a
+    /// clone, then drops the vertices the new indices don't use like
+    /// [`MeshExt::optimize_vertex_fetch`], which reorders them. Every attribute, the topology,
+    /// the asset usage and the index format (`u16` or `u32`) of the source are kept, unless
+    /// [`SimplifyParams::shrink_indices`] narrows them. Fails with
+    /// [`OptError::MorphTargetsUnsupported`] on meshes with morph targets.
+    fn simplified(&self, params: &SimplifyParams) -> Result<Mesh, OptError>;
+    /// Best-effort source triangle of every triangle of `simplified_indices`, e.g. to carry
+    /// per-triangle data (lightmap charts, surface types) over to a simplified mesh. The indices
+    /// have to refer to the vertices of this mesh, like the output of
+    /// [`MeshExt::simplify_new_indices`].
+    ///
+    /// meshoptimizer doesn't expose its collapse history, so this is a heuristic: output triangles
+    /// are matched to the source triangles sharing the most corner positions, preferring the one
+    /// facing the most alike, then to the source triangle closest to their centroid. See
+    /// [`ProvenanceConfidence`] for how reliable each match is.
+    fn triangle_provenance(
+        &self,
+        simplified_indices: &[u32],
+    ) -> Result<Vec<TriangleProvenance>, OptError>;
+    /// Runs the simplification described by `params` without modifying the mesh, returning what
+    /// the result would look like. Like [`MeshExt::simplify`], works on `u16`, `u32` and
+    /// non-indexed triangle lists, the vertices of non-indexed meshes are counted after merging
+    /// identical ones.
+    fn simplify_dry_run(&self, params: &SimplifyParams) -> Result<SimplifyReport, OptError>;
+    /// Repeatedly simplifies the mesh by [`StepParams::reduction`], calling `predicate` with the
+    /// cumulative report and the simplified mesh after every step. Stops once `predicate` breaks,
+    /// in which case the step it rejected is rolled back, or once the simplifier can't make any
+    /// more progress. Returns the report of the last accepted step. Like [`MeshExt::simplify`],
+    /// works on `u16`, `u32` and non-indexed triangle lists, `predicate` sees the mesh with `u32`
+    /// indices.
+    ///
+    /// Every step runs a full simplification pass over the current mesh and then calls
+    /// `predicate` once, so the total cost is roughly `log(1 / ratio) / reduction` simplifications
+    /// plus as many predicate calls. Predicates that compare against the original mesh (e.g.
+    /// measuring surface deviation) are usually far more expensive than the simplification itself,
+    /// prefer a larger `reduction` when using those. The rollback copy only holds the previous
+    /// index buffer.
+    fn simplify_until(
+        &mut self,
+        step: StepParams,
+        predicate: impl FnMut(&SimplifyReport, &Mesh) -> ControlFlow<()>,
+    ) -> Result<SimplifyReport, OptError>;
+    /// Simplifies the mesh and checks the result against `guard`, rolling back and retrying with
+    /// half the `max_error` when it is violated. Returns every attempt along with the accepted
+    /// one, or [`OptError::QualityGuardFailed`] with the mesh left untouched if none passed.
+    fn simplify_guarded(
+        &mut self,
+        params: &SimplifyParams,
+        guard: &QualityGuard,
+    ) -> Result<GuardedSimplifyReport, OptError>;
+    /// Decimates `PointList` meshes like scanned point clouds with meshoptimizer's point
+    /// simplifier, keeping every attribute of the points that remain. Targets at or above the
+    /// current point count leave the mesh as is, otherwise it ends up non-indexed.
+    fn simplify_points_in_place(&mut self, params: &PointSimplifyParams) -> Result<(), OptError>;
+    /// Measures the vertex cache, overdraw and vertex fetch efficiency of the mesh with `u16` or
+    /// `u32` indices, e.g. to decide whether optimizing or simplifying it is worth it.
+    /// `cache_size` is the number of entries of the simulated vertex cache, 16 by default.
+    fn analyze(&self, cache_size: Option<u32>) -> Result<MeshStats, OptError>;
+    /// Extent of the mesh relative errors are measured against, [`meshopt::simplify_scale`] of the
+    /// positions: a relative `max_error` times the scale is the error in mesh units. Picking an
+    /// absolute error instead, see [`SimplifyParams::in_world_space`], keeps LODs of meshes of
+    /// different sizes equally detailed.
+    fn simplify_scale(&self) -> Result<f32, OptError>;
+    /// Compresses every attribute and the indices with meshoptimizer's vertex and index codecs,
+    /// e.g. to store or send the mesh, decoded with [`CompressedMesh::decode`]. Works with any
+    /// topology and `u16` or `u32` indices, keeping their order. Optimizing the mesh for vertex
+    /// cache and fetch first makes the result smaller.
+    fn encode_compressed(&self) -> Result<CompressedMesh, OptError>;
+    /// Runs the optimization stages enabled in `settings`, measuring the mesh before and after.
+    /// Stages run in the order meshoptimizer recommends: vertex cache, overdraw, then vertex fetch,
+    /// as each one keeps what the previous ones gained.
+    ///
+    /// Like the single passes below, keeps every attribute in step and works on `u16` and `u32`
+    /// indices alike, narrowing widened `u16` indices back afterwards. Non-indexed meshes are
+    /// indexed by merging identical vertices first and stay indexed.
+    fn optimize(&mut self, settings: &OptimizeSettings) -> Result<OptimizeReport, OptError>;
+    /// [`meshopt::optimize_vertex_fetch_remap`], reordering every vertex attribute and dropping
+    /// unused vertices.
     fn optimize_vertex_fetch(&mut self) -> Result<(), OptError>;
+    /// [`MeshExt::optimize_vertex_fetch`], returning the table mapping the old vertices to the new
+    /// ones so per-vertex data stored outside of the mesh can be remapped the same way, e.g. the
+    /// morph targets with [`RemapTable::apply_morph_targets`]. The other operations that remap
+    /// vertices fail with [`OptError::MorphTargetsUnsupported`] on meshes with morph targets.
+    fn optimize_vertex_fetch_remap(&mut self) -> Result<RemapTable, OptError>;
+    /// Interleaves the attributes listed in `layout`, in that order, into a single vertex buffer
+    /// for renderers that don't use Bevy's. `None` takes every attribute in the order Bevy lays
+    /// them out, sorted by id. Attributes keep their format and the indices are widened to `u32`.
+    ///
+    /// Fails with [`OptError::InvalidVertexLayout`] if an attribute of `layout` is missing from
+    /// the mesh or listed twice.
+    fn to_interleaved(
+        &self,
+        layout: Option<&[MeshVertexAttributeId]>,
+    ) -> Result<InterleavedVertexBuffer, OptError>;
+    /// Merges vertices that are bit-identical in every attribute and drops unused ones, e.g. after
+    /// quantizing attributes made formerly distinct vertices identical. Vertices that differ in
+    /// any byte of any attribute are kept apart.
+    fn weld_identical_vertices(&mut self) -> Result<WeldReport, OptError>;
+    /// Merges duplicate vertices and drops unused ones, rebuilding every attribute and the
+    /// indices, e.g. before simplifying glTF exports that split their topology with duplicated
+    /// vertices. `None` merges only bit-identical vertices like
+    /// [`MeshExt::weld_identical_vertices`]. With a tolerance, vertices whose positions round to
+    /// the same point of a grid with that spacing are merged as long as every other attribute is
+    /// identical, so UV and normal seams are kept. Positions closer than `tolerance` on both sides
+    /// of a grid line stay apart.
+    ///
+    /// Unlike [`MeshExt::weld_identical_vertices`], non-indexed meshes are indexed and `u16`
+    /// indices are kept. Fails with [`OptError::InvalidWeldTolerance`] on a tolerance that isn't
+    /// positive and finite.
+    fn weld_vertices(&mut self, tolerance: Option<f32>) -> Result<WeldReport, OptError>;
+    /// Sorts the vertices by their contents and the triangles by their indices, each rotated to
+    /// start at its smallest index without changing its winding. Meshes that only differ in the
+    /// order their vertices and triangles were written in become bit-identical, and so do their
+    /// simplified results, e.g. when an exporter doesn't write meshes in a stable order. Duplicate
+    /// vertices keep their relative order, weld them first with
+    /// [`MeshExt::weld_identical_vertices`] to make the result independent of it as well.
+    ///
+    /// Works on `u16`, `u32` and non-indexed triangle lists, other topologies fail with
+    /// [`OptError::UnsupportedPrimitiveTopology`]. Fails with [`OptError::MorphTargetsUnsupported`]
+    /// on meshes with morph targets, which can't be reordered along with the vertices.
+    fn canonicalize(&mut self) -> Result<(), OptError>;
+    /// Removes `attributes` from the mesh, runs `f` on the slimmer mesh and regenerates them
+    /// afterwards. Attributes that can be derived anyway only hold back welding and simplification,
+    /// e.g. split normals keep vertices apart. Listed attributes the mesh doesn't have are ignored.
+    ///
+    /// Fails with [`OptError::MissingRegenerationInput`] without changing the mesh if an attribute
+    /// couldn't be regenerated.
+    fn with_stripped_attributes<R>(
+        &mut self,
+        attributes: AttributeSet,
+        f: impl FnOnce(&mut Mesh) -> Result<R, OptError>,
+    ) -> Result<(R, StripReport), OptError>;
+    /// Converts the normalized attributes (`Snorm16`, `Unorm16`, `Snorm8` and `Unorm8` formats) to
+    /// `f32`, runs `f` on the widened mesh and encodes them back into their original format,
+    /// rounding to the nearest step, so compact meshes stay compact. `keep_widened` leaves them as
+    /// `f32` instead. Attributes `f` replaces with another format are left as is, the report lists
+    /// every attribute whose format changed and why.
+    ///
+    /// Bevy has no half float vertex attribute values, so `Float16` attributes can't occur.
+    fn with_widened_attributes<R>(
+        &mut self,
+        keep_widened: bool,
+        f: impl FnOnce(&mut Mesh) -> Result<R, OptError>,
+    ) -> Result<(R, FormatReport), OptError>;
     /// [`meshopt::optimize_overdraw`]
     fn optimize_overdraw(&mut self, threshold: f32) -> Result<(), OptError>;
     /// [`meshopt::optimize_vertex_cache`]
     fn optimize_vertex_cache(&mut self) -> Result<(), OptError>;
+    /// Orders the triangles by the projection of their centroid onto `axis`, farthest along it
+    /// last unless `descending`. Winding and the index format are preserved.
+    ///
+    /// Meant for alpha-blended meshes (hair cards, glass) that are mostly seen from one direction:
+    /// sorting descending along the view direction draws them roughly back-to-front and reduces
+    /// sorting artifacts. [`MeshExt::optimize_overdraw`] assumes opaque materials and reorders
+    /// triangles front-to-back, so use this instead of it for transparent materials, after
+    /// [`MeshExt::optimize_vertex_cache`] and before [`MeshExt::optimize_vertex_fetch`].
+    fn sort_triangles_along_axis_in_place(
+        &mut self,
+        axis: Vec3,
+        descending: bool,
+    ) -> Result<(), OptError>;
+    /// Orders the triangles along a space-filling curve through their centroids, which improves
+    /// raster locality for depth prepasses and shadow maps where the vertex cache matters less,
+    /// and the quality of BVHs built over the triangles in order, e.g. for ray tracing. The index
+    /// format is preserved, follow it with [`MeshExt::optimize_vertex_fetch`] to lay the vertices
+    /// out in the same order. For transparency, which needs an order along the view direction, see
+    /// [`MeshExt::sort_triangles_along_axis_in_place`].
+    fn spatial_sort_in_place(&mut self) -> Result<(), OptError>;
+    /// Index buffer of the same length and format as the mesh's in which every vertex is replaced
+    /// by the first vertex with the same position, so passes that only read positions, like
+    /// shadows and depth prepasses, transform fewer unique vertices. The mesh is left untouched,
+    /// upload the result as an alternate index buffer next to it.
+    fn shadow_indices(&self) -> Result<Indices, OptError>;
+    /// Separate mesh for passes that only read positions: just the positions of the mesh, indexed
+    /// by its [`MeshExt::shadow_indices`] with the vertices they no longer use dropped and the
+    /// triangles reordered for the vertex cache, so it is smaller to keep around and faster to
+    /// draw than the mesh itself. The index format is preserved.
+    ///
+    /// Meant for renderers or materials with their own depth-only passes, Bevy's shadow and
+    /// prepasses draw the mesh of the entity.
+    fn shadow_mesh(&self) -> Result<Mesh, OptError>;
+    /// Converts a triangle list to a `TriangleStrip` mesh with [`meshopt::stripify()`], carrying
+    /// every attribute over untouched. Strips are restarted at `restart_index`, which has to be
+    /// `u16::MAX` for `u16` indices or `u32::MAX` for `u32` ones as those are the only values Bevy
+    /// restarts at, or joined with degenerate triangles when it is `None`.
+    ///
+    /// Winding is kept, degenerate triangles draw nothing. Optimize the vertex cache beforehand,
+    /// strips of an optimized list are longer, and longest with [`CacheModel::Strip`].
+    fn to_triangle_strip(&self, restart_index: Option<u32>) -> Result<Mesh, OptError>;
+    /// Converts a `TriangleStrip` mesh back to a triangle list with [`meshopt::unstripify`],
+    /// restarting at the largest value of the index format and dropping degenerate triangles.
+    fn to_triangle_list(&self) -> Result<Mesh, OptError>;
+    /// Converts positions, normals, tangents and UVs to the compact formats selected by `config`,
+    /// which `StandardMaterial` renders as they are. Quantized positions are relative to the
+    /// bounds of the mesh, render the mesh with [`QuantizedPositions::transform`] from the report.
+    ///
+    /// Nothing is converted if an attribute fails to. [`QuantizeConfig::reweld`] welds the vertices
+    /// quantization made identical. Meant as the last step before upload: most
+    /// other methods need `f32` positions, [`MeshExt::with_widened_attributes`] widens the other
+    /// attributes back.
+    fn quantize_attributes(&mut self, config: &QuantizeConfig) -> Result<QuantizeReport, OptError>;
+    /// Generates a chain of progressively coarser levels of detail, each simplified from the
+    /// previous one. Levels keep `u16` indices and non-indexed meshes are indexed by merging
+    /// identical vertices, so LOD0 is the indexed mesh. The default [`LodChainParams`] halve the
+    /// triangles three times:
+    ///
+    /// ```no_run
+    /// use bevy::prelude::*;
+    /// use bevy_meshopt::{LodChainParams, MeshExt, OptError};
+    ///
+    /// fn lods(mesh: &Mesh) -> Result<Vec<Mesh>, OptError> {
+    ///     // LOD0 and levels at about 50%, 25% and 12.5% of its triangles.
+    ///     let chain = mesh.generate_lod_chain(&LodChainParams::default())?;
+    ///     Ok(chain.levels)
+    /// }
+    /// ```
+    fn generate_lod_chain(&self, params: &LodChainParams) -> Result<LodChain, OptError>;
+    /// Open boundary edges of the mesh matching `selection`, oriented the same way as in the
+    /// triangle using them. Vertices sharing a position are treated as one, so attribute seams
+    /// aren't borders.
+    fn border_edges(&self, selection: BorderSelection) -> Result<Vec<[u32; 2]>, OptError>;
+    /// One flag per vertex, set on the vertices on the seams selected by `seams`: vertices
+    /// sharing a position with another vertex whose UVs or normal differ. Can be merged into
+    /// [`SimplifyParams::vertex_locks`] along with other locks, [`SimplifyParams::seam_locks`]
+    /// does so automatically.
+    fn seam_vertices(&self, seams: &SeamLocks) -> Result<Vec<bool>, OptError>;
+    /// Extrudes the selected border edges by `depth` along `direction`, appending two skirt
+    /// triangles per edge that hide cracks between neighboring chunks of a different LOD. Returns
+    /// the number of extruded edges.
+    ///
+    /// Skirt vertices copy all attributes of the border vertex they were extruded from, so
+    /// normals keep following the original surface. Run this after simplifying, otherwise the
+    /// skirt gets simplified away.
+    fn generate_skirt(
+        &mut self,
+        direction: Vec3,
+        depth: f32,
+        border: BorderSelection,
+    ) -> Result<usize, OptError>;
+    /// Removes the second triangle of every pair of coincident triangles with opposite winding, see
+    /// [`SimplifyParams::merge_double_sided`], and drops the vertices only they used. Returns the
+    /// number of removed triangles.
+    fn merge_double_sided(&mut self) -> Result<usize, OptError>;
+    /// Triangle neighbors, incident triangles of every vertex and the edges of the mesh, with
+    /// vertices sharing a position treated as one so attribute seams don't split the surface.
+    /// Works with `u16` or `u32` indices, non-indexed meshes are indexed by merging identical
+    /// vertices first and the vertices of the result refer to the indexed mesh.
+    fn build_adjacency(&self) -> Result<TriangleAdjacency, OptError>;
+    /// Boundary and non-manifold edges of the mesh, vertices sharing a position are treated as
+    /// one.
+    fn manifold_status(&self) -> Result<ManifoldStatus, OptError>;
+    /// Splits the mesh into its connected components, triangles sharing an edge, with vertices
+    /// sharing a position treated as one so attribute seams don't split them. Every component
+    /// gets its own mesh with all attributes and only the vertices it uses, largest component
+    /// first. Gives full control over which small islands to drop, LOD or replace, unlike
+    /// `SimplifyOptions::Prune`.
+    fn split_by_connectivity(&self) -> Result<Vec<Mesh>, OptError>;
+    /// Triangle count of every component [`MeshExt::split_by_connectivity`] would return, in the
+    /// same order, without building the meshes.
+    fn component_sizes(&self) -> Result<Vec<usize>, OptError>;
+    /// Removes the connected components, as split by [`MeshExt::split_by_connectivity`], whose
+    /// bounding sphere has a radius of at most `min_error` relative to the extent of the mesh,
+    /// like `SimplifyOptions::Prune` does while simplifying, e.g. the floating debris of scans
+    /// that simplification keeps. Drops the vertices only they used and returns the number of
+    /// removed triangles.
+    fn prune_components(&mut self, min_error: f32) -> Result<usize, OptError>;
+    /// Splits the mesh into meshlets along with their bounds, see [`meshopt::build_meshlets`].
+    /// Works on `u16` and `u32` indices, [`Meshlets::vertices`] index the vertices of the mesh.
+    fn build_meshlets(&self, params: &MeshletParams) -> Result<Meshlets, OptError>;
+    /// Converts the mesh into Bevy's `MeshletMesh` for the virtual geometry renderer, which builds
+    /// its own meshlet hierarchy. Only positions, normals and `ATTRIBUTE_UV_0` are kept: normalized
+    /// formats are widened, missing normals are computed smooth and missing UVs are set to zero.
+    /// `vertex_position_quantization_factor` is passed on to `MeshletMesh::from_mesh`, see
+    /// `MESHLET_DEFAULT_VERTEX_POSITION_QUANTIZATION_FACTOR`. Like the conversion itself, needs
+    /// the `AsyncComputeTaskPool` Bevy apps set up.
+    #[cfg(feature = "meshlet_mesh")]
+    fn to_meshlet_mesh(
+        &self,
+        vertex_position_quantization_factor: u8,
+    ) -> Result<bevy::pbr::experimental::meshlet::MeshletMesh, OptError>;
+    /// Generates a position-only occluder for software occlusion culling. The mesh is simplified
+    /// aggressively and then shrunk along its vertex normals until it sits inside of the original
+    /// surface, see [`OccluderReport::conservative`].
+    fn generate_occluder(
+        &self,
+        params: &OccluderParams,
+    ) -> Result<(Mesh, OccluderReport), OptError>;
+    /// Generates a position-only simplification of level geometry for a navmesh generator.
+    /// Triangles are classified as walkable by their slope, the vertices between walkable and
+    /// non-walkable regions are locked and walkable vertices are held to
+    /// [`NavmeshParams::vertical_error`] vertically so floors stay flat.
+    fn generate_navmesh_source(
+        &self,
+        params: &NavmeshParams,
+    ) -> Result<(Mesh, NavmeshReport), OptError>;
 }
 
-#[derive(Debug, Copy, Clone)]
+/// Index count to simplify to, see [`TargetIndices::resolve`] for how it maps to an actual count.
+#[derive(Debug, Copy, Clone, PartialEq, Reflect)]
+#[reflect(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 pub enum TargetIndices {
+    /// Number of indices, three per triangle.
     Count(usize),
+    /// Fraction of the current index count, clamped to `0.0..=1.0`. NaN counts as `0.0`.
     Multiplier(f32),
+    /// Number of triangles.
+    TriangleCount(usize),
+    /// Number of vertices the result may use at most, e.g. for GPU memory budgets. Resolves to
+    /// six indices per vertex, the ratio of closed meshes, which the simplifier then lowers until
+    /// the result fits. The point simplifier keeps this many points.
+    VertexCount(usize),
+    /// No count target, the mesh is simplified as far as `max_error` allows, down to
+    /// `min_target_index_count`. [`SimplifyParams::fallback`] never engages and the point
+    /// simplifier, which has no error bound, keeps every point.
+    ErrorOnly,
 }
 
 impl Default for TargetIndices {
@@ -33,41 +561,291 @@ impl Default for TargetIndices {
 }
 
 impl TargetIndices {
+    /// [`TargetIndices::resolve`] with a minimum of one triangle.
     pub fn count(&self, current_count: usize) -> usize {
-        let count = match self {
-            TargetIndices::Count(count) => *count,
+        self.resolve(current_count, 3)
+    }
+
+    /// Target for a mesh of `current_count` indices: rounded down to whole triangles, at least
+    /// `min_count` (also rounded down to whole triangles) and at most `current_count`.
+    pub fn resolve(&self, current_count: usize, min_count: usize) -> usize {
+        let count = match *self {
+            TargetIndices::Count(count) => count,
             TargetIndices::Multiplier(multiplier) => {
-                ((current_count as f32 * multiplier) as usize / 3 * 3).max(3)
+                (current_count as f32 * multiplier.clamp(0.0, 1.0)) as usize
             }
+            TargetIndices::TriangleCount(triangles) => triangles.saturating_mul(3),
+            TargetIndices::VertexCount(vertices) => vertices.saturating_mul(6),
+            TargetIndices::ErrorOnly => 0,
         };
 
-        count.min(current_count)
+        (count / 3 * 3).max(min_count / 3 * 3).min(current_count)
     }
 }
 
-#[derive(Debug, Copy, Clone)]
-pub struct SimplifyParams<'a> {
+/// Simplifier [`SimplifyParams`] run with.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Reflect)]
+#[reflect(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub enum SimplifyMode {
+    /// [`meshopt::simplify()`] with the attributes, locks and options of the params.
+    #[default]
+    Precise,
+    /// [`meshopt::simplify_sloppy`], which reaches the target regardless of topology and
+    /// attributes. It doesn't take any `SimplifyOptions`, simplifying with options set fails with
+    /// [`OptError::UnsupportedSimplifyOptions`], and only reads the locks. See
+    /// [`FallbackPolicy::Sloppy`] to only use it when the precise simplifier misses the target.
+    Sloppy,
+    /// meshoptimizer's point simplifier, for extreme reductions where triangles bottom out, e.g.
+    /// distant splats. The mesh becomes a non-indexed `PointList` of the vertices the simplifier
+    /// keeps out of those the triangles use, one point per triangle `target_index_count`
+    /// resolves to. Only `color_weight` applies and the reported error is `0.0`.
+    ///
+    /// Only [`MeshExt::simplify`], [`MeshExt::simplify_with_report`] and
+    /// [`MeshExt::simplified`] can change the topology, the other ways of simplifying fail with
+    /// [`OptError::UnsupportedSimplifyMode`].
+    Points,
+}
+
+/// Settings of the simplifier. Owns all of its data, so it can be stored in resources and
+/// components, edited through reflection and, with the `serialize` feature, loaded from RON.
+/// Missing fields deserialize to their [`Default`]. Reflection serializes the params with serde
+/// when the feature is enabled, so scenes keep the fields that aren't reflected.
+#[derive(Debug, Clone, PartialEq, Reflect)]
+#[reflect(Debug, Clone, PartialEq, Default, from_reflect = false)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default),
+    reflect(Serialize, Deserialize)
+)]
+pub struct SimplifyParams {
     /// Maximum error allowed during simplification. This will be somewhat ignored if using sloppy mode.
     pub max_error: f32,
     /// Target index count for simplification.
     pub target_index_count: TargetIndices,
+    /// Smallest index count `target_index_count` resolves to, see [`TargetIndices::resolve`].
+    /// Defaults to one triangle, `0` lets the simplifier remove every triangle within
+    /// `max_error`.
+    pub min_target_index_count: usize,
+    /// Not reflected, [`SimplifyOptions`] is defined by meshopt. Serialized as a list of flag
+    /// names, e.g. `["LockBorder", "ErrorAbsolute"]`.
+    #[reflect(ignore, default = "SimplifyOptions::empty")]
+    #[cfg_attr(feature = "serialize", serde(with = "simplify_options_names"))]
     pub options: SimplifyOptions,
-    /// Note: Sloppy will ignore all `SimplifyOptions`.
-    pub sloppy: bool,
-    /// Lock specific vertices in place during simplification.
-    pub vertex_locks: Option<&'a [bool]>,
+    /// Simplifier to run, see [`SimplifyMode::Sloppy`] and [`SimplifyMode::Points`].
+    pub mode: SimplifyMode,
+    /// Lock specific vertices in place during simplification, one entry per vertex of the mesh
+    /// being simplified. Being tied to one mesh, settings shared between meshes, like
+    /// [`SimplifySettings`] or asset meta, select vertices with `locked_vertices` instead.
+    pub vertex_locks: Option<Vec<bool>>,
+    /// More vertices to lock in addition to `vertex_locks`, as a bitmask, a predicate over
+    /// positions or a custom attribute. Not reflected, see [`VertexLocks`] for which are
+    /// serialized.
+    #[reflect(ignore)]
+    pub locked_vertices: Option<VertexLocks>,
+    /// Plane the mesh is mirror-symmetric about, vertices lying on it are locked in addition to
+    /// `vertex_locks`.
+    pub symmetry: Option<SymmetryPlane>,
+    /// Weight of the skin influences of skinned meshes (`ATTRIBUTE_JOINT_INDEX` and
+    /// `ATTRIBUTE_JOINT_WEIGHT`) in the simplification error, `0.0` disables it.
+    ///
+    /// This is a heuristic: the error of the joint-weighted positions is added to the geometric
+    /// error, which penalizes collapses in the blend regions around joints (elbows, shoulders)
+    /// that deform the most when animated. Regions driven by a single joint are barely affected.
+    /// Values between `0.25` and `1.0` work well, ignored in sloppy mode. Reads the `Uint16x4`
+    /// joints and `Float32x4` weights Bevy's glTF loader produces. Whatever the weight, joints and
+    /// weights are kept in step with the other attributes like any vertex attribute.
+    pub skinning_weight: f32,
+    /// How strongly `ATTRIBUTE_UV_0` and `ATTRIBUTE_UV_1` are preserved, `None` leaves UVs out of
+    /// the simplification error. Ignored in sloppy mode.
+    pub uv_weighting: [Option<UvWeighting>; 2],
+    /// Weight of `ATTRIBUTE_NORMAL` in the simplification error, `0.0` leaves normals out of it.
+    /// Keeps shading intact around creases and seams, values around `0.5` work well for unit
+    /// normals. Ignored in sloppy mode.
+    pub normal_weight: f32,
+    /// Weight of `ATTRIBUTE_COLOR` in the simplification error, `0.0` leaves vertex colors out of
+    /// it. Only `Float32x4` colors are read, see [`MeshExt::with_widened_attributes`] for
+    /// normalized ones. Ignored in sloppy mode.
+    pub color_weight: f32,
+    /// Silhouettes from a set of view directions to lock in addition to `vertex_locks`, see
+    /// [`SimplifyParams::lock_silhouettes`].
+    pub silhouette_locks: Option<SilhouetteLocks>,
+    /// Locks the vertices along hard edges (bevels, creases) in addition to `vertex_locks`.
+    pub hard_edges: Option<HardEdges>,
+    /// Locks the vertices along UV and normal seams in addition to `vertex_locks`, see
+    /// [`MeshExt::seam_vertices`] for the vertices it locks.
+    pub seam_locks: Option<SeamLocks>,
+    /// Angle in radians within which neighboring faces are treated as one planar region, `None`
+    /// disables the planarity analysis. Ignored in sloppy mode.
+    ///
+    /// Meant for CAD tessellations made of large flat faces: attributes inside of a region don't
+    /// count towards the error and collapses across attribute seams are allowed within a region,
+    /// while the attribute discontinuities on the boundaries between regions are protected. Flat
+    /// faces then collapse to a few triangles without rounding off the edges between them.
+    pub planarity_tolerance: Option<f32>,
+    /// Locks the vertices of non-manifold edges (edges shared by more than two triangles) in
+    /// addition to `vertex_locks`, so collapses can't tangle them further. Combine with
+    /// [`QualityGuard::require_manifold`] to reject results that still break the topology.
+    pub lock_non_manifold: bool,
+    /// Locks both vertices of every edge whose ends are dominated by different joints (the one with
+    /// the largest `ATTRIBUTE_JOINT_WEIGHT`) in addition to `vertex_locks`, so the seams between
+    /// bones of skinned meshes don't collapse into one another. `skinning_weight` only makes such
+    /// collapses more expensive. Meshes without `Uint16x4` joints and `Float32x4` weights are
+    /// simplified as usual.
+    pub lock_joint_seams: bool,
+    /// Merges coincident triangles with opposite winding before simplifying, keeping one triangle
+    /// of each pair. Meant for double-sided faces baked as two triangles, which otherwise get
+    /// simplified independently and z-fight. The mesh has to be rendered double-sided afterwards,
+    /// see [`SimplifyReport::double_sided_triangles`].
+    pub merge_double_sided: bool,
+    /// Drops degenerate triangles, whose vertices are repeated or collinear, before simplifying
+    /// instead of handing them to the simplifier. Meant for meshes exported from CAD tools, which
+    /// tend to be littered with zero-area slivers. The dropped triangles are gone from the result.
+    pub strip_degenerates: bool,
+    /// How the triangle count is reduced, see [`SimplifyStrategy::CardRemoval`] for foliage.
+    pub strategy: SimplifyStrategy,
+    /// Non-indexed meshes are indexed by merging identical vertices before being simplified in
+    /// place, set to expand the result back into one vertex per index instead of keeping it
+    /// indexed. Per-vertex data like `vertex_locks` refers to the merged vertices.
+    pub expand_generated_indices: bool,
+    /// Converts `u32` indices of the result to `u16` when its vertex count fits, saving index
+    /// memory on the small meshes aggressive simplification produces. Only [`MeshExt::simplified`]
+    /// drops the vertices the result no longer uses, in place the vertex count of the source
+    /// decides.
+    pub shrink_indices: bool,
+    /// What to do when the simplifier can't get within `fallback_tolerance` of
+    /// `target_index_count`, the run used ends up in [`SimplifyReport::path`].
+    pub fallback: FallbackPolicy,
+    /// Fraction by which the result may exceed `target_index_count` before `fallback` engages,
+    /// e.g. `0.1` accepts up to 10% more indices.
+    pub fallback_tolerance: f32,
+    /// Sorts the triangles before simplifying, each rotated to start at its smallest index, so the
+    /// result doesn't depend on the order the triangles were written in and comes out sorted.
+    /// [`MeshExt::canonicalize`] sorts the vertices as well.
+    ///
+    /// Simplification is deterministic either way: the same mesh and params give bit-identical
+    /// results on every run and thread count. Across platforms as long as meshoptimizer's float
+    /// math compiles the same, compilers for ARM fuse multiply-adds unless meshoptimizer is
+    /// built with `CXXFLAGS=-ffp-contract=off`.
+    pub canonical_order: bool,
+    /// Scale from mesh to world units, e.g. of the `GlobalTransform` the mesh is drawn with, see
+    /// [`SimplifyParams::in_world_space`]. With `SimplifyOptions::ErrorAbsolute`, `max_error` and
+    /// the reported `result_error` are then in world units and converted to mesh units for the
+    /// simplifier. Ignored for relative errors and unless positive.
+    pub world_scale: Option<f32>,
 }
 
-impl Default for SimplifyParams<'_> {
+impl Default for SimplifyParams {
     fn default() -> Self {
         SimplifyParams {
             max_error: 0.01,
             target_index_count: TargetIndices::default(),
+            min_target_index_count: 3,
             options: SimplifyOptions::None,
-            sloppy: false,
+            mode: SimplifyMode::Precise,
             vertex_locks: None,
+            locked_vertices: None,
+            symmetry: None,
+            skinning_weight: 0.0,
+            uv_weighting: [None; 2],
+            normal_weight: 0.0,
+            color_weight: 0.0,
+            silhouette_locks: None,
+            hard_edges: None,
+            seam_locks: None,
+            planarity_tolerance: None,
+            lock_non_manifold: false,
+            lock_joint_seams: false,
+            merge_double_sided: false,
+            strip_degenerates: false,
+            strategy: SimplifyStrategy::EdgeCollapse,
+            expand_generated_indices: false,
+            shrink_indices: false,
+            fallback: FallbackPolicy::None,
+            fallback_tolerance: 0.1,
+            canonical_order: false,
+            world_scale: None,
+        }
+    }
+}
+
+/// Clones concrete params, e.g. deserialized through their serde impl, and otherwise applies the
+/// reflected fields to [`SimplifyParams::default`], leaving the fields that aren't reflected at
+/// their default.
+impl FromReflect for SimplifyParams {
+    fn from_reflect(reflect: &dyn PartialReflect) -> Option<Self> {
+        if let Some(params) = reflect.try_downcast_ref::<SimplifyParams>() {
+            return Some(params.clone());
+        }
+        let mut params = SimplifyParams::default();
+        params.try_apply(reflect).ok()?;
+        Some(params)
+    }
+}
+
+impl SimplifyParams {
+    /// Locks the vertices of edges that are on the silhouette of the mesh when viewed along any of
+    /// `directions` (in mesh space), within `angle_tolerance` radians. Useful for props only ever
+    /// seen from a few angles, the rest of the mesh can then be simplified much more aggressively
+    /// without changing its outline.
+    pub fn lock_silhouettes(self, directions: &[Vec3], angle_tolerance: f32) -> Self {
+        SimplifyParams {
+            silhouette_locks: Some(SilhouetteLocks {
+                directions: directions.to_vec(),
+                angle_tolerance,
+            }),
+            ..self
         }
     }
+
+    /// Measures `max_error` in world units for a mesh drawn with `transform`: sets
+    /// `SimplifyOptions::ErrorAbsolute` and `world_scale` to the largest scale of the transform,
+    /// so non-uniformly scaled meshes stay within the error along every axis.
+    pub fn in_world_space(mut self, transform: &GlobalTransform) -> Self {
+        self.options |= SimplifyOptions::ErrorAbsolute;
+        self.world_scale = Some(transform.scale().abs().max_element());
+        self
+    }
+
+    /// Factor turning the errors of these params, like `max_error`, into the units the simplifier
+    /// works in: `1 / world_scale` for absolute errors with a world scale, `1.0` otherwise.
+    pub(crate) fn error_units(&self) -> f32 {
+        match self.world_scale {
+            Some(scale) if scale > 0.0 && self.options.contains(SimplifyOptions::ErrorAbsolute) => {
+                1.0 / scale
+            }
+            _ => 1.0,
+        }
+    }
+}
+
+#[cfg(feature = "serialize")]
+mod simplify_options_names {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer, de::Error};
+
+    use crate::SimplifyOptions;
+
+    pub(super) fn serialize<S: Serializer>(
+        options: &SimplifyOptions,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let names: Vec<&str> = options.iter_names().map(|(name, _)| name).collect();
+        names.serialize(serializer)
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<SimplifyOptions, D::Error> {
+        let names = Vec::<String>::deserialize(deserializer)?;
+        names
+            .iter()
+            .try_fold(SimplifyOptions::empty(), |options, name| {
+                SimplifyOptions::from_name(name)
+                    .map(|option| options | option)
+                    .ok_or_else(|| D::Error::custom(format!("unknown simplify option `{name}`")))
+            })
+    }
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -75,8 +853,89 @@ pub enum OptError {
     MissingIndices,
     UnsupportedIndexFormat,
     MissingPositions,
+    /// Positions that aren't `Float32x3`, or `Float32x2` for 2D meshes on the `z = 0` plane.
+    UnsupportedPositionFormat(VertexFormat),
+    /// Attribute without one value per position, which Bevy would truncate the other attributes to
+    /// and remapping the vertices would misalign. `expected` is the number of positions.
+    MismatchedAttributeLength {
+        attribute: &'static str,
+        expected: usize,
+        actual: usize,
+    },
     UnsupportedPrimitiveTopology(PrimitiveTopology),
     InvalidIndexCount(usize),
+    /// Index referencing a vertex past the end of the vertex buffers.
+    IndexOutOfBounds(u32),
+    InvalidVertexLockCount(usize),
+    AsymmetricMesh(usize),
+    /// Meshlet limits outside of what meshoptimizer supports, see [`MeshletParams`].
+    InvalidMeshletParams,
+    /// Level of a LOD schedule that doesn't decrease the triangle count or has an invalid error.
+    InvalidLodSchedule(usize),
+    /// Mesh asset that isn't loaded.
+    MissingMesh,
+    /// Every attempt of [`MeshExt::simplify_guarded`] was rejected, holds the measurements of the
+    /// last one.
+    QualityGuardFailed(GuardMeasurement),
+    /// Per-vertex data that doesn't have one value per vertex.
+    InvalidVertexCount(usize),
+    /// Level of an LOD chain whose vertex attributes differ from LOD0.
+    MismatchedLodAttributes(usize),
+    /// Attribute needed to regenerate a stripped attribute, see [`AttributeSet`].
+    MissingRegenerationInput(&'static str),
+    /// mikktspace couldn't generate tangents for the processed mesh.
+    TangentGenerationFailed,
+    /// LOD chain that doesn't fit its [`LodMemoryBudget`] even at the minimum triangle counts,
+    /// holds the bytes it would take.
+    MemoryBudgetExceeded(usize),
+    /// Processing settings that can't be right for any mesh, names the offending field, see
+    /// `MeshProcessSettings::validate`.
+    InvalidProcessSettings(&'static str),
+    /// Attribute of an interleaved layout that the mesh doesn't have or that is listed twice.
+    InvalidVertexLayout(MeshVertexAttributeId),
+    /// Simulated vertex cache smaller than the 3 entries meshoptimizer needs.
+    InvalidCacheSize(u32),
+    /// `SimplifyOptions` the sloppy and point simplifiers don't support, see
+    /// [`SimplifyMode::Sloppy`].
+    UnsupportedSimplifyOptions(SimplifyOptions),
+    /// Strip restart index other than `u16::MAX` or `u32::MAX`, or `u16::MAX` on a mesh with that
+    /// many vertices, see [`MeshExt::to_triangle_strip`].
+    InvalidRestartIndex(u32),
+    /// Vertex whose position is NaN or infinite.
+    InvalidPositions(usize),
+    /// Attribute whose values the requested quantization can't represent, see
+    /// [`QuantizeConfig::skip_out_of_range`].
+    QuantizationOutOfRange(&'static str),
+    /// Quantization the renderer can't read, see [`QuantizeConfig::custom_decoding`].
+    UnsupportedQuantization(&'static str),
+    /// Attribute of [`VertexLocks::Attribute`] that the mesh doesn't have, that doesn't have one
+    /// value per vertex or that isn't `Float32`, `Uint32` or `Sint32`.
+    InvalidLockAttribute(&'static str),
+    /// Attribute whose format Bevy's `MeshletMesh` can't take even after widening it, see
+    /// `MeshExt::to_meshlet_mesh`.
+    InvalidMeshletAttribute {
+        attribute: &'static str,
+        format: VertexFormat,
+    },
+    /// Tolerance of [`MeshExt::weld_vertices`] that isn't positive and finite.
+    InvalidWeldTolerance(f32),
+    /// Simplifier the operation can't run, see [`SimplifyMode::Points`].
+    UnsupportedSimplifyMode(SimplifyMode),
+    /// Mesh with morph targets whose vertices the operation would merge, drop or reorder, leaving
+    /// the morph target image out of step with them. Simplify in place and remap the image with
+    /// [`MeshExt::optimize_vertex_fetch_remap`] and [`RemapTable::apply_morph_targets`] instead.
+    MorphTargetsUnsupported,
+    /// Morph target image that isn't a 3D `R32Float` image holding the deltas of every source
+    /// vertex, see [`RemapTable::apply_morph_targets`].
+    InvalidMorphTargetImage,
+    /// Attribute whose format the export format can't store, see `meshes_to_glb`.
+    UnsupportedExportFormat {
+        attribute: &'static str,
+        format: VertexFormat,
+    },
+    /// Request replaced by a newer one or whose entity was despawned before it finished, see
+    /// [`MeshSimplifyPlugin`].
+    Cancelled,
 }
 
 impl Display for OptError {
@@ -85,12 +944,148 @@ impl Display for OptError {
             OptError::MissingIndices => write!(f, "Missing indices"),
             OptError::UnsupportedIndexFormat => write!(f, "Unsupported index format"),
             OptError::MissingPositions => write!(f, "Missing positions"),
+            OptError::UnsupportedPositionFormat(format) => write!(
+                f,
+                "Unsupported position format: {:?}, expected Float32x3 or Float32x2",
+                format
+            ),
+            OptError::MismatchedAttributeLength {
+                attribute,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "Mismatched attribute length: {} has {} values, expected one per vertex, {}",
+                attribute, actual, expected
+            ),
             OptError::UnsupportedPrimitiveTopology(topology) => write!(
                 f,
-                "Unsupported topology: {:?}, bevy_meshopt currently only works with `TriangleList` topology,",
+                "Unsupported topology: {:?}, bevy_meshopt works with `TriangleList` meshes and simplifies `PointList` meshes",
                 topology
             ),
             OptError::InvalidIndexCount(count) => write!(f, "Invalid index count: {}", count),
+            OptError::IndexOutOfBounds(index) => {
+                write!(f, "Index out of bounds: {}", index)
+            }
+            OptError::InvalidVertexLockCount(count) => write!(
+                f,
+                "Invalid vertex lock count: {}, expected one lock per vertex",
+                count
+            ),
+            OptError::AsymmetricMesh(count) => write!(
+                f,
+                "Mesh is not symmetric about the symmetry plane: {} vertices or triangles without a mirrored counterpart",
+                count
+            ),
+            OptError::InvalidMeshletParams => write!(
+                f,
+                "Invalid meshlet params: expected 3..=256 vertices, a multiple of 4 up to 512 triangles and a cone weight in 0..=1"
+            ),
+            OptError::InvalidLodSchedule(level) => write!(
+                f,
+                "Invalid LOD schedule: level {} has to target fewer triangles than the previous level",
+                level
+            ),
+            OptError::MissingMesh => write!(f, "Missing mesh asset"),
+            OptError::QualityGuardFailed(measurement) => write!(
+                f,
+                "Quality guard failed: normal deviation {:?} rad, geometric deviation {:?}, {:?} boundary and {:?} non-manifold edges",
+                measurement.normal_deviation,
+                measurement.geometric_deviation,
+                measurement.boundary_edges,
+                measurement.non_manifold_edges
+            ),
+            OptError::InvalidVertexCount(count) => write!(
+                f,
+                "Invalid vertex count: {}, expected one value per vertex",
+                count
+            ),
+            OptError::MismatchedLodAttributes(level) => write!(
+                f,
+                "Mismatched LOD attributes: level {} doesn't have the same vertex attributes as LOD0",
+                level
+            ),
+            OptError::MissingRegenerationInput(attribute) => write!(
+                f,
+                "Missing regeneration input: stripped attributes can't be regenerated without {}",
+                attribute
+            ),
+            OptError::TangentGenerationFailed => write!(f, "Tangent generation failed"),
+            OptError::MemoryBudgetExceeded(bytes) => write!(
+                f,
+                "Memory budget exceeded: the LOD chain takes at least {} bytes",
+                bytes
+            ),
+            OptError::InvalidProcessSettings(message) => {
+                write!(f, "Invalid process settings: {}", message)
+            }
+            OptError::InvalidVertexLayout(id) => write!(
+                f,
+                "Invalid vertex layout: attribute {:?} is missing from the mesh or listed twice",
+                id
+            ),
+            OptError::InvalidCacheSize(size) => write!(
+                f,
+                "Invalid cache size: {}, the vertex cache needs at least 3 entries",
+                size
+            ),
+            OptError::UnsupportedSimplifyOptions(options) => write!(
+                f,
+                "Unsupported simplify options: {:?} can't be used with the sloppy or point simplifier",
+                options
+            ),
+            OptError::InvalidRestartIndex(index) => write!(
+                f,
+                "Invalid restart index: {}, strips restart at u16::MAX or u32::MAX below that many vertices",
+                index
+            ),
+            OptError::InvalidPositions(vertex) => write!(
+                f,
+                "Invalid positions: vertex {} isn't at a finite position",
+                vertex
+            ),
+            OptError::QuantizationOutOfRange(attribute) => write!(
+                f,
+                "Quantization out of range: {} has values the format can't represent",
+                attribute
+            ),
+            OptError::UnsupportedQuantization(message) => {
+                write!(f, "Unsupported quantization: {}", message)
+            }
+            OptError::InvalidLockAttribute(attribute) => write!(
+                f,
+                "Invalid lock attribute: {} is missing or isn't one Float32, Uint32 or Sint32 value per vertex",
+                attribute
+            ),
+            OptError::InvalidMeshletAttribute { attribute, format } => write!(
+                f,
+                "Invalid meshlet attribute: {} is {:?}, a MeshletMesh can't be built from it",
+                attribute, format
+            ),
+            OptError::InvalidWeldTolerance(tolerance) => write!(
+                f,
+                "Invalid weld tolerance: {}, expected a positive finite distance",
+                tolerance
+            ),
+            OptError::UnsupportedSimplifyMode(mode) => write!(
+                f,
+                "Unsupported simplify mode: {:?} can't be used here, it doesn't produce triangles",
+                mode
+            ),
+            OptError::MorphTargetsUnsupported => write!(
+                f,
+                "Morph targets unsupported: remapping the vertices would invalidate the morph target image"
+            ),
+            OptError::InvalidMorphTargetImage => write!(
+                f,
+                "Invalid morph target image: expected the R32Float deltas of every source vertex"
+            ),
+            OptError::UnsupportedExportFormat { attribute, format } => write!(
+                f,
+                "Unsupported export format: {} is {:?}, which glTF can't store",
+                attribute, format
+            ),
+            OptError::Cancelled => write!(f, "Cancelled"),
         }
     }
 }
@@ -100,17 +1095,215 @@ impl Error for OptError {}
 fn assert_u32_indices(indices: Option<&mut Indices>) {
     let new_indices = match indices {
         Some(Indices::U16(u16_indices)) => Some(Indices::U32(
-            u16_indices.into_iter().map(|i| *i as u32).collect(),
+            u16_indices.iter().map(|i| *i as u32).collect(),
         )),
         Some(_) => None,
         None => None,
     };
 
-    if let Some(new_indices) = new_indices {
-        if let Some(indices) = indices {
-            *indices = new_indices;
+    if let Some(new_indices) = new_indices
+        && let Some(indices) = indices
+    {
+        *indices = new_indices;
+    }
+}
+
+/// Index buffer a mesh had before [`with_u32_indices`].
+enum SourceIndices {
+    U16,
+    U32,
+    Generated,
+}
+
+/// Runs `f` with `u32` indices, whatever the mesh came with. `u16` indices are widened and
+/// narrowed back afterwards if the vertex count still allows it. Non-indexed meshes are indexed by
+/// merging identical vertices and expanded back if `expand_generated` is set or `f` fails.
+/// `Float32x2` positions are widened for `f` as well, see [`with_planar_positions`].
+fn with_u32_indices<R>(
+    mesh: &mut Mesh,
+    expand_generated: bool,
+    f: impl FnOnce(&mut Mesh) -> Result<R, OptError>,
+) -> Result<R, OptError> {
+    with_planar_positions(mesh, |mesh| {
+        // Other topologies can't be indexed as triangles.
+        mesh_positions(mesh)?;
+        let source = match mesh.indices() {
+            Some(Indices::U16(_)) => SourceIndices::U16,
+            Some(Indices::U32(_)) => SourceIndices::U32,
+            None => SourceIndices::Generated,
+        };
+        index_u32(mesh)?;
+
+        let result = f(mesh);
+
+        match source {
+            SourceIndices::U16 => narrow_indices(mesh),
+            SourceIndices::Generated if expand_generated || result.is_err() => {
+                vertex::expand_indices(mesh);
+            }
+            _ => {}
         }
+        result
+    })
+}
+
+/// Position attribute of meshes with `Float32x2` positions, like 2D meshes that only store the
+/// coordinates they need.
+fn planar_position_attribute(mesh: &Mesh) -> Option<MeshVertexAttribute> {
+    mesh.attributes()
+        .find_map(|(attribute, values)| match values {
+            VertexAttributeValues::Float32x2(_) if attribute.id == Mesh::ATTRIBUTE_POSITION.id => {
+                Some(*attribute)
+            }
+            _ => None,
+        })
+}
+
+/// Replaces `Float32x2` positions with `Float32x3` ones on the `z = 0` plane, which meshoptimizer
+/// reads, returning the attribute to narrow them back to with [`narrow_planar_positions`].
+fn widen_planar_positions(mesh: &mut Mesh) -> Option<MeshVertexAttribute> {
+    let attribute = planar_position_attribute(mesh)?;
+    if let Some(VertexAttributeValues::Float32x2(positions)) =
+        mesh.remove_attribute(Mesh::ATTRIBUTE_POSITION)
+    {
+        let positions: Vec<[f32; 3]> = positions.into_iter().map(|[x, y]| [x, y, 0.0]).collect();
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    }
+    Some(attribute)
+}
+
+/// Drops the `z` of positions [`widen_planar_positions`] widened.
+fn narrow_planar_positions(mesh: &mut Mesh, attribute: MeshVertexAttribute) {
+    if let Some(VertexAttributeValues::Float32x3(positions)) =
+        mesh.remove_attribute(Mesh::ATTRIBUTE_POSITION)
+    {
+        let positions: Vec<[f32; 2]> = positions.into_iter().map(|[x, y, _]| [x, y]).collect();
+        mesh.insert_attribute(attribute, positions);
+    }
+}
+
+/// Runs `f` with `Float32x2` positions widened to the `z = 0` plane, narrowing them back
+/// afterwards whether `f` succeeds or not. Simplifying and optimizing only moves whole vertices
+/// around, so the narrowed positions are the ones the mesh had.
+fn with_planar_positions<R>(mesh: &mut Mesh, f: impl FnOnce(&mut Mesh) -> R) -> R {
+    let planar = widen_planar_positions(mesh);
+    let result = f(mesh);
+    if let Some(attribute) = planar {
+        narrow_planar_positions(mesh, attribute);
+    }
+    result
+}
+
+/// `mesh`, or a copy with `Float32x2` positions widened to the `z = 0` plane, for functions that
+/// only read the mesh and keep its vertex and index order.
+fn widened_planar(mesh: &Mesh) -> Cow<'_, Mesh> {
+    match planar_position_attribute(mesh) {
+        Some(_) => {
+            let mut widened = mesh.clone();
+            widen_planar_positions(&mut widened);
+            Cow::Owned(widened)
+        }
+        None => Cow::Borrowed(mesh),
+    }
+}
+
+/// [`with_u32_indices`] for simplifying with `params`, which also decide whether to narrow the
+/// indices of the result with [`SimplifyParams::shrink_indices`].
+fn with_simplified_indices<R>(
+    mesh: &mut Mesh,
+    params: &SimplifyParams,
+    f: impl FnOnce(&mut Mesh) -> Result<R, OptError>,
+) -> Result<R, OptError> {
+    let result = with_u32_indices(mesh, params.expand_generated_indices, f)?;
+    if params.shrink_indices {
+        narrow_indices(mesh);
+    }
+    Ok(result)
+}
+
+/// `mesh` if it has `u32` indices and `Float32x3` positions, otherwise a copy given them the way
+/// [`with_u32_indices`] does, for functions that only read the mesh.
+pub(crate) fn u32_indexed(mesh: &Mesh) -> Result<Cow<'_, Mesh>, OptError> {
+    let planar = planar_position_attribute(mesh).is_some();
+    if let (false, Some(Indices::U32(_))) = (planar, mesh.indices()) {
+        return Ok(Cow::Borrowed(mesh));
+    }
+    let mut indexed = mesh.clone();
+    widen_planar_positions(&mut indexed);
+    mesh_positions(&indexed)?;
+    index_u32(&mut indexed)?;
+    Ok(Cow::Owned(indexed))
+}
+
+/// Widens `u16` indices and indexes non-indexed meshes by merging identical vertices.
+fn index_u32(mesh: &mut Mesh) -> Result<(), OptError> {
+    match mesh.indices() {
+        Some(Indices::U16(_)) => mesh.assert_indices_u32(),
+        Some(Indices::U32(_)) => {}
+        None => {
+            refuse_morph_targets(mesh)?;
+            let mut indices: Vec<u32> = (0..mesh.count_vertices() as u32).collect();
+            validate_indices(&indices, mesh.count_vertices())?;
+            vertex::deduplicate_vertices(mesh, &mut indices);
+            mesh.insert_indices(Indices::U32(indices));
+        }
+    }
+    Ok(())
+}
+
+/// Fails with [`OptError::MorphTargetsUnsupported`] if the mesh has morph targets, for operations
+/// about to remap its vertices.
+fn refuse_morph_targets(mesh: &Mesh) -> Result<(), OptError> {
+    match mesh.morph_targets() {
+        Some(_) => Err(OptError::MorphTargetsUnsupported),
+        None => Ok(()),
+    }
+}
+
+/// Sets the indices of `mesh` to `indices` and drops the vertices they don't use, keeping the
+/// order of the others.
+fn keep_used_vertices(mesh: &mut Mesh, mut indices: Vec<u32>) {
+    let mut remap = vec![u32::MAX; mesh.count_vertices()];
+    for &index in &indices {
+        remap[index as usize] = 0;
+    }
+    let mut used = 0;
+    for new_index in remap.iter_mut().filter(|new_index| **new_index == 0) {
+        *new_index = used;
+        used += 1;
+    }
+    for index in &mut indices {
+        *index = remap[*index as usize];
+    }
+    vertex::remap_vertices(mesh, &remap, used as usize);
+    mesh.insert_indices(Indices::U32(indices));
+}
+
+/// Narrows `u32` indices back to `u16` if the vertex count allows it.
+fn narrow_indices(mesh: &mut Mesh) {
+    if mesh.count_vertices() <= u16::MAX as usize + 1
+        && let Some(Indices::U32(indices)) = mesh.indices()
+    {
+        let indices = indices.iter().map(|&index| index as u16).collect();
+        mesh.insert_indices(Indices::U16(indices));
+    }
+}
+
+/// Checks that the indices form whole triangles and only reference existing vertices, the
+/// meshoptimizer functions abort on anything else.
+fn validate_indices(indices: &[u32], vertex_count: usize) -> Result<(), OptError> {
+    if !indices.len().is_multiple_of(3) || indices.is_empty() {
+        return Err(OptError::InvalidIndexCount(indices.len()));
+    }
+
+    if let Some(&index) = indices
+        .iter()
+        .find(|&&index| index as usize >= vertex_count)
+    {
+        return Err(OptError::IndexOutOfBounds(index));
     }
+
+    Ok(())
 }
 
 fn mesh_indices(mesh: &Mesh) -> Result<&Vec<u32>, OptError> {
@@ -120,25 +1313,35 @@ fn mesh_indices(mesh: &Mesh) -> Result<&Vec<u32>, OptError> {
         None => return Err(OptError::MissingIndices),
     };
 
-    if indices.len() % 3 != 0 || indices.len() == 0 {
-        return Err(OptError::InvalidIndexCount(indices.len()));
-    }
+    validate_indices(indices, mesh.count_vertices())?;
+    Ok(indices)
+}
 
-    return Ok(indices);
+/// Indices of a mesh with `u16` or `u32` indices, widened to `u32` for meshoptimizer functions
+/// that only read them.
+fn mesh_indices_widened(mesh: &Mesh) -> Result<Cow<'_, [u32]>, OptError> {
+    let indices = match mesh.indices() {
+        Some(Indices::U32(indices)) => Cow::Borrowed(indices.as_slice()),
+        Some(Indices::U16(indices)) => {
+            Cow::Owned(indices.iter().map(|&index| index as u32).collect())
+        }
+        None => return Err(OptError::MissingIndices),
+    };
+
+    validate_indices(&indices, mesh.count_vertices())?;
+    Ok(indices)
 }
 
 fn mesh_indices_mut(mesh: &mut Mesh) -> Result<&mut Vec<u32>, OptError> {
+    let vertex_count = mesh.count_vertices();
     let indices = match mesh.indices_mut() {
         Some(Indices::U32(indices)) => indices,
         Some(_) => return Err(OptError::UnsupportedIndexFormat),
         None => return Err(OptError::MissingIndices),
     };
 
-    if indices.len() % 3 != 0 || indices.len() == 0 {
-        return Err(OptError::InvalidIndexCount(indices.len()));
-    }
-
-    return Ok(indices);
+    validate_indices(indices, vertex_count)?;
+    Ok(indices)
 }
 
 fn take_mesh_indices_mut(mesh: &mut Mesh) -> Result<Vec<u32>, OptError> {
@@ -151,11 +1354,12 @@ fn take_mesh_indices_mut(mesh: &mut Mesh) -> Result<Vec<u32>, OptError> {
         None => return Err(OptError::MissingIndices),
     };
 
-    if indices.len() % 3 != 0 || indices.len() == 0 {
-        return Err(OptError::InvalidIndexCount(indices.len()));
+    if let Err(err) = validate_indices(&indices, mesh.count_vertices()) {
+        mesh.insert_indices(Indices::U32(indices));
+        return Err(err);
     }
 
-    return Ok(indices);
+    Ok(indices)
 }
 
 fn mesh_positions(mesh: &Mesh) -> Result<&Vec<[f32; 3]>, OptError> {
@@ -165,106 +1369,416 @@ fn mesh_positions(mesh: &Mesh) -> Result<&Vec<[f32; 3]>, OptError> {
         ));
     };
 
-    // TODO: support other vertex formats
-    let Some(VertexAttributeValues::Float32x3(positions)) =
-        mesh.attribute(Mesh::ATTRIBUTE_POSITION)
-    else {
-        return Err(OptError::MissingPositions);
-    };
+    float32x3_positions(mesh)
+}
 
+/// Positions of a mesh of any topology, `Float32x2` positions have to be widened first. Checks the
+/// other attributes with [`check_attribute_lengths`] as well.
+fn float32x3_positions(mesh: &Mesh) -> Result<&Vec<[f32; 3]>, OptError> {
+    let positions = match mesh.attribute(Mesh::ATTRIBUTE_POSITION) {
+        Some(VertexAttributeValues::Float32x3(positions)) => positions,
+        Some(values) => {
+            return Err(OptError::UnsupportedPositionFormat(VertexFormat::from(
+                values,
+            )));
+        }
+        None => return Err(OptError::MissingPositions),
+    };
+    check_attribute_lengths(mesh)?;
     Ok(positions)
 }
 
+/// Fails if any attribute, custom ones included, doesn't have as many values as there are
+/// positions, so the vertices can be remapped in every attribute.
+fn check_attribute_lengths(mesh: &Mesh) -> Result<(), OptError> {
+    let Some(vertex_count) = mesh
+        .attribute(Mesh::ATTRIBUTE_POSITION)
+        .map(VertexAttributeValues::len)
+    else {
+        return Ok(());
+    };
+    match mesh
+        .attributes()
+        .find(|(_, values)| values.len() != vertex_count)
+    {
+        Some((attribute, values)) => Err(OptError::MismatchedAttributeLength {
+            attribute: attribute.name,
+            expected: vertex_count,
+            actual: values.len(),
+        }),
+        None => Ok(()),
+    }
+}
+
 impl MeshExt for Mesh {
     fn assert_indices_u32(&mut self) {
         assert_u32_indices(self.indices_mut());
     }
 
     fn simplify(&mut self, params: &SimplifyParams) -> Result<f32, OptError> {
-        let (new_indices, error) = self.simplify_new_indices(params)?;
-        if new_indices.len() >= 3 {
-            self.insert_indices(Indices::U32(new_indices));
+        if self.primitive_topology() == PrimitiveTopology::PointList {
+            return points::simplify_point_list(self, params).map(|report| report.result_error);
+        }
+        with_simplified_indices(self, params, |mesh| {
+            simplify::simplify_with_report(mesh, params).map(|report| report.result_error)
+        })
+    }
+
+    fn simplify_with_report(
+        &mut self,
+        params: &SimplifyParams,
+    ) -> Result<SimplifyReport, OptError> {
+        if self.primitive_topology() == PrimitiveTopology::PointList {
+            return points::simplify_point_list(self, params);
         }
-        Ok(error)
+        with_simplified_indices(self, params, |mesh| {
+            simplify::simplify_with_report(mesh, params)
+        })
+    }
+
+    fn simplified(&self, params: &SimplifyParams) -> Result<Mesh, OptError> {
+        simplify::simplified(self, params)
     }
 
     fn simplify_new_indices(&self, params: &SimplifyParams) -> Result<(Vec<u32>, f32), OptError> {
-        let indices = mesh_indices(self)?;
-        let positions = mesh_positions(self)?;
-
-        let target_index_count = params.target_index_count.count(indices.len());
-
-        let mut result_error = 0.0;
-        let new_indices = if params.sloppy {
-            if let Some(locks) = params.vertex_locks {
-                meshopt::simplify_sloppy_with_locks_decoder(
-                    indices,
-                    &positions,
-                    locks,
-                    target_index_count,
-                    params.max_error,
-                    Some(&mut result_error),
-                )
-            } else {
-                meshopt::simplify_sloppy_decoder(
-                    indices,
-                    positions.as_slice(),
-                    target_index_count,
-                    params.max_error,
-                    Some(&mut result_error),
-                )
-            }
-        } else {
-            if let Some(locks) = params.vertex_locks {
-                meshopt::simplify_with_locks_decoder(
-                    indices,
-                    positions.as_slice(),
-                    locks,
-                    target_index_count,
-                    params.max_error,
-                    params.options,
-                    Some(&mut result_error),
-                )
-            } else {
-                meshopt::simplify_decoder(
-                    indices,
-                    positions.as_slice(),
-                    target_index_count,
-                    params.max_error,
-                    params.options,
-                    Some(&mut result_error),
+        simplify::simplify_mesh_indices(&widened_planar(self), params)
+            .map(|(indices, error, _)| (indices, error))
+    }
+
+    fn triangle_provenance(
+        &self,
+        simplified_indices: &[u32],
+    ) -> Result<Vec<TriangleProvenance>, OptError> {
+        provenance::triangle_provenance(self, simplified_indices)
+    }
+
+    fn simplify_dry_run(&self, params: &SimplifyParams) -> Result<SimplifyReport, OptError> {
+        let mesh = &*u32_indexed(self)?;
+        simplify::with_scratch(|scratch| {
+            let result_error = simplify::simplify_mesh_into(mesh, params, scratch)?;
+            let used_vertices = simplify::count_used_vertices(
+                &scratch.indices,
+                mesh.count_vertices(),
+                &mut scratch.seen,
+            );
+            Ok(SimplifyReport {
+                path: scratch.path,
+                ..SimplifyReport::new(
+                    mesh,
+                    params,
+                    scratch.indices.len(),
+                    used_vertices,
+                    result_error,
                 )
-            }
-        };
+            })
+        })
+    }
+
+    fn simplify_until(
+        &mut self,
+        step: StepParams,
+        predicate: impl FnMut(&SimplifyReport, &Mesh) -> ControlFlow<()>,
+    ) -> Result<SimplifyReport, OptError> {
+        with_simplified_indices(self, &step.simplify, |mesh| {
+            simplify::simplify_until(mesh, &step, predicate)
+        })
+    }
+
+    fn simplify_guarded(
+        &mut self,
+        params: &SimplifyParams,
+        guard: &QualityGuard,
+    ) -> Result<GuardedSimplifyReport, OptError> {
+        guard::simplify_guarded(self, params, guard)
+    }
+
+    fn simplify_points_in_place(&mut self, params: &PointSimplifyParams) -> Result<(), OptError> {
+        points::simplify_points(self, params)
+    }
+
+    fn analyze(&self, cache_size: Option<u32>) -> Result<MeshStats, OptError> {
+        stats::analyze(self, cache_size)
+    }
+
+    fn simplify_scale(&self) -> Result<f32, OptError> {
+        Ok(meshopt::simplify_scale_decoder(mesh_positions(self)?))
+    }
+
+    fn encode_compressed(&self) -> Result<CompressedMesh, OptError> {
+        compress::encode_compressed(self)
+    }
 
-        Ok((new_indices, result_error))
+    fn optimize(&mut self, settings: &OptimizeSettings) -> Result<OptimizeReport, OptError> {
+        if settings.vertex_fetch {
+            refuse_morph_targets(self)?;
+        }
+        with_u32_indices(self, false, |mesh| optimize::optimize(mesh, settings))
     }
 
     fn optimize_vertex_fetch(&mut self) -> Result<(), OptError> {
-        let mut indices_mut = take_mesh_indices_mut(self)?;
-        let Some(VertexAttributeValues::Float32x3(positions)) =
-            self.attribute_mut(Mesh::ATTRIBUTE_POSITION)
-        else {
-            return Err(OptError::MissingPositions);
-        };
+        refuse_morph_targets(self)?;
+        with_u32_indices(self, false, |mesh| {
+            optimize::optimize_vertex_fetch(mesh)?;
+            Ok(())
+        })
+    }
+
+    fn to_interleaved(
+        &self,
+        layout: Option<&[MeshVertexAttributeId]>,
+    ) -> Result<InterleavedVertexBuffer, OptError> {
+        interleave::to_interleaved(self, layout)
+    }
+
+    fn optimize_vertex_fetch_remap(&mut self) -> Result<RemapTable, OptError> {
+        let source_hash = remap::mesh_content_hash(self);
+        let source_vertex_count = self.count_vertices();
+        let (remap, destination_vertex_count) = optimize::optimize_vertex_fetch(self)?;
+        Ok(RemapTable {
+            remap,
+            source_vertex_count,
+            destination_vertex_count,
+            source_hash,
+        })
+    }
+
+    fn weld_identical_vertices(&mut self) -> Result<WeldReport, OptError> {
+        refuse_morph_targets(self)?;
+        check_attribute_lengths(self)?;
+        vertex::weld_identical_vertices(self)
+    }
 
-        meshopt::optimize_vertex_fetch_in_place(&mut indices_mut, positions);
-        self.insert_indices(Indices::U32(indices_mut));
-        Ok(())
+    fn weld_vertices(&mut self, tolerance: Option<f32>) -> Result<WeldReport, OptError> {
+        refuse_morph_targets(self)?;
+        check_attribute_lengths(self)?;
+        vertex::weld_vertices(self, tolerance)
+    }
+
+    fn canonicalize(&mut self) -> Result<(), OptError> {
+        refuse_morph_targets(self)?;
+        check_attribute_lengths(self)?;
+        with_u32_indices(self, true, vertex::canonicalize)
+    }
+
+    fn with_stripped_attributes<R>(
+        &mut self,
+        attributes: AttributeSet,
+        f: impl FnOnce(&mut Mesh) -> Result<R, OptError>,
+    ) -> Result<(R, StripReport), OptError> {
+        regenerate::with_stripped_attributes(self, attributes, f)
+    }
+
+    fn with_widened_attributes<R>(
+        &mut self,
+        keep_widened: bool,
+        f: impl FnOnce(&mut Mesh) -> Result<R, OptError>,
+    ) -> Result<(R, FormatReport), OptError> {
+        formats::with_widened_attributes(self, keep_widened, f)
     }
 
     fn optimize_overdraw(&mut self, threshold: f32) -> Result<(), OptError> {
-        let mut indices_mut = take_mesh_indices_mut(self)?;
-        let positions = mesh_positions(self)?;
-        meshopt::optimize_overdraw_in_place_decoder(&mut indices_mut, positions, threshold);
-        self.insert_indices(Indices::U32(indices_mut));
-        Ok(())
+        with_u32_indices(self, false, |mesh| {
+            optimize::optimize_overdraw(mesh, threshold)
+        })
     }
 
     fn optimize_vertex_cache(&mut self) -> Result<(), OptError> {
-        let positions_len = mesh_positions(self)?.len();
-        let mut indices_mut = mesh_indices_mut(self)?;
-        meshopt::optimize_vertex_cache_in_place(&mut indices_mut, positions_len);
-        Ok(())
+        with_u32_indices(self, false, |mesh| {
+            optimize::optimize_vertex_cache(mesh, &CacheModel::Lru)
+        })
+    }
+
+    fn sort_triangles_along_axis_in_place(
+        &mut self,
+        axis: Vec3,
+        descending: bool,
+    ) -> Result<(), OptError> {
+        optimize::sort_triangles_along_axis(self, axis, descending)
+    }
+
+    fn spatial_sort_in_place(&mut self) -> Result<(), OptError> {
+        optimize::spatial_sort_triangles(self)
+    }
+
+    fn shadow_indices(&self) -> Result<Indices, OptError> {
+        optimize::shadow_indices(self)
+    }
+
+    fn shadow_mesh(&self) -> Result<Mesh, OptError> {
+        optimize::shadow_mesh(self)
+    }
+
+    fn to_triangle_strip(&self, restart_index: Option<u32>) -> Result<Mesh, OptError> {
+        strip::to_triangle_strip(self, restart_index)
+    }
+
+    fn to_triangle_list(&self) -> Result<Mesh, OptError> {
+        strip::to_triangle_list(self)
+    }
+
+    fn quantize_attributes(&mut self, config: &QuantizeConfig) -> Result<QuantizeReport, OptError> {
+        quantize::quantize_attributes(self, config)
+    }
+
+    fn generate_lod_chain(&self, params: &LodChainParams) -> Result<LodChain, OptError> {
+        let mut chain = lod::generate_lod_chain(&*u32_indexed(self)?, params)?;
+        if let Some(Indices::U16(_)) = self.indices() {
+            chain.levels.iter_mut().for_each(narrow_indices);
+        }
+        if let Some(attribute) = planar_position_attribute(self) {
+            for level in &mut chain.levels {
+                narrow_planar_positions(level, attribute);
+            }
+        }
+        Ok(chain)
+    }
+
+    fn border_edges(&self, selection: BorderSelection) -> Result<Vec<[u32; 2]>, OptError> {
+        border::border_edges(self, selection)
+    }
+
+    fn seam_vertices(&self, seams: &SeamLocks) -> Result<Vec<bool>, OptError> {
+        seam::seam_vertices(self, seams)
+    }
+
+    fn generate_skirt(
+        &mut self,
+        direction: Vec3,
+        depth: f32,
+        border: BorderSelection,
+    ) -> Result<usize, OptError> {
+        refuse_morph_targets(self)?;
+        border::generate_skirt(self, direction, depth, border)
+    }
+
+    fn merge_double_sided(&mut self) -> Result<usize, OptError> {
+        refuse_morph_targets(self)?;
+        with_u32_indices(self, false, |mesh| {
+            let mut merged = Vec::new();
+            let removed = double_sided::merge_double_sided(
+                mesh_indices(mesh)?,
+                mesh_positions(mesh)?,
+                &mut merged,
+            );
+            if removed > 0 {
+                keep_used_vertices(mesh, merged);
+            }
+            Ok(removed)
+        })
+    }
+
+    fn build_adjacency(&self) -> Result<TriangleAdjacency, OptError> {
+        let mesh = &*u32_indexed(self)?;
+        Ok(TriangleAdjacency::new(
+            mesh_indices(mesh)?,
+            mesh_positions(mesh)?,
+        ))
+    }
+
+    fn manifold_status(&self) -> Result<ManifoldStatus, OptError> {
+        Ok(ManifoldStatus::new(
+            mesh_indices(self)?,
+            mesh_positions(self)?,
+        ))
+    }
+
+    fn split_by_connectivity(&self) -> Result<Vec<Mesh>, OptError> {
+        connectivity::split_by_connectivity(self)
+    }
+
+    fn component_sizes(&self) -> Result<Vec<usize>, OptError> {
+        connectivity::component_sizes(self)
+    }
+
+    fn prune_components(&mut self, min_error: f32) -> Result<usize, OptError> {
+        with_u32_indices(self, true, |mesh| {
+            let (kept, removed) = connectivity::prune_components(mesh, min_error)?;
+            if removed > 0 {
+                refuse_morph_targets(mesh)?;
+                keep_used_vertices(mesh, kept);
+            }
+            Ok(removed)
+        })
+    }
+
+    fn build_meshlets(&self, params: &MeshletParams) -> Result<Meshlets, OptError> {
+        meshlet::build_meshlets(self, params)
+    }
+
+    #[cfg(feature = "meshlet_mesh")]
+    fn to_meshlet_mesh(
+        &self,
+        vertex_position_quantization_factor: u8,
+    ) -> Result<bevy::pbr::experimental::meshlet::MeshletMesh, OptError> {
+        meshlet_mesh::to_meshlet_mesh(self, vertex_position_quantization_factor)
+    }
+
+    fn generate_occluder(
+        &self,
+        params: &OccluderParams,
+    ) -> Result<(Mesh, OccluderReport), OptError> {
+        occluder::generate_occluder(self, params)
+    }
+
+    fn generate_navmesh_source(
+        &self,
+        params: &NavmeshParams,
+    ) -> Result<(Mesh, NavmeshReport), OptError> {
+        navmesh::generate_navmesh_source(self, params)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::asset::RenderAssetUsages;
+
+    use crate::test_util::indices;
+
+    #[test]
+    fn targets_round_down_to_whole_triangles() {
+        assert_eq!(TargetIndices::Count(100).count(300), 99);
+        assert_eq!(TargetIndices::Multiplier(0.5).count(300), 150);
+        assert_eq!(TargetIndices::Multiplier(0.33).count(300), 99);
+        assert_eq!(TargetIndices::TriangleCount(7).count(300), 21);
+        assert_eq!(TargetIndices::VertexCount(10).count(300), 60);
+    }
+
+    #[test]
+    fn targets_are_clamped_to_the_minimum_and_current_count() {
+        assert_eq!(TargetIndices::Multiplier(0.0).count(300), 3);
+        assert_eq!(TargetIndices::Multiplier(-2.0).count(300), 3);
+        assert_eq!(TargetIndices::Multiplier(f32::NAN).count(300), 3);
+        assert_eq!(TargetIndices::Multiplier(4.0).count(300), 300);
+        assert_eq!(TargetIndices::Count(1_000).count(300), 300);
+        assert_eq!(TargetIndices::ErrorOnly.count(300), 3);
+        // The minimum is rounded down too and never exceeds the mesh.
+        assert_eq!(TargetIndices::Count(0).resolve(300, 50), 48);
+        assert_eq!(TargetIndices::Count(0).resolve(30, 50), 30);
+        assert_eq!(TargetIndices::Count(0).resolve(300, 0), 0);
+    }
+
+    #[test]
+    fn single_triangle_is_kept() {
+        assert_eq!(TargetIndices::Multiplier(0.0).count(3), 3);
+        assert_eq!(TargetIndices::Count(1).count(3), 3);
+
+        let mut triangle = Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::default(),
+        )
+        .with_inserted_attribute(
+            Mesh::ATTRIBUTE_POSITION,
+            vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+        )
+        .with_inserted_indices(Indices::U32(vec![0, 1, 2]));
+        triangle
+            .simplify_with_report(&SimplifyParams {
+                target_index_count: TargetIndices::Multiplier(0.0),
+                max_error: 1.0,
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(indices(&triangle), [0, 1, 2]);
     }
 }
@@ -0,0 +1,926 @@
+use std::ops::Range;
+
+use bevy::{
+    math::Vec3,
+    mesh::{Indices, Mesh, MeshVertexAttribute, VertexFormat},
+};
+
+use crate::{
+    OptError, SimplifyParams, SimplifyReport, TargetIndices, mesh_indices, mesh_indices_widened,
+    mesh_positions,
+    metrics::SurfaceIndex,
+    narrow_indices, narrow_planar_positions,
+    optimize::optimize_vertex_fetch,
+    planar_position_attribute,
+    simplify::{count_used_vertices, simplify_mesh_indices, with_scratch},
+    vertex::{append_mesh_vertices, deduplicate_vertices},
+    widened_planar,
+};
+
+/// Upper bound on the number of levels generated in [`LodLevels::Auto`] mode.
+const MAX_AUTO_LEVELS: usize = 16;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LodLevels {
+    /// Always produce `count` levels (including LOD0), unless simplification stops making progress.
+    Fixed {
+        count: usize,
+        /// Triangle count of each level relative to the previous one, e.g. `0.5` halves the
+        /// triangles every level.
+        reduction_per_level: f32,
+    },
+    /// Keep producing levels until the next one would fall below `target_min_triangles` or the
+    /// simplifier stops making meaningful progress.
+    Auto {
+        target_min_triangles: usize,
+        /// Triangle count of each level relative to the previous one.
+        reduction_per_level: f32,
+        /// Smallest fraction of the previous level's triangles a new level has to remove to be
+        /// kept, generation stops once a level falls short of this.
+        min_progress: f32,
+    },
+    /// Explicit list of the levels following LOD0, which is always the source mesh. Targets are
+    /// relative to LOD0 and have to be strictly decreasing, e.g. `[0.6, 0.3, 0.1, 0.03]`.
+    Schedule(Vec<LevelSpec>),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub enum LevelTarget {
+    /// Fraction of the triangles of LOD0.
+    Multiplier(f32),
+    Triangles(usize),
+}
+
+impl LevelTarget {
+    pub fn triangles(&self, lod0_triangles: usize) -> usize {
+        match self {
+            LevelTarget::Multiplier(multiplier) => (lod0_triangles as f32 * multiplier) as usize,
+            LevelTarget::Triangles(triangles) => *triangles,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serialize", serde(deny_unknown_fields))]
+pub struct LevelSpec {
+    pub target: LevelTarget,
+    /// Overrides `LodChainParams::simplify.max_error` for this level.
+    #[cfg_attr(feature = "serialize", serde(default))]
+    pub max_error: Option<f32>,
+    /// Overrides [`LodChainParams::min_triangles`] for this level.
+    #[cfg_attr(feature = "serialize", serde(default))]
+    pub min_triangles: Option<u32>,
+}
+
+impl From<f32> for LevelSpec {
+    fn from(multiplier: f32) -> Self {
+        LevelSpec {
+            target: LevelTarget::Multiplier(multiplier),
+            max_error: None,
+            min_triangles: None,
+        }
+    }
+}
+
+/// What happens to a level whose target falls below [`LodChainParams::min_triangles`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum MinTrianglesPolicy {
+    /// Generate the level at the minimum triangle count instead, later levels are only generated
+    /// if they can still go lower.
+    #[default]
+    Clamp,
+    /// Stop the chain before that level.
+    Skip,
+}
+
+/// How the levels of a chain are laid out in memory, for [`LodMemoryBudget`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum LodVertexBuffers {
+    /// Every level draws from one vertex buffer, as with [`LodChain::concatenate`], so vertices
+    /// used by several levels are only counted once.
+    #[default]
+    Shared,
+    /// Every level has its own vertex buffer holding only the vertices it uses.
+    PerLevel,
+}
+
+/// What happens when a chain can't fit its [`LodMemoryBudget`] even with every level at its
+/// minimum triangle count.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum MemoryBudgetPolicy {
+    /// Fail with [`OptError::MemoryBudgetExceeded`].
+    #[default]
+    Fail,
+    /// Generate the smallest chain anyway, [`LodMemoryReport::within_budget`] tells it went over.
+    BestEffort,
+}
+
+/// Memory the whole chain, LOD0 included, has to fit in.
+///
+/// The budget picks the triangle count of LOD0, simplifying the source mesh when it doesn't fit,
+/// and the other levels follow from it through [`LodChainParams::levels`]. Detail is biased
+/// toward LOD0 by the falloff of the levels: a lower `reduction_per_level` (or a steeper schedule)
+/// leaves more of the budget to LOD0.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct LodMemoryBudget {
+    /// Size of the vertex and index buffers of every level in bytes, using the vertex attribute
+    /// formats and index format of the source mesh.
+    pub bytes: usize,
+    pub vertex_buffers: LodVertexBuffers,
+    pub policy: MemoryBudgetPolicy,
+}
+
+impl LodMemoryBudget {
+    pub fn new(bytes: usize) -> Self {
+        LodMemoryBudget {
+            bytes,
+            vertex_buffers: LodVertexBuffers::default(),
+            policy: MemoryBudgetPolicy::default(),
+        }
+    }
+}
+
+/// Which mesh each level is simplified from.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub enum LodStrategy {
+    /// Simplify every level from the previous one. Faster for long chains, but errors add up.
+    #[default]
+    Cascaded,
+    /// Simplify every level from LOD0.
+    FromOriginal,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LodChainParams {
+    /// Simplification used for every level, `target_index_count` is ignored in favor of `levels`.
+    /// `max_error` applies to each level relative to the mesh it is simplified from.
+    pub simplify: SimplifyParams,
+    pub levels: LodLevels,
+    pub strategy: LodStrategy,
+    /// With [`LodStrategy::Cascaded`], simplifies a level from LOD0 instead of the previous level
+    /// once the error accumulated by the previous level exceeds this, so long chains keep the speed
+    /// of cascading without drifting too far from the source. Later levels cascade from the
+    /// restarted one. `None` always cascades.
+    pub restart_error: Option<f32>,
+    /// Triangle count no level is simplified below, resolved after multipliers so it protects
+    /// small props from being reduced to a handful of triangles. `0` disables it.
+    pub min_triangles: u32,
+    pub min_triangles_policy: MinTrianglesPolicy,
+    /// Fits the whole chain in a memory budget, see [`LodMemoryBudget`].
+    pub memory_budget: Option<LodMemoryBudget>,
+}
+
+impl Default for LodChainParams {
+    fn default() -> Self {
+        LodChainParams {
+            simplify: SimplifyParams::default(),
+            levels: LodLevels::Fixed {
+                count: 4,
+                reduction_per_level: 0.5,
+            },
+            strategy: LodStrategy::default(),
+            restart_error: None,
+            min_triangles: 0,
+            min_triangles_policy: MinTrianglesPolicy::default(),
+            memory_budget: None,
+        }
+    }
+}
+
+impl LodChainParams {
+    /// Picks the number of levels based on the mesh complexity, see [`LodLevels::Auto`].
+    pub fn auto(target_min_triangles: usize, reduction_per_level: f32) -> Self {
+        LodChainParams {
+            levels: LodLevels::Auto {
+                target_min_triangles,
+                reduction_per_level,
+                min_progress: 0.05,
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Uses an explicit per-level schedule, see [`LodLevels::Schedule`].
+    pub fn schedule(levels: impl IntoIterator<Item = impl Into<LevelSpec>>) -> Self {
+        LodChainParams {
+            levels: LodLevels::Schedule(levels.into_iter().map(Into::into).collect()),
+            ..Default::default()
+        }
+    }
+}
+
+/// Why [`MeshExt::generate_lod_chain`](crate::MeshExt::generate_lod_chain) stopped adding levels.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LodStopReason {
+    /// The requested number of levels was generated.
+    LevelCount,
+    /// The next level would have fallen below the minimum triangle count, either
+    /// [`LodLevels::Auto`]'s `target_min_triangles` or [`LodChainParams::min_triangles`].
+    MinTriangles,
+    /// The simplifier couldn't remove enough triangles, usually because of the error bound or
+    /// locked vertices.
+    NoProgress,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LodLevelReport {
+    /// Triangle count the level was asked for.
+    pub requested_triangles: usize,
+    /// Whether `requested_triangles` was raised to the level's minimum triangle count.
+    pub clamped: bool,
+    /// Level the level was simplified from, `0` for LOD0 itself.
+    pub source_level: usize,
+    /// Simplification result relative to LOD0, `result_error` accumulates over cascaded levels.
+    pub simplify: SimplifyReport,
+}
+
+impl LodLevelReport {
+    /// Whether the simplifier got down to the requested triangle count, levels usually fall short
+    /// of it when the error bound is hit.
+    pub fn reached_target(&self) -> bool {
+        self.simplify.triangles_after() <= self.requested_triangles
+    }
+}
+
+/// Memory of a chain generated with a [`LodMemoryBudget`], in bytes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct LodMemoryReport {
+    pub budget: usize,
+    /// Estimated memory of the chain at the triangle counts the budget was solved for, assuming
+    /// the vertex count scales with the triangle count.
+    pub projected: usize,
+    /// Memory of the generated chain, more than `projected` when the simplifier fell short of the
+    /// level targets because of the error bound.
+    pub actual: usize,
+    /// Estimated memory of the chain with every level at its minimum triangle count.
+    pub minimum: usize,
+}
+
+impl LodMemoryReport {
+    pub fn within_budget(&self) -> bool {
+        self.actual <= self.budget
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LodChainReport {
+    /// One report per level, starting with LOD0.
+    pub levels: Vec<LodLevelReport>,
+    pub stop_reason: LodStopReason,
+    /// Set when the chain was generated with a [`LodMemoryBudget`].
+    pub memory: Option<LodMemoryReport>,
+}
+
+impl LodChainReport {
+    /// Number of levels clamped to their minimum triangle count, chains with clamped levels are
+    /// effectively shorter than requested.
+    pub fn clamped_levels(&self) -> usize {
+        self.levels.iter().filter(|level| level.clamped).count()
+    }
+}
+
+/// Levels of detail of a mesh, ordered from LOD0 (the source mesh, simplified only to fit a
+/// [`LodMemoryBudget`]) to the coarsest level.
+///
+/// Every level keeps the vertex buffer of the source mesh and only replaces its indices. See
+/// [`LodChain::morph_targets`] for blending between levels instead of switching abruptly.
+#[derive(Debug, Clone)]
+pub struct LodChain {
+    pub levels: Vec<Mesh>,
+    pub report: LodChainReport,
+}
+
+/// Every level of an [`LodChain`] in a single mesh, for drawing the levels out of one vertex and
+/// index buffer.
+#[derive(Debug, Clone)]
+pub struct ConcatenatedLods {
+    pub mesh: Mesh,
+    /// Range of the index buffer holding each level, from LOD0 to the coarsest level.
+    pub ranges: Vec<Range<u32>>,
+    /// `result_error` of each level, relative to the mesh extents unless
+    /// `SimplifyOptions::ErrorAbsolute` was used.
+    pub errors: Vec<f32>,
+}
+
+impl LodChain {
+    /// Concatenates the indices of every level into a single mesh and optimizes its vertex fetch
+    /// for all levels at once. The indices are `u16` when the vertices of all levels fit, `u32`
+    /// otherwise, and `Float32x2` positions stay `Float32x2`.
+    ///
+    /// Levels that no longer share the vertex buffer of LOD0 have their vertices appended and
+    /// welded with the existing ones, they have to have the same attributes as LOD0.
+    pub fn concatenate(&self) -> Result<ConcatenatedLods, OptError> {
+        let lod0 = self.levels.first().ok_or(OptError::MissingMesh)?;
+        let planar = planar_position_attribute(lod0);
+        let lod0 = widened_planar(lod0);
+        let mut mesh = lod0.clone().into_owned();
+        mesh.remove_indices();
+
+        let mut indices = Vec::new();
+        let mut ranges = Vec::with_capacity(self.levels.len());
+        let mut appended = false;
+        for (level, level_mesh) in self.levels.iter().enumerate() {
+            let level_mesh = widened_planar(level_mesh);
+            let level_indices = mesh_indices_widened(&level_mesh)?;
+            let offset = if shares_vertices(&lod0, &level_mesh) {
+                0
+            } else {
+                let offset = mesh.count_vertices() as u32;
+                if !append_mesh_vertices(&mut mesh, &level_mesh) {
+                    return Err(OptError::MismatchedLodAttributes(level));
+                }
+                appended = true;
+                offset
+            };
+
+            let start = indices.len() as u32;
+            indices.extend(level_indices.iter().map(|index| index + offset));
+            ranges.push(start..indices.len() as u32);
+        }
+        if appended {
+            deduplicate_vertices(&mut mesh, &mut indices);
+        }
+
+        // Vertex fetch optimization only renumbers the vertices, the order of the indices and so
+        // the ranges of every level stay the same.
+        mesh.insert_indices(Indices::U32(indices));
+        optimize_vertex_fetch(&mut mesh)?;
+        narrow_indices(&mut mesh);
+        if let Some(attribute) = planar {
+            narrow_planar_positions(&mut mesh, attribute);
+        }
+
+        let errors = self
+            .report
+            .levels
+            .iter()
+            .map(|level| level.simplify.result_error)
+            .collect();
+        Ok(ConcatenatedLods {
+            mesh,
+            ranges,
+            errors,
+        })
+    }
+}
+
+/// Where the vertices of one level of an [`LodChain`] end up on the next coarser level, so a
+/// vertex shader can geomorph between the two instead of popping: blending every position towards
+/// its target turns the finer level into the shape of the coarser one right before switching.
+///
+/// meshoptimizer doesn't report the collapses it makes, so vertices the coarser level dropped
+/// target the closest point on its surface rather than the vertex they were collapsed into.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct LodMorph {
+    /// Target of every vertex of the finer level, in its vertex order. Vertices the coarser level
+    /// still uses and vertices the finer level doesn't use keep their own position.
+    pub targets: Vec<[f32; 3]>,
+    /// Largest distance between a vertex and its target.
+    pub max_distance: f32,
+}
+
+impl LodMorph {
+    /// `Float32x3` attribute to insert [`LodMorph::targets`] as into the finer level, for
+    /// shaders reading them at a location of their own.
+    pub const ATTRIBUTE_TARGET: MeshVertexAttribute = MeshVertexAttribute::new(
+        "Vertex_LodMorphTarget",
+        988_540_917,
+        VertexFormat::Float32x3,
+    );
+}
+
+impl LodChain {
+    /// [`LodMorph`] from every level to the next coarser one, the first one morphing LOD0 into
+    /// LOD1. Empty for chains of a single level. Targets of `Float32x2` levels are on the `z = 0`
+    /// plane.
+    pub fn morph_targets(&self) -> Result<Vec<LodMorph>, OptError> {
+        self.levels
+            .windows(2)
+            .map(|levels| lod_morph(&levels[0], &levels[1]))
+            .collect()
+    }
+}
+
+fn lod_morph(finer: &Mesh, coarser: &Mesh) -> Result<LodMorph, OptError> {
+    let (finer, coarser) = (&*widened_planar(finer), &*widened_planar(coarser));
+    let positions = mesh_positions(finer)?;
+    let finer_indices = mesh_indices_widened(finer)?;
+    let coarser_indices = mesh_indices_widened(coarser)?;
+    let coarser_positions = mesh_positions(coarser)?;
+
+    let mut kept = vec![false; positions.len()];
+    let mut used = vec![false; positions.len()];
+    for &index in finer_indices.iter() {
+        used[index as usize] = true;
+    }
+    if shares_vertices(finer, coarser) {
+        for &index in coarser_indices.iter() {
+            kept[index as usize] = true;
+        }
+    }
+
+    let surface = SurfaceIndex::new(&coarser_indices, coarser_positions);
+    let mut morph = LodMorph {
+        targets: positions.clone(),
+        max_distance: 0.0,
+    };
+    for (vertex, target) in morph.targets.iter_mut().enumerate() {
+        if kept[vertex] || !used[vertex] {
+            continue;
+        }
+        if let Some(hit) = surface.closest_point(Vec3::from(*target)) {
+            *target = hit.point.to_array();
+            morph.max_distance = morph.max_distance.max(hit.distance_squared.sqrt());
+        }
+    }
+    Ok(morph)
+}
+
+/// Whether `level` still uses the vertex buffer of `lod0`.
+fn shares_vertices(lod0: &Mesh, level: &Mesh) -> bool {
+    std::ptr::eq(lod0, level)
+        || (lod0.count_vertices() == level.count_vertices()
+            && lod0.attributes().count() == level.attributes().count()
+            && lod0
+                .attributes()
+                .all(|(attribute, values)| level.attribute(attribute.id) == Some(values)))
+}
+
+pub(crate) fn generate_lod_chain(
+    mesh: &Mesh,
+    params: &LodChainParams,
+) -> Result<LodChain, OptError> {
+    let vertex_count = mesh.count_vertices();
+    let indices = mesh_indices(mesh)?;
+    let used_vertices =
+        with_scratch(|scratch| count_used_vertices(indices, vertex_count, &mut scratch.seen));
+    let base = SimplifyReport::new(mesh, &params.simplify, indices.len(), used_vertices, 0.0);
+    let sizes = MemorySizes::new(mesh);
+
+    let mut levels = vec![mesh.clone()];
+    let mut reports = vec![LodLevelReport {
+        requested_triangles: base.triangles_after(),
+        clamped: false,
+        source_level: 0,
+        simplify: base,
+    }];
+    let mut memory = None;
+    if let Some(budget) = &params.memory_budget {
+        let solved = solve_budget(params, budget, &sizes, &base)?;
+        if solved.lod0_triangles < base.triangles_after() {
+            let simplify = SimplifyParams {
+                target_index_count: TargetIndices::TriangleCount(solved.lod0_triangles),
+                ..params.simplify.clone()
+            };
+            let (new_indices, error, path) = simplify_mesh_indices(mesh, &simplify)?;
+            if new_indices.len() < 3 {
+                return Err(OptError::InvalidIndexCount(new_indices.len()));
+            }
+            let used_vertices = with_scratch(|scratch| {
+                count_used_vertices(&new_indices, vertex_count, &mut scratch.seen)
+            });
+            reports[0] = LodLevelReport {
+                requested_triangles: solved.lod0_triangles,
+                clamped: false,
+                source_level: 0,
+                simplify: SimplifyReport {
+                    indices_before: base.indices_before,
+                    vertices_before: base.vertices_before,
+                    memory_before: base.memory_before,
+                    path,
+                    ..SimplifyReport::new(mesh, &simplify, new_indices.len(), used_vertices, error)
+                },
+            };
+            levels[0].insert_indices(Indices::U32(new_indices));
+        }
+        memory = Some(LodMemoryReport {
+            budget: budget.bytes,
+            projected: solved.projected,
+            actual: 0,
+            minimum: solved.minimum,
+        });
+    }
+
+    let lod0_triangles = reports[0].simplify.triangles_after();
+    if let LodLevels::Schedule(schedule) = &params.levels {
+        validate_schedule(schedule, lod0_triangles)?;
+    }
+
+    let stop_reason = loop {
+        let previous_report = reports.last().unwrap().simplify;
+        let previous_triangles = previous_report.triangles_after();
+        let next = match next_level(params, levels.len(), previous_triangles, lod0_triangles) {
+            Ok(next) => next,
+            Err(reason) => break reason,
+        };
+
+        let restart = params
+            .restart_error
+            .is_some_and(|error| previous_report.result_error > error);
+        let source_level = match params.strategy {
+            LodStrategy::Cascaded if !restart => levels.len() - 1,
+            _ => 0,
+        };
+        let (source, source_error) = (
+            &levels[source_level],
+            reports[source_level].simplify.result_error,
+        );
+        let simplify = SimplifyParams {
+            target_index_count: TargetIndices::TriangleCount(next.target_triangles),
+            max_error: next.max_error.unwrap_or(params.simplify.max_error),
+            ..params.simplify.clone()
+        };
+        let (new_indices, error, path) = simplify_mesh_indices(source, &simplify)?;
+        let removed = previous_triangles.saturating_sub(new_indices.len() / 3);
+        if new_indices.len() < 3
+            || removed == 0
+            || (removed as f32) < previous_triangles as f32 * next.min_progress
+        {
+            break LodStopReason::NoProgress;
+        }
+
+        let used_vertices = with_scratch(|scratch| {
+            count_used_vertices(&new_indices, vertex_count, &mut scratch.seen)
+        });
+        let report = SimplifyReport {
+            indices_before: base.indices_before,
+            vertices_before: base.vertices_before,
+            memory_before: base.memory_before,
+            result_error: source_error + error,
+            path,
+            ..SimplifyReport::new(mesh, &simplify, new_indices.len(), used_vertices, 0.0)
+        };
+
+        let mut level = source.clone();
+        level.insert_indices(Indices::U32(new_indices));
+        levels.push(level);
+        reports.push(LodLevelReport {
+            requested_triangles: next.target_triangles,
+            clamped: next.clamped,
+            source_level,
+            simplify: report,
+        });
+    };
+
+    if let (Some(memory), Some(budget)) = (&mut memory, &params.memory_budget) {
+        // Levels are only ever simplified from LOD0 or its descendants, so the vertices they use
+        // are all used by LOD0.
+        memory.actual = sizes.chain(
+            budget.vertex_buffers,
+            reports[0].simplify.vertices_after,
+            reports
+                .iter()
+                .map(|level| (level.simplify.indices_after, level.simplify.vertices_after)),
+        );
+    }
+
+    Ok(LodChain {
+        levels,
+        report: LodChainReport {
+            levels: reports,
+            stop_reason,
+            memory,
+        },
+    })
+}
+
+/// Target of the level following one with `previous_triangles`.
+struct NextLevel {
+    target_triangles: usize,
+    clamped: bool,
+    max_error: Option<f32>,
+    min_progress: f32,
+}
+
+/// Target of level `level` of the chain, or why the chain stops before it.
+fn next_level(
+    params: &LodChainParams,
+    level: usize,
+    previous_triangles: usize,
+    lod0_triangles: usize,
+) -> Result<NextLevel, LodStopReason> {
+    let (mut target_triangles, max_error, floor, min_triangles, min_progress) = match &params.levels
+    {
+        LodLevels::Fixed {
+            count,
+            reduction_per_level,
+        } => {
+            if level >= (*count).max(1) {
+                return Err(LodStopReason::LevelCount);
+            }
+            let target = previous_triangles as f32 * reduction_per_level.clamp(0.0, 1.0);
+            (target as usize, None, params.min_triangles, 1, 0.0)
+        }
+        LodLevels::Auto {
+            target_min_triangles,
+            reduction_per_level,
+            min_progress,
+        } => {
+            if level >= MAX_AUTO_LEVELS {
+                return Err(LodStopReason::LevelCount);
+            }
+            let target = previous_triangles as f32 * reduction_per_level.clamp(0.0, 1.0);
+            (
+                target as usize,
+                None,
+                params.min_triangles,
+                (*target_min_triangles).max(1),
+                *min_progress,
+            )
+        }
+        LodLevels::Schedule(schedule) => {
+            let Some(spec) = schedule.get(level - 1) else {
+                return Err(LodStopReason::LevelCount);
+            };
+            (
+                spec.target.triangles(lod0_triangles),
+                spec.max_error,
+                spec.min_triangles.unwrap_or(params.min_triangles),
+                1,
+                0.0,
+            )
+        }
+    };
+    let floor = floor as usize;
+    let clamped = target_triangles < floor;
+    if clamped {
+        if previous_triangles <= floor || params.min_triangles_policy == MinTrianglesPolicy::Skip {
+            return Err(LodStopReason::MinTriangles);
+        }
+        target_triangles = floor;
+    }
+    if target_triangles < min_triangles {
+        return Err(LodStopReason::MinTriangles);
+    }
+    Ok(NextLevel {
+        target_triangles,
+        clamped,
+        max_error,
+        min_progress,
+    })
+}
+
+/// Buffer element sizes of the source mesh, for estimating the memory of a chain.
+struct MemorySizes {
+    vertex_size: usize,
+    index_size: usize,
+}
+
+impl MemorySizes {
+    fn new(mesh: &Mesh) -> Self {
+        MemorySizes {
+            vertex_size: mesh.get_vertex_size() as usize,
+            index_size: match mesh.indices() {
+                Some(Indices::U16(_)) => size_of::<u16>(),
+                _ => size_of::<u32>(),
+            },
+        }
+    }
+
+    /// Memory of a chain from the index and vertex counts of its levels, `shared_vertices` is the
+    /// size of the vertex buffer in [`LodVertexBuffers::Shared`] mode.
+    fn chain(
+        &self,
+        vertex_buffers: LodVertexBuffers,
+        shared_vertices: usize,
+        levels: impl Iterator<Item = (usize, usize)>,
+    ) -> usize {
+        let (indices, vertices) = levels.fold((0, 0), |(indices, vertices), level| {
+            (indices + level.0, vertices + level.1)
+        });
+        let vertices = match vertex_buffers {
+            LodVertexBuffers::Shared => shared_vertices,
+            LodVertexBuffers::PerLevel => vertices,
+        };
+        vertices * self.vertex_size + indices * self.index_size
+    }
+}
+
+/// LOD0 triangle count a [`LodMemoryBudget`] was solved for.
+struct SolvedBudget {
+    lod0_triangles: usize,
+    projected: usize,
+    minimum: usize,
+}
+
+/// Finds the largest LOD0 triangle count whose projected chain fits the budget.
+fn solve_budget(
+    params: &LodChainParams,
+    budget: &LodMemoryBudget,
+    sizes: &MemorySizes,
+    source: &SimplifyReport,
+) -> Result<SolvedBudget, OptError> {
+    let source_triangles = source.triangles_after();
+    // Simplification removes vertices roughly in proportion to triangles.
+    let vertices_per_triangle = source.vertices_after as f64 / source_triangles.max(1) as f64;
+    let projected = |lod0_triangles: usize| {
+        let mut triangles = vec![lod0_triangles];
+        while let Ok(next) = next_level(
+            params,
+            triangles.len(),
+            *triangles.last().unwrap(),
+            lod0_triangles,
+        ) {
+            if next.target_triangles == 0 || next.target_triangles >= *triangles.last().unwrap() {
+                break;
+            }
+            triangles.push(next.target_triangles);
+        }
+        let vertices =
+            |triangles: usize| (triangles as f64 * vertices_per_triangle).ceil() as usize;
+        sizes.chain(
+            budget.vertex_buffers,
+            vertices(lod0_triangles),
+            triangles
+                .iter()
+                .map(|&triangles| (triangles * 3, vertices(triangles))),
+        )
+    };
+
+    let floor = (params.min_triangles as usize).clamp(1, source_triangles.max(1));
+    let minimum = projected(floor);
+    let solved = |lod0_triangles, projected| SolvedBudget {
+        lod0_triangles,
+        projected,
+        minimum,
+    };
+    let full = projected(source_triangles);
+    if full <= budget.bytes {
+        return Ok(solved(source_triangles, full));
+    }
+    if minimum > budget.bytes {
+        return match budget.policy {
+            MemoryBudgetPolicy::Fail => Err(OptError::MemoryBudgetExceeded(minimum)),
+            MemoryBudgetPolicy::BestEffort => Ok(solved(floor, minimum)),
+        };
+    }
+
+    // The projection grows with the LOD0 triangle count, `low` always fits and `high` never does.
+    let (mut low, mut high) = (floor, source_triangles);
+    while high - low > 1 {
+        let middle = low + (high - low) / 2;
+        if projected(middle) <= budget.bytes {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    Ok(solved(low, projected(low)))
+}
+
+/// Checks that every level of the schedule asks for fewer triangles than the one before it.
+fn validate_schedule(schedule: &[LevelSpec], lod0_triangles: usize) -> Result<(), OptError> {
+    let mut previous = lod0_triangles;
+    for (level, spec) in schedule.iter().enumerate() {
+        let triangles = spec.target.triangles(lod0_triangles);
+        if triangles >= previous
+            || spec
+                .max_error
+                .is_some_and(|error| error.is_nan() || error < 0.0)
+        {
+            return Err(OptError::InvalidLodSchedule(level + 1));
+        }
+        previous = triangles;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::mesh::VertexAttributeValues;
+
+    use super::*;
+    use crate::{
+        MeshExt,
+        test_util::{indices, planar_grid, sphere, triangle_set, with_u16_indices},
+    };
+
+    /// Every range has to reproduce its level with vertices of the concatenated mesh.
+    fn assert_concatenated(chain: &LodChain) -> ConcatenatedLods {
+        let concatenated = chain.concatenate().unwrap();
+        let indices = indices(&concatenated.mesh);
+        let vertex_count = concatenated.mesh.count_vertices() as u32;
+        assert_eq!(concatenated.ranges.len(), chain.levels.len());
+        assert_eq!(concatenated.errors.len(), chain.levels.len());
+        for (range, level) in concatenated.ranges.iter().zip(&chain.levels) {
+            let range = &indices[range.start as usize..range.end as usize];
+            assert_eq!(range.len(), level.indices().unwrap().len());
+            assert!(range.iter().all(|&index| index < vertex_count));
+        }
+        concatenated
+    }
+
+    /// Level drawn by every range of `concatenated`, to compare against the levels of the chain.
+    fn drawn_levels(concatenated: &ConcatenatedLods) -> Vec<Mesh> {
+        let indices = indices(&concatenated.mesh);
+        concatenated
+            .ranges
+            .iter()
+            .map(|range| {
+                let mut level = concatenated.mesh.clone();
+                let range = indices[range.start as usize..range.end as usize].to_vec();
+                level.insert_indices(Indices::U32(range));
+                level
+            })
+            .collect()
+    }
+
+    #[test]
+    fn concatenated_ranges_draw_their_levels() {
+        let mut chain = sphere(8)
+            .generate_lod_chain(&LodChainParams::default())
+            .unwrap();
+        // A level with a vertex buffer of its own has to be welded onto LOD0's.
+        chain.levels[2].optimize_vertex_fetch().unwrap();
+        assert!(chain.levels[2].count_vertices() < chain.levels[0].count_vertices());
+
+        let concatenated = assert_concatenated(&chain);
+        let ends: Vec<u32> = concatenated.ranges.iter().map(|range| range.end).collect();
+        let starts: Vec<u32> = concatenated
+            .ranges
+            .iter()
+            .map(|range| range.start)
+            .collect();
+        assert_eq!(starts[0], 0);
+        assert_eq!(starts[1..], ends[..ends.len() - 1]);
+        assert_eq!(
+            *ends.last().unwrap() as usize,
+            indices(&concatenated.mesh).len()
+        );
+        assert_eq!(
+            concatenated.mesh.count_vertices(),
+            chain.levels[0].count_vertices()
+        );
+        for (drawn, level) in drawn_levels(&concatenated).iter().zip(&chain.levels) {
+            assert_eq!(triangle_set(drawn), triangle_set(level));
+        }
+    }
+
+    #[test]
+    fn concatenate_u16_chain() {
+        let chain = with_u16_indices(sphere(8))
+            .generate_lod_chain(&LodChainParams::default())
+            .unwrap();
+        assert_eq!(chain.levels.len(), 4);
+        let concatenated = assert_concatenated(&chain);
+        assert!(matches!(concatenated.mesh.indices(), Some(Indices::U16(_))));
+    }
+
+    #[test]
+    fn concatenate_planar_chain() {
+        let chain = planar_grid(16)
+            .generate_lod_chain(&LodChainParams::default())
+            .unwrap();
+        assert!(chain.levels.len() > 1);
+        let concatenated = assert_concatenated(&chain);
+        assert!(matches!(
+            concatenated.mesh.attribute(Mesh::ATTRIBUTE_POSITION),
+            Some(VertexAttributeValues::Float32x2(_))
+        ));
+    }
+
+    #[test]
+    fn morph_targets_of_planar_chain() {
+        let chain = planar_grid(16)
+            .generate_lod_chain(&LodChainParams::default())
+            .unwrap();
+        let morphs = chain.morph_targets().unwrap();
+        assert_eq!(morphs.len(), chain.levels.len() - 1);
+        for (morph, level) in morphs.iter().zip(&chain.levels) {
+            assert_eq!(morph.targets.len(), level.count_vertices());
+            assert!(morph.targets.iter().all(|target| target[2] == 0.0));
+        }
+    }
+
+    fn auto_chain(mesh: &Mesh) -> LodChain {
+        let params = LodChainParams {
+            simplify: SimplifyParams {
+                max_error: 1.0,
+                ..Default::default()
+            },
+            ..LodChainParams::auto(500, 0.5)
+        };
+        mesh.generate_lod_chain(&params).unwrap()
+    }
+
+    #[test]
+    fn auto_levels_follow_mesh_complexity() {
+        let tiny = auto_chain(&sphere(3));
+        assert_eq!(tiny.levels.len(), 1);
+        assert_eq!(tiny.report.stop_reason, LodStopReason::MinTriangles);
+
+        let huge = auto_chain(&sphere(40));
+        assert!(huge.levels.len() >= 5, "{} levels", huge.levels.len());
+        assert_eq!(huge.report.stop_reason, LodStopReason::MinTriangles);
+        let triangles: Vec<usize> = huge
+            .levels
+            .iter()
+            .map(|level| indices(level).len() / 3)
+            .collect();
+        assert!(triangles.windows(2).all(|pair| pair[1] < pair[0]));
+        assert!(triangles.iter().all(|&count| count >= 500));
+    }
+}
@@ -0,0 +1,422 @@
+use std::collections::HashMap;
+
+use bevy::{
+    app::{App, Plugin, Update},
+    asset::{Assets, Handle},
+    camera::{Camera, Projection},
+    ecs::prelude::*,
+    mesh::{Indices, Mesh, Mesh3d},
+    reflect::{Reflect, std_traits::ReflectDefault},
+    transform::components::GlobalTransform,
+};
+
+use crate::{CurrentLod, LodChain, LodChainParams, MeshExt, MeshoptSet, OptError};
+
+/// Switches the [`Mesh3d`] of entities with [`MeshLods`] between their levels of detail in
+/// [`MeshoptSet::LodSwitch`], keeping their [`CurrentLod`] up to date. Entities without
+/// [`MeshLods`] are left alone.
+///
+/// With several active cameras, every entity uses the level the closest one asks for, made
+/// coarser when that camera has a [`LodTriangleBudget`] it would go over. Entities keep the
+/// [`Aabb`](bevy::camera::primitives::Aabb) of the level they were spawned with, which is fine for
+/// levels simplified from the same vertices.
+///
+/// Every entity switches its own [`Mesh3d`] handle and the level meshes are never modified, so
+/// instances sharing the same [`MeshLods`] handles use their own level at their own distance.
+///
+/// ```no_run
+/// use bevy::prelude::*;
+/// use bevy_meshopt::{LodChainParams, MeshLods};
+///
+/// fn spawn_with_lods(mut commands: Commands, mut meshes: ResMut<Assets<Mesh>>) {
+///     let mesh = Sphere::new(1.0).mesh().ico(5).unwrap();
+///     let Ok(lods) = MeshLods::from_mesh(&mesh, &LodChainParams::default(), &mut meshes) else {
+///         return;
+///     };
+///     commands.spawn((Mesh3d(lods.levels[0].0.clone()), lods));
+/// }
+/// ```
+pub struct MeshLodPlugin;
+
+impl Plugin for MeshLodPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<MeshLods>()
+            .register_type::<LodTriangleBudget>()
+            .register_type::<LodBudgetStatus>()
+            .add_systems(Update, switch_mesh_lods.in_set(MeshoptSet::LodSwitch));
+    }
+}
+
+/// What [`MeshLods::levels`] switch on.
+#[derive(Debug, Copy, Clone, PartialEq, Reflect)]
+#[reflect(Debug, Clone, PartialEq)]
+pub enum LodMetric {
+    /// Levels switch at the distance between the camera and the entity's origin.
+    Distance,
+    /// Levels are paired with their simplification error in mesh units, the coarsest level whose
+    /// error projects to at most `max_pixels` physical pixels at the entity's origin is used.
+    /// Needs cameras with a perspective or orthographic projection and a known viewport size.
+    ScreenSpaceError { max_pixels: f32 },
+}
+
+/// Fractions of a level's value the metric has to go past before switching, so entities sitting on
+/// a boundary don't flicker between two levels. Both are clamped to `0.0..=1.0`.
+#[derive(Debug, Copy, Clone, PartialEq, Reflect)]
+#[reflect(Debug, Clone, PartialEq, Default)]
+pub struct LodHysteresis {
+    /// Fraction past a coarser level's value the metric has to reach to switch to it, `0.1`
+    /// switches 10% past it.
+    pub enter: f32,
+    /// Fraction before the current level's value the metric has to drop to to switch back to a
+    /// finer level, `0.1` switches 10% before it.
+    pub exit: f32,
+}
+
+impl LodHysteresis {
+    /// The same fraction on both sides of a level's value.
+    pub fn symmetric(fraction: f32) -> Self {
+        LodHysteresis {
+            enter: fraction,
+            exit: fraction,
+        }
+    }
+}
+
+impl Default for LodHysteresis {
+    fn default() -> Self {
+        LodHysteresis::symmetric(0.1)
+    }
+}
+
+/// Levels of detail an entity switches between, see [`MeshLodPlugin`].
+#[derive(Component, Debug, Clone, Reflect)]
+#[reflect(Component, Debug, Clone)]
+#[require(CurrentLod)]
+pub struct MeshLods {
+    /// Meshes from LOD0 to the coarsest level, each with the value of `metric` it is used from.
+    /// Values have to increase with the level, the one of LOD0 is ignored.
+    pub levels: Vec<(Handle<Mesh>, f32)>,
+    pub metric: LodMetric,
+    pub hysteresis: LodHysteresis,
+}
+
+impl MeshLods {
+    /// Levels switching at the given distances, with a hysteresis of `0.1` both ways.
+    pub fn from_distances(levels: impl IntoIterator<Item = (Handle<Mesh>, f32)>) -> Self {
+        MeshLods {
+            levels: levels.into_iter().collect(),
+            metric: LodMetric::Distance,
+            hysteresis: LodHysteresis::default(),
+        }
+    }
+
+    /// Generates the LOD chain of `mesh` and adds its levels to `meshes`, switching on their
+    /// accumulated error with [`LodMetric::ScreenSpaceError`] and a `max_pixels` of `1.0`.
+    pub fn from_mesh(
+        mesh: &Mesh,
+        params: &LodChainParams,
+        meshes: &mut Assets<Mesh>,
+    ) -> Result<Self, OptError> {
+        let chain = mesh.generate_lod_chain(params)?;
+        let lod0 = meshes.reserve_handle();
+        let (lods, lod0_mesh) = MeshLods::from_chain(chain, &lod0, meshes);
+        if let Some(lod0_mesh) = lod0_mesh {
+            meshes
+                .insert(&lod0, lod0_mesh)
+                .map_err(|_| OptError::MissingMesh)?;
+        }
+        Ok(lods)
+    }
+
+    /// Levels of `chain` switching on their accumulated error like [`MeshLods::from_mesh`], LOD0
+    /// drawn from `lod0` and the other levels added to `meshes`. LOD0 is returned to be stored in
+    /// `lod0`, e.g. replacing the mesh the chain was generated from.
+    pub fn from_chain(
+        chain: LodChain,
+        lod0: &Handle<Mesh>,
+        meshes: &mut Assets<Mesh>,
+    ) -> (Self, Option<Mesh>) {
+        let mut lod0_mesh = None;
+        // Errors only ever grow along the chain, even if a level is simplified from LOD0.
+        let mut error = 0.0f32;
+        let levels = chain
+            .levels
+            .into_iter()
+            .zip(&chain.report.levels)
+            .enumerate()
+            .map(|(level, (mesh, report))| {
+                error = error.max(report.simplify.result_error_absolute());
+                let handle = match level {
+                    0 => {
+                        lod0_mesh = Some(mesh);
+                        lod0.clone()
+                    }
+                    _ => meshes.add(mesh),
+                };
+                (handle, error)
+            })
+            .collect();
+        let lods = MeshLods {
+            levels,
+            metric: LodMetric::ScreenSpaceError { max_pixels: 1.0 },
+            hysteresis: LodHysteresis::default(),
+        };
+        (lods, lod0_mesh)
+    }
+
+    /// Level to use at `value` of the metric, coming from level `current`.
+    pub fn select_level(&self, value: f32, current: usize) -> usize {
+        let enter = 1.0 + self.hysteresis.enter.clamp(0.0, 1.0);
+        let exit = 1.0 - self.hysteresis.exit.clamp(0.0, 1.0);
+        self.levels
+            .iter()
+            .enumerate()
+            .skip(1)
+            .take_while(|(level, (_, switch))| {
+                let factor = if *level > current { enter } else { exit };
+                switch * factor <= value
+            })
+            .last()
+            .map_or(0, |(level, _)| level)
+    }
+}
+
+/// Triangles the entities a camera is the closest of may render together, see [`MeshLodPlugin`].
+/// When their levels go over it, every one of them switches as if its metric value was scaled by
+/// the smallest factor that fits, coarsening the far and the near alike, and hysteresis still
+/// applies. Entities count whether they are visible or not.
+#[derive(Component, Debug, Copy, Clone, PartialEq, Reflect)]
+#[reflect(Component, Debug, Clone, PartialEq)]
+#[require(LodBudgetStatus)]
+pub struct LodTriangleBudget {
+    pub triangles: usize,
+    /// Largest factor the metric values are scaled by, `4.0` lets an entity use the level it would
+    /// use 4 times further away. Budgets that still don't fit are exceeded. At least `1.0`.
+    pub max_bias: f32,
+}
+
+impl LodTriangleBudget {
+    /// Budget of `triangles` with a `max_bias` of `4.0`.
+    pub fn new(triangles: usize) -> Self {
+        LodTriangleBudget {
+            triangles,
+            max_bias: 4.0,
+        }
+    }
+}
+
+/// How the [`LodTriangleBudget`] of a camera was met in the last frame, e.g. to show it in debug
+/// tooling.
+#[derive(Component, Debug, Copy, Clone, PartialEq, Reflect)]
+#[reflect(Component, Debug, Clone, PartialEq, Default)]
+pub struct LodBudgetStatus {
+    /// Factor the metric values were scaled by, `1.0` when the levels fit without it.
+    pub bias: f32,
+    /// Triangles of the levels the camera's entities switched to.
+    pub triangles: usize,
+}
+
+impl Default for LodBudgetStatus {
+    fn default() -> Self {
+        LodBudgetStatus {
+            bias: 1.0,
+            triangles: 0,
+        }
+    }
+}
+
+/// Smallest bias in `1.0..=max_bias` whose levels fit the budget, or `max_bias` if none does, with
+/// the triangles at that bias.
+fn solve_bias(budget: &LodTriangleBudget, triangles_at: impl Fn(f32) -> usize) -> (f32, usize) {
+    let unbiased = triangles_at(1.0);
+    if unbiased <= budget.triangles {
+        return (1.0, unbiased);
+    }
+    let (mut low, mut high) = (1.0, budget.max_bias.max(1.0));
+    let mut triangles = triangles_at(high);
+    if triangles > budget.triangles {
+        return (high, triangles);
+    }
+    // Levels only get coarser as the bias grows, so the triangles only ever decrease.
+    for _ in 0..16 {
+        let middle = (low + high) * 0.5;
+        let middle_triangles = triangles_at(middle);
+        if middle_triangles <= budget.triangles {
+            (high, triangles) = (middle, middle_triangles);
+        } else {
+            low = middle;
+        }
+    }
+    (high, triangles)
+}
+
+/// Value of `metric` for an entity at `entity` seen from `camera`, `None` if the camera can't
+/// tell.
+fn metric_value(
+    metric: LodMetric,
+    entity: &GlobalTransform,
+    (camera, camera_transform, projection): (&Camera, &GlobalTransform, &Projection),
+) -> Option<f32> {
+    let distance = entity
+        .translation()
+        .distance(camera_transform.translation());
+    let LodMetric::ScreenSpaceError { max_pixels } = metric else {
+        return Some(distance);
+    };
+
+    let viewport_height = camera.physical_viewport_size()?.y as f32;
+    let units_per_pixel = match projection {
+        Projection::Perspective(perspective) => {
+            distance * 2.0 * (perspective.fov * 0.5).tan() / viewport_height
+        }
+        Projection::Orthographic(orthographic) => orthographic.area.height() / viewport_height,
+        Projection::Custom(_) => return None,
+    };
+    // Errors are in mesh units, scaled entities show them larger.
+    Some(max_pixels * units_per_pixel / entity.scale().abs().max_element())
+}
+
+/// Swaps the [`Mesh3d`] of every entity with [`MeshLods`] for the level the closest active camera
+/// asks for, within its [`LodTriangleBudget`], see [`MeshLodPlugin`].
+pub fn switch_mesh_lods(
+    cameras: Query<(
+        Entity,
+        &Camera,
+        &GlobalTransform,
+        &Projection,
+        Option<&LodTriangleBudget>,
+    )>,
+    mut statuses: Query<&mut LodBudgetStatus>,
+    meshes: Res<Assets<Mesh>>,
+    mut query: Query<(
+        Entity,
+        &MeshLods,
+        &GlobalTransform,
+        &mut Mesh3d,
+        &mut CurrentLod,
+    )>,
+) {
+    let cameras: Vec<_> = cameras
+        .iter()
+        .filter(|(_, camera, ..)| camera.is_active)
+        .collect();
+    // Closest camera of every entity and the value of its metric there.
+    let closest: Vec<_> = query
+        .iter()
+        .filter_map(|(entity, lods, transform, ..)| {
+            cameras
+                .iter()
+                .enumerate()
+                .filter_map(|(index, (_, camera, camera_transform, projection, _))| {
+                    let camera = (*camera, *camera_transform, *projection);
+                    Some((index, metric_value(lods.metric, transform, camera)?))
+                })
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|(camera, value)| (entity, camera, value))
+        })
+        .collect();
+
+    let mut biases = vec![1.0; cameras.len()];
+    let mut level_triangles = HashMap::new();
+    for (index, (camera, .., budget)) in cameras.iter().enumerate() {
+        let Some(budget) = budget else {
+            // Left behind by a budget that was removed.
+            if let Ok(mut status) = statuses.get_mut(*camera) {
+                status.set_if_neq(LodBudgetStatus::default());
+            }
+            continue;
+        };
+        let entities: Vec<_> = closest
+            .iter()
+            .filter(|(_, closest, _)| *closest == index)
+            .filter_map(|&(entity, _, value)| {
+                let (_, lods, _, _, current) = query.get(entity).ok()?;
+                // Nothing to switch to, like in the loop below.
+                if lods.levels.is_empty() {
+                    return None;
+                }
+                let triangles: Vec<usize> = lods
+                    .levels
+                    .iter()
+                    .map(|(mesh, _)| {
+                        *level_triangles.entry(mesh.id()).or_insert_with(|| {
+                            meshes.get(mesh).map_or(0, |mesh| {
+                                mesh.indices().map_or(mesh.count_vertices(), Indices::len) / 3
+                            })
+                        })
+                    })
+                    .collect();
+                Some((lods, value, current.0, triangles))
+            })
+            .collect();
+        let (bias, triangles) = solve_bias(budget, |bias| {
+            entities
+                .iter()
+                .map(|(lods, value, current, triangles)| {
+                    triangles[lods.select_level(value * bias, *current)]
+                })
+                .sum()
+        });
+        biases[index] = bias;
+        if let Ok(mut status) = statuses.get_mut(*camera) {
+            status.set_if_neq(LodBudgetStatus { bias, triangles });
+        }
+    }
+
+    for (entity, camera, value) in closest {
+        let Ok((_, lods, _, mut mesh3d, mut current)) = query.get_mut(entity) else {
+            continue;
+        };
+        let level = lods.select_level(value * biases[camera], current.0);
+        let Some((mesh, _)) = lods.levels.get(level) else {
+            continue;
+        };
+        if mesh3d.0 != *mesh {
+            mesh3d.0 = mesh.clone();
+        }
+        current.set_if_neq(CurrentLod(level));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+    use crate::test_util::sphere;
+
+    #[test]
+    fn from_mesh_adds_every_level() {
+        let mut meshes = Assets::<Mesh>::default();
+        let lods =
+            MeshLods::from_mesh(&sphere(8), &LodChainParams::default(), &mut meshes).unwrap();
+        assert_eq!(lods.levels.len(), 4);
+        assert!(lods.levels.iter().all(|(mesh, _)| meshes.contains(mesh)));
+        assert!(
+            lods.levels
+                .windows(2)
+                .all(|levels| levels[0].1 <= levels[1].1)
+        );
+    }
+
+    #[test]
+    fn budget_skips_entities_without_levels() {
+        let mut world = World::new();
+        world.init_resource::<Assets<Mesh>>();
+        world.spawn((
+            Camera::default(),
+            GlobalTransform::default(),
+            Projection::default(),
+            LodTriangleBudget::new(0),
+        ));
+        let entity = world
+            .spawn((
+                MeshLods::from_distances([]),
+                Mesh3d::default(),
+                GlobalTransform::default(),
+            ))
+            .id();
+        world.run_system_once(switch_mesh_lods).unwrap();
+        assert_eq!(world.get::<CurrentLod>(entity), Some(&CurrentLod(0)));
+    }
+}
@@ -0,0 +1,85 @@
+use crate::adjacency::TriangleAdjacency;
+
+/// Edges that keep a mesh from being a closed 2-manifold, with vertices compared by position so
+/// attribute seams don't count. Edges are given by the first vertex of each position, triangles
+/// with two corners at the same position are ignored.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ManifoldStatus {
+    /// Edges used by a single triangle.
+    pub boundary_edges: Vec<[u32; 2]>,
+    /// Edges used by more than two triangles.
+    pub non_manifold_edges: Vec<[u32; 2]>,
+}
+
+impl ManifoldStatus {
+    /// Whether every edge is used by at most two triangles.
+    pub fn is_manifold(&self) -> bool {
+        self.non_manifold_edges.is_empty()
+    }
+
+    /// Whether every edge is used by exactly two triangles, i.e. the mesh is closed.
+    pub fn is_watertight(&self) -> bool {
+        self.is_manifold() && self.boundary_edges.is_empty()
+    }
+
+    /// Whether `self` has no violations that `before` didn't have: manifold meshes stay
+    /// manifold and watertight meshes stay watertight.
+    pub fn preserves(&self, before: &ManifoldStatus) -> bool {
+        self.non_manifold_edges.len() <= before.non_manifold_edges.len()
+            && (!before.is_watertight() || self.is_watertight())
+    }
+
+    pub(crate) fn new(indices: &[u32], positions: &[[f32; 3]]) -> Self {
+        let welded = TriangleAdjacency::new(indices, positions);
+        let degenerate = degenerate_triangles(indices, &welded);
+        let mut status = ManifoldStatus::default();
+        for edge in welded.edges() {
+            match edge
+                .triangles
+                .iter()
+                .filter(|&&triangle| !degenerate[triangle as usize])
+                .count()
+            {
+                0 => {}
+                1 => status.boundary_edges.push(edge.vertices),
+                2 => {}
+                _ => status.non_manifold_edges.push(edge.vertices),
+            }
+        }
+        status
+    }
+}
+
+/// Locks every vertex at the position of a vertex of a non-manifold edge.
+pub(crate) fn lock_non_manifold_vertices(
+    indices: &[u32],
+    positions: &[[f32; 3]],
+    locks: &mut [bool],
+) {
+    let welded = TriangleAdjacency::new(indices, positions);
+    let degenerate = degenerate_triangles(indices, &welded);
+    let mut welded_locks = vec![false; welded.welded_vertex_count];
+    for ((a, b), faces) in welded.welded_edges() {
+        if faces
+            .iter()
+            .filter(|&&triangle| !degenerate[triangle as usize])
+            .count()
+            > 2
+        {
+            welded_locks[a as usize] = true;
+            welded_locks[b as usize] = true;
+        }
+    }
+    welded.flag_welded(&welded_locks, locks);
+}
+
+/// Triangles with two corners welded together, they don't cover any area.
+fn degenerate_triangles(indices: &[u32], welded: &TriangleAdjacency) -> Vec<bool> {
+    indices
+        .chunks_exact(3)
+        .map(|triangle| {
+            let [a, b, c] = [0, 1, 2].map(|corner| welded.remap[triangle[corner] as usize]);
+            a == b || b == c || c == a
+        })
+        .collect()
+}
@@ -0,0 +1,262 @@
+use std::io;
+
+use bevy::{
+    asset::{AssetLoader, LoadContext, io::Reader},
+    mesh::Mesh,
+};
+
+use crate::{
+    CompressedAttribute, CompressedIndices, CompressedMesh, MeshExt, OptError,
+    remap::{FNV_OFFSET, fnv1a},
+};
+
+/// Identifies a compressed mesh file, followed by the format version.
+const MAGIC: [u8; 4] = *b"MOPT";
+const VERSION: u32 = 1;
+/// Magic, version and the level count.
+const HEADER_SIZE: usize = 12;
+const CHECKSUM_SIZE: usize = 8;
+
+/// Contents of a `.meshopt` file: a mesh compressed with
+/// [`MeshExt::encode_compressed`] followed by its coarser levels of detail, if any. Loaded with
+/// [`CompressedMeshLoader`] and written by the processor of `MeshoptProcessorPlugin`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CompressedMeshFile {
+    /// LOD0 first, then the coarser levels.
+    pub levels: Vec<CompressedMesh>,
+}
+
+impl CompressedMeshFile {
+    /// Compresses `levels`, LOD0 first.
+    pub fn from_meshes<'a>(levels: impl IntoIterator<Item = &'a Mesh>) -> Result<Self, OptError> {
+        let levels = levels
+            .into_iter()
+            .map(MeshExt::encode_compressed)
+            .collect::<Result<_, _>>()?;
+        Ok(CompressedMeshFile { levels })
+    }
+
+    /// Encodes the levels into a little-endian binary format, read back with
+    /// [`CompressedMeshFile::from_bytes`].
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(self.levels.len() as u32).to_le_bytes());
+        for level in &self.levels {
+            bytes.extend_from_slice(&[level.topology, level.asset_usage]);
+            bytes.extend_from_slice(&level.vertex_count.to_le_bytes());
+            match &level.indices {
+                Some(indices) => {
+                    bytes.extend_from_slice(&[1, indices.wide as u8]);
+                    bytes.extend_from_slice(&indices.count.to_le_bytes());
+                    write_bytes(&mut bytes, &indices.data);
+                }
+                None => bytes.extend_from_slice(&[0, 0]),
+            }
+            bytes.extend_from_slice(&(level.attributes.len() as u32).to_le_bytes());
+            for attribute in &level.attributes {
+                write_bytes(&mut bytes, attribute.name.as_bytes());
+                write_bytes(&mut bytes, attribute.format.as_bytes());
+                write_bytes(&mut bytes, &attribute.data);
+            }
+        }
+
+        bytes.extend_from_slice(&fnv1a(FNV_OFFSET, &bytes).to_le_bytes());
+        bytes
+    }
+
+    /// Decodes levels encoded with [`CompressedMeshFile::to_bytes`].
+    ///
+    /// Fails with [`io::ErrorKind::InvalidData`] on data that is truncated, corrupted or was
+    /// written by another version. The streams themselves aren't known to be valid until they are
+    /// decoded with [`CompressedMesh::decode`].
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<CompressedMeshFile> {
+        let Some((payload, checksum)) = bytes.split_last_chunk::<CHECKSUM_SIZE>() else {
+            return Err(invalid("truncated compressed mesh file"));
+        };
+        if payload.len() < HEADER_SIZE || payload[..4] != MAGIC {
+            return Err(invalid("not a compressed mesh file"));
+        }
+        let mut cursor = Cursor {
+            bytes: payload,
+            offset: 4,
+        };
+        if cursor.u32()? != VERSION {
+            return Err(invalid("compressed mesh file with an unsupported version"));
+        }
+        if fnv1a(FNV_OFFSET, payload) != u64::from_le_bytes(*checksum) {
+            return Err(invalid("compressed mesh file checksum mismatch"));
+        }
+
+        let level_count = cursor.u32()?;
+        let mut levels = Vec::new();
+        for _ in 0..level_count {
+            let [topology, asset_usage] = [cursor.u8()?, cursor.u8()?];
+            let vertex_count = cursor.u32()?;
+            let indices = match [cursor.u8()?, cursor.u8()?] {
+                [0, _] => None,
+                [_, wide] => Some(CompressedIndices {
+                    wide: wide != 0,
+                    count: cursor.u32()?,
+                    data: cursor.bytes()?.to_vec(),
+                }),
+            };
+            let attribute_count = cursor.u32()?;
+            let mut attributes = Vec::new();
+            for _ in 0..attribute_count {
+                attributes.push(CompressedAttribute {
+                    name: cursor.string()?,
+                    format: cursor.string()?,
+                    data: cursor.bytes()?.to_vec(),
+                });
+            }
+            levels.push(CompressedMesh {
+                topology,
+                asset_usage,
+                vertex_count,
+                indices,
+                attributes,
+            });
+        }
+        if cursor.offset != payload.len() {
+            return Err(invalid(
+                "compressed mesh file size doesn't match its levels",
+            ));
+        }
+        Ok(CompressedMeshFile { levels })
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Writes the length of `data` followed by `data`.
+fn write_bytes(bytes: &mut Vec<u8>, data: &[u8]) {
+    bytes.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(data);
+}
+
+/// Reads what [`CompressedMeshFile::to_bytes`] wrote, failing on truncated data.
+struct Cursor<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn take(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        let taken = self
+            .bytes
+            .get(self.offset..self.offset.saturating_add(len))
+            .ok_or_else(|| invalid("truncated compressed mesh file"))?;
+        self.offset += len;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn bytes(&mut self) -> io::Result<&'a [u8]> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        String::from_utf8(self.bytes()?.to_vec())
+            .map_err(|_| invalid("compressed mesh file with a name that isn't UTF-8"))
+    }
+}
+
+/// Loads [`Mesh`]es from `.meshopt` files written with [`CompressedMeshFile::to_bytes`]. Levels
+/// past LOD0 are added as the labeled sub-assets `LOD1`, `LOD2` and so on, e.g.
+/// `rock.meshopt#LOD2`. Only the attributes Bevy defines on [`Mesh`] are recognized, see
+/// [`CompressedMesh::decode`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CompressedMeshLoader;
+
+impl AssetLoader for CompressedMeshLoader {
+    type Asset = Mesh;
+    type Settings = ();
+    type Error = io::Error;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        load_context: &mut LoadContext<'_>,
+    ) -> io::Result<Mesh> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let file = CompressedMeshFile::from_bytes(&bytes)?;
+        let Some((lod0, levels)) = file.levels.split_first() else {
+            return Err(invalid("compressed mesh file without levels"));
+        };
+        for (level, compressed) in levels.iter().enumerate() {
+            load_context.add_labeled_asset(format!("LOD{}", level + 1), compressed.decode()?);
+        }
+        lod0.decode()
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["meshopt"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        SimplifyParams, TargetIndices,
+        test_util::{indices, sphere},
+    };
+
+    fn file() -> CompressedMeshFile {
+        let mut lod1 = sphere(3);
+        lod1.simplify_with_report(&SimplifyParams {
+            target_index_count: TargetIndices::Multiplier(0.5),
+            max_error: 1.0,
+            ..Default::default()
+        })
+        .unwrap();
+        CompressedMeshFile::from_meshes([&sphere(3), &lod1]).unwrap()
+    }
+
+    #[test]
+    fn compressed_mesh_file_round_trips() {
+        let file = file();
+        let loaded = CompressedMeshFile::from_bytes(&file.to_bytes()).unwrap();
+        assert_eq!(loaded, file);
+        let lod0 = loaded.levels[0].decode().unwrap();
+        assert_eq!(indices(&lod0), indices(&sphere(3)));
+        let empty = CompressedMeshFile::default();
+        assert_eq!(
+            CompressedMeshFile::from_bytes(&empty.to_bytes()).unwrap(),
+            empty
+        );
+    }
+
+    #[test]
+    fn truncated_or_flipped_bytes_fail_to_load() {
+        let bytes = file().to_bytes();
+        for len in 0..bytes.len() {
+            assert!(
+                CompressedMeshFile::from_bytes(&bytes[..len]).is_err(),
+                "loaded {len} bytes"
+            );
+        }
+        for offset in 0..bytes.len() {
+            let mut flipped = bytes.clone();
+            flipped[offset] ^= 0x01;
+            assert!(
+                CompressedMeshFile::from_bytes(&flipped).is_err(),
+                "loaded flip at {offset}"
+            );
+        }
+    }
+}
@@ -0,0 +1,396 @@
+use bevy::{
+    asset::Asset,
+    ecs::component::Component,
+    math::Vec3,
+    mesh::Mesh,
+    prelude::{Deref, DerefMut},
+    reflect::TypePath,
+};
+
+use crate::{OptError, mesh_indices_widened, mesh_positions};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct MeshletParams {
+    /// Maximum vertices per meshlet, between 3 and 256.
+    pub max_vertices: usize,
+    /// Maximum triangles per meshlet, a multiple of 4 up to 512 so the packed triangles stay
+    /// 4 byte aligned.
+    pub max_triangles: usize,
+    /// Balances spatial locality against normal cone tightness, `0.0` ignores the cones and `1.0`
+    /// makes them as tight as possible which helps cone culling.
+    pub cone_weight: f32,
+}
+
+impl Default for MeshletParams {
+    fn default() -> Self {
+        MeshletParams {
+            max_vertices: 64,
+            max_triangles: 124,
+            cone_weight: 0.25,
+        }
+    }
+}
+
+impl MeshletParams {
+    fn validate(&self) -> Result<(), OptError> {
+        let valid = (3..=256).contains(&self.max_vertices)
+            && (4..=512).contains(&self.max_triangles)
+            && self.max_triangles.is_multiple_of(4)
+            && (0.0..=1.0).contains(&self.cone_weight);
+        if valid {
+            Ok(())
+        } else {
+            Err(OptError::InvalidMeshletParams)
+        }
+    }
+}
+
+/// Range of a single meshlet within [`Meshlets::vertices`] and [`Meshlets::triangles`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct Meshlet {
+    pub vertex_offset: u32,
+    pub vertex_count: u32,
+    pub triangle_offset: u32,
+    pub triangle_count: u32,
+}
+
+/// Bounding sphere and normal cone of a meshlet, in mesh space.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct MeshletBounds {
+    pub center: Vec3,
+    pub radius: f32,
+    pub cone_apex: Vec3,
+    pub cone_axis: Vec3,
+    /// Cosine of the cone's half-angle, `1.0` when the triangles face too many directions to ever
+    /// be cone culled.
+    pub cone_cutoff: f32,
+}
+
+impl MeshletBounds {
+    /// Whether every triangle of the meshlet faces away from a camera at `camera_position`
+    /// (in mesh space), meaning the whole meshlet can be skipped.
+    pub fn is_backfacing(&self, camera_position: Vec3) -> bool {
+        let offset = self.center - camera_position;
+        offset.dot(self.cone_axis) >= self.cone_cutoff * offset.length() + self.radius
+    }
+}
+
+/// Bounds of every meshlet of a mesh without the meshlets themselves, for GPU-driven culling
+/// pipelines that only test the bounds. As a component it is uploaded by the
+/// [`MeshletRenderPlugin`](crate::MeshletRenderPlugin), as an asset it can be shared by every entity
+/// drawing the mesh.
+#[derive(Component, Asset, TypePath, Deref, DerefMut, Debug, Clone, PartialEq, Default)]
+pub struct MeshletCullingBounds(pub Vec<MeshletBounds>);
+
+impl From<&Meshlets> for MeshletCullingBounds {
+    fn from(meshlets: &Meshlets) -> Self {
+        MeshletCullingBounds(meshlets.bounds.clone())
+    }
+}
+
+/// Mesh split into small clusters of triangles for GPU-driven rendering and fine-grained culling.
+#[derive(Component, Debug, Clone, PartialEq, Default)]
+pub struct Meshlets {
+    pub meshlets: Vec<Meshlet>,
+    /// Vertex indices into the source mesh, referenced by the meshlets.
+    pub vertices: Vec<u32>,
+    /// Meshlet-local triangle indices into `vertices`, three per triangle.
+    pub triangles: Vec<u8>,
+    /// Bounds of every meshlet, parallel to `meshlets`, see [`MeshletCullingBounds`] for them
+    /// alone.
+    pub bounds: Vec<MeshletBounds>,
+}
+
+impl Meshlets {
+    pub fn len(&self) -> usize {
+        self.meshlets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.meshlets.is_empty()
+    }
+
+    /// Vertex indices of a meshlet into the source mesh.
+    pub fn meshlet_vertices(&self, meshlet: usize) -> &[u32] {
+        let meshlet = self.meshlets[meshlet];
+        &self.vertices[meshlet.vertex_offset as usize..][..meshlet.vertex_count as usize]
+    }
+
+    /// Meshlet-local triangle indices of a meshlet.
+    pub fn meshlet_triangles(&self, meshlet: usize) -> &[u8] {
+        let meshlet = self.meshlets[meshlet];
+        &self.triangles[meshlet.triangle_offset as usize..][..meshlet.triangle_count as usize * 3]
+    }
+
+    /// Reorders the meshlets along a Morton curve through their bounding sphere centers, so that
+    /// meshlets close in space are also close in [`Meshlets::meshlets`], which helps streaming and
+    /// culling neighboring meshlets together.
+    ///
+    /// Only the meshlet descriptors and their bounds move, [`Meshlets::vertices`] and
+    /// [`Meshlets::triangles`] are left as is. The bounds are recomputed from `mesh` if they don't
+    /// match the meshlets.
+    pub fn sort_spatially(&mut self, mesh: &Mesh) -> Result<(), OptError> {
+        if self.bounds.len() != self.meshlets.len() {
+            let positions = mesh_positions(mesh)?;
+            if let Some(&vertex) = self
+                .vertices
+                .iter()
+                .find(|&&vertex| vertex as usize >= positions.len())
+            {
+                return Err(OptError::IndexOutOfBounds(vertex));
+            }
+            self.bounds = meshlet_bounds(self, &position_adapter(positions)?);
+        }
+
+        let (min, max) = self.bounds.iter().fold(
+            (Vec3::INFINITY, Vec3::NEG_INFINITY),
+            |(min, max), bounds| (min.min(bounds.center), max.max(bounds.center)),
+        );
+        let mut order: Vec<(u32, usize)> = self
+            .bounds
+            .iter()
+            .enumerate()
+            .map(|(meshlet, bounds)| (morton_code(bounds.center, min, max), meshlet))
+            .collect();
+        order.sort_unstable();
+
+        self.meshlets = order
+            .iter()
+            .map(|&(_, meshlet)| self.meshlets[meshlet])
+            .collect();
+        self.bounds = order
+            .iter()
+            .map(|&(_, meshlet)| self.bounds[meshlet])
+            .collect();
+        Ok(())
+    }
+}
+
+pub(crate) fn build_meshlets(mesh: &Mesh, params: &MeshletParams) -> Result<Meshlets, OptError> {
+    params.validate()?;
+    let positions = mesh_positions(mesh)?;
+    let indices = mesh_indices_widened(mesh)?;
+    let adapter = position_adapter(positions)?;
+
+    let built = meshopt::build_meshlets(
+        &indices,
+        &adapter,
+        params.max_vertices,
+        params.max_triangles,
+        params.cone_weight,
+    );
+
+    let mut meshlets = Meshlets {
+        meshlets: built
+            .meshlets
+            .iter()
+            .map(|meshlet| Meshlet {
+                vertex_offset: meshlet.vertex_offset,
+                vertex_count: meshlet.vertex_count,
+                triangle_offset: meshlet.triangle_offset,
+                triangle_count: meshlet.triangle_count,
+            })
+            .collect(),
+        vertices: built.vertices,
+        triangles: built.triangles,
+        bounds: Vec::new(),
+    };
+    meshlets.bounds = meshlet_bounds(&meshlets, &adapter);
+    Ok(meshlets)
+}
+
+fn position_adapter(positions: &[[f32; 3]]) -> Result<meshopt::VertexDataAdapter<'_>, OptError> {
+    meshopt::VertexDataAdapter::new(meshopt::typed_to_bytes(positions), size_of::<[f32; 3]>(), 0)
+        .map_err(|_| OptError::MissingPositions)
+}
+
+fn meshlet_bounds(meshlets: &Meshlets, adapter: &meshopt::VertexDataAdapter) -> Vec<MeshletBounds> {
+    (0..meshlets.len())
+        .map(|meshlet| {
+            let bounds = meshopt::compute_meshlet_bounds(
+                meshopt::Meshlet {
+                    vertices: meshlets.meshlet_vertices(meshlet),
+                    triangles: meshlets.meshlet_triangles(meshlet),
+                },
+                adapter,
+            );
+            MeshletBounds {
+                center: Vec3::from_array(bounds.center),
+                radius: bounds.radius,
+                cone_apex: Vec3::from_array(bounds.cone_apex),
+                cone_axis: Vec3::from_array(bounds.cone_axis),
+                cone_cutoff: bounds.cone_cutoff,
+            }
+        })
+        .collect()
+}
+
+/// Interleaves the bits of the position's coordinates, quantized to 10 bits each within
+/// `min..max`, so that positions close in space get close codes.
+fn morton_code(position: Vec3, min: Vec3, max: Vec3) -> u32 {
+    let normalized = (position - min) / (max - min).max(Vec3::splat(f32::EPSILON));
+    let quantized = (normalized.clamp(Vec3::ZERO, Vec3::ONE) * 1023.0).as_uvec3();
+
+    // Spreads the low 10 bits of `x` two bits apart.
+    fn spread(mut x: u32) -> u32 {
+        x &= 0x0000_03ff;
+        x = (x ^ (x << 16)) & 0xff00_00ff;
+        x = (x ^ (x << 8)) & 0x0300_f00f;
+        x = (x ^ (x << 4)) & 0x030c_30c3;
+        x = (x ^ (x << 2)) & 0x0924_9249;
+        x
+    }
+    spread(quantized.x) | (spread(quantized.y) << 1) | (spread(quantized.z) << 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        MeshExt,
+        test_util::{grid, indices, positions, sphere},
+        vertex::sort_triangles,
+    };
+
+    #[test]
+    fn every_triangle_is_in_exactly_one_meshlet() {
+        let mesh = sphere(8);
+        let params = MeshletParams::default();
+        let meshlets = mesh.build_meshlets(&params).unwrap();
+        let positions = positions(&mesh);
+        assert!(meshlets.len() > 1);
+        assert_eq!(meshlets.bounds.len(), meshlets.len());
+
+        let mut triangles = Vec::new();
+        for meshlet in 0..meshlets.len() {
+            let vertices = meshlets.meshlet_vertices(meshlet);
+            let local = meshlets.meshlet_triangles(meshlet);
+            assert!(vertices.len() <= params.max_vertices);
+            assert!(local.len() / 3 <= params.max_triangles);
+            assert!(
+                vertices
+                    .iter()
+                    .all(|&vertex| (vertex as usize) < positions.len())
+            );
+            assert!(
+                local
+                    .iter()
+                    .all(|&vertex| (vertex as usize) < vertices.len())
+            );
+            triangles.extend(local.iter().map(|&vertex| vertices[vertex as usize]));
+
+            let bounds = meshlets.bounds[meshlet];
+            for &vertex in vertices {
+                let distance = Vec3::from_array(positions[vertex as usize]).distance(bounds.center);
+                assert!(distance <= bounds.radius * 1.001);
+            }
+        }
+
+        let mut source = indices(&mesh);
+        sort_triangles(&mut source);
+        sort_triangles(&mut triangles);
+        assert_eq!(triangles, source);
+    }
+
+    #[test]
+    fn limits_outside_of_meshoptimizer_fail() {
+        for params in [
+            MeshletParams {
+                max_vertices: 257,
+                ..Default::default()
+            },
+            MeshletParams {
+                max_triangles: 126,
+                ..Default::default()
+            },
+            MeshletParams {
+                cone_weight: 1.5,
+                ..Default::default()
+            },
+        ] {
+            let result = sphere(2).build_meshlets(&params);
+            assert!(matches!(result, Err(OptError::InvalidMeshletParams)));
+        }
+    }
+
+    /// Triangles of every meshlet as indices into the mesh, along with its bounds.
+    fn resolved(meshlets: &Meshlets) -> Vec<(Vec<u32>, MeshletBounds)> {
+        (0..meshlets.len())
+            .map(|meshlet| {
+                let vertices = meshlets.meshlet_vertices(meshlet);
+                let triangles = meshlets
+                    .meshlet_triangles(meshlet)
+                    .iter()
+                    .map(|&vertex| vertices[vertex as usize])
+                    .collect();
+                (triangles, meshlets.bounds[meshlet])
+            })
+            .collect()
+    }
+
+    fn gcd(a: usize, b: usize) -> usize {
+        if b == 0 { a } else { gcd(b, a % b) }
+    }
+
+    /// Mean distance between the centers of consecutive meshlets.
+    fn mean_step(meshlets: &Meshlets) -> f32 {
+        let steps = meshlets.bounds.windows(2);
+        let count = steps.len() as f32;
+        steps
+            .map(|pair| pair[0].center.distance(pair[1].center))
+            .sum::<f32>()
+            / count
+    }
+
+    #[test]
+    fn spatial_sort_follows_the_morton_curve() {
+        let mesh = grid(64);
+        let mut meshlets = mesh.build_meshlets(&MeshletParams::default()).unwrap();
+        // Strided through the meshlets, so neighbors in the list are far apart.
+        let count = meshlets.len();
+        let stride = (count / 3..)
+            .find(|stride| gcd(*stride, count) == 1)
+            .unwrap();
+        let order: Vec<usize> = (0..count).map(|i| i * stride % count).collect();
+        meshlets.meshlets = order.iter().map(|&i| meshlets.meshlets[i]).collect();
+        meshlets.bounds = order.iter().map(|&i| meshlets.bounds[i]).collect();
+        let shuffled_step = mean_step(&meshlets);
+
+        meshlets.sort_spatially(&mesh).unwrap();
+        let (min, max) = meshlets.bounds.iter().fold(
+            (Vec3::INFINITY, Vec3::NEG_INFINITY),
+            |(min, max), bounds| (min.min(bounds.center), max.max(bounds.center)),
+        );
+        let codes: Vec<u32> = meshlets
+            .bounds
+            .iter()
+            .map(|bounds| morton_code(bounds.center, min, max))
+            .collect();
+        assert!(codes.is_sorted());
+        assert!(mean_step(&meshlets) < shuffled_step * 0.5);
+    }
+
+    #[test]
+    fn spatial_sort_keeps_offsets_into_the_shared_arrays() {
+        let mesh = sphere(8);
+        let built = mesh.build_meshlets(&MeshletParams::default()).unwrap();
+        let mut sorted_built = resolved(&built);
+        sorted_built.sort_by(|a, b| a.0.cmp(&b.0));
+
+        // With the bounds dropped they are recomputed from the mesh.
+        for bounds in [built.bounds.clone(), Vec::new()] {
+            let mut meshlets = Meshlets {
+                bounds,
+                ..built.clone()
+            };
+            meshlets.sort_spatially(&mesh).unwrap();
+            assert_ne!(meshlets.meshlets, built.meshlets);
+            assert_eq!(meshlets.vertices, built.vertices);
+            assert_eq!(meshlets.triangles, built.triangles);
+
+            let mut sorted = resolved(&meshlets);
+            sorted.sort_by(|a, b| a.0.cmp(&b.0));
+            assert_eq!(sorted, sorted_built);
+        }
+    }
+}
@@ -0,0 +1,273 @@
+use std::io;
+
+use bevy::{
+    asset::{Asset, AssetLoader, LoadContext, io::Reader},
+    math::Vec3,
+    prelude::{Deref, DerefMut},
+    reflect::TypePath,
+};
+
+use crate::{
+    Meshlet, MeshletBounds, Meshlets,
+    remap::{FNV_OFFSET, fnv1a},
+};
+
+/// Identifies encoded meshlets, followed by the format version.
+const MAGIC: [u8; 4] = *b"MSLT";
+const VERSION: u32 = 1;
+/// Magic, version and the four counts.
+const HEADER_SIZE: usize = 24;
+const MESHLET_SIZE: usize = 4 * 4;
+const BOUNDS_SIZE: usize = 11 * 4;
+const CHECKSUM_SIZE: usize = 8;
+
+impl Meshlets {
+    /// Encodes the meshlets, their vertices, triangles and bounds into a little-endian binary
+    /// format, read back with [`Meshlets::from_bytes`].
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(
+            HEADER_SIZE
+                + self.meshlets.len() * MESHLET_SIZE
+                + self.vertices.len() * 4
+                + self.triangles.len()
+                + self.bounds.len() * BOUNDS_SIZE
+                + CHECKSUM_SIZE,
+        );
+        bytes.extend_from_slice(&MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        for count in [
+            self.meshlets.len(),
+            self.vertices.len(),
+            self.triangles.len(),
+            self.bounds.len(),
+        ] {
+            bytes.extend_from_slice(&(count as u32).to_le_bytes());
+        }
+
+        for meshlet in &self.meshlets {
+            for value in [
+                meshlet.vertex_offset,
+                meshlet.vertex_count,
+                meshlet.triangle_offset,
+                meshlet.triangle_count,
+            ] {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        for vertex in &self.vertices {
+            bytes.extend_from_slice(&vertex.to_le_bytes());
+        }
+        bytes.extend_from_slice(&self.triangles);
+        for bounds in &self.bounds {
+            let values: [f32; 11] = [
+                bounds.center.x,
+                bounds.center.y,
+                bounds.center.z,
+                bounds.radius,
+                bounds.cone_apex.x,
+                bounds.cone_apex.y,
+                bounds.cone_apex.z,
+                bounds.cone_axis.x,
+                bounds.cone_axis.y,
+                bounds.cone_axis.z,
+                bounds.cone_cutoff,
+            ];
+            for value in values {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+
+        bytes.extend_from_slice(&fnv1a(FNV_OFFSET, &bytes).to_le_bytes());
+        bytes
+    }
+
+    /// Decodes meshlets encoded with [`Meshlets::to_bytes`].
+    ///
+    /// Fails with [`io::ErrorKind::InvalidData`] on data that is truncated, corrupted or was
+    /// written by another version, and on meshlets whose ranges or triangle indices fall outside
+    /// of [`Meshlets::vertices`] and [`Meshlets::triangles`]. The vertex indices into the source
+    /// mesh aren't known to be valid until they are checked against it.
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Meshlets> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message);
+
+        let Some((payload, checksum)) = bytes.split_last_chunk::<CHECKSUM_SIZE>() else {
+            return Err(invalid("truncated meshlets"));
+        };
+        if payload.len() < HEADER_SIZE || payload[..4] != MAGIC {
+            return Err(invalid("not encoded meshlets"));
+        }
+        let u32_at =
+            |offset: usize| u32::from_le_bytes(payload[offset..offset + 4].try_into().unwrap());
+        if u32_at(4) != VERSION {
+            return Err(invalid("meshlets encoded with an unsupported version"));
+        }
+        if fnv1a(FNV_OFFSET, payload) != u64::from_le_bytes(*checksum) {
+            return Err(invalid("meshlets checksum mismatch"));
+        }
+
+        let [meshlet_count, vertex_count, triangle_len, bounds_count] =
+            [8, 12, 16, 20].map(|offset| u32_at(offset) as usize);
+        if bounds_count != 0 && bounds_count != meshlet_count {
+            return Err(invalid("meshlet bounds don't match the meshlets"));
+        }
+        // Computed in u64 so absurd counts can't overflow on 32-bit targets.
+        let expected_len = HEADER_SIZE as u64
+            + meshlet_count as u64 * MESHLET_SIZE as u64
+            + vertex_count as u64 * 4
+            + triangle_len as u64
+            + bounds_count as u64 * BOUNDS_SIZE as u64;
+        if payload.len() as u64 != expected_len {
+            return Err(invalid("meshlets size doesn't match their counts"));
+        }
+
+        let mut offset = HEADER_SIZE;
+        let mut next_u32 = || {
+            offset += 4;
+            u32_at(offset - 4)
+        };
+        let meshlets: Vec<Meshlet> = (0..meshlet_count)
+            .map(|_| Meshlet {
+                vertex_offset: next_u32(),
+                vertex_count: next_u32(),
+                triangle_offset: next_u32(),
+                triangle_count: next_u32(),
+            })
+            .collect();
+        let vertices: Vec<u32> = (0..vertex_count).map(|_| next_u32()).collect();
+        let triangles = payload[offset..offset + triangle_len].to_vec();
+        offset += triangle_len;
+        let mut next_f32 = || {
+            offset += 4;
+            f32::from_bits(u32_at(offset - 4))
+        };
+        let bounds: Vec<MeshletBounds> = (0..bounds_count)
+            .map(|_| {
+                let values: [f32; 11] = std::array::from_fn(|_| next_f32());
+                MeshletBounds {
+                    center: Vec3::from_slice(&values[0..3]),
+                    radius: values[3],
+                    cone_apex: Vec3::from_slice(&values[4..7]),
+                    cone_axis: Vec3::from_slice(&values[7..10]),
+                    cone_cutoff: values[10],
+                }
+            })
+            .collect();
+
+        for meshlet in &meshlets {
+            let vertex_end = meshlet.vertex_offset as u64 + meshlet.vertex_count as u64;
+            let triangle_end = meshlet.triangle_offset as u64 + meshlet.triangle_count as u64 * 3;
+            if vertex_end > vertices.len() as u64 || triangle_end > triangles.len() as u64 {
+                return Err(invalid("meshlet range out of bounds"));
+            }
+            let local = &triangles[meshlet.triangle_offset as usize..triangle_end as usize];
+            if local
+                .iter()
+                .any(|&vertex| vertex as u32 >= meshlet.vertex_count)
+            {
+                return Err(invalid("meshlet triangle index out of bounds"));
+            }
+        }
+
+        Ok(Meshlets {
+            meshlets,
+            vertices,
+            triangles,
+            bounds,
+        })
+    }
+}
+
+/// [`Meshlets`] loaded as an asset from a `.meshlets` file written with [`Meshlets::to_bytes`],
+/// so they can be shipped next to the mesh they were built from instead of being built at
+/// runtime. Registered by [`MeshoptPlugin`](crate::MeshoptPlugin).
+#[derive(Asset, TypePath, Deref, DerefMut, Debug, Clone, PartialEq, Default)]
+pub struct MeshletsAsset(pub Meshlets);
+
+/// Loads [`MeshletsAsset`]s, see [`Meshlets::from_bytes`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MeshletsLoader;
+
+impl AssetLoader for MeshletsLoader {
+    type Asset = MeshletsAsset;
+    type Settings = ();
+    type Error = io::Error;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> io::Result<MeshletsAsset> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Meshlets::from_bytes(&bytes).map(MeshletsAsset)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["meshlets"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MeshExt, MeshletParams, test_util::sphere};
+
+    fn meshlets() -> Meshlets {
+        sphere(4).build_meshlets(&MeshletParams::default()).unwrap()
+    }
+
+    #[test]
+    fn meshlets_round_trip() {
+        let mut meshlets = meshlets();
+        assert!(meshlets.len() > 1 && !meshlets.bounds.is_empty());
+        assert_eq!(
+            Meshlets::from_bytes(&meshlets.to_bytes()).unwrap(),
+            meshlets
+        );
+        meshlets.bounds.clear();
+        assert_eq!(
+            Meshlets::from_bytes(&meshlets.to_bytes()).unwrap(),
+            meshlets
+        );
+    }
+
+    #[test]
+    fn truncated_or_flipped_bytes_fail_to_load() {
+        let bytes = meshlets().to_bytes();
+        for len in 0..bytes.len() {
+            assert!(
+                Meshlets::from_bytes(&bytes[..len]).is_err(),
+                "loaded {len} bytes"
+            );
+        }
+        for offset in 0..bytes.len() {
+            let mut flipped = bytes.clone();
+            flipped[offset] ^= 0x01;
+            assert!(
+                Meshlets::from_bytes(&flipped).is_err(),
+                "loaded flip at {offset}"
+            );
+        }
+    }
+
+    #[test]
+    fn out_of_bounds_ranges_fail_to_load() {
+        let corruptions: [fn(&mut Meshlets); 4] = [
+            |meshlets| meshlets.meshlets[1].vertex_offset = u32::MAX,
+            |meshlets| meshlets.meshlets[1].triangle_count += 1_000,
+            |meshlets| meshlets.triangles[5] = u8::MAX,
+            |meshlets| {
+                meshlets.bounds.pop();
+            },
+        ];
+        for (i, corrupt) in corruptions.into_iter().enumerate() {
+            let mut meshlets = meshlets();
+            corrupt(&mut meshlets);
+            assert!(
+                Meshlets::from_bytes(&meshlets.to_bytes()).is_err(),
+                "corruption {i} loaded"
+            );
+        }
+    }
+}
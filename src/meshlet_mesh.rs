@@ -0,0 +1,74 @@
+use bevy::{
+    mesh::{
+        Indices, Mesh, MeshVertexAttribute, PrimitiveTopology, VertexAttributeValues, VertexFormat,
+    },
+    pbr::experimental::meshlet::MeshletMesh,
+};
+
+use crate::{
+    MeshExt, OptError, formats::widen, mesh_positions, validate_indices, with_u32_indices,
+};
+
+/// Bevy's [`MeshletMesh`] of `mesh`, after bringing it into the shape its conversion insists on:
+/// only `u32` indexed `Float32x3` positions and normals and `Float32x2` UVs. Other attributes are
+/// dropped, normalized normals and UVs are widened, missing normals are computed smooth and
+/// missing UVs are set to zero. Non-indexed meshes are indexed by merging identical vertices.
+pub(crate) fn to_meshlet_mesh(
+    mesh: &Mesh,
+    vertex_position_quantization_factor: u8,
+) -> Result<MeshletMesh, OptError> {
+    let positions = mesh_positions(mesh)?;
+    let mut prepared = Mesh::new(PrimitiveTopology::TriangleList, mesh.asset_usage)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions.clone());
+    match widened(mesh, Mesh::ATTRIBUTE_NORMAL) {
+        Some(VertexAttributeValues::Float32x3(normals)) => {
+            prepared.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+        }
+        None => {}
+        Some(values) => {
+            return Err(OptError::InvalidMeshletAttribute {
+                attribute: Mesh::ATTRIBUTE_NORMAL.name,
+                format: VertexFormat::from(&values),
+            });
+        }
+    }
+    match widened(mesh, Mesh::ATTRIBUTE_UV_0) {
+        Some(VertexAttributeValues::Float32x2(uvs)) => {
+            prepared.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+        }
+        None => prepared.insert_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0.0f32; 2]; positions.len()]),
+        Some(values) => {
+            return Err(OptError::InvalidMeshletAttribute {
+                attribute: Mesh::ATTRIBUTE_UV_0.name,
+                format: VertexFormat::from(&values),
+            });
+        }
+    }
+    if let Some(indices) = mesh.indices() {
+        prepared.insert_indices(indices.clone());
+    }
+
+    with_u32_indices(&mut prepared, false, |prepared| {
+        if let Some(Indices::U32(indices)) = prepared.indices() {
+            validate_indices(indices, prepared.count_vertices())?;
+        }
+        // After indexing, so triangles sharing a merged vertex are smoothed together.
+        if prepared.attribute(Mesh::ATTRIBUTE_NORMAL).is_none() {
+            prepared.compute_smooth_normals();
+        }
+        Ok(())
+    })?;
+    // `u16` indices were narrowed back.
+    prepared.assert_indices_u32();
+
+    Ok(
+        MeshletMesh::from_mesh(&prepared, vertex_position_quantization_factor)
+            .expect("mesh was prepared for the meshlet conversion"),
+    )
+}
+
+/// Values of `attribute` in `f32`, widened from normalized formats.
+fn widened(mesh: &Mesh, attribute: MeshVertexAttribute) -> Option<VertexAttributeValues> {
+    let values = mesh.attribute(attribute.id)?;
+    Some(widen(values).unwrap_or_else(|| values.clone()))
+}
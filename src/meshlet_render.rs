@@ -0,0 +1,366 @@
+use bevy::{
+    app::{App, Plugin},
+    ecs::{prelude::*, query::QueryItem},
+    render::{
+        Render, RenderApp, RenderSystems,
+        extract_component::{ExtractComponent, ExtractComponentPlugin},
+        render_resource::{
+            BindGroupEntry, BindGroupLayoutEntry, BindingType, Buffer, BufferBindingType,
+            BufferInitDescriptor, BufferUsages, ShaderStages, ShaderType,
+        },
+        renderer::RenderDevice,
+    },
+};
+use bytemuck::{Pod, Zeroable};
+
+use crate::{MeshletBounds, MeshletCullingBounds, Meshlets};
+
+/// Uploads the [`Meshlets`] of every entity as storage buffers for custom mesh shader or
+/// GPU culling pipelines, see [`MeshletBuffers`], and the [`MeshletCullingBounds`] of every entity
+/// as a [`MeshletBoundsBuffer`].
+///
+/// Meshlets are packed during extraction and uploaded once when they are added or changed.
+pub struct MeshletRenderPlugin;
+
+impl Plugin for MeshletRenderPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((
+            ExtractComponentPlugin::<Meshlets>::default(),
+            ExtractComponentPlugin::<MeshletCullingBounds>::default(),
+        ));
+
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app.add_systems(
+            Render,
+            (prepare_meshlet_buffers, prepare_meshlet_bounds_buffers)
+                .in_set(RenderSystems::PrepareResources),
+        );
+    }
+}
+
+/// Meshlet descriptor as laid out in [`MeshletBuffers::meshlets`], 16 bytes, matching this WGSL
+/// struct:
+///
+/// ```wgsl
+/// struct Meshlet {
+///     vertex_offset: u32,
+///     vertex_count: u32,
+///     triangle_offset: u32,
+///     triangle_count: u32,
+/// }
+/// ```
+///
+/// Offsets index into [`MeshletBuffers::vertices`] and into the bytes of
+/// [`MeshletBuffers::triangles`]. Also a [`ShaderType`], for writing into a `StorageBuffer`.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Pod, Zeroable, ShaderType)]
+pub struct GpuMeshlet {
+    pub vertex_offset: u32,
+    pub vertex_count: u32,
+    pub triangle_offset: u32,
+    pub triangle_count: u32,
+}
+
+/// Meshlet bounds as laid out in [`MeshletBuffers::bounds`], 48 bytes, matching this WGSL struct:
+///
+/// ```wgsl
+/// struct MeshletBounds {
+///     center: vec3<f32>,
+///     radius: f32,
+///     cone_apex: vec3<f32>,
+///     cone_cutoff: f32,
+///     cone_axis: vec3<f32>,
+/// }
+/// ```
+///
+/// Also a [`ShaderType`] with the same layout, for writing into a `StorageBuffer` with
+/// [`MeshletCullingBounds::packed`].
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Default, Pod, Zeroable, ShaderType)]
+pub struct GpuMeshletBounds {
+    pub center: [f32; 3],
+    pub radius: f32,
+    pub cone_apex: [f32; 3],
+    pub cone_cutoff: f32,
+    pub cone_axis: [f32; 3],
+    pub _padding: f32,
+}
+
+impl From<&MeshletBounds> for GpuMeshletBounds {
+    fn from(bounds: &MeshletBounds) -> Self {
+        GpuMeshletBounds {
+            center: bounds.center.to_array(),
+            radius: bounds.radius,
+            cone_apex: bounds.cone_apex.to_array(),
+            cone_cutoff: bounds.cone_cutoff,
+            cone_axis: bounds.cone_axis.to_array(),
+            _padding: 0.0,
+        }
+    }
+}
+
+impl MeshletCullingBounds {
+    /// Bounds laid out for upload, one [`GpuMeshletBounds`] per meshlet.
+    pub fn packed(&self) -> Vec<GpuMeshletBounds> {
+        self.iter().map(GpuMeshletBounds::from).collect()
+    }
+}
+
+/// [`Meshlets`] packed for upload, extracted to the render world whenever they change.
+#[derive(Component, Debug, Clone, PartialEq, Default)]
+pub struct PackedMeshlets {
+    pub meshlets: Vec<GpuMeshlet>,
+    pub vertices: Vec<u32>,
+    /// Meshlet-local triangle indices, four bytes per `u32` in little-endian order and padded with
+    /// zeros to a multiple of four.
+    pub triangles: Vec<u32>,
+    /// Empty if the meshlets have no bounds.
+    pub bounds: Vec<GpuMeshletBounds>,
+}
+
+impl PackedMeshlets {
+    pub fn new(meshlets: &Meshlets) -> Self {
+        PackedMeshlets {
+            meshlets: meshlets
+                .meshlets
+                .iter()
+                .map(|meshlet| GpuMeshlet {
+                    vertex_offset: meshlet.vertex_offset,
+                    vertex_count: meshlet.vertex_count,
+                    triangle_offset: meshlet.triangle_offset,
+                    triangle_count: meshlet.triangle_count,
+                })
+                .collect(),
+            vertices: meshlets.vertices.clone(),
+            triangles: meshlets
+                .triangles
+                .chunks(4)
+                .map(|bytes| {
+                    let mut word = [0; 4];
+                    word[..bytes.len()].copy_from_slice(bytes);
+                    u32::from_le_bytes(word)
+                })
+                .collect(),
+            bounds: meshlets.bounds.iter().map(GpuMeshletBounds::from).collect(),
+        }
+    }
+}
+
+impl ExtractComponent for Meshlets {
+    type QueryData = &'static Meshlets;
+    type QueryFilter = Changed<Meshlets>;
+    type Out = PackedMeshlets;
+
+    fn extract_component(meshlets: QueryItem<'_, '_, Self::QueryData>) -> Option<PackedMeshlets> {
+        Some(PackedMeshlets::new(meshlets))
+    }
+}
+
+impl ExtractComponent for MeshletCullingBounds {
+    type QueryData = &'static MeshletCullingBounds;
+    type QueryFilter = Changed<MeshletCullingBounds>;
+    type Out = MeshletCullingBounds;
+
+    fn extract_component(
+        bounds: QueryItem<'_, '_, Self::QueryData>,
+    ) -> Option<MeshletCullingBounds> {
+        Some(bounds.clone())
+    }
+}
+
+/// Storage buffers holding the [`Meshlets`] of an entity, on its render world entity.
+///
+/// The layout is stable and meant to be bound as:
+///
+/// ```wgsl
+/// @group(N) @binding(B + 0) var<storage, read> meshlets: array<Meshlet>;
+/// @group(N) @binding(B + 1) var<storage, read> meshlet_vertices: array<u32>;
+/// @group(N) @binding(B + 2) var<storage, read> meshlet_triangles: array<u32>;
+/// @group(N) @binding(B + 3) var<storage, read> meshlet_bounds: array<MeshletBounds>;
+/// ```
+///
+/// with [`GpuMeshlet`] and [`GpuMeshletBounds`] giving the structs. Triangle `t` of a meshlet
+/// uses the bytes `triangle_offset + t * 3 + 0..3` of `meshlet_triangles`, each of them offsetting
+/// `vertex_offset` into `meshlet_vertices`. Storage buffers can't be empty, so empty arrays hold
+/// one zeroed element, use the counts to tell.
+#[derive(Component, Debug, Clone)]
+pub struct MeshletBuffers {
+    pub meshlets: Buffer,
+    pub vertices: Buffer,
+    pub triangles: Buffer,
+    pub bounds: Buffer,
+    pub meshlet_count: u32,
+    /// Whether `bounds` holds one entry per meshlet.
+    pub has_bounds: bool,
+}
+
+impl MeshletBuffers {
+    /// Layout entries of the four buffers, starting at `first_binding`.
+    pub fn bind_group_layout_entries(
+        first_binding: u32,
+        visibility: ShaderStages,
+    ) -> [BindGroupLayoutEntry; 4] {
+        std::array::from_fn(|buffer| BindGroupLayoutEntry {
+            binding: first_binding + buffer as u32,
+            visibility,
+            ty: BindingType::Buffer {
+                ty: BufferBindingType::Storage { read_only: true },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        })
+    }
+
+    /// Bind group entries of the four buffers, matching [`MeshletBuffers::bind_group_layout_entries`].
+    pub fn bind_group_entries(&self, first_binding: u32) -> [BindGroupEntry<'_>; 4] {
+        let buffers = [
+            &self.meshlets,
+            &self.vertices,
+            &self.triangles,
+            &self.bounds,
+        ];
+        std::array::from_fn(|buffer| BindGroupEntry {
+            binding: first_binding + buffer as u32,
+            resource: buffers[buffer].as_entire_binding(),
+        })
+    }
+}
+
+/// Storage buffer holding the [`MeshletCullingBounds`] of an entity, on its render world entity,
+/// bound as `var<storage, read> meshlet_bounds: array<MeshletBounds>` with [`GpuMeshletBounds`]
+/// giving the struct. Holds one zeroed element when there are no bounds, use `count` to tell.
+#[derive(Component, Debug, Clone)]
+pub struct MeshletBoundsBuffer {
+    pub buffer: Buffer,
+    pub count: u32,
+}
+
+fn prepare_meshlet_buffers(
+    mut commands: Commands,
+    render_device: Res<RenderDevice>,
+    packed: Query<(Entity, &PackedMeshlets), Changed<PackedMeshlets>>,
+) {
+    for (entity, packed) in &packed {
+        let storage = |label: &str, contents: &[u8]| {
+            render_device.create_buffer_with_data(&BufferInitDescriptor {
+                label: Some(label),
+                contents,
+                usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+            })
+        };
+        commands.entity(entity).insert(MeshletBuffers {
+            meshlets: storage("meshlets", non_empty(&packed.meshlets)),
+            vertices: storage("meshlet_vertices", non_empty(&packed.vertices)),
+            triangles: storage("meshlet_triangles", non_empty(&packed.triangles)),
+            bounds: storage("meshlet_bounds", non_empty(&packed.bounds)),
+            meshlet_count: packed.meshlets.len() as u32,
+            has_bounds: packed.bounds.len() == packed.meshlets.len() && !packed.bounds.is_empty(),
+        });
+    }
+}
+
+fn prepare_meshlet_bounds_buffers(
+    mut commands: Commands,
+    render_device: Res<RenderDevice>,
+    bounds: Query<(Entity, &MeshletCullingBounds), Changed<MeshletCullingBounds>>,
+) {
+    for (entity, bounds) in &bounds {
+        let packed = bounds.packed();
+        let buffer = render_device.create_buffer_with_data(&BufferInitDescriptor {
+            label: Some("meshlet_culling_bounds"),
+            contents: non_empty(&packed),
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+        });
+        commands.entity(entity).insert(MeshletBoundsBuffer {
+            buffer,
+            count: packed.len() as u32,
+        });
+    }
+}
+
+/// Bytes of `values`, or of a single zeroed element when empty.
+fn non_empty<T: Pod>(values: &[T]) -> &[u8] {
+    if values.is_empty() {
+        &ZEROED[..size_of::<T>()]
+    } else {
+        bytemuck::cast_slice(values)
+    }
+}
+
+/// Zeroed bytes backing the single element of empty buffers, as large as the largest element.
+const ZEROED: [u8; size_of::<GpuMeshletBounds>()] = [0; size_of::<GpuMeshletBounds>()];
+
+#[cfg(test)]
+mod tests {
+    use std::mem::offset_of;
+
+    use super::*;
+    use crate::{MeshExt, MeshletParams, test_util::sphere};
+
+    #[test]
+    fn gpu_structs_match_their_wgsl_layout() {
+        assert_eq!(size_of::<GpuMeshlet>(), 16);
+        assert_eq!(GpuMeshlet::min_size().get(), 16);
+        assert_eq!(size_of::<GpuMeshletBounds>(), 48);
+        assert_eq!(GpuMeshletBounds::min_size().get(), 48);
+        assert_eq!(offset_of!(GpuMeshletBounds, radius), 12);
+        assert_eq!(offset_of!(GpuMeshletBounds, cone_apex), 16);
+        assert_eq!(offset_of!(GpuMeshletBounds, cone_cutoff), 28);
+        assert_eq!(offset_of!(GpuMeshletBounds, cone_axis), 32);
+        assert_eq!(ZEROED.len(), size_of::<GpuMeshletBounds>());
+    }
+
+    #[test]
+    fn extracted_meshlets_fill_their_storage_buffers() {
+        let meshlets = sphere(4).build_meshlets(&MeshletParams::default()).unwrap();
+        let packed = Meshlets::extract_component(&meshlets).unwrap();
+        assert_eq!(packed, PackedMeshlets::new(&meshlets));
+
+        assert_eq!(
+            non_empty(&packed.meshlets).len(),
+            meshlets.len() * size_of::<GpuMeshlet>()
+        );
+        assert_eq!(
+            non_empty(&packed.vertices).len(),
+            meshlets.vertices.len() * 4
+        );
+        assert_eq!(
+            non_empty(&packed.triangles).len(),
+            meshlets.triangles.len().next_multiple_of(4)
+        );
+        assert_eq!(
+            non_empty(&packed.bounds).len(),
+            meshlets.len() * size_of::<GpuMeshletBounds>()
+        );
+
+        // The shader reads the triangles of a meshlet as bytes at its triangle offset.
+        let bytes: &[u8] = bytemuck::cast_slice(&packed.triangles);
+        for (meshlet, descriptor) in packed.meshlets.iter().enumerate() {
+            let start = descriptor.triangle_offset as usize;
+            let end = start + descriptor.triangle_count as usize * 3;
+            assert_eq!(&bytes[start..end], meshlets.meshlet_triangles(meshlet));
+            assert_eq!(
+                GpuMeshletBounds::from(&meshlets.bounds[meshlet]),
+                packed.bounds[meshlet]
+            );
+        }
+        assert!(
+            bytes[meshlets.triangles.len()..]
+                .iter()
+                .all(|&byte| byte == 0)
+        );
+    }
+
+    #[test]
+    fn empty_buffers_hold_one_zeroed_element() {
+        let packed = PackedMeshlets::new(&Meshlets::default());
+        assert_eq!(non_empty(&packed.meshlets), [0; 16]);
+        assert_eq!(non_empty(&packed.vertices), [0; 4]);
+        assert_eq!(non_empty(&packed.bounds), [0; 48]);
+        let bounds = MeshletCullingBounds::default();
+        assert_eq!(non_empty(&bounds.packed()).len(), 48);
+    }
+}
@@ -0,0 +1,312 @@
+use bevy::math::Vec3;
+
+/// Closest point query result against a [`SurfaceIndex`].
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct SurfaceHit {
+    /// Index of the triangle in the indexed surface.
+    pub triangle: u32,
+    /// Closest point on the surface.
+    pub point: Vec3,
+    /// Barycentric coordinates of `point` within the triangle.
+    pub barycentric: Vec3,
+    pub distance_squared: f32,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct BvhNode {
+    min: Vec3,
+    max: Vec3,
+    /// Leaf: offset into `SurfaceIndex::order`, internal: index of the left child.
+    first: u32,
+    /// Leaf: triangle count, internal: 0.
+    count: u32,
+}
+
+const LEAF_SIZE: usize = 4;
+
+/// Bounding volume hierarchy over the triangles of a mesh, used for closest point queries when
+/// comparing two surfaces.
+pub(crate) struct SurfaceIndex {
+    positions: Vec<Vec3>,
+    triangles: Vec<[u32; 3]>,
+    nodes: Vec<BvhNode>,
+    order: Vec<u32>,
+}
+
+impl SurfaceIndex {
+    pub fn new(indices: &[u32], positions: &[[f32; 3]]) -> Self {
+        let positions: Vec<Vec3> = positions.iter().map(|p| Vec3::from_array(*p)).collect();
+        let triangles: Vec<[u32; 3]> = indices
+            .chunks_exact(3)
+            .map(|t| [t[0], t[1], t[2]])
+            .collect();
+
+        let centroids: Vec<Vec3> = triangles
+            .iter()
+            .map(|t| {
+                (positions[t[0] as usize] + positions[t[1] as usize] + positions[t[2] as usize])
+                    / 3.0
+            })
+            .collect();
+
+        let mut index = SurfaceIndex {
+            order: (0..triangles.len() as u32).collect(),
+            nodes: Vec::with_capacity(triangles.len() / LEAF_SIZE * 2 + 1),
+            positions,
+            triangles,
+        };
+
+        if !index.triangles.is_empty() {
+            index.nodes.push(BvhNode {
+                min: Vec3::ZERO,
+                max: Vec3::ZERO,
+                first: 0,
+                count: 0,
+            });
+            index.build(0, 0, index.triangles.len(), &centroids);
+        }
+
+        index
+    }
+
+    fn build(&mut self, node: usize, start: usize, end: usize, centroids: &[Vec3]) {
+        let mut min = Vec3::splat(f32::INFINITY);
+        let mut max = Vec3::splat(f32::NEG_INFINITY);
+        let mut centroid_min = Vec3::splat(f32::INFINITY);
+        let mut centroid_max = Vec3::splat(f32::NEG_INFINITY);
+        for &triangle in &self.order[start..end] {
+            for &vertex in &self.triangles[triangle as usize] {
+                let position = self.positions[vertex as usize];
+                min = min.min(position);
+                max = max.max(position);
+            }
+            centroid_min = centroid_min.min(centroids[triangle as usize]);
+            centroid_max = centroid_max.max(centroids[triangle as usize]);
+        }
+
+        self.nodes[node].min = min;
+        self.nodes[node].max = max;
+
+        if end - start <= LEAF_SIZE {
+            self.nodes[node].first = start as u32;
+            self.nodes[node].count = (end - start) as u32;
+            return;
+        }
+
+        let extent = centroid_max - centroid_min;
+        let axis = if extent.x >= extent.y && extent.x >= extent.z {
+            0
+        } else if extent.y >= extent.z {
+            1
+        } else {
+            2
+        };
+
+        let mid = (start + end) / 2;
+        self.order[start..end].select_nth_unstable_by(mid - start, |a, b| {
+            centroids[*a as usize][axis].total_cmp(&centroids[*b as usize][axis])
+        });
+
+        let left = self.nodes.len();
+        let empty = BvhNode {
+            min: Vec3::ZERO,
+            max: Vec3::ZERO,
+            first: 0,
+            count: 0,
+        };
+        self.nodes.push(empty);
+        self.nodes.push(empty);
+        self.nodes[node].first = left as u32;
+        self.nodes[node].count = 0;
+
+        self.build(left, start, mid, centroids);
+        self.build(left + 1, mid, end, centroids);
+    }
+
+    pub fn triangle_positions(&self, triangle: u32) -> [Vec3; 3] {
+        let [a, b, c] = self.triangles[triangle as usize];
+        [
+            self.positions[a as usize],
+            self.positions[b as usize],
+            self.positions[c as usize],
+        ]
+    }
+
+    /// Point of a triangle at the given barycentric coordinates.
+    pub fn point_at(&self, triangle: u32, barycentric: Vec3) -> Vec3 {
+        let [a, b, c] = self.triangle_positions(triangle);
+        a * barycentric.x + b * barycentric.y + c * barycentric.z
+    }
+
+    /// Unit face normal of a triangle, zero for degenerate triangles.
+    pub fn triangle_normal(&self, triangle: u32) -> Vec3 {
+        let [a, b, c] = self.triangle_positions(triangle);
+        (b - a).cross(c - a).normalize_or_zero()
+    }
+
+    /// Finds the closest point on the indexed surface to `point`.
+    pub fn closest_point(&self, point: Vec3) -> Option<SurfaceHit> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+
+        let mut best: Option<SurfaceHit> = None;
+        let mut best_distance = f32::INFINITY;
+        let mut stack = Vec::with_capacity(64);
+        stack.push(0u32);
+
+        while let Some(node) = stack.pop() {
+            let node = self.nodes[node as usize];
+            if aabb_distance_squared(point, node.min, node.max) >= best_distance {
+                continue;
+            }
+
+            if node.count > 0 {
+                let start = node.first as usize;
+                for &triangle in &self.order[start..start + node.count as usize] {
+                    let [a, b, c] = self.triangle_positions(triangle);
+                    let (closest, barycentric) = closest_point_on_triangle(point, a, b, c);
+                    let distance_squared = closest.distance_squared(point);
+                    if distance_squared < best_distance {
+                        best_distance = distance_squared;
+                        best = Some(SurfaceHit {
+                            triangle,
+                            point: closest,
+                            barycentric,
+                            distance_squared,
+                        });
+                    }
+                }
+            } else {
+                // Visit the nearer child first so the far one is more likely to be culled.
+                let left = node.first;
+                let right = node.first + 1;
+                let left_node = self.nodes[left as usize];
+                let right_node = self.nodes[right as usize];
+                let left_distance = aabb_distance_squared(point, left_node.min, left_node.max);
+                let right_distance = aabb_distance_squared(point, right_node.min, right_node.max);
+                if left_distance < right_distance {
+                    stack.push(right);
+                    stack.push(left);
+                } else {
+                    stack.push(left);
+                    stack.push(right);
+                }
+            }
+        }
+
+        best
+    }
+
+    /// Signed distance of `point` from the surface along the face normal of the closest triangle,
+    /// positive values are outside of the surface.
+    pub fn signed_distance(&self, point: Vec3) -> Option<f32> {
+        let hit = self.closest_point(point)?;
+        let normal = self.triangle_normal(hit.triangle);
+        let offset = point - hit.point;
+        let distance = hit.distance_squared.sqrt();
+        Some(if offset.dot(normal) < 0.0 {
+            -distance
+        } else {
+            distance
+        })
+    }
+}
+
+fn aabb_distance_squared(point: Vec3, min: Vec3, max: Vec3) -> f32 {
+    let clamped = point.clamp(min, max);
+    clamped.distance_squared(point)
+}
+
+/// Closest point on triangle `abc` to `p`, along with its barycentric coordinates.
+///
+/// See Real-Time Collision Detection, section 5.1.5.
+pub(crate) fn closest_point_on_triangle(p: Vec3, a: Vec3, b: Vec3, c: Vec3) -> (Vec3, Vec3) {
+    let ab = b - a;
+    let ac = c - a;
+    let ap = p - a;
+    let d1 = ab.dot(ap);
+    let d2 = ac.dot(ap);
+    if d1 <= 0.0 && d2 <= 0.0 {
+        return (a, Vec3::X);
+    }
+
+    let bp = p - b;
+    let d3 = ab.dot(bp);
+    let d4 = ac.dot(bp);
+    if d3 >= 0.0 && d4 <= d3 {
+        return (b, Vec3::Y);
+    }
+
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        let v = d1 / (d1 - d3);
+        return (a + ab * v, Vec3::new(1.0 - v, v, 0.0));
+    }
+
+    let cp = p - c;
+    let d5 = ab.dot(cp);
+    let d6 = ac.dot(cp);
+    if d6 >= 0.0 && d5 <= d6 {
+        return (c, Vec3::Z);
+    }
+
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        let w = d2 / (d2 - d6);
+        return (a + ac * w, Vec3::new(1.0 - w, 0.0, w));
+    }
+
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0.0 && (d4 - d3) >= 0.0 && (d5 - d6) >= 0.0 {
+        let w = (d4 - d3) / ((d4 - d3) + (d5 - d6));
+        return (b + (c - b) * w, Vec3::new(0.0, 1.0 - w, w));
+    }
+
+    let denom = va + vb + vc;
+    if denom.abs() <= f32::EPSILON {
+        // Degenerate triangle, fall back to the first vertex.
+        return (a, Vec3::X);
+    }
+    let denom = 1.0 / denom;
+    let v = vb * denom;
+    let w = vc * denom;
+    (a + ab * v + ac * w, Vec3::new(1.0 - v - w, v, w))
+}
+
+/// Area-weighted vertex normals for an indexed triangle list, normalized to unit length, or zero
+/// where the triangles of a vertex cancel out or no triangle uses it.
+pub(crate) fn vertex_normals(indices: &[u32], positions: &[[f32; 3]]) -> Vec<Vec3> {
+    let mut normals = vec![Vec3::ZERO; positions.len()];
+    for triangle in indices.chunks_exact(3) {
+        let a = Vec3::from_array(positions[triangle[0] as usize]);
+        let b = Vec3::from_array(positions[triangle[1] as usize]);
+        let c = Vec3::from_array(positions[triangle[2] as usize]);
+        let normal = (b - a).cross(c - a);
+        for &vertex in triangle {
+            normals[vertex as usize] += normal;
+        }
+    }
+
+    for normal in &mut normals {
+        *normal = normal.normalize_or_zero();
+    }
+    normals
+}
+
+/// Sample points over an indexed triangle list: every vertex, plus the centroid and edge midpoints
+/// of each triangle.
+pub(crate) fn surface_samples(indices: &[u32], positions: &[[f32; 3]]) -> Vec<Vec3> {
+    let mut samples: Vec<Vec3> = positions.iter().map(|p| Vec3::from_array(*p)).collect();
+    samples.reserve(indices.len() / 3 * 4);
+    for triangle in indices.chunks_exact(3) {
+        let a = Vec3::from_array(positions[triangle[0] as usize]);
+        let b = Vec3::from_array(positions[triangle[1] as usize]);
+        let c = Vec3::from_array(positions[triangle[2] as usize]);
+        samples.push((a + b + c) / 3.0);
+        samples.push((a + b) * 0.5);
+        samples.push((b + c) * 0.5);
+        samples.push((c + a) * 0.5);
+    }
+    samples
+}
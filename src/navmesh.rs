@@ -0,0 +1,420 @@
+use bevy::{
+    math::Vec3,
+    mesh::{Indices, Mesh, PrimitiveTopology},
+};
+
+use crate::{
+    FallbackPolicy, OptError, SimplifyMode, SimplifyOptions, SimplifyParams, TargetIndices,
+    attributes::VertexAttributes,
+    mesh_indices, mesh_positions,
+    metrics::SurfaceIndex,
+    simplify::{SimplifyInput, simplify_into},
+    vertex_lock::user_vertex_locks,
+};
+
+/// Simplifications with the heights weighted ever more before settling for a floor bending further
+/// than `vertical_error`.
+const VERTICAL_ATTEMPTS: u32 = 8;
+
+#[derive(Debug, Clone)]
+pub struct NavmeshParams {
+    /// Simplification of the level geometry. Only `max_error`, `target_index_count`, `options`
+    /// and `vertex_locks` and `locked_vertices` (referring to vertices of the source mesh) are
+    /// used, the other modes would ignore the vertical error bound and aren't supported.
+    pub simplify: SimplifyParams,
+    /// Up direction of the level, in mesh space.
+    pub up: Vec3,
+    /// Steepest slope in radians a triangle can have to be walkable.
+    pub max_slope: f32,
+    /// Error bound for moving walkable vertices along `up`, usually much tighter than
+    /// `simplify.max_error` so floors don't tilt or get bumps. Relative to the mesh extents unless
+    /// `SimplifyOptions::ErrorAbsolute` is set on `simplify`.
+    pub vertical_error: f32,
+}
+
+impl Default for NavmeshParams {
+    fn default() -> Self {
+        NavmeshParams {
+            simplify: SimplifyParams {
+                max_error: 0.01,
+                target_index_count: TargetIndices::Multiplier(0.1),
+                ..Default::default()
+            },
+            up: Vec3::Y,
+            max_slope: 45f32.to_radians(),
+            vertical_error: 0.001,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct NavmeshReport {
+    pub index_count: usize,
+    pub vertex_count: usize,
+    /// Error reported by the simplifier.
+    pub simplify_error: f32,
+    /// Area of the walkable triangles, in mesh units.
+    pub walkable_area_before: f32,
+    pub walkable_area_after: f32,
+    /// Fraction of the source surface, by area, whose walkability matches the closest point of
+    /// the simplified mesh. `1.0` when simplification didn't change what is walkable.
+    pub walkable_agreement: f32,
+}
+
+pub(crate) fn generate_navmesh_source(
+    mesh: &Mesh,
+    params: &NavmeshParams,
+) -> Result<(Mesh, NavmeshReport), OptError> {
+    let indices = mesh_indices(mesh)?;
+    let positions = mesh_positions(mesh)?;
+    let user_locks = user_vertex_locks(mesh, positions, &params.simplify)?;
+
+    // Only positions end up in the navmesh source, so weld away attribute seams to let the
+    // simplifier collapse across them.
+    let (vertex_count, remap) = meshopt::generate_vertex_remap(positions, Some(indices));
+    let welded_indices = meshopt::remap_index_buffer(Some(indices), vertex_count, &remap);
+    let welded_positions = meshopt::remap_vertex_buffer(positions, vertex_count, &remap);
+
+    let up = params.up.normalize_or(Vec3::Y);
+    let cos_slope = params.max_slope.cos();
+    let walkable = walkable_triangles(&welded_indices, &welded_positions, up, cos_slope);
+
+    // Vertices of walkable triangles and of the others, those used by both are on the boundary.
+    let mut walkable_vertices = vec![false; vertex_count];
+    let mut other_vertices = vec![false; vertex_count];
+    for (corners, &walkable) in welded_indices.chunks_exact(3).zip(&walkable) {
+        let vertices = if walkable {
+            &mut walkable_vertices
+        } else {
+            &mut other_vertices
+        };
+        for &vertex in corners {
+            vertices[vertex as usize] = true;
+        }
+    }
+    let mut locks: Vec<bool> = walkable_vertices
+        .iter()
+        .zip(&other_vertices)
+        .map(|(&walkable, &other)| walkable && other)
+        .collect();
+    if let Some(user_locks) = &user_locks {
+        for (&locked, &new_index) in user_locks.iter().zip(&remap) {
+            if locked && new_index != u32::MAX {
+                locks[new_index as usize] = true;
+            }
+        }
+    }
+
+    // The height of walkable vertices is fed to the simplifier as an attribute, weighted so that
+    // moving one by `vertical_error` costs as much as `max_error`. The others get a constant so
+    // they are only bound by `max_error`.
+    let scale = meshopt::simplify_scale_decoder(&welded_positions).max(f32::EPSILON);
+    let heights: Vec<f32> = welded_positions
+        .iter()
+        .zip(&walkable_vertices)
+        .map(|(position, &walkable)| {
+            if walkable {
+                Vec3::from_array(*position).dot(up) / scale
+            } else {
+                0.0
+            }
+        })
+        .collect();
+    let mut weight = params.simplify.max_error / params.vertical_error.max(f32::EPSILON);
+    let vertical_bound = if params
+        .simplify
+        .options
+        .contains(SimplifyOptions::ErrorAbsolute)
+    {
+        params.vertical_error * params.simplify.error_units()
+    } else {
+        params.vertical_error * scale
+    };
+
+    let simplify = SimplifyParams {
+        mode: SimplifyMode::Precise,
+        // The sloppy simplifier doesn't hold walkable vertices to `vertical_error` either.
+        fallback: match params.simplify.fallback {
+            FallbackPolicy::Sloppy { .. } => FallbackPolicy::None,
+            fallback => fallback,
+        },
+        ..params.simplify.clone()
+    };
+    let target_index_count = simplify
+        .target_index_count
+        .resolve(welded_indices.len(), simplify.min_target_index_count);
+    let mut navmesh_indices = Vec::new();
+    let mut simplify_error = 0.0;
+    // The simplifier bounds the height error over the surface as a whole rather than at every
+    // vertex, so floors can still bend further than `vertical_error` in places. The heights are
+    // weighted more until they don't.
+    for _ in 0..VERTICAL_ATTEMPTS {
+        (simplify_error, _) = simplify_into(
+            &mut navmesh_indices,
+            SimplifyInput {
+                indices: &welded_indices,
+                positions: &welded_positions,
+                attributes: Some(VertexAttributes {
+                    values: &heights,
+                    weights: &[weight],
+                }),
+                locks: Some(&locks),
+                protect: None,
+            },
+            target_index_count,
+            &simplify,
+        );
+        let deviation = vertical_deviation(
+            &welded_positions,
+            &walkable_vertices,
+            &navmesh_indices,
+            up,
+            cos_slope,
+        );
+        if navmesh_indices.is_empty() || deviation <= vertical_bound {
+            break;
+        }
+        weight *= 2.0;
+    }
+    if navmesh_indices.is_empty() {
+        return Err(OptError::InvalidIndexCount(0));
+    }
+
+    let mut navmesh_positions = welded_positions;
+    let used =
+        meshopt::optimize_vertex_fetch_in_place(&mut navmesh_indices, &mut navmesh_positions);
+    navmesh_positions.truncate(used);
+
+    let walkable_after = walkable_triangles(&navmesh_indices, &navmesh_positions, up, cos_slope);
+    let report = NavmeshReport {
+        index_count: navmesh_indices.len(),
+        vertex_count: navmesh_positions.len(),
+        simplify_error,
+        walkable_area_before: walkable_area(indices, positions, &walkable),
+        walkable_area_after: walkable_area(&navmesh_indices, &navmesh_positions, &walkable_after),
+        walkable_agreement: walkable_agreement(
+            indices,
+            positions,
+            &walkable,
+            &navmesh_indices,
+            &navmesh_positions,
+            &walkable_after,
+        ),
+    };
+
+    let navmesh = Mesh::new(PrimitiveTopology::TriangleList, mesh.asset_usage)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, navmesh_positions)
+        .with_inserted_indices(Indices::U32(navmesh_indices));
+    Ok((navmesh, report))
+}
+
+/// Largest distance along `up` from the flagged `vertices` to the surface of `indices`, from the
+/// closest point on it divided by the cosine of the slope there, for walkable slopes.
+fn vertical_deviation(
+    positions: &[[f32; 3]],
+    vertices: &[bool],
+    indices: &[u32],
+    up: Vec3,
+    cos_slope: f32,
+) -> f32 {
+    let surface = SurfaceIndex::new(indices, positions);
+    positions
+        .iter()
+        .zip(vertices)
+        .filter(|(_, flagged)| **flagged)
+        .filter_map(|(&position, _)| {
+            let hit = surface.closest_point(Vec3::from(position))?;
+            let corners = &indices[hit.triangle as usize * 3..][..3];
+            let [a, b, c] = [0, 1, 2].map(|corner| Vec3::from(positions[corners[corner] as usize]));
+            let cos = (b - a).cross(c - a).normalize_or_zero().dot(up).abs();
+            let distance = hit.distance_squared.sqrt();
+            Some(if cos > 0.0 && cos >= cos_slope {
+                distance / cos
+            } else {
+                distance
+            })
+        })
+        .fold(0.0, f32::max)
+}
+
+/// Whether the face normal of every triangle is within the slope limit of `up`.
+fn walkable_triangles(
+    indices: &[u32],
+    positions: &[[f32; 3]],
+    up: Vec3,
+    cos_slope: f32,
+) -> Vec<bool> {
+    indices
+        .chunks_exact(3)
+        .map(|corners| {
+            let [a, b, c] = [0, 1, 2].map(|corner| Vec3::from(positions[corners[corner] as usize]));
+            let normal = (b - a).cross(c - a).normalize_or_zero();
+            normal != Vec3::ZERO && normal.dot(up) >= cos_slope
+        })
+        .collect()
+}
+
+fn triangle_area(positions: &[[f32; 3]], corners: &[u32]) -> f32 {
+    let [a, b, c] = [0, 1, 2].map(|corner| Vec3::from(positions[corners[corner] as usize]));
+    (b - a).cross(c - a).length() * 0.5
+}
+
+fn walkable_area(indices: &[u32], positions: &[[f32; 3]], walkable: &[bool]) -> f32 {
+    indices
+        .chunks_exact(3)
+        .zip(walkable)
+        .filter(|(_, walkable)| **walkable)
+        .map(|(corners, _)| triangle_area(positions, corners))
+        .sum()
+}
+
+/// Area-weighted fraction of the source triangles whose walkability matches the simplified
+/// triangle closest to their centroid.
+fn walkable_agreement(
+    indices: &[u32],
+    positions: &[[f32; 3]],
+    walkable: &[bool],
+    simplified_indices: &[u32],
+    simplified_positions: &[[f32; 3]],
+    simplified_walkable: &[bool],
+) -> f32 {
+    let surface = SurfaceIndex::new(simplified_indices, simplified_positions);
+    let mut total = 0.0;
+    let mut agreeing = 0.0;
+    for (corners, &walkable) in indices.chunks_exact(3).zip(walkable) {
+        let area = triangle_area(positions, corners);
+        let centroid = corners
+            .iter()
+            .map(|&vertex| Vec3::from(positions[vertex as usize]))
+            .sum::<Vec3>()
+            / 3.0;
+        total += area;
+        if surface
+            .closest_point(centroid)
+            .is_some_and(|hit| simplified_walkable[hit.triangle as usize] == walkable)
+        {
+            agreeing += area;
+        }
+    }
+    if total > 0.0 { agreeing / total } else { 1.0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::TAU;
+
+    use bevy::{
+        math::{Vec2, Vec3Swizzles},
+        mesh::VertexAttributeValues,
+    };
+
+    use super::*;
+    use crate::{
+        MeshExt,
+        test_util::{grid, indices, positions},
+    };
+
+    const CELLS: u32 = 32;
+    const MAX_ERROR: f32 = 0.05;
+    const VERTICAL_ERROR: f32 = 0.002;
+
+    /// Floor rising gently along X with shallow ripples along Z up to `x = 0.5`, where it meets a
+    /// wall too steep to walk on.
+    fn sloped_floor() -> Mesh {
+        let mut mesh = grid(CELLS);
+        let Some(VertexAttributeValues::Float32x3(positions)) =
+            mesh.attribute_mut(Mesh::ATTRIBUTE_POSITION)
+        else {
+            unreachable!()
+        };
+        for [x, y, z] in positions {
+            *y = if *x <= 0.5 {
+                0.2 * *x + 0.01 * (TAU * 2.0 * *z).sin()
+            } else {
+                0.1 + 4.0 * (*x - 0.5)
+            };
+        }
+        mesh
+    }
+
+    fn params(vertical_error: f32) -> NavmeshParams {
+        NavmeshParams {
+            simplify: SimplifyParams {
+                max_error: MAX_ERROR,
+                target_index_count: TargetIndices::Multiplier(0.0),
+                ..Default::default()
+            },
+            vertical_error,
+            ..Default::default()
+        }
+    }
+
+    /// Height of `mesh` above `point` on the XZ plane.
+    fn height_at(mesh: &Mesh, point: Vec2) -> Option<f32> {
+        let positions = positions(mesh);
+        indices(mesh).chunks_exact(3).find_map(|corners| {
+            let [a, b, c] = [0, 1, 2].map(|corner| Vec3::from(positions[corners[corner] as usize]));
+            let [ab, ac, ap] = [b.xz() - a.xz(), c.xz() - a.xz(), point - a.xz()];
+            let denominator = ab.perp_dot(ac);
+            if denominator.abs() <= f32::EPSILON {
+                return None;
+            }
+            let v = ap.perp_dot(ac) / denominator;
+            let w = ab.perp_dot(ap) / denominator;
+            let inside = [v, w, 1.0 - v - w].iter().all(|&weight| weight >= -1e-4);
+            inside.then_some(a.y + v * (b.y - a.y) + w * (c.y - a.y))
+        })
+    }
+
+    /// Largest vertical distance from the walkable and the other source vertices to `navmesh`.
+    fn vertical_deviation(source: &Mesh, navmesh: &Mesh) -> (f32, f32) {
+        let mut deviation = (0.0f32, 0.0f32);
+        for &[x, y, z] in positions(source) {
+            let height = height_at(navmesh, Vec2::new(x, z)).unwrap();
+            let distance = (height - y).abs();
+            if x <= 0.5 {
+                deviation.0 = deviation.0.max(distance);
+            } else {
+                deviation.1 = deviation.1.max(distance);
+            }
+        }
+        deviation
+    }
+
+    #[test]
+    fn sloped_floor_stays_within_the_vertical_error() {
+        let source = sloped_floor();
+        let (navmesh, report) = source
+            .generate_navmesh_source(&params(VERTICAL_ERROR))
+            .unwrap();
+        assert!(report.index_count < indices(&source).len() / 2);
+        assert_eq!(report.walkable_agreement, 1.0);
+
+        // Both errors are relative to the extents of the level.
+        let scale = source.simplify_scale().unwrap();
+        let (walkable, other) = vertical_deviation(&source, &navmesh);
+        assert!(walkable <= VERTICAL_ERROR * scale, "{walkable}");
+        assert!(other <= MAX_ERROR * scale, "{other}");
+
+        // Held to `max_error` only, the ripples of the floor are flattened.
+        let (loose, _) = source.generate_navmesh_source(&params(MAX_ERROR)).unwrap();
+        assert!(vertical_deviation(&source, &loose).0 > VERTICAL_ERROR * scale);
+    }
+
+    #[test]
+    fn walkable_boundary_vertices_are_kept() {
+        let source = sloped_floor();
+        let (navmesh, _) = source
+            .generate_navmesh_source(&params(VERTICAL_ERROR))
+            .unwrap();
+        let kept = positions(&navmesh);
+        let boundary: Vec<_> = positions(&source)
+            .iter()
+            .filter(|position| position[0] == 0.5)
+            .collect();
+        assert_eq!(boundary.len(), CELLS as usize + 1);
+        for position in boundary {
+            assert!(kept.contains(position), "{position:?}");
+        }
+    }
+}
@@ -0,0 +1,227 @@
+use bevy::{
+    math::Vec3,
+    mesh::{Indices, Mesh, PrimitiveTopology},
+};
+use meshopt::SimplifyOptions;
+
+use crate::{
+    OptError, SimplifyParams, TargetIndices, mesh_indices, mesh_positions,
+    metrics::{SurfaceIndex, surface_samples, vertex_normals},
+    simplify::simplify_mesh_indices,
+    vertex_lock::user_vertex_locks,
+};
+
+#[derive(Debug, Clone)]
+pub struct OccluderParams {
+    /// Simplification used to produce the occluder before it gets shrunk, this should be much more
+    /// aggressive than what would be used for a LOD.
+    ///
+    /// Vertex locks refer to vertices of the source mesh.
+    pub simplify: SimplifyParams,
+    /// Maximum distance the occluder may stick out of the source surface. Relative to the mesh
+    /// extents unless `SimplifyOptions::ErrorAbsolute` is set on `simplify`, in world units with
+    /// its `world_scale`.
+    pub tolerance: f32,
+    /// Maximum number of shrink passes used to get the protrusion within `tolerance`.
+    pub max_iterations: u32,
+}
+
+impl Default for OccluderParams {
+    fn default() -> Self {
+        OccluderParams {
+            simplify: SimplifyParams {
+                max_error: 0.05,
+                target_index_count: TargetIndices::Multiplier(0.05),
+                ..Default::default()
+            },
+            tolerance: 0.001,
+            max_iterations: 4,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct OccluderReport {
+    pub index_count: usize,
+    pub vertex_count: usize,
+    /// Error reported by the simplifier.
+    pub simplify_error: f32,
+    /// Total distance the occluder was moved inward along its vertex normals.
+    pub shrink_distance: f32,
+    /// Largest measured distance of the occluder outside of the source surface, in mesh units.
+    pub max_protrusion: f32,
+    /// Whether `max_protrusion` is within the requested tolerance. Occluders that aren't
+    /// conservative may over-occlude and should usually be rejected.
+    pub conservative: bool,
+}
+
+pub(crate) fn generate_occluder(
+    mesh: &Mesh,
+    params: &OccluderParams,
+) -> Result<(Mesh, OccluderReport), OptError> {
+    let indices = mesh_indices(mesh)?;
+    let positions = mesh_positions(mesh)?;
+
+    // Only positions end up in the occluder, so weld away attribute seams to let the simplifier
+    // collapse across them.
+    let (vertex_count, remap) = meshopt::generate_vertex_remap(positions, Some(indices));
+    let welded_indices = meshopt::remap_index_buffer(Some(indices), vertex_count, &remap);
+    let welded_positions = meshopt::remap_vertex_buffer(positions, vertex_count, &remap);
+    let welded_locks = user_vertex_locks(mesh, positions, &params.simplify)?.map(|locks| {
+        let mut welded = vec![false; vertex_count];
+        for (&locked, &new_index) in locks.iter().zip(&remap) {
+            if locked && new_index != u32::MAX {
+                welded[new_index as usize] = true;
+            }
+        }
+        welded
+    });
+
+    let simplify = SimplifyParams {
+        vertex_locks: welded_locks,
+        locked_vertices: None,
+        ..params.simplify.clone()
+    };
+    let welded = Mesh::new(PrimitiveTopology::TriangleList, mesh.asset_usage)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, welded_positions)
+        .with_inserted_indices(Indices::U32(welded_indices));
+    let (mut occluder_indices, simplify_error, _) = simplify_mesh_indices(&welded, &simplify)?;
+    let mut welded_positions = mesh_positions(&welded)?.clone();
+    if occluder_indices.is_empty() {
+        return Err(OptError::InvalidIndexCount(0));
+    }
+
+    let used =
+        meshopt::optimize_vertex_fetch_in_place(&mut occluder_indices, &mut welded_positions);
+    welded_positions.truncate(used);
+    let base_positions = welded_positions;
+    let normals = vertex_normals(&occluder_indices, &base_positions);
+
+    let surface = SurfaceIndex::new(indices, positions);
+    let tolerance = if params
+        .simplify
+        .options
+        .contains(SimplifyOptions::ErrorAbsolute)
+    {
+        params.tolerance * params.simplify.error_units()
+    } else {
+        params.tolerance * meshopt::simplify_scale_decoder(positions)
+    };
+
+    let mut occluder_positions = base_positions.clone();
+    let mut shrink_distance = 0.0;
+    let mut max_protrusion = max_protrusion(&surface, &occluder_indices, &occluder_positions);
+    for _ in 0..params.max_iterations {
+        if max_protrusion <= tolerance {
+            break;
+        }
+
+        shrink_distance += max_protrusion;
+        for ((position, base), normal) in occluder_positions
+            .iter_mut()
+            .zip(&base_positions)
+            .zip(&normals)
+        {
+            *position = (Vec3::from_array(*base) - *normal * shrink_distance).to_array();
+        }
+        max_protrusion = self::max_protrusion(&surface, &occluder_indices, &occluder_positions);
+    }
+
+    let report = OccluderReport {
+        index_count: occluder_indices.len(),
+        vertex_count: occluder_positions.len(),
+        simplify_error,
+        shrink_distance,
+        max_protrusion,
+        conservative: max_protrusion <= tolerance,
+    };
+
+    let occluder = Mesh::new(PrimitiveTopology::TriangleList, mesh.asset_usage)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, occluder_positions)
+        .with_inserted_indices(Indices::U32(occluder_indices));
+
+    Ok((occluder, report))
+}
+
+/// Largest distance of the sampled occluder surface outside of `surface`.
+fn max_protrusion(surface: &SurfaceIndex, indices: &[u32], positions: &[[f32; 3]]) -> f32 {
+    surface_samples(indices, positions)
+        .into_iter()
+        .filter_map(|sample| surface.signed_distance(sample))
+        .fold(0.0, f32::max)
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{
+        math::{Vec2, Vec3Swizzles, primitives::Torus},
+        mesh::Meshable,
+    };
+
+    use super::*;
+    use crate::{
+        MeshExt,
+        test_util::{indices, positions, sphere},
+    };
+
+    /// Corners and centroids of the triangles of `mesh`.
+    fn surface_points(mesh: &Mesh) -> Vec<Vec3> {
+        let positions = positions(mesh);
+        indices(mesh)
+            .chunks_exact(3)
+            .flat_map(|corners| {
+                let [a, b, c] =
+                    [0, 1, 2].map(|corner| Vec3::from(positions[corners[corner] as usize]));
+                [a, b, c, (a + b + c) / 3.0]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn sphere_occluder_stays_inside() {
+        let source = sphere(8);
+        let params = OccluderParams::default();
+        let (occluder, report) = source.generate_occluder(&params).unwrap();
+        let tolerance = params.tolerance * source.simplify_scale().unwrap();
+
+        assert!(report.conservative);
+        assert!(report.max_protrusion <= tolerance);
+        assert!(report.index_count < indices(&source).len() / 10);
+        assert_eq!(occluder.attributes().count(), 1);
+        for point in surface_points(&occluder) {
+            assert!(point.length() <= 1.0 + tolerance, "{point}");
+        }
+    }
+
+    #[test]
+    fn torus_occluder_is_shrunk_inside() {
+        let torus = Torus::new(0.5, 1.5);
+        let source: Mesh = torus
+            .mesh()
+            .minor_resolution(32)
+            .major_resolution(64)
+            .into();
+        let params = OccluderParams {
+            simplify: SimplifyParams {
+                max_error: 0.05,
+                target_index_count: TargetIndices::Multiplier(0.02),
+                ..Default::default()
+            },
+            max_iterations: 8,
+            ..Default::default()
+        };
+        let (occluder, report) = source.generate_occluder(&params).unwrap();
+        let tolerance = params.tolerance * source.simplify_scale().unwrap();
+
+        assert!(report.shrink_distance > 0.0);
+        assert!(report.conservative, "{report:?}");
+        assert!(report.max_protrusion <= tolerance);
+        // The tessellation is within `chord` of the torus it approximates.
+        let chord = 0.01;
+        for point in surface_points(&occluder) {
+            let distance = Vec2::new(point.xz().length() - torus.major_radius, point.y).length()
+                - torus.minor_radius;
+            assert!(distance <= tolerance + chord, "{point} {distance}");
+        }
+    }
+}
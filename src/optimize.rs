@@ -0,0 +1,569 @@
+use bevy::{
+    ecs::resource::Resource,
+    math::Vec3,
+    mesh::{Indices, Mesh, PrimitiveTopology},
+};
+
+use crate::{
+    AttributeSet, OptError, mesh_indices, mesh_indices_mut, mesh_positions,
+    regenerate::with_stripped_attributes,
+    stats::{DEFAULT_CACHE_SIZE, validate_cache_size},
+    take_mesh_indices_mut, validate_indices,
+    vertex::remap_vertices,
+    with_u32_indices,
+};
+
+/// Vertex cache model the index buffer is optimized for.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum CacheModel {
+    /// [`meshopt::optimize_vertex_cache`], suited for most modern GPUs.
+    #[default]
+    Lru,
+    /// [`meshopt::optimize_vertex_cache_fifo`], faster but produces worse results, for hardware
+    /// with a fixed size FIFO cache.
+    Fifo { cache_size: u32 },
+    /// meshoptimizer's strip order, trading some cache efficiency for longer strips and so a
+    /// much smaller index buffer from
+    /// [`MeshExt::to_triangle_strip`](crate::MeshExt::to_triangle_strip).
+    Strip,
+}
+
+impl CacheModel {
+    /// Cache size used when analyzing the vertex cache efficiency.
+    fn analyze_cache_size(&self) -> u32 {
+        match self {
+            CacheModel::Lru | CacheModel::Strip => DEFAULT_CACHE_SIZE,
+            CacheModel::Fifo { cache_size } => *cache_size,
+        }
+    }
+}
+
+/// Analyzer results under which a mesh is considered already optimized, for
+/// [`OptimizeSettings::skip_if_optimized`]. The defaults follow meshoptimizer's guidance: an
+/// optimized index buffer usually has an ACMR of 0.5 to 0.8 with a 16 entry cache, an ATVR close
+/// to 1 means hardly any vertex is transformed twice, and an overfetch of 1 is optimal.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct OptimizedThresholds {
+    /// Highest average cache miss ratio, see [`OptimizeReport::acmr_before`].
+    pub max_acmr: f32,
+    /// Highest transformed vertices per vertex. Meshes with few shared vertices, like flat shaded
+    /// ones, can't get a good ACMR and pass with a good ATVR instead.
+    pub max_atvr: f32,
+    /// Highest fetched bytes per byte of vertex buffer, see [`OptimizeReport::overfetch_before`].
+    pub max_overfetch: f32,
+}
+
+impl Default for OptimizedThresholds {
+    fn default() -> Self {
+        OptimizedThresholds {
+            max_acmr: 0.8,
+            max_atvr: 1.1,
+            max_overfetch: 1.5,
+        }
+    }
+}
+
+/// Why [`MeshExt::optimize`](crate::MeshExt::optimize) left a mesh as is.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum OptimizeSkipReason {
+    /// The mesh was within [`OptimizeSettings::skip_if_optimized`].
+    AlreadyOptimized,
+}
+
+/// Which optimization stages to run, they are applied in the order recommended by meshoptimizer:
+/// vertex cache, overdraw then vertex fetch.
+#[derive(Resource, Debug, Copy, Clone, PartialEq)]
+pub struct OptimizeSettings {
+    pub vertex_cache: bool,
+    pub cache_model: CacheModel,
+    pub overdraw: bool,
+    /// How much the vertex cache efficiency may degrade to reduce overdraw, `1.05` allows it to
+    /// get 5% worse.
+    pub overdraw_threshold: f32,
+    pub vertex_fetch: bool,
+    /// Attributes removed before the stages run and regenerated afterwards, see
+    /// [`MeshExt::with_stripped_attributes`](crate::MeshExt::with_stripped_attributes).
+    pub strip_and_regenerate: AttributeSet,
+    /// Skips every stage for meshes whose vertex cache and vertex fetch efficiency are already
+    /// within these thresholds, e.g. assets optimized upstream, as reordering them again gains
+    /// next to nothing. `None` always runs the stages.
+    pub skip_if_optimized: Option<OptimizedThresholds>,
+}
+
+impl Default for OptimizeSettings {
+    fn default() -> Self {
+        OptimizeSettings {
+            vertex_cache: true,
+            cache_model: CacheModel::default(),
+            overdraw: true,
+            overdraw_threshold: 1.05,
+            vertex_fetch: true,
+            strip_and_regenerate: AttributeSet::NONE,
+            skip_if_optimized: Some(OptimizedThresholds::default()),
+        }
+    }
+}
+
+/// Efficiency of a mesh before and after optimization, as measured by meshoptimizer's analyzers.
+///
+/// Counts are kept as totals so reports of several meshes can be combined with
+/// [`OptimizeReport::accumulate`].
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct OptimizeReport {
+    pub triangles: usize,
+    pub vertices_before: usize,
+    pub vertices_after: usize,
+    /// Vertex shader invocations with the simulated vertex cache.
+    pub vertices_transformed_before: usize,
+    pub vertices_transformed_after: usize,
+    /// Pixels covered by the mesh in the overdraw analyzer.
+    pub pixels_covered: usize,
+    pub pixels_shaded_before: usize,
+    pub pixels_shaded_after: usize,
+    pub bytes_fetched_before: usize,
+    pub bytes_fetched_after: usize,
+    pub vertex_buffer_bytes_before: usize,
+    pub vertex_buffer_bytes_after: usize,
+    /// Size of the vertex buffers the stages ran on, smaller than `vertex_buffer_bytes_before`
+    /// when attributes were stripped and zero when the stages were skipped.
+    pub vertex_buffer_bytes_processed: usize,
+    /// Set when the stages were skipped, only describes a single mesh and is left as is by
+    /// [`OptimizeReport::accumulate`].
+    pub skipped_reason: Option<OptimizeSkipReason>,
+}
+
+impl OptimizeReport {
+    /// Average cache miss ratio, transformed vertices per triangle.
+    pub fn acmr_before(&self) -> f32 {
+        ratio(self.vertices_transformed_before, self.triangles)
+    }
+
+    pub fn acmr_after(&self) -> f32 {
+        ratio(self.vertices_transformed_after, self.triangles)
+    }
+
+    /// Shaded pixels per covered pixel.
+    pub fn overdraw_before(&self) -> f32 {
+        ratio(self.pixels_shaded_before, self.pixels_covered)
+    }
+
+    pub fn overdraw_after(&self) -> f32 {
+        ratio(self.pixels_shaded_after, self.pixels_covered)
+    }
+
+    /// Fetched bytes per byte of vertex buffer.
+    pub fn overfetch_before(&self) -> f32 {
+        ratio(self.bytes_fetched_before, self.vertex_buffer_bytes_before)
+    }
+
+    pub fn overfetch_after(&self) -> f32 {
+        ratio(self.bytes_fetched_after, self.vertex_buffer_bytes_after)
+    }
+
+    /// Adds the totals of `other` to this report.
+    pub fn accumulate(&mut self, other: &OptimizeReport) {
+        self.triangles += other.triangles;
+        self.vertices_before += other.vertices_before;
+        self.vertices_after += other.vertices_after;
+        self.vertices_transformed_before += other.vertices_transformed_before;
+        self.vertices_transformed_after += other.vertices_transformed_after;
+        self.pixels_covered += other.pixels_covered;
+        self.pixels_shaded_before += other.pixels_shaded_before;
+        self.pixels_shaded_after += other.pixels_shaded_after;
+        self.bytes_fetched_before += other.bytes_fetched_before;
+        self.bytes_fetched_after += other.bytes_fetched_after;
+        self.vertex_buffer_bytes_before += other.vertex_buffer_bytes_before;
+        self.vertex_buffer_bytes_after += other.vertex_buffer_bytes_after;
+        self.vertex_buffer_bytes_processed += other.vertex_buffer_bytes_processed;
+    }
+}
+
+pub(crate) fn ratio(numerator: usize, denominator: usize) -> f32 {
+    if denominator == 0 {
+        0.0
+    } else {
+        numerator as f32 / denominator as f32
+    }
+}
+
+/// Raw analyzer results for a single state of the mesh.
+struct Analysis {
+    vertices: usize,
+    vertices_transformed: usize,
+    pixels_covered: usize,
+    pixels_shaded: usize,
+    bytes_fetched: usize,
+    vertex_buffer_bytes: usize,
+}
+
+impl Analysis {
+    fn is_optimized(&self, triangles: usize, thresholds: &OptimizedThresholds) -> bool {
+        let cache = ratio(self.vertices_transformed, triangles) <= thresholds.max_acmr
+            || ratio(self.vertices_transformed, self.vertices) <= thresholds.max_atvr;
+        cache && ratio(self.bytes_fetched, self.vertex_buffer_bytes) <= thresholds.max_overfetch
+    }
+}
+
+fn analyze(mesh: &Mesh, cache_model: &CacheModel) -> Result<Analysis, OptError> {
+    let indices = mesh_indices(mesh)?;
+    let positions = mesh_positions(mesh)?;
+    let vertex_count = mesh.count_vertices();
+    let vertex_size = mesh.get_vertex_size() as usize;
+
+    let cache = meshopt::analyze_vertex_cache(
+        indices,
+        vertex_count,
+        cache_model.analyze_cache_size(),
+        0,
+        0,
+    );
+    let overdraw = meshopt::analyze_overdraw_decoder(indices, positions);
+    let fetch = meshopt::analyze_vertex_fetch(indices, vertex_count, vertex_size);
+
+    Ok(Analysis {
+        vertices: vertex_count,
+        vertices_transformed: cache.vertices_transformed as usize,
+        pixels_covered: overdraw.pixels_covered as usize,
+        pixels_shaded: overdraw.pixels_shaded as usize,
+        bytes_fetched: fetch.bytes_fetched as usize,
+        vertex_buffer_bytes: vertex_count * vertex_size,
+    })
+}
+
+/// Runs the stages enabled in `settings` over the mesh.
+pub(crate) fn optimize(
+    mesh: &mut Mesh,
+    settings: &OptimizeSettings,
+) -> Result<OptimizeReport, OptError> {
+    validate_cache_size(settings.cache_model.analyze_cache_size())?;
+    let before = analyze(mesh, &settings.cache_model)?;
+    let triangles = mesh_indices(mesh)?.len() / 3;
+    if let Some(thresholds) = &settings.skip_if_optimized
+        && before.is_optimized(triangles, thresholds)
+    {
+        return Ok(OptimizeReport {
+            triangles,
+            vertices_before: before.vertices,
+            vertices_after: before.vertices,
+            vertices_transformed_before: before.vertices_transformed,
+            vertices_transformed_after: before.vertices_transformed,
+            pixels_covered: before.pixels_covered,
+            pixels_shaded_before: before.pixels_shaded,
+            pixels_shaded_after: before.pixels_shaded,
+            bytes_fetched_before: before.bytes_fetched,
+            bytes_fetched_after: before.bytes_fetched,
+            vertex_buffer_bytes_before: before.vertex_buffer_bytes,
+            vertex_buffer_bytes_after: before.vertex_buffer_bytes,
+            vertex_buffer_bytes_processed: 0,
+            skipped_reason: Some(OptimizeSkipReason::AlreadyOptimized),
+        });
+    }
+
+    let ((), stripped) = with_stripped_attributes(mesh, settings.strip_and_regenerate, |mesh| {
+        if settings.vertex_cache {
+            optimize_vertex_cache(mesh, &settings.cache_model)?;
+        }
+        if settings.overdraw {
+            optimize_overdraw(mesh, settings.overdraw_threshold)?;
+        }
+        if settings.vertex_fetch {
+            optimize_vertex_fetch(mesh)?;
+        }
+        Ok(())
+    })?;
+
+    let after = analyze(mesh, &settings.cache_model)?;
+    Ok(OptimizeReport {
+        triangles: mesh_indices(mesh)?.len() / 3,
+        vertices_before: before.vertices,
+        vertices_after: after.vertices,
+        vertices_transformed_before: before.vertices_transformed,
+        vertices_transformed_after: after.vertices_transformed,
+        pixels_covered: before.pixels_covered,
+        pixels_shaded_before: before.pixels_shaded,
+        pixels_shaded_after: after.pixels_shaded,
+        bytes_fetched_before: before.bytes_fetched,
+        bytes_fetched_after: after.bytes_fetched,
+        vertex_buffer_bytes_before: before.vertex_buffer_bytes,
+        vertex_buffer_bytes_after: after.vertex_buffer_bytes,
+        vertex_buffer_bytes_processed: before.vertices * stripped.vertex_size_stripped,
+        skipped_reason: None,
+    })
+}
+
+pub(crate) fn optimize_vertex_cache(
+    mesh: &mut Mesh,
+    cache_model: &CacheModel,
+) -> Result<(), OptError> {
+    let positions_len = mesh_positions(mesh)?.len();
+    let indices = mesh_indices_mut(mesh)?;
+    match cache_model {
+        CacheModel::Lru => meshopt::optimize_vertex_cache_in_place(indices, positions_len),
+        CacheModel::Fifo { cache_size } => {
+            meshopt::optimize_vertex_cache_fifo_in_place(indices, positions_len, *cache_size)
+        }
+        CacheModel::Strip => {
+            let mut ordered = vec![0; indices.len()];
+            // SAFETY: `ordered` is as long as `indices`, which are all below `positions_len`.
+            unsafe {
+                meshopt::ffi::meshopt_optimizeVertexCacheStrip(
+                    ordered.as_mut_ptr(),
+                    indices.as_ptr(),
+                    indices.len(),
+                    positions_len,
+                );
+            }
+            *indices = ordered;
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn optimize_overdraw(mesh: &mut Mesh, threshold: f32) -> Result<(), OptError> {
+    let mut indices = take_mesh_indices_mut(mesh)?;
+    let positions = mesh_positions(mesh)?;
+    meshopt::optimize_overdraw_in_place_decoder(&mut indices, positions, threshold);
+    mesh.insert_indices(Indices::U32(indices));
+    Ok(())
+}
+
+/// Reorders the vertices of every attribute for locality of reference, dropping unused ones.
+/// Returns the table mapping old vertices to new ones, `u32::MAX` for dropped vertices, along
+/// with the new vertex count.
+pub(crate) fn optimize_vertex_fetch(mesh: &mut Mesh) -> Result<(Vec<u32>, usize), OptError> {
+    mesh_positions(mesh)?;
+    let vertex_count = mesh.count_vertices();
+    let indices = mesh_indices_mut(mesh)?;
+    // `meshopt::optimize_vertex_fetch_remap` truncates the table to the new vertex count, even
+    // though it is indexed by the old vertices.
+    let mut remap = vec![u32::MAX; vertex_count];
+    // SAFETY: `remap` has one entry per vertex and every index is below `vertex_count`.
+    let used = unsafe {
+        meshopt::ffi::meshopt_optimizeVertexFetchRemap(
+            remap.as_mut_ptr(),
+            indices.as_ptr(),
+            indices.len(),
+            vertex_count,
+        )
+    };
+    for index in indices.iter_mut() {
+        *index = remap[*index as usize];
+    }
+
+    remap_vertices(mesh, &remap, used);
+    Ok((remap, used))
+}
+
+/// Reorders the triangles by the projection of their centroid onto `axis`, keeping the order of
+/// triangles with the same projection. The index format is kept.
+pub(crate) fn sort_triangles_along_axis(
+    mesh: &mut Mesh,
+    axis: Vec3,
+    descending: bool,
+) -> Result<(), OptError> {
+    let positions = mesh_positions(mesh)?;
+    let (indices, wide) = indices_keeping_format(mesh)?;
+
+    let mut triangles: Vec<(&[u32], f32)> = indices
+        .chunks_exact(3)
+        .map(|triangle| {
+            let centroid = triangle
+                .iter()
+                .map(|&vertex| Vec3::from(positions[vertex as usize]))
+                .sum::<Vec3>();
+            let projection = centroid.dot(axis);
+            (triangle, if descending { -projection } else { projection })
+        })
+        .collect();
+    triangles.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+    let sorted = triangles
+        .iter()
+        .flat_map(|(triangle, _)| triangle.iter().copied())
+        .collect();
+    mesh.insert_indices(narrowed_indices(sorted, wide));
+    Ok(())
+}
+
+/// Reorders the triangles along a space-filling curve with meshoptimizer, keeping the index
+/// format.
+pub(crate) fn spatial_sort_triangles(mesh: &mut Mesh) -> Result<(), OptError> {
+    let positions = mesh_positions(mesh)?;
+    let (indices, wide) = indices_keeping_format(mesh)?;
+    let mut sorted = vec![0; indices.len()];
+    // SAFETY: `sorted` is as long as `indices`, which were validated against the positions.
+    unsafe {
+        meshopt::ffi::meshopt_spatialSortTriangles(
+            sorted.as_mut_ptr(),
+            indices.as_ptr(),
+            indices.len(),
+            positions.as_ptr().cast(),
+            positions.len(),
+            size_of::<[f32; 3]>(),
+        );
+    }
+    mesh.insert_indices(narrowed_indices(sorted, wide));
+    Ok(())
+}
+
+/// Indices in the format of the mesh, with every vertex remapped to the first vertex with the
+/// same position.
+pub(crate) fn shadow_indices(mesh: &Mesh) -> Result<Indices, OptError> {
+    let positions = mesh_positions(mesh)?;
+    let (indices, wide) = indices_keeping_format(mesh)?;
+    let mut shadow = vec![0; indices.len()];
+    // SAFETY: `shadow` is as long as `indices`, which were validated against the positions.
+    unsafe {
+        meshopt::ffi::meshopt_generateShadowIndexBuffer(
+            shadow.as_mut_ptr(),
+            indices.as_ptr(),
+            indices.len(),
+            positions.as_ptr().cast(),
+            positions.len(),
+            size_of::<[f32; 3]>(),
+            size_of::<[f32; 3]>(),
+        );
+    }
+    Ok(narrowed_indices(shadow, wide))
+}
+
+/// Position-only mesh indexed by the shadow indices of `mesh`, optimized for the vertex cache and
+/// without the vertices the shadow indices no longer use.
+pub(crate) fn shadow_mesh(mesh: &Mesh) -> Result<Mesh, OptError> {
+    let indices = shadow_indices(mesh)?;
+    let mut shadow = Mesh::new(PrimitiveTopology::TriangleList, mesh.asset_usage)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, mesh_positions(mesh)?.clone())
+        .with_inserted_indices(indices);
+    with_u32_indices(&mut shadow, false, |shadow| {
+        optimize_vertex_cache(shadow, &CacheModel::Lru)?;
+        optimize_vertex_fetch(shadow)?;
+        Ok(())
+    })?;
+    Ok(shadow)
+}
+
+/// Validated indices of the mesh widened to `u32`, along with whether they were `u32` already.
+pub(crate) fn indices_keeping_format(mesh: &Mesh) -> Result<(Vec<u32>, bool), OptError> {
+    let (indices, wide) = match mesh.indices() {
+        Some(Indices::U16(indices)) => (indices.iter().map(|&i| i as u32).collect(), false),
+        Some(Indices::U32(indices)) => (indices.clone(), true),
+        None => return Err(OptError::MissingIndices),
+    };
+    validate_indices(&indices, mesh.count_vertices())?;
+    Ok((indices, wide))
+}
+
+pub(crate) fn narrowed_indices(indices: Vec<u32>, wide: bool) -> Indices {
+    if wide {
+        Indices::U32(indices)
+    } else {
+        Indices::U16(indices.into_iter().map(|index| index as u16).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::asset::RenderAssetUsages;
+
+    use super::*;
+    use crate::{
+        MeshExt,
+        test_util::{beveled_cube, indices, positions, triangle_set, with_u16_indices},
+    };
+
+    fn unique(indices: &[u32]) -> usize {
+        let mut unique = indices.to_vec();
+        unique.sort_unstable();
+        unique.dedup();
+        unique.len()
+    }
+
+    #[test]
+    fn shadow_indices_share_vertices_with_the_same_position() {
+        let mesh = with_u16_indices(beveled_cube(0.2, 2));
+        let shadow = mesh.shadow_indices().unwrap();
+        assert!(matches!(shadow, Indices::U16(_)));
+        let shadow: Vec<u32> = shadow.iter().map(|index| index as u32).collect();
+        let source = indices(&mesh);
+        assert_eq!(shadow.len(), source.len());
+        // Split normals along the bevels duplicate vertices the shadow pass can share.
+        assert!(unique(&shadow) < unique(&source));
+
+        let positions = positions(&mesh);
+        for (shadow, source) in shadow.iter().zip(&source) {
+            assert_eq!(positions[*shadow as usize], positions[*source as usize]);
+        }
+    }
+
+    #[test]
+    fn shadow_indices_need_positions() {
+        let mesh = Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::default(),
+        )
+        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0.0f32, 1.0, 0.0]; 3])
+        .with_inserted_indices(Indices::U32(vec![0, 1, 2]));
+        assert!(matches!(
+            mesh.shadow_indices(),
+            Err(OptError::MissingPositions)
+        ));
+    }
+
+    #[test]
+    fn spatial_sort_keeps_the_triangles() {
+        let source = with_u16_indices(beveled_cube(0.2, 4));
+        let mut sorted = source.clone();
+        sorted.spatial_sort_in_place().unwrap();
+        assert!(matches!(sorted.indices(), Some(Indices::U16(_))));
+        assert_ne!(indices(&sorted), indices(&source));
+        assert_eq!(triangle_set(&sorted), triangle_set(&source));
+    }
+
+    /// Triangles of `indices` with their corners in order, sorted.
+    fn wound_triangles(indices: &[u32]) -> Vec<[u32; 3]> {
+        let mut triangles: Vec<[u32; 3]> = indices
+            .chunks_exact(3)
+            .map(|triangle| [triangle[0], triangle[1], triangle[2]])
+            .collect();
+        triangles.sort_unstable();
+        triangles
+    }
+
+    #[test]
+    fn axis_sort_orders_centroids_and_keeps_winding() {
+        let axis = Vec3::new(1.0, 2.0, -0.5).normalize();
+        for source in [beveled_cube(0.2, 4), with_u16_indices(beveled_cube(0.2, 4))] {
+            for descending in [false, true] {
+                let mut sorted = source.clone();
+                sorted
+                    .sort_triangles_along_axis_in_place(axis, descending)
+                    .unwrap();
+                assert_eq!(
+                    matches!(sorted.indices(), Some(Indices::U16(_))),
+                    matches!(source.indices(), Some(Indices::U16(_)))
+                );
+                assert_eq!(
+                    wound_triangles(&indices(&sorted)),
+                    wound_triangles(&indices(&source))
+                );
+
+                let positions = positions(&sorted);
+                let projections: Vec<f32> = indices(&sorted)
+                    .chunks_exact(3)
+                    .map(|triangle| {
+                        triangle
+                            .iter()
+                            .map(|&vertex| Vec3::from(positions[vertex as usize]))
+                            .sum::<Vec3>()
+                            .dot(axis)
+                    })
+                    .collect();
+                let ordered = projections.windows(2).all(|pair| match descending {
+                    false => pair[0] <= pair[1],
+                    true => pair[0] >= pair[1],
+                });
+                assert!(ordered, "descending: {descending}");
+            }
+        }
+    }
+}
@@ -0,0 +1,207 @@
+use std::{collections::HashSet, time::Duration};
+
+use bevy::{
+    app::{App, Plugin, Update},
+    asset::Assets,
+    color::Color,
+    diagnostic::{DiagnosticPath, DiagnosticsStore},
+    ecs::prelude::*,
+    input::{ButtonInput, keyboard::KeyCode},
+    mesh::{Mesh, Mesh3d},
+    text::TextFont,
+    time::Time,
+    ui::{BackgroundColor, Display, Node, PositionType, UiRect, Val, ZIndex, widget::Text},
+};
+
+use crate::{CurrentLod, MeshoptSet, SimplificationCompleted, SimplifyStats};
+
+/// In-game overlay showing the numbers of [`MeshoptPlugin`](crate::MeshoptPlugin) with plain
+/// `bevy_ui` text, for builds that don't ship egui. Toggled with
+/// [`MeshoptOverlay::toggle_key`].
+///
+/// Shows the meshes simplified and triangles saved since the app started, the last batch from
+/// [`SimplifyStats`] and the diagnostics the crate registers, a histogram of [`CurrentLod`] and
+/// the entities whose mesh hasn't loaded yet. The text is only rebuilt every
+/// [`MeshoptOverlay::refresh_interval`] while visible, so it doesn't get laid out again every
+/// frame.
+pub struct MeshoptOverlayPlugin;
+
+impl Plugin for MeshoptOverlayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MeshoptOverlay>()
+            .init_resource::<SessionTotals>()
+            .add_systems(
+                Update,
+                (toggle_overlay, accumulate_session, update_overlay)
+                    .chain()
+                    .after(MeshoptSet::LodSwitch),
+            );
+    }
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct MeshoptOverlay {
+    pub visible: bool,
+    /// Key flipping `visible`, `None` leaves it to the app.
+    pub toggle_key: Option<KeyCode>,
+    /// Time between text updates while visible.
+    pub refresh_interval: Duration,
+}
+
+impl Default for MeshoptOverlay {
+    fn default() -> Self {
+        MeshoptOverlay {
+            visible: false,
+            toggle_key: Some(KeyCode::F9),
+            refresh_interval: Duration::from_millis(250),
+        }
+    }
+}
+
+/// Totals since the app started, summed from [`SimplificationCompleted`] messages.
+#[derive(Resource, Debug, Default)]
+struct SessionTotals {
+    simplified_meshes: usize,
+    triangles_saved: usize,
+}
+
+/// Text node of the overlay, spawned the first time it is shown.
+#[derive(Component)]
+struct OverlayText;
+
+fn toggle_overlay(keys: Option<Res<ButtonInput<KeyCode>>>, mut overlay: ResMut<MeshoptOverlay>) {
+    if let (Some(keys), Some(key)) = (keys, overlay.toggle_key)
+        && keys.just_pressed(key)
+    {
+        overlay.visible = !overlay.visible;
+    }
+}
+
+fn accumulate_session(
+    mut completed: MessageReader<SimplificationCompleted>,
+    mut totals: ResMut<SessionTotals>,
+) {
+    // Entities sharing a mesh get a message each, the mesh is only counted once.
+    let mut seen = HashSet::new();
+    for message in completed.read() {
+        if let Ok(report) = &message.result
+            && seen.insert(message.mesh.id())
+        {
+            totals.simplified_meshes += 1;
+            totals.triangles_saved += report.triangles_before() - report.triangles_after();
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update_overlay(
+    mut commands: Commands,
+    overlay: Res<MeshoptOverlay>,
+    time: Res<Time>,
+    mut last_refresh: Local<Option<Duration>>,
+    mut text: Query<(&mut Text, &mut Node), With<OverlayText>>,
+    stats: Res<SimplifyStats>,
+    totals: Res<SessionTotals>,
+    diagnostics: Option<Res<DiagnosticsStore>>,
+    lods: Query<&CurrentLod>,
+    mesh3ds: Query<&Mesh3d>,
+    meshes: Res<Assets<Mesh>>,
+) {
+    let existing = text.single_mut().ok();
+    if !overlay.visible {
+        if let Some((_, mut node)) = existing
+            && node.display != Display::None
+        {
+            node.display = Display::None;
+        }
+        *last_refresh = None;
+        return;
+    }
+
+    let now = time.elapsed();
+    if last_refresh.is_some_and(|last| now.saturating_sub(last) < overlay.refresh_interval) {
+        return;
+    }
+    *last_refresh = Some(now);
+
+    let mut histogram = Vec::new();
+    for lod in &lods {
+        if histogram.len() <= lod.0 {
+            histogram.resize(lod.0 + 1, 0);
+        }
+        histogram[lod.0] += 1;
+    }
+    let pending = mesh3ds
+        .iter()
+        .filter(|mesh3d| !meshes.contains(mesh3d.id()))
+        .count();
+    let diagnostic = |path: &DiagnosticPath| {
+        diagnostics
+            .as_ref()
+            .and_then(|store| store.get(path))
+            .and_then(|diagnostic| diagnostic.value())
+            .map_or_else(|| "-".to_string(), |value| format!("{value:.4}"))
+    };
+    let histogram = if histogram.is_empty() {
+        "-".to_string()
+    } else {
+        let levels: Vec<_> = histogram
+            .iter()
+            .enumerate()
+            .map(|(level, count)| format!("{level}: {count}"))
+            .collect();
+        levels.join(", ")
+    };
+    let last_error = stats
+        .last_error
+        .or(stats.optimize_last_error)
+        .map_or_else(|| "-".to_string(), |error| error.to_string());
+
+    let content = format!(
+        "meshopt\n\
+         Session: {} meshes simplified, {} triangles saved\n\
+         Last batch: {} simplified ({} cached, {} failed), {} optimized ({} failed)\n\
+         Triangles: {}, error: {}, ACMR: {}, overdraw: {}\n\
+         LODs: {histogram}\n\
+         Pending: {pending} meshes loading\n\
+         Last error: {last_error}",
+        totals.simplified_meshes,
+        totals.triangles_saved,
+        stats.simplified_meshes,
+        stats.cache_hits,
+        stats.failed,
+        stats.optimized_meshes,
+        stats.optimize_failed,
+        diagnostic(&SimplifyStats::SIMPLIFY_TRIANGLES),
+        diagnostic(&SimplifyStats::SIMPLIFY_ERROR),
+        diagnostic(&SimplifyStats::OPTIMIZE_ACMR),
+        diagnostic(&SimplifyStats::OPTIMIZE_OVERDRAW),
+    );
+
+    match existing {
+        Some((mut text, mut node)) => {
+            if text.0 != content {
+                text.0 = content;
+            }
+            if node.display == Display::None {
+                node.display = Display::default();
+            }
+        }
+        None => {
+            commands.spawn((
+                OverlayText,
+                Text::new(content),
+                TextFont::from_font_size(14.0),
+                Node {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(8.0),
+                    left: Val::Px(8.0),
+                    padding: UiRect::all(Val::Px(6.0)),
+                    ..Default::default()
+                },
+                BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.7)),
+                ZIndex(i32::MAX),
+            ));
+        }
+    }
+}
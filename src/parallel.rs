@@ -0,0 +1,20 @@
+use bevy::tasks::ComputeTaskPool;
+
+/// Runs `f` over every item on the [`ComputeTaskPool`], returning the results in the order of
+/// `items`. Runs on the calling thread when the pool isn't set up, e.g. outside of an app, and for
+/// a single item. Without Bevy's `multi_threaded` feature the pool runs the items one after the
+/// other as well.
+pub(crate) fn par_map<T: Send, R: Send + 'static>(
+    items: Vec<T>,
+    f: impl Fn(T) -> R + Sync,
+) -> Vec<R> {
+    match ComputeTaskPool::try_get() {
+        Some(pool) if items.len() > 1 => pool.scope(|scope| {
+            let f = &f;
+            for item in items {
+                scope.spawn(async move { f(item) });
+            }
+        }),
+        _ => items.into_iter().map(f).collect(),
+    }
+}
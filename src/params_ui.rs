@@ -0,0 +1,394 @@
+use std::{hash::Hash, mem::discriminant};
+
+use bevy::math::UVec2;
+use egui::{ComboBox, DragValue, Grid, Response, Slider, Ui};
+use meshopt::SimplifyOptions;
+
+use crate::{
+    FallbackPolicy, HardEdgeDetection, HardEdges, SeamLocks, SimplifyMode, SimplifyParams,
+    SimplifyStrategy, TargetIndices, UvWeighting,
+};
+
+/// Edits `params` in `ui`, e.g. in a `bevy_egui` window or an editor panel. The returned response
+/// is marked as changed when any of the params is, and the options list every `SimplifyOptions`
+/// flag meshopt defines.
+///
+/// The per-mesh data, `vertex_locks`, `locked_vertices`, `symmetry`, `silhouette_locks` and
+/// `world_scale`, isn't shown and is left as it is.
+pub fn simplify_params_ui(ui: &mut Ui, params: &mut SimplifyParams) -> Response {
+    let mut changed = false;
+    let mut response = ui
+        .vertical(|ui| {
+            Grid::new("simplify_params").num_columns(2).show(ui, |ui| {
+                ui.label("Max Error:");
+                changed |= ui
+                    .add(Slider::new(&mut params.max_error, 0.0..=1.0).logarithmic(true))
+                    .changed();
+                ui.end_row();
+
+                ui.label("Target:");
+                changed |= variant_ui(
+                    ui,
+                    "target_index_count",
+                    &mut params.target_index_count,
+                    &[
+                        (TargetIndices::Multiplier(0.5), "Multiplier"),
+                        (TargetIndices::Count(3000), "Count"),
+                        (TargetIndices::TriangleCount(1000), "Triangles"),
+                        (TargetIndices::VertexCount(1000), "Vertices"),
+                        (TargetIndices::ErrorOnly, "Error Only"),
+                    ],
+                );
+                ui.end_row();
+                ui.label("");
+                changed |= match &mut params.target_index_count {
+                    TargetIndices::Count(count) => ui.add(
+                        Slider::new(count, 3..=300_000)
+                            .logarithmic(true)
+                            .text("indices"),
+                    ),
+                    TargetIndices::TriangleCount(triangles) => ui.add(
+                        Slider::new(triangles, 1..=100_000)
+                            .logarithmic(true)
+                            .text("triangles"),
+                    ),
+                    TargetIndices::VertexCount(vertices) => ui.add(
+                        Slider::new(vertices, 1..=100_000)
+                            .logarithmic(true)
+                            .text("vertices"),
+                    ),
+                    TargetIndices::Multiplier(multiplier) => {
+                        ui.add(Slider::new(multiplier, 0.0..=1.0))
+                    }
+                    TargetIndices::ErrorOnly => ui.label("until max error"),
+                }
+                .changed();
+                ui.end_row();
+
+                ui.label("Min Target:");
+                changed |= ui
+                    .add(DragValue::new(&mut params.min_target_index_count).suffix(" indices"))
+                    .on_hover_text("Smallest index count the target resolves to")
+                    .changed();
+                ui.end_row();
+
+                ui.label("Mode:");
+                changed |= variant_ui(
+                    ui,
+                    "mode",
+                    &mut params.mode,
+                    &[
+                        (SimplifyMode::Precise, "Precise"),
+                        (SimplifyMode::Sloppy, "Sloppy"),
+                        (SimplifyMode::Points, "Points"),
+                    ],
+                );
+                ui.end_row();
+
+                ui.label("Strategy:");
+                changed |= variant_ui(
+                    ui,
+                    "strategy",
+                    &mut params.strategy,
+                    &[
+                        (SimplifyStrategy::EdgeCollapse, "Edge Collapse"),
+                        (SimplifyStrategy::CardRemoval, "Card Removal"),
+                    ],
+                );
+                ui.end_row();
+
+                for (label, weight) in [
+                    ("Normal Weight:", &mut params.normal_weight),
+                    ("Color Weight:", &mut params.color_weight),
+                    ("Skinning Weight:", &mut params.skinning_weight),
+                ] {
+                    ui.label(label);
+                    changed |= ui.add(Slider::new(weight, 0.0..=2.0)).changed();
+                    ui.end_row();
+                }
+
+                for (label, weighting) in ["UV 0 Weight", "UV 1 Weight"]
+                    .into_iter()
+                    .zip(&mut params.uv_weighting)
+                {
+                    changed |= optional_ui(
+                        ui,
+                        label,
+                        weighting,
+                        || UvWeighting::Weight(1.0),
+                        |ui, weighting| uv_weighting_ui(ui, label, weighting),
+                    );
+                }
+
+                changed |= optional_ui(
+                    ui,
+                    "Planarity",
+                    &mut params.planarity_tolerance,
+                    || 1f32.to_radians(),
+                    |ui, tolerance| ui.drag_angle(tolerance).changed(),
+                );
+                changed |= optional_ui(
+                    ui,
+                    "Hard Edges",
+                    &mut params.hard_edges,
+                    HardEdges::default,
+                    |ui, hard_edges| {
+                        ui.horizontal(|ui| {
+                            ui.drag_angle(&mut hard_edges.angle_threshold).changed()
+                                | variant_ui(
+                                    ui,
+                                    "hard_edges",
+                                    &mut hard_edges.detection,
+                                    &[
+                                        (HardEdgeDetection::SplitNormals, "Split Normals"),
+                                        (HardEdgeDetection::FaceAngle, "Face Angle"),
+                                    ],
+                                )
+                        })
+                        .inner
+                    },
+                );
+
+                changed |= optional_ui(
+                    ui,
+                    "Seams",
+                    &mut params.seam_locks,
+                    SeamLocks::default,
+                    |ui, seams| {
+                        ui.horizontal(|ui| {
+                            ui.checkbox(&mut seams.uv_0, "UV 0").changed()
+                                | ui.checkbox(&mut seams.uv_1, "UV 1").changed()
+                        })
+                        .inner
+                    },
+                );
+
+                ui.label("Fallback:");
+                changed |= variant_ui(
+                    ui,
+                    "fallback",
+                    &mut params.fallback,
+                    &[
+                        (FallbackPolicy::None, "None"),
+                        (FallbackPolicy::Sloppy { max_error: 1.0 }, "Sloppy"),
+                        (
+                            FallbackPolicy::RelaxError {
+                                factor: 2.0,
+                                max_attempts: 4,
+                            },
+                            "Relax Error",
+                        ),
+                    ],
+                );
+                ui.end_row();
+                if params.fallback != FallbackPolicy::None {
+                    ui.label("");
+                    changed |= match &mut params.fallback {
+                        FallbackPolicy::None => false,
+                        FallbackPolicy::Sloppy { max_error } => ui
+                            .add(
+                                Slider::new(max_error, 0.0..=1.0)
+                                    .logarithmic(true)
+                                    .text("max error"),
+                            )
+                            .changed(),
+                        FallbackPolicy::RelaxError {
+                            factor,
+                            max_attempts,
+                        } => {
+                            ui.horizontal(|ui| {
+                                ui.add(Slider::new(factor, 1.0..=10.0).text("factor"))
+                                    .changed()
+                                    | ui.add(
+                                        DragValue::new(max_attempts)
+                                            .range(1..=16)
+                                            .suffix(" attempts"),
+                                    )
+                                    .changed()
+                            })
+                            .inner
+                        }
+                    };
+                    ui.end_row();
+                    ui.label("Tolerance:");
+                    changed |= ui
+                        .add(Slider::new(&mut params.fallback_tolerance, 0.0..=1.0))
+                        .on_hover_text("Fraction the result may exceed the target by")
+                        .changed();
+                    ui.end_row();
+                }
+            });
+
+            ui.add_space(10.0);
+            ui.label("Options:");
+            for (name, option) in SimplifyOptions::all().iter_names() {
+                let mut enabled = params.options.contains(option);
+                let checkbox = ui.checkbox(&mut enabled, name);
+                let checkbox = match OPTION_HINTS.iter().find(|(hinted, _)| *hinted == option) {
+                    Some((_, hint)) => checkbox.on_hover_text(*hint),
+                    None => checkbox,
+                };
+                if checkbox.changed() {
+                    params.options.set(option, enabled);
+                    changed = true;
+                }
+            }
+
+            ui.add_space(10.0);
+            for (flag, name, hint) in [
+                (
+                    &mut params.lock_non_manifold,
+                    "Lock Non-Manifold",
+                    "Lock the vertices of edges shared by more than two triangles",
+                ),
+                (
+                    &mut params.lock_joint_seams,
+                    "Lock Joint Seams",
+                    "Lock the edges between vertices dominated by different joints",
+                ),
+                (
+                    &mut params.merge_double_sided,
+                    "Merge Double-Sided",
+                    "Keep one of every pair of coincident triangles with opposite winding",
+                ),
+                (
+                    &mut params.strip_degenerates,
+                    "Strip Degenerates",
+                    "Drop zero-area triangles before simplifying",
+                ),
+                (
+                    &mut params.expand_generated_indices,
+                    "Expand Generated Indices",
+                    "Expand the result of non-indexed meshes back into one vertex per index",
+                ),
+                (
+                    &mut params.shrink_indices,
+                    "Shrink Indices",
+                    "Convert the indices of the result to u16 when its vertices fit",
+                ),
+                (
+                    &mut params.canonical_order,
+                    "Canonical Order",
+                    "Sort the triangles before simplifying, for results independent of their order",
+                ),
+            ] {
+                changed |= ui.checkbox(flag, name).on_hover_text(hint).changed();
+            }
+        })
+        .response;
+
+    if changed {
+        response.mark_changed();
+    }
+    response
+}
+
+/// Combo box selecting the variant of `value` out of `variants`, switching to another variant
+/// replaces `value` with the one given for it. Returns whether `value` changed.
+fn variant_ui<T: Clone>(
+    ui: &mut Ui,
+    id_salt: impl Hash,
+    value: &mut T,
+    variants: &[(T, &str)],
+) -> bool {
+    let selected = variants
+        .iter()
+        .find(|(variant, _)| discriminant(variant) == discriminant(value))
+        .map_or("", |&(_, name)| name);
+    let mut changed = false;
+    ComboBox::from_id_salt(id_salt)
+        .selected_text(selected)
+        .show_ui(ui, |ui| {
+            for (variant, name) in variants {
+                let is_selected = discriminant(variant) == discriminant(value);
+                if ui.selectable_label(is_selected, *name).clicked() && !is_selected {
+                    *value = variant.clone();
+                    changed = true;
+                }
+            }
+        });
+    changed
+}
+
+/// Grid row toggling `value` between `None` and `default` with a checkbox, edited by `edit` while
+/// it is set. Returns whether `value` changed.
+fn optional_ui<T>(
+    ui: &mut Ui,
+    label: &str,
+    value: &mut Option<T>,
+    default: impl FnOnce() -> T,
+    edit: impl FnOnce(&mut Ui, &mut T) -> bool,
+) -> bool {
+    let mut enabled = value.is_some();
+    let mut changed = ui.checkbox(&mut enabled, label).changed();
+    match (enabled, value.as_mut()) {
+        (true, Some(value)) => changed |= edit(ui, value),
+        (true, None) => *value = Some(default()),
+        (false, _) => {
+            *value = None;
+            ui.label("");
+        }
+    }
+    ui.end_row();
+    changed
+}
+
+fn uv_weighting_ui(ui: &mut Ui, id_salt: &str, weighting: &mut UvWeighting) -> bool {
+    ui.horizontal(|ui| {
+        let mut changed = variant_ui(
+            ui,
+            id_salt,
+            weighting,
+            &[
+                (UvWeighting::Weight(1.0), "Weight"),
+                (
+                    UvWeighting::Texels {
+                        resolution: UVec2::splat(1024),
+                        tolerance_texels: 1.0,
+                    },
+                    "Texels",
+                ),
+            ],
+        );
+        changed |= match weighting {
+            UvWeighting::Weight(weight) => ui.add(Slider::new(weight, 0.0..=2.0)).changed(),
+            UvWeighting::Texels {
+                resolution,
+                tolerance_texels,
+            } => {
+                ui.add(DragValue::new(&mut resolution.x).range(1..=16384))
+                    .changed()
+                    | ui.add(DragValue::new(&mut resolution.y).range(1..=16384))
+                        .changed()
+                    | ui.add(Slider::new(tolerance_texels, 0.1..=16.0).text("texels"))
+                        .changed()
+            }
+        };
+        changed
+    })
+    .inner
+}
+
+const OPTION_HINTS: [(SimplifyOptions, &str); 6] = [
+    (
+        SimplifyOptions::LockBorder,
+        "Prevent border vertices from moving",
+    ),
+    (SimplifyOptions::Sparse, "Use sparse decimation"),
+    (
+        SimplifyOptions::ErrorAbsolute,
+        "Use absolute error instead of relative",
+    ),
+    (
+        SimplifyOptions::Prune,
+        "Remove isolated components that fit within the error",
+    ),
+    (
+        SimplifyOptions::Regularize,
+        "Produce more regular triangle sizes and shapes, at some cost to geometric quality",
+    ),
+    (
+        SimplifyOptions::Permissive,
+        "Allow collapses across attribute seams",
+    ),
+];
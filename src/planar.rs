@@ -0,0 +1,188 @@
+use std::collections::VecDeque;
+
+use bevy::math::Vec3;
+
+use crate::adjacency::TriangleAdjacency;
+
+/// Per-vertex flags derived from grouping the triangles of a mesh into planar regions.
+pub(crate) struct PlanarRegions {
+    /// Vertices at a position shared by more than one region.
+    pub boundaries: Vec<bool>,
+    /// Vertices only used by the triangles of a single region.
+    pub interior: Vec<bool>,
+}
+
+/// Groups the triangles of a mesh into regions of faces whose normals stay within `tolerance`
+/// radians of the face the region was grown from.
+pub(crate) fn planar_regions(
+    indices: &[u32],
+    positions: &[[f32; 3]],
+    tolerance: f32,
+) -> PlanarRegions {
+    let welded = TriangleAdjacency::new(indices, positions);
+    let triangle_count = indices.len() / 3;
+
+    let cos_tolerance = tolerance.max(0.0).cos();
+    let mut regions = vec![u32::MAX; triangle_count];
+    let mut queue = VecDeque::new();
+    let mut region_count = 0;
+    for seed in 0..triangle_count {
+        if regions[seed] != u32::MAX {
+            continue;
+        }
+
+        let normal = welded.face_normals[seed];
+        regions[seed] = region_count;
+        queue.push_back(seed as u32);
+        while let Some(triangle) = queue.pop_front() {
+            for &neighbor in welded.neighbors(triangle) {
+                let neighbor_normal = welded.face_normals[neighbor as usize];
+                // Degenerate triangles join whichever region reaches them first.
+                let coplanar =
+                    neighbor_normal == Vec3::ZERO || normal.dot(neighbor_normal) >= cos_tolerance;
+                if regions[neighbor as usize] == u32::MAX && coplanar {
+                    regions[neighbor as usize] = region_count;
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        region_count += 1;
+    }
+
+    // Region of the first triangle seen around every vertex and welded vertex.
+    let mut welded_regions = vec![u32::MAX; welded.welded_vertex_count];
+    let mut welded_boundaries = vec![false; welded.welded_vertex_count];
+    let mut vertex_regions = vec![u32::MAX; positions.len()];
+    let mut interior = vec![false; positions.len()];
+    for (triangle, corners) in indices.chunks_exact(3).enumerate() {
+        let region = regions[triangle];
+        for &vertex in corners {
+            let welded_vertex = welded.remap[vertex as usize] as usize;
+            if welded_regions[welded_vertex] == u32::MAX {
+                welded_regions[welded_vertex] = region;
+            } else if welded_regions[welded_vertex] != region {
+                welded_boundaries[welded_vertex] = true;
+            }
+
+            let vertex = vertex as usize;
+            if vertex_regions[vertex] == u32::MAX {
+                vertex_regions[vertex] = region;
+                interior[vertex] = true;
+            } else if vertex_regions[vertex] != region {
+                interior[vertex] = false;
+            }
+        }
+    }
+
+    let mut boundaries = vec![false; positions.len()];
+    welded.flag_welded(&welded_boundaries, &mut boundaries);
+    PlanarRegions {
+        boundaries,
+        interior,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{
+        asset::RenderAssetUsages,
+        mesh::{Indices, Mesh, PrimitiveTopology},
+    };
+
+    use super::*;
+    use crate::{
+        MeshExt, SimplifyParams, TargetIndices,
+        test_util::{indices, positions},
+    };
+
+    /// Unit cube whose faces are `cells` by `cells` quads with slightly noisy normals, split along
+    /// the edges of the cube, like the tessellation of a CAD box.
+    fn tessellated_cube(cells: u32) -> Mesh {
+        let mut positions = Vec::new();
+        let mut normals = Vec::new();
+        let mut indices = Vec::new();
+        for normal in [
+            Vec3::X,
+            Vec3::NEG_X,
+            Vec3::Y,
+            Vec3::NEG_Y,
+            Vec3::Z,
+            Vec3::NEG_Z,
+        ] {
+            let u = normal.any_orthonormal_vector();
+            let v = normal.cross(u);
+            let first = positions.len() as u32;
+            for j in 0..=cells {
+                for i in 0..=cells {
+                    let [s, t] = [i, j].map(|step| step as f32 / cells as f32 - 0.5);
+                    positions.push((normal * 0.5 + u * s + v * t).to_array());
+                    let noise = 0.05 * ((i * 7 + j * 13) % 5) as f32;
+                    normals.push((normal + u * noise).normalize().to_array());
+                }
+            }
+            for j in 0..cells {
+                for i in 0..cells {
+                    let a = first + j * (cells + 1) + i;
+                    let [b, c, d] = [a + 1, a + cells + 2, a + cells + 1];
+                    indices.extend([a, b, c, a, c, d]);
+                }
+            }
+        }
+        Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::default(),
+        )
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+        .with_inserted_indices(Indices::U32(indices))
+    }
+
+    fn simplified(planarity_tolerance: Option<f32>) -> Mesh {
+        let mut mesh = tessellated_cube(8);
+        mesh.simplify_with_report(&SimplifyParams {
+            target_index_count: TargetIndices::Multiplier(0.0),
+            max_error: 0.001,
+            normal_weight: 1.0,
+            planarity_tolerance,
+            ..Default::default()
+        })
+        .unwrap();
+        mesh
+    }
+
+    #[test]
+    fn cube_regions_follow_its_faces() {
+        let cube = tessellated_cube(2);
+        let regions = planar_regions(&indices(&cube), positions(&cube), 0.01);
+        for (vertex, position) in positions(&cube).iter().enumerate() {
+            let on_edge = position.iter().filter(|value| value.abs() == 0.5).count() > 1;
+            assert_eq!(regions.boundaries[vertex], on_edge, "{position:?}");
+            assert!(regions.interior[vertex]);
+        }
+    }
+
+    #[test]
+    fn flat_faces_collapse_and_keep_the_edges_between_them() {
+        // The noise in the normals keeps the faces from collapsing without the planarity analysis.
+        let rough = indices(&simplified(None)).len() / 3;
+        assert!(rough > 6 * 16, "{rough} triangles left");
+        let mesh = simplified(Some(0.01));
+        let triangles = indices(&mesh).len() / 3;
+        assert!(triangles <= 6 * 4, "{triangles} triangles left");
+
+        // Every triangle still lies on a face of the cube, no collapse rounded off an edge.
+        let positions = positions(&mesh);
+        for corners in indices(&mesh).chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|corner| Vec3::from(positions[corners[corner] as usize]));
+            let normal = (b - a).cross(c - a).normalize();
+            assert_eq!(normal.abs().max_element(), 1.0, "{normal}");
+            for corner in [a, b, c] {
+                assert_eq!(corner.dot(normal), 0.5);
+            }
+        }
+        for corner in 0..8 {
+            let corner = [1, 2, 4].map(|bit| if corner & bit == 0 { -0.5 } else { 0.5 });
+            assert!(positions.contains(&corner), "{corner:?}");
+        }
+    }
+}
@@ -0,0 +1,986 @@
+use std::{borrow::Cow, collections::HashMap, sync::Arc, time::Duration};
+
+#[cfg(feature = "serialize")]
+use bevy::reflect::{ReflectDeserialize, ReflectSerialize};
+use bevy::{
+    app::{App, Plugin, Update},
+    asset::{AssetApp, AssetId, Assets, Handle},
+    diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic},
+    ecs::prelude::*,
+    ecs::schedule::{InternedScheduleLabel, ScheduleLabel},
+    mesh::{Indices, Mesh, Mesh3d, PrimitiveTopology},
+    platform::time::Instant,
+    prelude::{Deref, DerefMut},
+    reflect::{Reflect, std_traits::ReflectDefault},
+};
+
+use crate::{
+    CacheSettings, CompressedMeshLoader, DerivedMesh, DerivedMeshRefreshed, DerivedMeshes,
+    LodChainReport, MeshBoundsPlugin, MeshExt, MeshModified, MeshPass, MeshletCullingBounds,
+    MeshletsAsset, MeshletsLoader, MeshoptDiagnosticsPlugin, OptError, OptimizeReport,
+    OptimizeSettings, SimplifyCache, SimplifyMode, SimplifyParams, SimplifyReport, TargetIndices,
+    parallel::par_map,
+    refresh_derived_meshes,
+    simplify::{apply_simplified_indices, simplify_mesh_indices},
+    with_simplified_indices,
+};
+
+/// Adds the batch simplify/optimize workflow: set [`Simplify`] or [`Optimize`] to process every
+/// [`Mesh3d`] with the current [`SimplifySettings`] or [`OptimizeSettings`].
+///
+/// Processed meshes are added as new assets, the originals are left untouched so they can still be
+/// restored unless [`SourceReclaim`] says otherwise. Every processed mesh is reported with
+/// [`MeshModified`] so the [`MeshBoundsPlugin`] it adds refreshes the bounds of the entities using
+/// it. Processed meshes are tracked in [`DerivedMeshes`] and processed again by
+/// [`refresh_derived_meshes`] when their source is modified, e.g. hot-reloaded.
+///
+/// Also registers the [`MeshletsAsset`] loader for `.meshlets` files, the [`MeshletCullingBounds`]
+/// asset, and the diagnostics of [`SimplifyStats`], add [`MeshoptDiagnosticsPlugin`] for the
+/// timings of the batches.
+#[derive(Debug, Clone)]
+pub struct MeshoptPlugin {
+    /// Keeps simplified meshes on disk so identical meshes and settings are only simplified once
+    /// across sessions, see [`SimplifyCache`]. Ignored on wasm.
+    pub cache: Option<CacheSettings>,
+    /// Schedule the [`MeshoptSet`]s are configured and the batches run in, [`Update`] by default.
+    pub schedule: InternedScheduleLabel,
+}
+
+impl Default for MeshoptPlugin {
+    fn default() -> Self {
+        MeshoptPlugin {
+            cache: None,
+            schedule: Update.intern(),
+        }
+    }
+}
+
+impl MeshoptPlugin {
+    /// Runs the batches in `schedule` instead of [`Update`], e.g. `FixedUpdate` or the schedule of
+    /// an editor.
+    pub fn in_schedule(self, schedule: impl ScheduleLabel) -> Self {
+        MeshoptPlugin {
+            schedule: schedule.intern(),
+            ..self
+        }
+    }
+}
+
+impl Plugin for MeshoptPlugin {
+    fn build(&self, app: &mut App) {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(cache) = &self.cache {
+            app.insert_resource(SimplifyCache::new(cache.clone()));
+        }
+        if !app.is_plugin_added::<MeshBoundsPlugin>() {
+            app.add_plugins(MeshBoundsPlugin);
+        }
+
+        app.add_message::<SimplificationCompleted>()
+            .add_message::<LodChainCompleted>()
+            .add_message::<SourceMeshReclaimed>()
+            .add_message::<DerivedMeshRefreshed>()
+            .init_asset::<MeshletsAsset>()
+            .init_asset::<MeshletCullingBounds>()
+            .register_asset_loader(MeshletsLoader)
+            .register_asset_loader(CompressedMeshLoader)
+            .init_resource::<SimplifySettings>()
+            .init_resource::<Simplify>()
+            .init_resource::<OptimizeSettings>()
+            .init_resource::<Optimize>()
+            .init_resource::<SourceReclaim>()
+            .init_resource::<StrippedMeshes>()
+            .init_resource::<DerivedMeshes>()
+            .init_resource::<SimplifyStats>()
+            .register_type::<SimplifySettings>()
+            .register_type::<SimplifyParams>()
+            .register_type::<TargetIndices>()
+            .register_type::<SimplifyOverride>()
+            .register_type::<RequestTag>()
+            .register_diagnostic(Diagnostic::new(SimplifyStats::SIMPLIFY_TRIANGLES))
+            .register_diagnostic(Diagnostic::new(SimplifyStats::SIMPLIFY_ERROR))
+            .register_diagnostic(Diagnostic::new(SimplifyStats::OPTIMIZE_ACMR))
+            .register_diagnostic(Diagnostic::new(SimplifyStats::OPTIMIZE_OVERDRAW))
+            .configure_sets(
+                self.schedule,
+                (
+                    MeshoptSet::Queue,
+                    MeshoptSet::Process,
+                    MeshoptSet::Apply,
+                    MeshoptSet::LodSwitch,
+                )
+                    .chain(),
+            )
+            .add_systems(
+                self.schedule,
+                (
+                    (refresh_derived_meshes, simplify_meshes, optimize_meshes)
+                        .chain()
+                        .in_set(MeshoptSet::Process),
+                    update_picking_meshes.in_set(MeshoptSet::Apply),
+                ),
+            );
+    }
+}
+
+/// Stages of the work done by [`MeshoptPlugin`], run one after the other in
+/// [`MeshoptPlugin::schedule`]. Systems ordered relative to them have to be in that schedule,
+/// e.g. `app.add_systems(Update, generate_meshes.before(MeshoptSet::Process))` with the default
+/// one.
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MeshoptSet {
+    /// Deciding what to process: spawning or editing meshes, setting [`Simplify`] and
+    /// [`Optimize`], so they are picked up by the same frame's batch. The
+    /// [`MeshSimplifyPlugin`](crate::MeshSimplifyPlugin) takes its requests off their entities in
+    /// it, requests inserted by other systems in this set can wait for the next frame.
+    Queue,
+    /// [`refresh_derived_meshes`], then [`simplify_meshes`] followed by [`optimize_meshes`],
+    /// swapping the processed meshes in. Background simplifications are started in it.
+    Process,
+    /// Bookkeeping of the processed meshes, [`update_picking_meshes`], and swapping in the
+    /// background simplifications that finished. Systems consuming the results, e.g. building
+    /// colliders or reading [`SimplifyStats`], go after it.
+    Apply,
+    /// Switching entities between levels of detail, after the meshes they switch between are
+    /// processed, keeping their [`CurrentLod`] up to date, e.g. the
+    /// [`MeshLodPlugin`](crate::MeshLodPlugin) in [`Update`].
+    LodSwitch,
+}
+
+/// Level of detail an entity currently renders, `0` being the full resolution mesh. Set by the
+/// systems in [`MeshoptSet::LodSwitch`], read by debug tooling.
+#[derive(Component, Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct CurrentLod(pub usize);
+
+/// [`SimplifyParams`] of the simplify batch, editable in inspectors and, with the `serialize`
+/// feature, loaded from RON as the params themselves.
+#[derive(Resource, Deref, DerefMut, Debug, Clone, Default, Reflect)]
+#[reflect(Resource, Debug, Clone, Default)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent),
+    reflect(Serialize, Deserialize)
+)]
+pub struct SimplifySettings(pub SimplifyParams);
+
+/// Set to `true` to simplify all meshes once, reset after the batch ran. Entities can deviate
+/// from the [`SimplifySettings`] with a [`SimplifyOverride`]. The distinct meshes of a batch, like
+/// those of [`Optimize`], are processed in parallel on the `ComputeTaskPool`.
+#[derive(Resource, Debug, Default)]
+pub struct Simplify(pub bool);
+
+/// Set to `true` to optimize all meshes once, reset after the batch ran.
+#[derive(Resource, Debug, Default)]
+pub struct Optimize(pub bool);
+
+/// Totals of the last simplify and optimize batches.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct SimplifyStats {
+    pub simplify: SimplifyReport,
+    pub simplified_meshes: usize,
+    /// Time the last simplify batch took.
+    pub simplify_time: Duration,
+    pub optimize: OptimizeReport,
+    pub optimized_meshes: usize,
+    /// Time the last optimize batch took.
+    pub optimize_time: Duration,
+    /// Meshes of the last optimize batch left as is because they were already optimized, see
+    /// [`OptimizeSettings::skip_if_optimized`].
+    pub optimize_skipped: usize,
+    /// Meshes of the last simplify batch loaded from the [`SimplifyCache`] instead of being
+    /// simplified.
+    pub cache_hits: usize,
+    /// Meshes of the last simplify batch simplified by meshoptimizer, whether or not a cache is
+    /// used.
+    pub cache_misses: usize,
+    /// Meshes that failed to simplify in the last simplify batch.
+    pub failed: usize,
+    /// Meshes that failed to optimize in the last optimize batch, kept apart from `failed` so
+    /// batches running in the same frame don't hide each other's failures.
+    pub optimize_failed: usize,
+    /// Source meshes of the last batch dropped or stripped by [`SourceReclaim`].
+    pub reclaimed_meshes: usize,
+    /// Vertex and index buffer bytes those meshes held.
+    pub reclaimed_bytes: usize,
+    /// Source meshes of the last batch [`SourceReclaim`] left alone because something else still
+    /// holds a strong handle to them, see [`ReclaimOutcome::Refused`].
+    pub reclaim_refused: usize,
+    /// Error of the last mesh that failed to simplify.
+    pub last_error: Option<OptError>,
+    /// Error of the last mesh that failed to optimize.
+    pub optimize_last_error: Option<OptError>,
+    /// Distinct meshes kept alive by [`PickingMesh`] components.
+    pub picking_meshes: usize,
+    /// Vertex and index buffer bytes of those meshes.
+    pub picking_mesh_memory: usize,
+}
+
+impl SimplifyStats {
+    /// Triangles left after the last simplify batch.
+    pub const SIMPLIFY_TRIANGLES: DiagnosticPath =
+        DiagnosticPath::const_new("meshopt/simplify/triangles");
+    /// Largest error of the last simplify batch.
+    pub const SIMPLIFY_ERROR: DiagnosticPath = DiagnosticPath::const_new("meshopt/simplify/error");
+    /// Average cache miss ratio after the last optimize batch.
+    pub const OPTIMIZE_ACMR: DiagnosticPath = DiagnosticPath::const_new("meshopt/optimize/acmr");
+    /// Overdraw after the last optimize batch.
+    pub const OPTIMIZE_OVERDRAW: DiagnosticPath =
+        DiagnosticPath::const_new("meshopt/optimize/overdraw");
+}
+
+/// Sent by [`simplify_meshes`] once for every entity with a [`Mesh3d`] when a batch runs, except
+/// those skipped by [`SimplifyOverride::Skip`], including when its mesh failed to simplify or
+/// wasn't loaded, so gameplay code can react to specific entities being done. Also sent once for
+/// every simplification requested from the [`MeshSimplifyPlugin`](crate::MeshSimplifyPlugin),
+/// including cancelled ones.
+#[derive(Message, Debug, Clone)]
+pub struct SimplificationCompleted {
+    /// [`RequestTag`] of the entity when the request was made.
+    pub request_tag: Option<RequestTag>,
+    /// `None` for a [`MeshProcessRequest`](crate::MeshProcessRequest) without an entity.
+    pub entity: Option<Entity>,
+    /// Mesh the entity uses now, the simplified one on success and the unchanged one otherwise.
+    pub mesh: Handle<Mesh>,
+    pub result: Result<SimplifyReport, OptError>,
+}
+
+/// Sent once for every LOD chain requested from the
+/// [`MeshSimplifyPlugin`](crate::MeshSimplifyPlugin), like [`SimplificationCompleted`].
+#[derive(Message, Debug, Clone)]
+pub struct LodChainCompleted {
+    /// [`RequestTag`] of the entity when the request was made.
+    pub request_tag: Option<RequestTag>,
+    /// `None` for a [`MeshProcessRequest`](crate::MeshProcessRequest) without an entity.
+    pub entity: Option<Entity>,
+    /// Meshes of the levels from LOD0 on, empty when the chain failed.
+    pub levels: Vec<Handle<Mesh>>,
+    /// Reports of every level on success.
+    pub result: Result<LodChainReport, OptError>,
+}
+
+/// Identifies the requests made for an entity in the [`SimplificationCompleted`] and
+/// [`LodChainCompleted`] messages they are reported by, e.g. to tell a streamed in chunk is done.
+#[derive(Component, Debug, Copy, Clone, PartialEq, Eq, Hash, Reflect)]
+#[reflect(Component, Debug, Clone, PartialEq, Hash)]
+pub struct RequestTag(pub u64);
+
+/// What happens to the source meshes of a batch once every entity using them was switched to the
+/// processed copies. Meant for sources that are much larger than what they are processed into,
+/// e.g. multi-million vertex scans.
+///
+/// A source is only reclaimed when the entities switched by the batch held the only strong
+/// handles to it. Anything else holding one, like the `Gltf` asset it was loaded from, a
+/// [`PickingMesh`] or an entity whose mesh failed to process, keeps the source intact and is
+/// reported as [`ReclaimOutcome::Refused`], which is also how to find out why a large source is
+/// still in memory. Every source is reported with a [`SourceMeshReclaimed`] message and in
+/// [`SimplifyStats`]. Reclaimed sources can't be restored.
+#[derive(Resource, Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum SourceReclaim {
+    /// Leaves the sources as they are, they are freed as usual once nothing holds a strong handle
+    /// to them.
+    #[default]
+    Keep,
+    /// Removes the sources from [`Assets<Mesh>`] right away, lookups through their id fail
+    /// afterwards.
+    Drop,
+    /// Replaces the sources with an empty mesh of the same topology and keeps it alive in
+    /// [`StrippedMeshes`], so code still looking them up by id finds an empty mesh instead of
+    /// nothing.
+    Strip,
+}
+
+/// Handles keeping the empty meshes left by [`SourceReclaim::Strip`] alive, clear it to free them.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct StrippedMeshes(pub Vec<Handle<Mesh>>);
+
+/// Sent for every source mesh of a batch when [`SourceReclaim`] isn't [`SourceReclaim::Keep`].
+#[derive(Message, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SourceMeshReclaimed {
+    pub source: AssetId<Mesh>,
+    pub outcome: ReclaimOutcome,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ReclaimOutcome {
+    /// The source was removed, freeing `bytes` of vertex and index buffers.
+    Dropped { bytes: usize },
+    /// The source was replaced by an empty mesh, freeing `bytes` of vertex and index buffers.
+    Stripped { bytes: usize },
+    /// The source is still in use through `untracked_handles` strong handles the batch doesn't
+    /// know about and was left intact.
+    Refused { untracked_handles: usize },
+}
+
+/// Per-entity exception to the [`SimplifySettings`] of the simplify batch, e.g. to keep hero
+/// assets at full detail while background props are decimated with the same settings. Entities
+/// sharing a mesh with different overrides get separate simplified copies of it.
+///
+/// Also consulted by [`simplify_hierarchy`](crate::simplify_hierarchy), which simplifies meshes in
+/// place for the first override using them and into new assets for the others. Not consulted by
+/// [`simplify_scene`](crate::simplify_scene), which is given assets rather than entities, nor by
+/// the asset processor, which runs before there are entities: give such assets their own settings
+/// in their `.meta` file instead.
+#[derive(Component, Debug, Clone, PartialEq, Reflect)]
+#[reflect(Component, Debug, Clone, PartialEq)]
+pub enum SimplifyOverride {
+    /// Leaves the entity out of the batch, it keeps its mesh and gets no
+    /// [`SimplificationCompleted`].
+    Skip,
+    /// Replaces the target and error of the settings, `None` keeps the one of the settings.
+    Params {
+        target_index_count: Option<TargetIndices>,
+        max_error: Option<f32>,
+    },
+}
+
+impl SimplifyOverride {
+    /// `params` with the override applied, `None` for [`SimplifyOverride::Skip`].
+    pub fn apply(&self, params: &SimplifyParams) -> Option<SimplifyParams> {
+        match *self {
+            SimplifyOverride::Skip => None,
+            SimplifyOverride::Params {
+                target_index_count,
+                max_error,
+            } => Some(SimplifyParams {
+                target_index_count: target_index_count.unwrap_or(params.target_index_count),
+                max_error: max_error.unwrap_or(params.max_error),
+                ..params.clone()
+            }),
+        }
+    }
+}
+
+/// Opts an entity into keeping the mesh it had before being processed by [`MeshoptPlugin`] as a
+/// [`PickingMesh`]. Removing it removes the [`PickingMesh`] as well.
+#[derive(Component, Debug, Default, Copy, Clone)]
+pub struct KeepPickingMesh;
+
+/// Full resolution mesh of an entity whose [`Mesh3d`] was swapped for a simplified or optimized
+/// one, so raycasts can still hit the original surface. Holding the handle keeps the asset alive,
+/// see [`SimplifyStats::picking_mesh_memory`].
+///
+/// Only the first processing pass stores the mesh, later passes keep the original one. Raycasts
+/// should go through [`PickingMesh::resolve`] to fall back to the rendered mesh:
+///
+/// ```no_run
+/// use bevy::prelude::*;
+/// use bevy_meshopt::PickingMesh;
+///
+/// fn raycast_targets(
+///     query: Query<(&Mesh3d, Option<&PickingMesh>, &GlobalTransform)>,
+///     meshes: Res<Assets<Mesh>>,
+/// ) {
+///     for (mesh3d, picking, transform) in &query {
+///         let Some(mesh) = meshes.get(PickingMesh::resolve(mesh3d, picking)) else {
+///             continue;
+///         };
+///         // Intersect the ray with the triangles of `mesh`, transformed by `transform`.
+///     }
+/// }
+/// ```
+#[derive(Component, Deref, Debug, Clone)]
+pub struct PickingMesh(pub Handle<Mesh>);
+
+impl PickingMesh {
+    /// Mesh to raycast against, the picking mesh if the entity has one and the rendered mesh
+    /// otherwise.
+    pub fn resolve<'a>(mesh3d: &'a Mesh3d, picking: Option<&'a PickingMesh>) -> &'a Handle<Mesh> {
+        picking.map_or(&mesh3d.0, |picking| &picking.0)
+    }
+}
+
+type ProcessQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static mut Mesh3d,
+        Has<KeepPickingMesh>,
+        Has<PickingMesh>,
+        Option<&'static SimplifyOverride>,
+        Option<&'static RequestTag>,
+    ),
+>;
+
+/// Runs `f` over a copy of every distinct mesh used by a [`Mesh3d`] and points the entities at the
+/// processed copies, sending [`MeshModified`] for each copy, then calls `done` for every entity
+/// with its [`RequestTag`], its mesh and the result of `f`. The copies are processed in parallel on the
+/// `ComputeTaskPool`. Returns a handle to every source mesh that was processed and the result of
+/// every run of `f`, in the order the meshes were first used.
+///
+/// With `overrides`, entities are left out on [`SimplifyOverride::Skip`] and meshes are processed
+/// once per distinct override of the entities using them, which `f` and `pass` are passed. Every
+/// copy is recorded in `derived` with the [`MeshPass`] returned by `pass`.
+#[allow(clippy::too_many_arguments)]
+fn process_meshes<R: Copy + Send + 'static>(
+    commands: &mut Commands,
+    query: &mut ProcessQuery,
+    meshes: &mut Assets<Mesh>,
+    modified: &mut MessageWriter<MeshModified>,
+    derived: &mut DerivedMeshes,
+    overrides: bool,
+    pass: impl Fn(Option<&SimplifyOverride>) -> MeshPass,
+    f: impl Fn(&mut Mesh, Option<&SimplifyOverride>) -> Result<R, OptError> + Sync,
+    mut done: impl FnMut(Entity, Option<RequestTag>, &Handle<Mesh>, Result<R, OptError>),
+) -> (Vec<Handle<Mesh>>, Vec<Result<R, OptError>>) {
+    let mut variants: Vec<Option<SimplifyOverride>> = vec![None];
+    // Every mesh with the index of the override in `variants`, once.
+    let mut jobs: Vec<(Handle<Mesh>, usize)> = Vec::new();
+    for (_, mesh3d, _, _, entity_override, _) in query.iter() {
+        let entity_override = entity_override.filter(|_| overrides);
+        if entity_override == Some(&SimplifyOverride::Skip) {
+            continue;
+        }
+        let variant = variant_index(&mut variants, entity_override);
+        if !jobs
+            .iter()
+            .any(|(source, job)| source.id() == mesh3d.id() && *job == variant)
+        {
+            jobs.push((mesh3d.0.clone(), variant));
+        }
+    }
+
+    let copies = jobs
+        .iter()
+        .map(|(source, variant)| Some((meshes.get(source)?.clone(), *variant)))
+        .collect();
+    let outputs = par_map(copies, |copy| {
+        let (mut mesh, variant) = copy?;
+        let output = f(&mut mesh, variants[variant].as_ref());
+        Some(output.map(|output| (mesh, output)))
+    });
+
+    let mut processed =
+        HashMap::<(AssetId<Mesh>, usize), Result<(Handle<Mesh>, R), OptError>>::new();
+    let mut sources: Vec<Handle<Mesh>> = Vec::new();
+    let mut results = Vec::new();
+    for ((source, variant), output) in jobs.into_iter().zip(outputs) {
+        let result = match output {
+            Some(output) => {
+                results.push(
+                    output
+                        .as_ref()
+                        .map(|(_, output)| *output)
+                        .map_err(|err| *err),
+                );
+                output.map(|(mesh, output)| {
+                    if !sources.iter().any(|kept| kept.id() == source.id()) {
+                        sources.push(source.clone());
+                    }
+                    let handle = meshes.add(mesh);
+                    modified.write(MeshModified(handle.id()));
+                    derived.insert(
+                        handle.id(),
+                        DerivedMesh {
+                            source: source.id(),
+                            pass: pass(variants[variant].as_ref()),
+                        },
+                    );
+                    (handle, output)
+                })
+            }
+            None => Err(OptError::MissingMesh),
+        };
+        processed.insert((source.id(), variant), result);
+    }
+
+    for (entity, mut mesh3d, keep_picking_mesh, has_picking_mesh, entity_override, request_tag) in
+        query.iter_mut()
+    {
+        let entity_override = entity_override.filter(|_| overrides);
+        if entity_override == Some(&SimplifyOverride::Skip) {
+            continue;
+        }
+        let variant = variant_index(&mut variants, entity_override);
+        let result = processed[&(mesh3d.id(), variant)].clone();
+        let result = result.map(|(handle, output)| {
+            if keep_picking_mesh && !has_picking_mesh {
+                commands
+                    .entity(entity)
+                    .insert(PickingMesh(mesh3d.0.clone()));
+            }
+            mesh3d.0 = handle;
+            output
+        });
+        done(entity, request_tag.copied(), &mesh3d.0, result);
+    }
+    (sources, results)
+}
+
+/// Index of `entity_override` in `variants`, added when it's new.
+pub(crate) fn variant_index(
+    variants: &mut Vec<Option<SimplifyOverride>>,
+    entity_override: Option<&SimplifyOverride>,
+) -> usize {
+    variants
+        .iter()
+        .position(|variant| variant.as_ref() == entity_override)
+        .unwrap_or_else(|| {
+            variants.push(entity_override.cloned());
+            variants.len() - 1
+        })
+}
+
+/// Reclaims the memory of the `sources` of a batch according to `reclaim`, now that every entity
+/// using them was switched to the processed copies.
+fn reclaim_sources(
+    sources: Vec<Handle<Mesh>>,
+    reclaim: SourceReclaim,
+    meshes: &mut Assets<Mesh>,
+    stripped: &mut StrippedMeshes,
+    derived: &mut DerivedMeshes,
+    reclaimed: &mut MessageWriter<SourceMeshReclaimed>,
+    stats: &mut SimplifyStats,
+) {
+    stats.reclaimed_meshes = 0;
+    stats.reclaimed_bytes = 0;
+    stats.reclaim_refused = 0;
+    if reclaim == SourceReclaim::Keep {
+        return;
+    }
+
+    for source in sources {
+        // Handles with a stable id don't keep the asset alive, they are never reclaimed.
+        let Handle::Strong(strong) = &source else {
+            continue;
+        };
+        // Not counting the handle held here.
+        let untracked_handles = Arc::strong_count(strong) - 1;
+        let outcome = if untracked_handles > 0 {
+            stats.reclaim_refused += 1;
+            ReclaimOutcome::Refused { untracked_handles }
+        } else {
+            let bytes = meshes.get(&source).map_or(0, mesh_memory);
+            derived.forget_source(source.id());
+            stats.reclaimed_meshes += 1;
+            stats.reclaimed_bytes += bytes;
+            match reclaim {
+                SourceReclaim::Drop => {
+                    meshes.remove(&source);
+                    ReclaimOutcome::Dropped { bytes }
+                }
+                _ => {
+                    if let Some(mesh) = meshes.get_mut(&source) {
+                        *mesh = Mesh::new(mesh.primitive_topology(), mesh.asset_usage);
+                    }
+                    stripped.0.push(source.clone());
+                    ReclaimOutcome::Stripped { bytes }
+                }
+            }
+        };
+        reclaimed.write(SourceMeshReclaimed {
+            source: source.id(),
+            outcome,
+        });
+    }
+}
+
+/// Vertex and index buffer bytes of `mesh`.
+fn mesh_memory(mesh: &Mesh) -> usize {
+    let index_size = match mesh.indices() {
+        Some(Indices::U16(_)) => size_of::<u16>(),
+        _ => size_of::<u32>(),
+    };
+    mesh.count_vertices() * mesh.get_vertex_size() as usize
+        + mesh.indices().map_or(0, Indices::len) * index_size
+}
+
+/// Removes the [`PickingMesh`] of entities that opted out and keeps the picking stats of
+/// [`SimplifyStats`] up to date.
+pub fn update_picking_meshes(
+    mut commands: Commands,
+    opted_out: Query<Entity, (With<PickingMesh>, Without<KeepPickingMesh>)>,
+    picking_meshes: Query<&PickingMesh>,
+    added: Query<(), Added<PickingMesh>>,
+    mut removed: RemovedComponents<PickingMesh>,
+    meshes: Res<Assets<Mesh>>,
+    mut stats: ResMut<SimplifyStats>,
+) {
+    for entity in &opted_out {
+        commands.entity(entity).remove::<PickingMesh>();
+    }
+
+    // Read every frame, so removals don't pile up for a frame that has additions as well.
+    let removed_any = removed.read().count() > 0;
+    if added.is_empty() && !removed_any {
+        return;
+    }
+
+    let mut seen = HashMap::new();
+    for picking in &picking_meshes {
+        seen.entry(picking.id())
+            .or_insert_with(|| meshes.get(picking.id()).map_or(0, mesh_memory));
+    }
+    stats.picking_meshes = seen.len();
+    stats.picking_mesh_memory = seen.values().sum();
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn simplify_meshes(
+    mut commands: Commands,
+    mut simplify: ResMut<Simplify>,
+    mut completed: MessageWriter<SimplificationCompleted>,
+    mut reclaimed: MessageWriter<SourceMeshReclaimed>,
+    mut modified: MessageWriter<MeshModified>,
+    settings: Res<SimplifySettings>,
+    reclaim: Res<SourceReclaim>,
+    mut stripped: ResMut<StrippedMeshes>,
+    mut derived: ResMut<DerivedMeshes>,
+    cache: Option<Res<SimplifyCache>>,
+    mut query: ProcessQuery,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut stats: ResMut<SimplifyStats>,
+    mut diagnostics: Diagnostics,
+) {
+    if !simplify.0 {
+        return;
+    }
+    simplify.0 = false;
+
+    let start = Instant::now();
+    let mut totals = SimplifyReport::default();
+    let mut count = 0;
+    let mut error_sum = 0.0;
+    let mut cache_hits = 0;
+    let mut cache_misses = 0;
+    let mut failed = 0;
+    let mut last_error = None;
+    let params = &settings.0;
+    let cache = cache.as_deref();
+    let (sources, results) = process_meshes(
+        &mut commands,
+        &mut query,
+        &mut meshes,
+        &mut modified,
+        &mut derived,
+        true,
+        |entity_override| {
+            MeshPass::Simplify(Box::new(
+                entity_override
+                    .and_then(|o| o.apply(params))
+                    .unwrap_or_else(|| params.clone()),
+            ))
+        },
+        |mesh, entity_override| {
+            let params = match entity_override.and_then(|o| o.apply(params)) {
+                Some(params) => Cow::Owned(params),
+                None => Cow::Borrowed(params),
+            };
+            simplify_with_cache(mesh, &params, cache)
+        },
+        |entity, request_tag, mesh, result| {
+            completed.write(SimplificationCompleted {
+                request_tag,
+                entity: Some(entity),
+                mesh: mesh.clone(),
+                result: result.map(|(report, _)| report),
+            });
+        },
+    );
+    for result in results {
+        match result {
+            Ok((report, cache_hit)) => {
+                totals.accumulate(&report);
+                count += 1;
+                error_sum += report.result_error as f64;
+                if cache_hit {
+                    cache_hits += 1;
+                } else {
+                    cache_misses += 1;
+                }
+            }
+            Err(err) => {
+                cache_misses += 1;
+                failed += 1;
+                last_error = Some(err);
+            }
+        }
+    }
+
+    diagnostics.add_measurement(&SimplifyStats::SIMPLIFY_TRIANGLES, || {
+        totals.triangles_after() as f64
+    });
+    diagnostics.add_measurement(&SimplifyStats::SIMPLIFY_ERROR, || {
+        totals.result_error as f64
+    });
+    stats.simplify_time = start.elapsed();
+    diagnostics.add_measurement(
+        &MeshoptDiagnosticsPlugin::SIMPLIFY_TRIANGLES_REMOVED,
+        || {
+            totals
+                .triangles_before()
+                .saturating_sub(totals.triangles_after()) as f64
+        },
+    );
+    diagnostics.add_measurement(&MeshoptDiagnosticsPlugin::SIMPLIFY_TIME, || {
+        stats.simplify_time.as_secs_f64() * 1000.0
+    });
+    if count > 0 {
+        diagnostics.add_measurement(&MeshoptDiagnosticsPlugin::SIMPLIFY_AVERAGE_ERROR, || {
+            error_sum / count as f64
+        });
+    }
+    stats.simplify = totals;
+    stats.simplified_meshes = count;
+    stats.cache_hits = cache_hits;
+    stats.cache_misses = cache_misses;
+    stats.failed = failed;
+    stats.last_error = last_error;
+    reclaim_sources(
+        sources,
+        *reclaim,
+        &mut meshes,
+        &mut stripped,
+        &mut derived,
+        &mut reclaimed,
+        &mut stats,
+    );
+}
+
+/// Simplifies `mesh` with `params` like [`MeshExt::simplify_with_report`] through `cache`,
+/// returning the report and whether the result came from the cache. Point simplification changes
+/// the vertices, which the cache doesn't store, so it always runs.
+pub(crate) fn simplify_with_cache(
+    mesh: &mut Mesh,
+    params: &SimplifyParams,
+    cache: Option<&SimplifyCache>,
+) -> Result<(SimplifyReport, bool), OptError> {
+    if mesh.primitive_topology() == PrimitiveTopology::PointList
+        || params.mode == SimplifyMode::Points
+    {
+        return mesh
+            .simplify_with_report(params)
+            .map(|report| (report, false));
+    }
+    with_simplified_indices(mesh, params, |mesh| {
+        simplify_indices_with_cache(mesh, params, cache)
+    })
+}
+
+/// [`simplify_with_cache`] of a mesh with `u32` indices and `Float32x3` positions.
+fn simplify_indices_with_cache(
+    mesh: &mut Mesh,
+    params: &SimplifyParams,
+    cache: Option<&SimplifyCache>,
+) -> Result<(SimplifyReport, bool), OptError> {
+    // Params that can't be keyed fail to simplify as well, without the cache.
+    let cached = cache.and_then(|cache| {
+        let key = SimplifyCache::key(mesh, params).ok()?;
+        Some((cache, key, cache.load(key, mesh.count_vertices())))
+    });
+    let (indices, error, path) = match cached {
+        Some((_, _, Some(entry))) => {
+            let report =
+                apply_simplified_indices(mesh, params, entry.indices, entry.error, entry.path);
+            return Ok((report, true));
+        }
+        Some((cache, key, None)) => {
+            simplify_mesh_indices(mesh, params).inspect(|(indices, error, path)| {
+                cache.store(key, indices, mesh.count_vertices(), *error, *path);
+            })?
+        }
+        None => simplify_mesh_indices(mesh, params)?,
+    };
+    let report = apply_simplified_indices(mesh, params, indices, error, path);
+    Ok((report, false))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn optimize_meshes(
+    mut commands: Commands,
+    mut optimize: ResMut<Optimize>,
+    mut reclaimed: MessageWriter<SourceMeshReclaimed>,
+    mut modified: MessageWriter<MeshModified>,
+    settings: Res<OptimizeSettings>,
+    reclaim: Res<SourceReclaim>,
+    mut stripped: ResMut<StrippedMeshes>,
+    mut derived: ResMut<DerivedMeshes>,
+    mut query: ProcessQuery,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut stats: ResMut<SimplifyStats>,
+    mut diagnostics: Diagnostics,
+) {
+    if !optimize.0 {
+        return;
+    }
+    optimize.0 = false;
+
+    let start = Instant::now();
+    let mut totals = OptimizeReport::default();
+    let mut count = 0;
+    let mut skipped = 0;
+    let mut failed = 0;
+    let mut last_error = None;
+    let settings = &*settings;
+    let (sources, results) = process_meshes(
+        &mut commands,
+        &mut query,
+        &mut meshes,
+        &mut modified,
+        &mut derived,
+        false,
+        |_| MeshPass::Optimize(*settings),
+        |mesh, _| mesh.optimize(settings),
+        |_, _, _, _| {},
+    );
+    for result in results {
+        match result {
+            Ok(report) => {
+                totals.accumulate(&report);
+                count += 1;
+                if report.skipped_reason.is_some() {
+                    skipped += 1;
+                }
+            }
+            Err(err) => {
+                failed += 1;
+                last_error = Some(err);
+            }
+        }
+    }
+
+    diagnostics.add_measurement(&SimplifyStats::OPTIMIZE_ACMR, || totals.acmr_after() as f64);
+    diagnostics.add_measurement(&SimplifyStats::OPTIMIZE_OVERDRAW, || {
+        totals.overdraw_after() as f64
+    });
+    stats.optimize_time = start.elapsed();
+    diagnostics.add_measurement(&MeshoptDiagnosticsPlugin::OPTIMIZE_TIME, || {
+        stats.optimize_time.as_secs_f64() * 1000.0
+    });
+    stats.optimize = totals;
+    stats.optimized_meshes = count;
+    stats.optimize_skipped = skipped;
+    stats.optimize_failed = failed;
+    stats.optimize_last_error = last_error;
+    reclaim_sources(
+        sources,
+        *reclaim,
+        &mut meshes,
+        &mut stripped,
+        &mut derived,
+        &mut reclaimed,
+        &mut stats,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::{diagnostic::DiagnosticsStore, ecs::system::RunSystemOnce};
+
+    use crate::test_util::{indices, sphere, with_u16_indices};
+
+    fn half() -> SimplifyParams {
+        SimplifyParams {
+            target_index_count: TargetIndices::Multiplier(0.5),
+            ..Default::default()
+        }
+    }
+
+    /// The batch has to simplify like `MeshExt::simplify_with_report` does.
+    fn assert_simplifies_like_trait(mesh: Mesh) -> Mesh {
+        let mut batch = mesh.clone();
+        let mut direct = mesh;
+        simplify_with_cache(&mut batch, &half(), None).unwrap();
+        direct.simplify_with_report(&half()).unwrap();
+        assert_eq!(indices(&batch), indices(&direct));
+        assert_eq!(batch.count_vertices(), direct.count_vertices());
+        batch
+    }
+
+    #[test]
+    fn batch_keeps_u16_indices() {
+        let simplified = assert_simplifies_like_trait(with_u16_indices(sphere(4)));
+        assert!(matches!(simplified.indices(), Some(Indices::U16(_))));
+    }
+
+    #[test]
+    fn batch_indexes_triangle_soup() {
+        let mut soup = sphere(4);
+        soup.duplicate_vertices();
+        soup.remove_indices();
+        assert_simplifies_like_trait(soup);
+    }
+
+    /// World ready to run a simplify batch halving the meshes.
+    fn batch_world() -> World {
+        let mut world = World::new();
+        world.init_resource::<Assets<Mesh>>();
+        world.init_resource::<Messages<SimplificationCompleted>>();
+        world.init_resource::<Messages<SourceMeshReclaimed>>();
+        world.init_resource::<Messages<MeshModified>>();
+        world.init_resource::<SourceReclaim>();
+        world.init_resource::<StrippedMeshes>();
+        world.init_resource::<DerivedMeshes>();
+        world.init_resource::<SimplifyStats>();
+        world.init_resource::<DiagnosticsStore>();
+        world.insert_resource(Simplify(true));
+        world.insert_resource(SimplifySettings(SimplifyParams {
+            max_error: 1.0,
+            ..half()
+        }));
+        world
+    }
+
+    /// Stats of a simplify batch over a fresh world with one entity using `sphere(4)`.
+    fn run_batch(cache: &SimplifyCache) -> SimplifyStats {
+        let mut world = batch_world();
+        world.insert_resource(cache.clone());
+        let handle = world.resource_mut::<Assets<Mesh>>().add(sphere(4));
+        world.spawn(Mesh3d(handle));
+        world.run_system_once(simplify_meshes).unwrap();
+        world.remove_resource::<SimplifyStats>().unwrap()
+    }
+
+    #[test]
+    fn second_batch_is_served_from_cache() {
+        let directory =
+            std::env::temp_dir().join(format!("bevy_meshopt_cache_test_{}", std::process::id()));
+        let cache = SimplifyCache::new(CacheSettings::new(&directory));
+
+        let first = run_batch(&cache);
+        // Entries are written on the `IoTaskPool` once another test started it.
+        for _ in 0..1000 {
+            let written = std::fs::read_dir(&directory).is_ok_and(|mut entries| {
+                entries.any(|entry| {
+                    entry.is_ok_and(|entry| entry.path().extension().is_some_and(|e| e == "msce"))
+                })
+            });
+            if written {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        let second = run_batch(&cache);
+        let _ = std::fs::remove_dir_all(&directory);
+        assert_eq!((first.cache_hits, first.cache_misses), (0, 1));
+        assert_eq!((second.cache_hits, second.cache_misses), (1, 0));
+        assert_eq!(first.simplify, second.simplify);
+    }
+
+    #[test]
+    fn batch_reports_every_entity_once_with_its_tag() {
+        let mut world = batch_world();
+        let handle = world.resource_mut::<Assets<Mesh>>().add(sphere(3));
+        let tagged = world.spawn((Mesh3d(handle.clone()), RequestTag(7))).id();
+        let untagged = world.spawn(Mesh3d(handle.clone())).id();
+        world.spawn((Mesh3d(handle), SimplifyOverride::Skip, RequestTag(8)));
+        world.run_system_once(simplify_meshes).unwrap();
+
+        let completed: Vec<_> = world
+            .resource_mut::<Messages<SimplificationCompleted>>()
+            .drain()
+            .map(|completed| {
+                (
+                    completed.entity,
+                    completed.request_tag,
+                    completed.result.is_ok(),
+                )
+            })
+            .collect();
+        assert_eq!(completed.len(), 2);
+        assert!(completed.contains(&(Some(tagged), Some(RequestTag(7)), true)));
+        assert!(completed.contains(&(Some(untagged), None, true)));
+    }
+}
@@ -0,0 +1,270 @@
+use bevy::mesh::{Mesh, PrimitiveTopology, VertexAttributeValues};
+use meshopt::SimplifyOptions;
+
+use crate::{
+    OptError, SimplifyParams, SimplifyReport, TargetIndices, float32x3_positions, mesh_indices,
+    mesh_positions, refuse_morph_targets, vertex::gather_vertices, with_planar_positions,
+};
+
+/// Number of points [`MeshExt::simplify_points_in_place`](crate::MeshExt::simplify_points_in_place)
+/// keeps.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PointTarget {
+    Count(usize),
+    /// Fraction of the current points, clamped to `0.0..=1.0`. NaN counts as `0.0`.
+    Multiplier(f32),
+}
+
+impl Default for PointTarget {
+    fn default() -> Self {
+        PointTarget::Multiplier(0.5)
+    }
+}
+
+impl PointTarget {
+    /// Point count this target resolves to for a cloud of `current_count` points, never more than
+    /// `current_count`.
+    pub fn resolve(self, current_count: usize) -> usize {
+        let count = match self {
+            PointTarget::Count(count) => count,
+            PointTarget::Multiplier(multiplier) => {
+                (current_count as f32 * multiplier.clamp(0.0, 1.0)) as usize
+            }
+        };
+        count.min(current_count)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct PointSimplifyParams {
+    pub target: PointTarget,
+    /// Weight of `ATTRIBUTE_COLOR` against the positions when picking the points to keep, `0.0`
+    /// only looks at positions. Only the RGB channels of `Float32x4` colors are read, values
+    /// around `1.0` work well for colors in `0.0..=1.0`.
+    pub color_weight: f32,
+}
+
+/// Keeps the points meshoptimizer picks out of a `PointList` mesh, in every attribute. The mesh
+/// ends up non-indexed: every vertex is simplified, whether the indices refer to it or not.
+pub(crate) fn simplify_points(
+    mesh: &mut Mesh,
+    params: &PointSimplifyParams,
+) -> Result<(), OptError> {
+    let topology = mesh.primitive_topology();
+    if topology != PrimitiveTopology::PointList {
+        return Err(OptError::UnsupportedPrimitiveTopology(topology));
+    }
+    with_planar_positions(mesh, |mesh| simplify_point_positions(mesh, params))
+}
+
+fn simplify_point_positions(mesh: &mut Mesh, params: &PointSimplifyParams) -> Result<(), OptError> {
+    let positions = float32x3_positions(mesh)?;
+    let vertex_count = positions.len();
+
+    let target = params.target.resolve(vertex_count);
+    if target >= vertex_count {
+        return Ok(());
+    }
+
+    let colors = match mesh.attribute(Mesh::ATTRIBUTE_COLOR) {
+        Some(VertexAttributeValues::Float32x4(colors)) if params.color_weight > 0.0 => Some(colors),
+        _ => None,
+    };
+    let mut kept = vec![0; target];
+    // SAFETY: `kept` has room for the `target` points the simplifier writes at most, which is no
+    // more than the vertex count, and `colors` has one value per position.
+    let count = unsafe {
+        meshopt::ffi::meshopt_simplifyPoints(
+            kept.as_mut_ptr(),
+            positions.as_ptr().cast(),
+            vertex_count,
+            size_of::<[f32; 3]>(),
+            colors.map_or(std::ptr::null(), |colors| colors.as_ptr().cast()),
+            colors.map_or(0, |_| size_of::<[f32; 4]>()),
+            params.color_weight,
+            target,
+        )
+    };
+    kept.truncate(count);
+
+    *mesh = gather_vertices(mesh, &kept);
+    Ok(())
+}
+
+/// [`SimplifyMode::Points`](crate::SimplifyMode::Points): replaces the mesh with the points
+/// meshoptimizer keeps out of the vertices its triangles use.
+pub(crate) fn simplify_to_points(
+    mesh: &mut Mesh,
+    params: &SimplifyParams,
+) -> Result<SimplifyReport, OptError> {
+    if params.options != SimplifyOptions::None {
+        return Err(OptError::UnsupportedSimplifyOptions(params.options));
+    }
+    refuse_morph_targets(mesh)?;
+    let indices = mesh_indices(mesh)?;
+    let positions = mesh_positions(mesh)?;
+    if let Some(vertex) = positions
+        .iter()
+        .position(|position| !position.iter().all(|value| value.is_finite()))
+    {
+        return Err(OptError::InvalidPositions(vertex));
+    }
+
+    let mut used = vec![false; positions.len()];
+    for &index in indices {
+        used[index as usize] = true;
+    }
+    let sources: Vec<u32> = (0..positions.len() as u32)
+        .filter(|&vertex| used[vertex as usize])
+        .collect();
+    let target = point_target(params, sources.len(), indices.len());
+
+    let gathered = gather_vertices(mesh, &sources);
+    let mut points = Mesh::new(PrimitiveTopology::PointList, mesh.asset_usage);
+    for (attribute, values) in gathered.attributes() {
+        points.insert_attribute(*attribute, values.clone());
+    }
+    simplify_points(
+        &mut points,
+        &PointSimplifyParams {
+            target: PointTarget::Count(target),
+            color_weight: params.color_weight,
+        },
+    )?;
+
+    let report = SimplifyReport::new(mesh, params, 0, points.count_vertices(), 0.0);
+    *mesh = points;
+    Ok(report)
+}
+
+/// [`MeshExt::simplify`](crate::MeshExt::simplify) of a `PointList` mesh, e.g. a scanned point
+/// cloud: keeps the points meshoptimizer picks like [`SimplifyMode::Points`](crate::SimplifyMode::Points),
+/// counting one triangle per point for the targets in indices, whatever the mode.
+pub(crate) fn simplify_point_list(
+    mesh: &mut Mesh,
+    params: &SimplifyParams,
+) -> Result<SimplifyReport, OptError> {
+    if params.options != SimplifyOptions::None {
+        return Err(OptError::UnsupportedSimplifyOptions(params.options));
+    }
+    refuse_morph_targets(mesh)?;
+    let point_count = mesh.count_vertices();
+    let source = mesh.clone();
+    simplify_points(
+        mesh,
+        &PointSimplifyParams {
+            target: PointTarget::Count(point_target(params, point_count, point_count * 3)),
+            color_weight: params.color_weight,
+        },
+    )?;
+    Ok(SimplifyReport::new(
+        &source,
+        params,
+        0,
+        mesh.count_vertices(),
+        0.0,
+    ))
+}
+
+/// Number of points the point simplifier keeps out of `point_count` with `params`, one per
+/// triangle the target resolves to for a mesh of `index_count` indices.
+fn point_target(params: &SimplifyParams, point_count: usize, index_count: usize) -> usize {
+    match params.target_index_count {
+        TargetIndices::VertexCount(vertices) => vertices,
+        TargetIndices::ErrorOnly => point_count,
+        target => target.resolve(index_count, params.min_target_index_count) / 3,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{asset::RenderAssetUsages, math::Vec3};
+
+    use super::*;
+    use crate::{MeshExt, test_util::positions};
+
+    /// `count` points spiraling around a unit sphere, colored by their position, with normals.
+    fn point_cloud(count: usize) -> Mesh {
+        let golden_angle = std::f32::consts::PI * (3.0 - 5f32.sqrt());
+        let positions: Vec<[f32; 3]> = (0..count)
+            .map(|i| {
+                let y = 1.0 - 2.0 * (i as f32 + 0.5) / count as f32;
+                let radius = (1.0 - y * y).sqrt();
+                let angle = golden_angle * i as f32;
+                [radius * angle.cos(), y, radius * angle.sin()]
+            })
+            .collect();
+        let colors: Vec<[f32; 4]> = positions
+            .iter()
+            .map(|&[x, y, z]| [x * 0.5 + 0.5, y * 0.5 + 0.5, z * 0.5 + 0.5, 1.0])
+            .collect();
+        Mesh::new(PrimitiveTopology::PointList, RenderAssetUsages::default())
+            .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, positions.clone())
+            .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, colors)
+    }
+
+    #[test]
+    fn point_cloud_keeps_consistent_attributes() {
+        let mut cloud = point_cloud(10_000);
+        cloud
+            .simplify_points_in_place(&PointSimplifyParams {
+                target: PointTarget::Count(1_000),
+                color_weight: 1.0,
+            })
+            .unwrap();
+        let count = cloud.count_vertices();
+        assert!((900..=1_000).contains(&count), "{count} points");
+        assert!(cloud.indices().is_none());
+        assert_eq!(cloud.attributes().count(), 3);
+        assert!(cloud.attributes().all(|(_, values)| values.len() == count));
+
+        // Every point keeps the normal and color it came with.
+        let Some(VertexAttributeValues::Float32x4(colors)) = cloud.attribute(Mesh::ATTRIBUTE_COLOR)
+        else {
+            unreachable!()
+        };
+        let Some(VertexAttributeValues::Float32x3(normals)) =
+            cloud.attribute(Mesh::ATTRIBUTE_NORMAL)
+        else {
+            unreachable!()
+        };
+        for ((position, normal), color) in positions(&cloud).iter().zip(normals).zip(colors) {
+            assert_eq!(position, normal);
+            let expected = Vec3::from_array(*position) * 0.5 + 0.5;
+            assert!(Vec3::new(color[0], color[1], color[2]).distance(expected) < 1e-6);
+        }
+    }
+
+    #[test]
+    fn target_at_or_above_point_count_leaves_cloud() {
+        for target in [PointTarget::Count(100), PointTarget::Multiplier(1.5)] {
+            let mut cloud = point_cloud(100);
+            cloud
+                .simplify_points_in_place(&PointSimplifyParams {
+                    target,
+                    ..Default::default()
+                })
+                .unwrap();
+            assert_eq!(positions(&cloud), positions(&point_cloud(100)));
+        }
+    }
+
+    #[test]
+    fn mismatched_attribute_lengths_fail() {
+        let mut cloud = point_cloud(100);
+        cloud.insert_attribute(Mesh::ATTRIBUTE_COLOR, vec![[1.0f32; 4]; 50]);
+        let result = cloud.simplify_points_in_place(&PointSimplifyParams {
+            color_weight: 1.0,
+            ..Default::default()
+        });
+        assert!(matches!(
+            result,
+            Err(OptError::MismatchedAttributeLength {
+                expected: 100,
+                actual: 50,
+                ..
+            })
+        ));
+    }
+}
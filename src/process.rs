@@ -0,0 +1,318 @@
+use bevy::mesh::Mesh;
+use meshopt::SimplifyOptions;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    LevelSpec, LevelTarget, LodChain, LodChainParams, LodLevels, LodStrategy, MeshExt, OptError,
+    OptimizeReport, OptimizeSettings, SimplifyMode, SimplifyParams, SimplifyReport, TargetIndices,
+};
+
+/// How a mesh asset is processed, meant to be stored per asset in the settings of its `.meta`
+/// file so individual assets can override the defaults, e.g. a hero statue kept at 200 000
+/// triangles next to generic rocks dropped to 2 000.
+///
+/// Every field is optional in the meta file, missing ones take the value of
+/// [`MeshProcessSettings::default`], and unknown fields are rejected so typos don't go unnoticed.
+/// As with any processed asset, changing the meta file changes its hash and the asset is processed
+/// again. An asset whose settings fail [`MeshProcessSettings::validate`] fails to process instead
+/// of falling back to the defaults.
+///
+/// ```ron
+/// (
+///     meta_format_version: "1.0",
+///     asset: Process(
+///         processor: "...",
+///         settings: (
+///             // `None` keeps every triangle of the source mesh.
+///             simplify: Some((
+///                 // Or `Multiplier(0.1)` for a fraction of the source triangles.
+///                 target: Triangles(2000),
+///                 max_error: 0.02,
+///                 mode: Precise,
+///                 lock_border: false,
+///                 prune: false,
+///             )),
+///             optimize: (vertex_cache: true, overdraw: true, vertex_fetch: true),
+///             // Levels following LOD0, relative to LOD0 and strictly decreasing. Empty
+///             // generates no LODs.
+///             lods: [
+///                 (target: Multiplier(0.5)),
+///                 (target: Multiplier(0.25), max_error: Some(0.05)),
+///                 (target: Triangles(100), min_triangles: Some(50)),
+///             ],
+///             lod_strategy: Cascaded,
+///             min_triangles: 0,
+///             canonicalize: false,
+///         ),
+///     ),
+/// )
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MeshProcessSettings {
+    /// Simplification applied to the source mesh before the other stages, after `canonicalize`,
+    /// `None` keeps its triangles.
+    pub simplify: Option<ProcessSimplify>,
+    pub optimize: ProcessOptimize,
+    /// Levels generated from the processed mesh, see [`LodLevels::Schedule`].
+    pub lods: Vec<LevelSpec>,
+    pub lod_strategy: LodStrategy,
+    /// See [`LodChainParams::min_triangles`].
+    pub min_triangles: u32,
+    /// Sorts the vertices and triangles of the source mesh before any other stage, see
+    /// [`MeshExt::canonicalize`], so sources exported in a different order between runs process
+    /// to the same bytes.
+    pub canonicalize: bool,
+}
+
+impl Default for MeshProcessSettings {
+    fn default() -> Self {
+        MeshProcessSettings {
+            simplify: Some(ProcessSimplify::default()),
+            optimize: ProcessOptimize::default(),
+            lods: Vec::new(),
+            lod_strategy: LodStrategy::default(),
+            min_triangles: 0,
+            canonicalize: false,
+        }
+    }
+}
+
+/// Simplification stage of [`MeshProcessSettings`].
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProcessSimplify {
+    /// Triangle count to simplify to, a multiplier is relative to the source mesh.
+    pub target: LevelTarget,
+    /// See [`SimplifyParams::max_error`].
+    pub max_error: f32,
+    /// See [`SimplifyParams::mode`], [`SimplifyMode::Points`] isn't supported since the processed
+    /// mesh has to stay triangles.
+    pub mode: SimplifyMode,
+    /// Keeps the open borders of the mesh in place, see `SimplifyOptions::LockBorder`.
+    pub lock_border: bool,
+    /// Removes the disconnected components that fit within the error, see
+    /// `SimplifyOptions::Prune` and [`MeshExt::prune_components`](crate::MeshExt::prune_components).
+    pub prune: bool,
+}
+
+impl Default for ProcessSimplify {
+    fn default() -> Self {
+        let params = SimplifyParams::default();
+        ProcessSimplify {
+            target: LevelTarget::Multiplier(0.5),
+            max_error: params.max_error,
+            mode: params.mode,
+            lock_border: false,
+            prune: false,
+        }
+    }
+}
+
+/// Optimization stages of [`MeshProcessSettings`], run after simplifying.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProcessOptimize {
+    pub vertex_cache: bool,
+    pub overdraw: bool,
+    pub vertex_fetch: bool,
+}
+
+impl Default for ProcessOptimize {
+    fn default() -> Self {
+        let settings = OptimizeSettings::default();
+        ProcessOptimize {
+            vertex_cache: settings.vertex_cache,
+            overdraw: settings.overdraw,
+            vertex_fetch: settings.vertex_fetch,
+        }
+    }
+}
+
+impl MeshProcessSettings {
+    /// Checks the values that can't be right for any mesh, failing with
+    /// [`OptError::InvalidProcessSettings`] naming the offending field or with
+    /// [`OptError::InvalidLodSchedule`] naming the offending level.
+    pub fn validate(&self) -> Result<(), OptError> {
+        if let Some(simplify) = &self.simplify {
+            if !valid_target(simplify.target) {
+                return Err(OptError::InvalidProcessSettings(
+                    "simplify.target has to be a multiplier in (0, 1] or a positive triangle count",
+                ));
+            }
+            if !(simplify.max_error.is_finite() && simplify.max_error >= 0.0) {
+                return Err(OptError::InvalidProcessSettings(
+                    "simplify.max_error has to be finite and non-negative",
+                ));
+            }
+            if simplify.mode == SimplifyMode::Points {
+                return Err(OptError::InvalidProcessSettings(
+                    "simplify.mode can't be Points, the processed mesh has to stay triangles",
+                ));
+            }
+            if simplify.mode == SimplifyMode::Sloppy && simplify.lock_border {
+                return Err(OptError::InvalidProcessSettings(
+                    "simplify.lock_border isn't supported with simplify.mode Sloppy",
+                ));
+            }
+            if simplify.mode == SimplifyMode::Sloppy && simplify.prune {
+                return Err(OptError::InvalidProcessSettings(
+                    "simplify.prune isn't supported with simplify.mode Sloppy",
+                ));
+            }
+        }
+
+        let mut previous: Option<LevelTarget> = None;
+        for (level, spec) in self.lods.iter().enumerate() {
+            let decreasing = match (previous, spec.target) {
+                (Some(LevelTarget::Multiplier(a)), LevelTarget::Multiplier(b)) => b < a,
+                (Some(LevelTarget::Triangles(a)), LevelTarget::Triangles(b)) => b < a,
+                // Mixed targets can only be compared once the triangle count of LOD0 is known.
+                _ => true,
+            };
+            let valid_error = spec
+                .max_error
+                .is_none_or(|error| error.is_finite() && error >= 0.0);
+            if !valid_target(spec.target) || !decreasing || !valid_error {
+                return Err(OptError::InvalidLodSchedule(level + 1));
+            }
+            previous = Some(spec.target);
+        }
+        Ok(())
+    }
+
+    /// Params of the simplification stage, `None` if it is disabled.
+    pub fn simplify_params(&self) -> Option<SimplifyParams> {
+        self.simplify.map(|simplify| {
+            let mut options = SimplifyOptions::None;
+            options.set(SimplifyOptions::LockBorder, simplify.lock_border);
+            options.set(SimplifyOptions::Prune, simplify.prune);
+            SimplifyParams {
+                max_error: simplify.max_error,
+                target_index_count: match simplify.target {
+                    LevelTarget::Multiplier(multiplier) => TargetIndices::Multiplier(multiplier),
+                    LevelTarget::Triangles(triangles) => TargetIndices::TriangleCount(triangles),
+                },
+                options,
+                mode: simplify.mode,
+                ..Default::default()
+            }
+        })
+    }
+
+    /// Settings of the optimization stages.
+    pub fn optimize_settings(&self) -> OptimizeSettings {
+        OptimizeSettings {
+            vertex_cache: self.optimize.vertex_cache,
+            overdraw: self.optimize.overdraw,
+            vertex_fetch: self.optimize.vertex_fetch,
+            ..Default::default()
+        }
+    }
+
+    /// Params of the LOD chain, `None` if no levels are listed. Levels use the `max_error` and
+    /// `mode` of the simplification stage unless they override them.
+    pub fn lod_chain_params(&self) -> Option<LodChainParams> {
+        if self.lods.is_empty() {
+            return None;
+        }
+        let mut simplify = self.simplify_params().unwrap_or_default();
+        simplify.target_index_count = TargetIndices::default();
+        Some(LodChainParams {
+            simplify,
+            levels: LodLevels::Schedule(self.lods.clone()),
+            strategy: self.lod_strategy,
+            min_triangles: self.min_triangles,
+            ..Default::default()
+        })
+    }
+
+    /// Validates the settings and runs every stage on `mesh`: canonicalization, simplification,
+    /// optimization and the LOD chain, which is returned if any levels are listed.
+    pub fn process(&self, mesh: &mut Mesh) -> Result<Option<LodChain>, OptError> {
+        self.process_with_report(mesh).map(|(chain, _)| chain)
+    }
+
+    /// [`MeshProcessSettings::process`] but also reports the outcome of the simplification and
+    /// optimization stages.
+    pub fn process_with_report(
+        &self,
+        mesh: &mut Mesh,
+    ) -> Result<(Option<LodChain>, MeshProcessReport), OptError> {
+        self.validate()?;
+        if self.canonicalize {
+            mesh.canonicalize()?;
+        }
+        let simplify = self
+            .simplify_params()
+            .map(|params| mesh.simplify_with_report(&params))
+            .transpose()?;
+        let optimize = mesh.optimize(&self.optimize_settings())?;
+        let chain = self
+            .lod_chain_params()
+            .map(|params| mesh.generate_lod_chain(&params))
+            .transpose()?;
+        Ok((chain, MeshProcessReport { simplify, optimize }))
+    }
+}
+
+/// Outcome of [`MeshProcessSettings::process_with_report`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct MeshProcessReport {
+    /// `None` if the simplification stage is disabled.
+    pub simplify: Option<SimplifyReport>,
+    /// [`OptimizeReport::skipped_reason`] tells whether the stages were skipped for a mesh that
+    /// was already optimized.
+    pub optimize: OptimizeReport,
+}
+
+fn valid_target(target: LevelTarget) -> bool {
+    match target {
+        LevelTarget::Multiplier(multiplier) => multiplier > 0.0 && multiplier <= 1.0,
+        LevelTarget::Triangles(triangles) => triangles > 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        OptimizeSkipReason,
+        test_util::{beveled_cube, indices},
+    };
+
+    #[test]
+    fn processing_an_optimized_mesh_skips_reordering_but_not_simplification() {
+        let settings = MeshProcessSettings {
+            simplify: Some(ProcessSimplify {
+                target: LevelTarget::Multiplier(0.5),
+                max_error: 1.0,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let mut mesh = beveled_cube(0.2, 8);
+        mesh.optimize(&OptimizeSettings {
+            skip_if_optimized: None,
+            ..Default::default()
+        })
+        .unwrap();
+
+        for pass in 0..2 {
+            let triangles = indices(&mesh).len() / 3;
+            let (_, report) = settings.process_with_report(&mut mesh).unwrap();
+            let simplify = report.simplify.unwrap();
+            assert_eq!(simplify.triangles_before(), triangles);
+            assert!(simplify.triangles_after() <= triangles / 2, "pass {pass}");
+
+            let optimize = report.optimize;
+            assert_eq!(
+                optimize.skipped_reason,
+                Some(OptimizeSkipReason::AlreadyOptimized),
+                "pass {pass}"
+            );
+            assert_eq!(optimize.vertex_buffer_bytes_processed, 0);
+            assert_eq!(optimize.vertices_after, optimize.vertices_before);
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use bevy::{math::Vec3, mesh::Mesh};
+
+use crate::{
+    OptError, adjacency::TriangleAdjacency, mesh_indices, mesh_positions, metrics::SurfaceIndex,
+    validate_indices,
+};
+
+/// How a [`TriangleProvenance`] was determined, from most to least reliable.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub enum ProvenanceConfidence {
+    /// A source triangle had all three corners at the same positions, the triangle survived
+    /// simplification as is.
+    Survived,
+    /// A source triangle shares two corners with the output triangle, the one facing the most
+    /// like it was picked.
+    SharedEdge,
+    /// No source triangle shares an edge, the one closest to the centroid was picked.
+    Nearest,
+}
+
+/// Source triangle an output triangle of the simplifier predominantly derives from.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct TriangleProvenance {
+    pub source_triangle: u32,
+    pub confidence: ProvenanceConfidence,
+}
+
+/// Matches every triangle of `simplified_indices` to a triangle of `mesh`, see
+/// [`MeshExt::triangle_provenance`](crate::MeshExt::triangle_provenance).
+pub(crate) fn triangle_provenance(
+    mesh: &Mesh,
+    simplified_indices: &[u32],
+) -> Result<Vec<TriangleProvenance>, OptError> {
+    let indices = mesh_indices(mesh)?;
+    let positions = mesh_positions(mesh)?;
+    validate_indices(simplified_indices, positions.len())?;
+
+    let adjacency = TriangleAdjacency::new(indices, positions);
+    let mut surface = None;
+    let position = |vertex: u32| Vec3::from_array(positions[vertex as usize]);
+    let mut shared: Vec<(u32, u32)> = Vec::new();
+
+    let provenance = simplified_indices
+        .chunks_exact(3)
+        .map(|corners| {
+            // Source triangles around each corner, and how many corners each of them touches.
+            shared.clear();
+            for &corner in corners {
+                for &triangle in adjacency.incident_triangles(corner) {
+                    match shared.iter_mut().find(|(source, _)| *source == triangle) {
+                        Some((_, count)) => *count += 1,
+                        None => shared.push((triangle, 1)),
+                    }
+                }
+            }
+
+            let [a, b, c] = [0, 1, 2].map(|corner| position(corners[corner]));
+            let normal = (b - a).cross(c - a).normalize_or_zero();
+            let best = shared.iter().filter(|(_, count)| *count >= 2).max_by(
+                |(a, a_count), (b, b_count)| {
+                    a_count.cmp(b_count).then_with(|| {
+                        let a_facing = adjacency.face_normal(*a).dot(normal);
+                        let b_facing = adjacency.face_normal(*b).dot(normal);
+                        a_facing.total_cmp(&b_facing)
+                    })
+                },
+            );
+
+            match best {
+                Some(&(source_triangle, count)) => TriangleProvenance {
+                    source_triangle,
+                    confidence: if count == 3 {
+                        ProvenanceConfidence::Survived
+                    } else {
+                        ProvenanceConfidence::SharedEdge
+                    },
+                },
+                None => {
+                    let surface =
+                        surface.get_or_insert_with(|| SurfaceIndex::new(indices, positions));
+                    TriangleProvenance {
+                        source_triangle: surface
+                            .closest_point((a + b + c) / 3.0)
+                            .map_or(0, |hit| hit.triangle),
+                        confidence: ProvenanceConfidence::Nearest,
+                    }
+                }
+            }
+        })
+        .collect();
+    Ok(provenance)
+}
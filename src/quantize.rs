@@ -0,0 +1,451 @@
+use bevy::{
+    math::{Vec2, Vec3, Vec4},
+    mesh::{Mesh, MeshVertexAttribute, VertexAttributeValues, VertexFormat},
+    transform::components::Transform,
+};
+
+use crate::{
+    OptError, WeldReport, check_attribute_lengths, formats::narrow, mesh_indices_widened,
+    refuse_morph_targets, vertex::weld_vertices,
+};
+
+/// Compact formats [`MeshExt::quantize_attributes`](crate::MeshExt::quantize_attributes) converts
+/// attributes to, `None` leaves an attribute as it is. Bevy has no half float or packed
+/// vertex attribute values, so `Float16` formats and `Unorm10_10_10_2` normals aren't offered,
+/// [`DirectionQuantization::Octahedral`] is as compact.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct QuantizeConfig {
+    pub positions: Option<PositionQuantization>,
+    pub normals: Option<DirectionQuantization>,
+    pub tangents: Option<DirectionQuantization>,
+    pub uv_0: Option<UvQuantization>,
+    pub uv_1: Option<UvQuantization>,
+    /// Leaves attributes whose values the requested format can't represent as they are and lists
+    /// them in [`QuantizeReport::skipped`], instead of failing with
+    /// [`OptError::QuantizationOutOfRange`].
+    pub skip_out_of_range: bool,
+    /// Allows formats `StandardMaterial` can't read as they are, for custom shaders decoding them,
+    /// i.e. [`DirectionQuantization::Octahedral`].
+    pub custom_decoding: bool,
+    /// Welds the vertices quantization made identical afterwards, like
+    /// [`MeshExt::weld_identical_vertices`](crate::MeshExt::weld_identical_vertices), reporting
+    /// the savings in [`QuantizeReport::welded`]. Indexes non-indexed meshes.
+    pub reweld: bool,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PositionQuantization {
+    /// `Snorm16x4` relative to the bounds of the mesh, decoded by
+    /// [`QuantizedPositions::transform`]. Steps are 1/32767 of half the longest side of the
+    /// bounds.
+    Snorm16,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DirectionQuantization {
+    /// `Snorm8x4` of the normalized direction, `w` keeps the handedness of tangents.
+    Snorm8,
+    /// `Snorm8x4` with the octahedral encoding of the direction in `x` and `y`, more precise than
+    /// [`DirectionQuantization::Snorm8`] for the same size but only readable by shaders decoding
+    /// it. `w` keeps the handedness of tangents. Needs [`QuantizeConfig::custom_decoding`].
+    Octahedral,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum UvQuantization {
+    /// `Unorm16x2`, only for UVs within `0.0..=1.0`.
+    Unorm16,
+}
+
+/// Outcome of [`MeshExt::quantize_attributes`](crate::MeshExt::quantize_attributes).
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuantizeReport {
+    /// How to decode the positions, `None` when they weren't quantized.
+    pub positions: Option<QuantizedPositions>,
+    /// Attributes converted, with the format they were converted to.
+    pub quantized: Vec<(&'static str, VertexFormat)>,
+    /// Attributes left as they are because the requested format can't represent their values or
+    /// they aren't in the `f32` format quantization starts from.
+    pub skipped: Vec<&'static str>,
+    /// Size of the vertex buffers in bytes.
+    pub bytes_before: usize,
+    /// Size after quantization, before [`QuantizeConfig::reweld`] welds vertices.
+    pub bytes_after: usize,
+    /// Vertices and bytes [`QuantizeConfig::reweld`] saved on top of quantization, `None` if it
+    /// wasn't set.
+    pub welded: Option<WeldReport>,
+}
+
+/// Quantized positions decode to `offset + scale * value`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct QuantizedPositions {
+    pub offset: Vec3,
+    pub scale: f32,
+}
+
+impl QuantizedPositions {
+    /// Transform decoding the positions back to mesh units. Put it on the entity of the mesh or
+    /// multiply it into its `Transform`.
+    pub fn transform(&self) -> Transform {
+        Transform::from_translation(self.offset).with_scale(Vec3::splat(self.scale))
+    }
+
+    /// Largest distance between a decoded position and the original one along any axis, in mesh
+    /// units.
+    pub fn max_error(&self) -> f32 {
+        self.scale * 0.5 / 32767.0
+    }
+}
+
+/// Converts the attributes selected by `config`, validating every one of them before touching
+/// the mesh.
+pub(crate) fn quantize_attributes(
+    mesh: &mut Mesh,
+    config: &QuantizeConfig,
+) -> Result<QuantizeReport, OptError> {
+    if !config.custom_decoding
+        && [config.normals, config.tangents].contains(&Some(DirectionQuantization::Octahedral))
+    {
+        return Err(OptError::UnsupportedQuantization(
+            "octahedral directions need custom decoding",
+        ));
+    }
+    if config.reweld {
+        refuse_morph_targets(mesh)?;
+        check_attribute_lengths(mesh)?;
+        if mesh.indices().is_some() {
+            mesh_indices_widened(mesh)?;
+        }
+    }
+
+    let mut report = QuantizeReport {
+        positions: None,
+        quantized: Vec::new(),
+        skipped: Vec::new(),
+        bytes_before: vertex_bytes(mesh),
+        bytes_after: 0,
+        welded: None,
+    };
+    let mut converted = Vec::new();
+    let mut convert = |attribute: MeshVertexAttribute,
+                       format: VertexFormat,
+                       values: Option<Option<VertexAttributeValues>>,
+                       report: &mut QuantizeReport|
+     -> Result<(), OptError> {
+        match values {
+            None => {}
+            Some(Some(values)) => converted.push((attribute, format, values)),
+            Some(None) if config.skip_out_of_range => report.skipped.push(attribute.name),
+            Some(None) => return Err(OptError::QuantizationOutOfRange(attribute.name)),
+        }
+        Ok(())
+    };
+
+    if config.positions.is_some() {
+        let values = match mesh.attribute(Mesh::ATTRIBUTE_POSITION) {
+            Some(VertexAttributeValues::Float32x3(positions)) => {
+                Some(normalized_positions(positions).map(|(values, positions)| {
+                    report.positions = Some(positions);
+                    values
+                }))
+            }
+            Some(_) => {
+                report.skipped.push(Mesh::ATTRIBUTE_POSITION.name);
+                None
+            }
+            None => None,
+        };
+        convert(
+            Mesh::ATTRIBUTE_POSITION,
+            VertexFormat::Snorm16x4,
+            values,
+            &mut report,
+        )?;
+    }
+    for (attribute, quantization) in [
+        (Mesh::ATTRIBUTE_NORMAL, config.normals),
+        (Mesh::ATTRIBUTE_TANGENT, config.tangents),
+    ] {
+        let Some(quantization) = quantization else {
+            continue;
+        };
+        let values = match mesh.attribute(attribute.id) {
+            Some(VertexAttributeValues::Float32x3(normals)) => Some(directions(
+                normals.iter().map(|&[x, y, z]| [x, y, z, 0.0]),
+                quantization,
+            )),
+            Some(VertexAttributeValues::Float32x4(tangents)) => {
+                Some(directions(tangents.iter().copied(), quantization))
+            }
+            Some(_) => {
+                report.skipped.push(attribute.name);
+                None
+            }
+            None => None,
+        };
+        convert(attribute, VertexFormat::Snorm8x4, values, &mut report)?;
+    }
+    for (attribute, quantization) in [
+        (Mesh::ATTRIBUTE_UV_0, config.uv_0),
+        (Mesh::ATTRIBUTE_UV_1, config.uv_1),
+    ] {
+        let Some(UvQuantization::Unorm16) = quantization else {
+            continue;
+        };
+        let values = match mesh.attribute(attribute.id) {
+            Some(VertexAttributeValues::Float32x2(uvs)) => Some(
+                uvs.iter()
+                    .flatten()
+                    .all(|v| (0.0..=1.0).contains(v))
+                    .then(|| VertexAttributeValues::Float32x2(uvs.clone())),
+            ),
+            Some(_) => {
+                report.skipped.push(attribute.name);
+                None
+            }
+            None => None,
+        };
+        convert(attribute, VertexFormat::Unorm16x2, values, &mut report)?;
+    }
+
+    for (attribute, format, values) in converted {
+        mesh.insert_attribute(
+            MeshVertexAttribute {
+                format,
+                ..attribute
+            },
+            narrow(&values, format),
+        );
+        report.quantized.push((attribute.name, format));
+    }
+    report.bytes_after = vertex_bytes(mesh);
+    if config.reweld {
+        report.welded = Some(weld_vertices(mesh, None)?);
+    }
+    Ok(report)
+}
+
+/// Positions mapped into `-1.0..=1.0` around the center of their bounds, scaled by the half
+/// extent of their longest side. `None` if any of them isn't finite.
+fn normalized_positions(
+    positions: &[[f32; 3]],
+) -> Option<(VertexAttributeValues, QuantizedPositions)> {
+    if positions.iter().flatten().any(|v| !v.is_finite()) {
+        return None;
+    }
+    let (min, max) = match positions.first() {
+        Some(&first) => positions.iter().fold(
+            (Vec3::from(first), Vec3::from(first)),
+            |(min, max), &position| (min.min(position.into()), max.max(position.into())),
+        ),
+        None => (Vec3::ZERO, Vec3::ZERO),
+    };
+    let offset = (min + max) * 0.5;
+    // Flat or single point meshes still need a scale the transform can be built from.
+    let scale = ((max - min) * 0.5).max_element().max(f32::MIN_POSITIVE);
+    let values = positions
+        .iter()
+        .map(|&position| {
+            ((Vec3::from(position) - offset) / scale)
+                .extend(0.0)
+                .to_array()
+        })
+        .collect();
+    Some((
+        VertexAttributeValues::Float32x4(values),
+        QuantizedPositions { offset, scale },
+    ))
+}
+
+/// Directions normalized and encoded for `quantization`, keeping `w` as is. `None` if any of them
+/// isn't finite.
+fn directions(
+    values: impl Iterator<Item = [f32; 4]>,
+    quantization: DirectionQuantization,
+) -> Option<VertexAttributeValues> {
+    values
+        .map(|value| {
+            let value = Vec4::from(value);
+            if !value.is_finite() {
+                return None;
+            }
+            let direction = value.truncate().normalize_or_zero();
+            let encoded = match quantization {
+                DirectionQuantization::Snorm8 => direction,
+                DirectionQuantization::Octahedral => octahedral(direction).extend(0.0),
+            };
+            Some(encoded.extend(value.w).to_array())
+        })
+        .collect::<Option<Vec<_>>>()
+        .map(VertexAttributeValues::Float32x4)
+}
+
+/// Octahedral encoding of the unit vector `direction`, in `-1.0..=1.0`.
+fn octahedral(direction: Vec3) -> Vec2 {
+    let sum = direction.abs().element_sum();
+    if sum == 0.0 {
+        return Vec2::ZERO;
+    }
+    let direction = direction / sum;
+    if direction.z >= 0.0 {
+        direction.truncate()
+    } else {
+        let sign = Vec2::select(
+            direction.truncate().cmpge(Vec2::ZERO),
+            Vec2::ONE,
+            -Vec2::ONE,
+        );
+        (Vec2::ONE - Vec2::new(direction.y, direction.x).abs()) * sign
+    }
+}
+
+fn vertex_bytes(mesh: &Mesh) -> usize {
+    mesh.attributes()
+        .map(|(attribute, values)| attribute.format.size() as usize * values.len())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        MeshExt,
+        formats::widen,
+        test_util::{grid, indices, positions, sphere},
+    };
+    use bevy::mesh::Indices;
+
+    /// [`grid`] whose odd triangles use copies of their vertices moved by far less than a
+    /// quantization step.
+    fn near_duplicate_grid() -> Mesh {
+        let mut mesh = grid(3);
+        let mut positions = positions(&mesh).to_vec();
+        let count = positions.len() as u32;
+        let copies: Vec<_> = positions
+            .iter()
+            .map(|&[x, y, z]| [x + 1e-6, y, z])
+            .collect();
+        positions.extend(copies);
+        let mut indices = indices(&mesh);
+        for triangle in indices.chunks_mut(3).skip(1).step_by(2) {
+            triangle.iter_mut().for_each(|index| *index += count);
+        }
+        for attribute in [Mesh::ATTRIBUTE_NORMAL, Mesh::ATTRIBUTE_UV_0] {
+            let values = match mesh.attribute(attribute).unwrap() {
+                VertexAttributeValues::Float32x3(values) => {
+                    VertexAttributeValues::Float32x3(values.repeat(2))
+                }
+                VertexAttributeValues::Float32x2(values) => {
+                    VertexAttributeValues::Float32x2(values.repeat(2))
+                }
+                _ => unreachable!(),
+            };
+            mesh.insert_attribute(attribute, values);
+        }
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        mesh.insert_indices(Indices::U32(indices));
+        mesh
+    }
+
+    #[test]
+    fn positions_round_trip_within_max_error() {
+        let mut mesh = sphere(6);
+        let uvs = mesh.attribute(Mesh::ATTRIBUTE_UV_0).unwrap().clone();
+        let original: Vec<_> = positions(&mesh)
+            .iter()
+            .map(|&position| Vec3::from(position) * Vec3::new(3.0, 0.5, 1.0) + 10.0)
+            .collect();
+        mesh.insert_attribute(
+            Mesh::ATTRIBUTE_POSITION,
+            original.iter().map(|p| p.to_array()).collect::<Vec<_>>(),
+        );
+        let report = mesh
+            .quantize_attributes(&QuantizeConfig {
+                positions: Some(PositionQuantization::Snorm16),
+                uv_0: Some(UvQuantization::Unorm16),
+                ..Default::default()
+            })
+            .unwrap();
+        let quantized = report.positions.unwrap();
+        assert!(quantized.scale <= 3.0);
+
+        let values = mesh.attribute(Mesh::ATTRIBUTE_POSITION).unwrap();
+        assert!(matches!(values, VertexAttributeValues::Snorm16x4(_)));
+        let Some(VertexAttributeValues::Float32x4(decoded)) = widen(values) else {
+            unreachable!();
+        };
+        let transform = quantized.transform();
+        // Decoding in f32 rounds once more.
+        let bound = quantized.max_error() + 1e-5;
+        for (&[x, y, z, _], &position) in decoded.iter().zip(&original) {
+            let error = transform.transform_point(Vec3::new(x, y, z)) - position;
+            assert!(error.abs().max_element() <= bound, "{error} > {bound}");
+        }
+
+        let (
+            Some(VertexAttributeValues::Float32x2(decoded)),
+            VertexAttributeValues::Float32x2(uvs),
+        ) = (widen(mesh.attribute(Mesh::ATTRIBUTE_UV_0).unwrap()), uvs)
+        else {
+            unreachable!();
+        };
+        for (&decoded, &uv) in decoded.iter().zip(&uvs) {
+            let error = (Vec2::from(decoded) - Vec2::from(uv)).abs().max_element();
+            assert!(error <= 0.5 / 65535.0 + 1e-7);
+        }
+    }
+
+    #[test]
+    fn octahedral_normals_need_custom_decoding() {
+        let mut mesh = sphere(1);
+        let result = mesh.quantize_attributes(&QuantizeConfig {
+            normals: Some(DirectionQuantization::Octahedral),
+            ..Default::default()
+        });
+        assert!(matches!(result, Err(OptError::UnsupportedQuantization(_))));
+        assert!(matches!(
+            mesh.attribute(Mesh::ATTRIBUTE_NORMAL),
+            Some(VertexAttributeValues::Float32x3(_))
+        ));
+    }
+
+    #[test]
+    fn reweld_merges_vertices_quantization_made_identical() {
+        let config = QuantizeConfig {
+            positions: Some(PositionQuantization::Snorm16),
+            ..Default::default()
+        };
+        let mut quantized = near_duplicate_grid();
+        let report = quantized.quantize_attributes(&config).unwrap();
+        assert!(report.welded.is_none());
+
+        let mut rewelded = near_duplicate_grid();
+        let report = rewelded
+            .quantize_attributes(&QuantizeConfig {
+                reweld: true,
+                ..config
+            })
+            .unwrap();
+        let welded = report.welded.unwrap();
+        assert_eq!(welded.vertices_before, quantized.count_vertices());
+        assert_eq!(welded.memory_before, report.bytes_after);
+        assert_eq!(rewelded.count_vertices(), grid(3).count_vertices());
+        assert!(welded.memory_saved() > 0);
+        assert_eq!(indices(&rewelded).len(), indices(&quantized).len());
+    }
+
+    #[test]
+    fn reweld_keeps_vertices_that_stay_distinct() {
+        let mut mesh = near_duplicate_grid();
+        let mut used = indices(&mesh);
+        used.sort_unstable();
+        used.dedup();
+        let report = mesh
+            .quantize_attributes(&QuantizeConfig {
+                reweld: true,
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(report.welded.unwrap().vertices_after, used.len());
+    }
+}
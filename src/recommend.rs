@@ -0,0 +1,100 @@
+use bevy::{math::Vec3, mesh::Mesh};
+
+use crate::{OptError, SimplifyOptions, SimplifyParams, TargetIndices, mesh_positions};
+
+/// How a mesh is going to be viewed, used to estimate how much detail is visible.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ViewParams {
+    /// Distance from the camera to the mesh, in mesh units.
+    pub distance: f32,
+    /// Vertical field of view in radians.
+    pub vertical_fov: f32,
+    /// Height of the viewport in pixels.
+    pub viewport_height: f32,
+    /// Density heuristic, how many triangles are worth keeping per pixel the mesh covers. `0.1`
+    /// keeps a triangle per ten pixels, anything above `0.5` is usually wasted on rasterization.
+    pub triangles_per_pixel: f32,
+    /// Surface deviation allowed when simplifying, in pixels.
+    pub error_pixels: f32,
+}
+
+impl Default for ViewParams {
+    fn default() -> Self {
+        ViewParams {
+            distance: 10.0,
+            vertical_fov: std::f32::consts::FRAC_PI_4,
+            viewport_height: 1080.0,
+            triangles_per_pixel: 0.1,
+            error_pixels: 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TargetRecommendation {
+    /// Suggested index count, never more than the mesh currently has.
+    pub index_count: usize,
+    /// Suggested error relative to the mesh extents, for use without
+    /// `SimplifyOptions::ErrorAbsolute`.
+    pub max_error: f32,
+    /// Suggested error in mesh units, for use with `SimplifyOptions::ErrorAbsolute`.
+    pub max_error_absolute: f32,
+    /// Radius of the mesh's bounding sphere on screen, in pixels.
+    pub projected_radius: f32,
+}
+
+impl TargetRecommendation {
+    /// Sets the target index count and max error of `params` from the recommendation, picking the
+    /// error matching `params.options`. The absolute error is converted to world units when
+    /// `params` has a [`SimplifyParams::world_scale`].
+    pub fn apply(&self, params: &mut SimplifyParams) {
+        params.target_index_count = TargetIndices::Count(self.index_count);
+        params.max_error = if params.options.contains(SimplifyOptions::ErrorAbsolute) {
+            self.max_error_absolute / params.error_units()
+        } else {
+            self.max_error
+        };
+    }
+}
+
+/// Recommends a simplification target for `mesh` from the size of its bounding sphere on screen.
+///
+/// This is a heuristic: the triangle count scales with the covered pixel area through
+/// [`ViewParams::triangles_per_pixel`], and the error is the world space size of
+/// [`ViewParams::error_pixels`] at the given distance.
+pub fn recommend_target(mesh: &Mesh, view: &ViewParams) -> Result<TargetRecommendation, OptError> {
+    let index_count = mesh.indices().ok_or(OptError::MissingIndices)?.len();
+    let positions = mesh_positions(mesh)?;
+
+    let (min, max) = positions.iter().fold(
+        (Vec3::splat(f32::INFINITY), Vec3::splat(f32::NEG_INFINITY)),
+        |(min, max), position| {
+            let position = Vec3::from_array(*position);
+            (min.min(position), max.max(position))
+        },
+    );
+    let center = (min + max) * 0.5;
+    let radius = positions
+        .iter()
+        .map(|position| Vec3::from_array(*position).distance(center))
+        .fold(0.0, f32::max);
+
+    // Height of the view frustum at the mesh's distance, in mesh units.
+    let view_height = 2.0 * view.distance.max(f32::EPSILON) * (view.vertical_fov * 0.5).tan();
+    let pixel_size = view_height / view.viewport_height.max(1.0);
+    let projected_radius = radius / pixel_size;
+
+    // The mesh covers at most the disk of its bounding sphere on screen.
+    let covered_pixels = std::f32::consts::PI * projected_radius * projected_radius;
+    let triangles = (covered_pixels * view.triangles_per_pixel).max(1.0) as usize;
+
+    let max_error_absolute = pixel_size * view.error_pixels;
+    let scale = meshopt::simplify_scale_decoder(positions).max(f32::EPSILON);
+
+    Ok(TargetRecommendation {
+        index_count: (triangles * 3).min(index_count),
+        max_error: max_error_absolute / scale,
+        max_error_absolute,
+        projected_radius,
+    })
+}
@@ -0,0 +1,139 @@
+use bevy::mesh::{Mesh, VertexAttributeValues};
+
+use crate::{OptError, mesh_indices, mesh_positions};
+
+/// Vertex attributes that can be derived from the rest of the mesh.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct AttributeSet {
+    /// `ATTRIBUTE_NORMAL`, regenerated as smooth normals from the triangles.
+    pub normals: bool,
+    /// `ATTRIBUTE_TANGENT`, regenerated with mikktspace from the normals and `ATTRIBUTE_UV_0`.
+    pub tangents: bool,
+}
+
+impl AttributeSet {
+    pub const NONE: AttributeSet = AttributeSet {
+        normals: false,
+        tangents: false,
+    };
+    pub const NORMALS: AttributeSet = AttributeSet {
+        normals: true,
+        tangents: false,
+    };
+    pub const TANGENTS: AttributeSet = AttributeSet {
+        normals: false,
+        tangents: true,
+    };
+    pub const ALL: AttributeSet = AttributeSet {
+        normals: true,
+        tangents: true,
+    };
+
+    pub fn is_empty(&self) -> bool {
+        !self.normals && !self.tangents
+    }
+
+    /// Attributes of the set the mesh actually has.
+    fn present_in(&self, mesh: &Mesh) -> AttributeSet {
+        AttributeSet {
+            normals: self.normals && mesh.contains_attribute(Mesh::ATTRIBUTE_NORMAL),
+            tangents: self.tangents && mesh.contains_attribute(Mesh::ATTRIBUTE_TANGENT),
+        }
+    }
+}
+
+/// Vertex size while attributes were stripped, see [`MeshExt::with_stripped_attributes`](crate::MeshExt::with_stripped_attributes).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct StripReport {
+    /// Bytes per vertex of the original mesh.
+    pub vertex_size_before: usize,
+    /// Bytes per vertex of the mesh the operations ran on.
+    pub vertex_size_stripped: usize,
+    /// Attributes that were stripped and regenerated, the ones the mesh didn't have are left out.
+    pub regenerated: AttributeSet,
+}
+
+impl StripReport {
+    /// Bytes per vertex the operations didn't have to carry around.
+    pub fn vertex_size_reduction(&self) -> usize {
+        self.vertex_size_before - self.vertex_size_stripped
+    }
+}
+
+/// Removes the `attributes` the mesh has, runs `f` and regenerates them. Fails before touching the
+/// mesh if the inputs of the regeneration are missing, the attributes are regenerated even if `f`
+/// fails.
+pub(crate) fn with_stripped_attributes<R>(
+    mesh: &mut Mesh,
+    attributes: AttributeSet,
+    f: impl FnOnce(&mut Mesh) -> Result<R, OptError>,
+) -> Result<(R, StripReport), OptError> {
+    let stripped = attributes.present_in(mesh);
+    check_regeneration_inputs(mesh, stripped)?;
+
+    let vertex_size_before = mesh.get_vertex_size() as usize;
+    if stripped.normals {
+        mesh.remove_attribute(Mesh::ATTRIBUTE_NORMAL);
+    }
+    if stripped.tangents {
+        mesh.remove_attribute(Mesh::ATTRIBUTE_TANGENT);
+    }
+    let report = StripReport {
+        vertex_size_before,
+        vertex_size_stripped: mesh.get_vertex_size() as usize,
+        regenerated: stripped,
+    };
+
+    let result = f(mesh);
+    regenerate(mesh, stripped)?;
+    Ok((result?, report))
+}
+
+/// Checks that `attributes` can be regenerated once stripped: normals need indexed triangles and
+/// tangents also need normals, unless they are regenerated too, and UVs.
+fn check_regeneration_inputs(mesh: &Mesh, attributes: AttributeSet) -> Result<(), OptError> {
+    if attributes.is_empty() {
+        return Ok(());
+    }
+
+    mesh_positions(mesh)?;
+    mesh_indices(mesh)?;
+    if attributes.tangents {
+        if !attributes.normals
+            && !matches!(
+                mesh.attribute(Mesh::ATTRIBUTE_NORMAL),
+                Some(VertexAttributeValues::Float32x3(_))
+            )
+        {
+            return Err(OptError::MissingRegenerationInput(
+                Mesh::ATTRIBUTE_NORMAL.name,
+            ));
+        }
+        if !matches!(
+            mesh.attribute(Mesh::ATTRIBUTE_UV_0),
+            Some(VertexAttributeValues::Float32x2(_))
+        ) {
+            return Err(OptError::MissingRegenerationInput(
+                Mesh::ATTRIBUTE_UV_0.name,
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn regenerate(mesh: &mut Mesh, attributes: AttributeSet) -> Result<(), OptError> {
+    if attributes.is_empty() {
+        return Ok(());
+    }
+
+    // Bevy panics on meshes without valid triangles.
+    mesh_indices(mesh)?;
+    if attributes.normals {
+        mesh.compute_smooth_normals();
+    }
+    if attributes.tangents {
+        mesh.generate_tangents()
+            .map_err(|_| OptError::TangentGenerationFailed)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+
+use bevy::{
+    asset::{AssetEvent, AssetId, Assets},
+    ecs::prelude::*,
+    mesh::Mesh,
+};
+
+use crate::{
+    MeshExt, MeshModified, OptError, OptimizeSettings, SimplifyCache, SimplifyParams,
+    parallel::par_map, plugin::simplify_with_cache,
+};
+
+/// Pass of [`MeshoptPlugin`](crate::MeshoptPlugin) that produced a derived mesh, with the settings
+/// it ran with, so it can be run again on the source.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MeshPass {
+    /// [`simplify_meshes`](crate::simplify_meshes) with the params of the batch, overrides applied.
+    Simplify(Box<SimplifyParams>),
+    /// [`optimize_meshes`](crate::optimize_meshes).
+    Optimize(OptimizeSettings),
+}
+
+impl MeshPass {
+    fn run(&self, mesh: &mut Mesh, cache: Option<&SimplifyCache>) -> Result<(), OptError> {
+        match self {
+            MeshPass::Simplify(params) => simplify_with_cache(mesh, params, cache).map(|_| ()),
+            MeshPass::Optimize(settings) => mesh.optimize(settings).map(|_| ()),
+        }
+    }
+}
+
+/// Source and pass of a mesh the batches of [`MeshoptPlugin`](crate::MeshoptPlugin) produced.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DerivedMesh {
+    pub source: AssetId<Mesh>,
+    pub pass: MeshPass,
+}
+
+/// Every mesh the batches of [`MeshoptPlugin`](crate::MeshoptPlugin) produced, by id, with the mesh
+/// it was processed from. A processed mesh that gets processed again is the source of the next
+/// copy, so chained passes are refreshed one after the other.
+///
+/// Entries are forgotten once either mesh is removed, and sources reclaimed by
+/// [`SourceReclaim`](crate::SourceReclaim) aren't tracked since there is nothing left to process
+/// again.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct DerivedMeshes {
+    derived: HashMap<AssetId<Mesh>, DerivedMesh>,
+}
+
+impl DerivedMeshes {
+    pub fn get(&self, derived: impl Into<AssetId<Mesh>>) -> Option<&DerivedMesh> {
+        self.derived.get(&derived.into())
+    }
+
+    /// Meshes processed from `source`, in no particular order.
+    pub fn derived_from(
+        &self,
+        source: impl Into<AssetId<Mesh>>,
+    ) -> impl Iterator<Item = AssetId<Mesh>> + '_ {
+        let source = source.into();
+        self.derived
+            .iter()
+            .filter(move |(_, derived)| derived.source == source)
+            .map(|(&id, _)| id)
+    }
+
+    pub(crate) fn insert(&mut self, derived: AssetId<Mesh>, entry: DerivedMesh) {
+        self.derived.insert(derived, entry);
+    }
+
+    /// Stops tracking the meshes processed from `source`.
+    pub(crate) fn forget_source(&mut self, source: AssetId<Mesh>) {
+        self.derived.retain(|_, derived| derived.source != source);
+    }
+}
+
+/// Sent by [`refresh_derived_meshes`] for every derived mesh it processed again after its source
+/// changed. The mesh keeps its id, so the entities using it already show the new version, this is
+/// for what else was built from it, e.g. colliders. On failure the derived mesh is left as it was.
+#[derive(Message, Debug, Clone)]
+pub struct DerivedMeshRefreshed {
+    pub source: AssetId<Mesh>,
+    pub derived: AssetId<Mesh>,
+    pub result: Result<(), OptError>,
+}
+
+/// Runs the pass of every [`DerivedMeshes`] entry again when its source mesh is modified, e.g. when
+/// the glTF it was loaded from is hot-reloaded, replacing the derived mesh in place and sending
+/// [`MeshModified`] and [`DerivedMeshRefreshed`] for it. Passes run with the settings they first
+/// ran with, in parallel on the `ComputeTaskPool`.
+pub fn refresh_derived_meshes(
+    mut events: MessageReader<AssetEvent<Mesh>>,
+    mut derived: ResMut<DerivedMeshes>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    cache: Option<Res<SimplifyCache>>,
+    mut modified: MessageWriter<MeshModified>,
+    mut refreshed: MessageWriter<DerivedMeshRefreshed>,
+) {
+    let mut stale = Vec::new();
+    for event in events.read() {
+        match *event {
+            AssetEvent::Modified { id } => stale.extend(derived.derived_from(id)),
+            AssetEvent::Removed { id } => {
+                derived.derived.remove(&id);
+                derived.forget_source(id);
+            }
+            _ => {}
+        }
+    }
+    if stale.is_empty() {
+        return;
+    }
+    stale.sort();
+    stale.dedup();
+
+    let jobs = stale
+        .into_iter()
+        .map(|id| {
+            let entry = derived.get(id)?;
+            meshes.get(id)?;
+            let source = meshes.get(entry.source)?.clone();
+            Some((id, entry.clone(), source))
+        })
+        .collect();
+    let cache = cache.as_deref();
+    let outputs = par_map(jobs, |job| {
+        let (id, entry, mut mesh) = job?;
+        let result = entry.pass.run(&mut mesh, cache);
+        Some((id, entry.source, result.map(|()| mesh)))
+    });
+
+    for (id, source, result) in outputs.into_iter().flatten() {
+        let result = result.and_then(|mesh| {
+            meshes.insert(id, mesh).map_err(|_| OptError::MissingMesh)?;
+            modified.write(MeshModified(id));
+            Ok(())
+        });
+        refreshed.write(DerivedMeshRefreshed {
+            source,
+            derived: id,
+            result,
+        });
+    }
+}
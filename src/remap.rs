@@ -0,0 +1,304 @@
+use std::io::{self, Read, Write};
+
+use bevy::{
+    image::Image,
+    math::Vec3,
+    mesh::{
+        Indices, Mesh,
+        morph::{MorphAttributes, MorphTargetImage},
+    },
+};
+
+use crate::OptError;
+
+/// Identifies the binary form written by [`RemapTable::save`], followed by the format version.
+const MAGIC: [u8; 4] = *b"MRMP";
+const VERSION: u32 = 1;
+
+/// Mapping from the vertices of a mesh to the vertices of an optimized version of it, for
+/// remapping per-vertex data that lives outside of the mesh.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct RemapTable {
+    /// New index of every source vertex, `u32::MAX` for vertices that were dropped.
+    pub remap: Vec<u32>,
+    pub source_vertex_count: usize,
+    pub destination_vertex_count: usize,
+    /// [`RemapTable::content_hash`] of the source mesh.
+    pub source_hash: u64,
+}
+
+impl RemapTable {
+    /// Source vertex of every destination vertex, in order.
+    pub fn surviving_vertices(&self) -> Vec<u32> {
+        let mut surviving = vec![u32::MAX; self.destination_vertex_count];
+        for (source, &destination) in self.remap.iter().enumerate() {
+            if let Some(surviving) = surviving.get_mut(destination as usize) {
+                *surviving = source as u32;
+            }
+        }
+        surviving
+    }
+
+    /// Remaps per-vertex data of the source mesh to the destination mesh.
+    pub fn apply<T: Copy>(&self, values: &[T]) -> Result<Vec<T>, OptError> {
+        if values.len() != self.source_vertex_count {
+            return Err(OptError::InvalidVertexCount(values.len()));
+        }
+        Ok(self
+            .surviving_vertices()
+            .into_iter()
+            .map(|source| values[source as usize])
+            .collect())
+    }
+
+    /// Morph target image of the destination mesh, rebuilt from the `image` of the source mesh,
+    /// i.e. the one behind its `Mesh::morph_targets` handle. Add it to the image assets and
+    /// set it on the destination with `Mesh::set_morph_targets`, the names stay the same. Fails
+    /// with [`OptError::InvalidMorphTargetImage`] on images whose data was dropped once uploaded,
+    /// which `RenderAssetUsages::MAIN_WORLD` keeps.
+    pub fn apply_morph_targets(&self, image: &Image) -> Result<Image, OptError> {
+        let format = image.texture_descriptor.format;
+        let Some(data) = image
+            .data
+            .as_ref()
+            .filter(|_| format.components() == 1 && format.block_copy_size(None) == Some(4))
+        else {
+            return Err(OptError::InvalidMorphTargetImage);
+        };
+        let size = image.texture_descriptor.size;
+        let layer_size = (size.width * size.height) as usize * size_of::<f32>();
+        let target_size = self.source_vertex_count * size_of::<MorphAttributes>();
+        if layer_size < target_size
+            || data.len() != layer_size * size.depth_or_array_layers as usize
+        {
+            return Err(OptError::InvalidMorphTargetImage);
+        }
+
+        let surviving = self.surviving_vertices();
+        let targets: Vec<Vec<MorphAttributes>> = data
+            .chunks_exact(layer_size)
+            .map(|layer| {
+                surviving
+                    .iter()
+                    .map(|&source| {
+                        let offset = source as usize * size_of::<MorphAttributes>();
+                        morph_attributes(&layer[offset..offset + size_of::<MorphAttributes>()])
+                    })
+                    .collect()
+            })
+            .collect();
+        MorphTargetImage::new(
+            targets.into_iter().map(Vec::into_iter),
+            self.destination_vertex_count,
+            image.asset_usage,
+        )
+        .map(|image| image.0)
+        .map_err(|_| OptError::InvalidMorphTargetImage)
+    }
+
+    /// Whether `mesh` is the mesh this table was generated from, tables of meshes that have since
+    /// been modified shouldn't be applied.
+    pub fn matches_source(&self, mesh: &Mesh) -> bool {
+        mesh.count_vertices() == self.source_vertex_count
+            && Self::content_hash(mesh) == self.source_hash
+    }
+
+    /// Hash of the vertex attributes and indices of a mesh, stable across runs and machines of the
+    /// same endianness.
+    pub fn content_hash(mesh: &Mesh) -> u64 {
+        mesh_content_hash(mesh)
+    }
+
+    /// Writes the table in a compact little-endian binary form.
+    pub fn save(&self, mut writer: impl Write) -> io::Result<()> {
+        writer.write_all(&MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        writer.write_all(&(self.source_vertex_count as u64).to_le_bytes())?;
+        writer.write_all(&(self.destination_vertex_count as u64).to_le_bytes())?;
+        writer.write_all(&self.source_hash.to_le_bytes())?;
+        for &index in &self.remap {
+            writer.write_all(&index.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Reads a table written by [`RemapTable::save`].
+    pub fn load(mut reader: impl Read) -> io::Result<Self> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a remap table",
+            ));
+        }
+        let version = u32::from_le_bytes(read_bytes(&mut reader)?);
+        if version != VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unsupported remap table version {version}"),
+            ));
+        }
+
+        let source_vertex_count = u64::from_le_bytes(read_bytes(&mut reader)?) as usize;
+        let destination_vertex_count = u64::from_le_bytes(read_bytes(&mut reader)?) as usize;
+        let source_hash = u64::from_le_bytes(read_bytes(&mut reader)?);
+        let mut bytes = Vec::new();
+        reader
+            .take(source_vertex_count as u64 * 4)
+            .read_to_end(&mut bytes)?;
+        if bytes.len() != source_vertex_count * 4 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+
+        let remap: Vec<u32> = bytes
+            .chunks_exact(4)
+            .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            .collect();
+        if remap
+            .iter()
+            .any(|&index| index != u32::MAX && index as usize >= destination_vertex_count)
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "remap table index out of bounds",
+            ));
+        }
+
+        Ok(RemapTable {
+            remap,
+            source_vertex_count,
+            destination_vertex_count,
+            source_hash,
+        })
+    }
+}
+
+/// Deltas of one vertex as `MorphTargetImage` lays them out, nine native-endian floats.
+fn morph_attributes(bytes: &[u8]) -> MorphAttributes {
+    let mut values = bytes
+        .chunks_exact(size_of::<f32>())
+        .map(|bytes| f32::from_ne_bytes(bytes.try_into().unwrap()));
+    let mut next = || Vec3::from_array([(); 3].map(|_| values.next().unwrap()));
+    MorphAttributes::new(next(), next(), next())
+}
+
+fn read_bytes<const N: usize>(reader: &mut impl Read) -> io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// 64-bit FNV-1a over the names and bytes of every vertex attribute and the indices.
+pub(crate) fn mesh_content_hash(mesh: &Mesh) -> u64 {
+    let mut state = FNV_OFFSET;
+    for (attribute, values) in mesh.attributes() {
+        state = fnv1a(state, attribute.name.as_bytes());
+        state = fnv1a(state, values.get_bytes());
+    }
+    match mesh.indices() {
+        Some(Indices::U16(indices)) => {
+            for index in indices {
+                state = fnv1a(state, &(*index as u32).to_le_bytes());
+            }
+        }
+        Some(Indices::U32(indices)) => {
+            for index in indices {
+                state = fnv1a(state, &index.to_le_bytes());
+            }
+        }
+        None => {}
+    }
+    state
+}
+
+pub(crate) const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// Continues a 64-bit FNV-1a hash starting from `hash` with `bytes`.
+pub(crate) fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+    bytes
+        .iter()
+        .fold(hash, |hash, &byte| (hash ^ byte as u64).wrapping_mul(PRIME))
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::mesh::VertexAttributeValues;
+
+    use super::*;
+    use crate::{
+        MeshExt, SimplifyParams, TargetIndices,
+        test_util::{positions, sphere},
+    };
+
+    /// Per-vertex byte standing in for data kept next to the mesh, derived from the position so
+    /// it can be checked on the destination.
+    fn mask(position: [f32; 3]) -> u8 {
+        position.iter().fold(0u8, |mask, &value| {
+            mask.wrapping_mul(31) ^ (value * 1000.0) as i32 as u8
+        })
+    }
+
+    /// Half the triangles of `source` and the table remapping its vertices, keyed to `source`.
+    fn simplified_with_table(source: &Mesh) -> (Mesh, RemapTable) {
+        let mut mesh = source.clone();
+        mesh.simplify_with_report(&SimplifyParams {
+            target_index_count: TargetIndices::Multiplier(0.5),
+            max_error: 1.0,
+            ..Default::default()
+        })
+        .unwrap();
+        let mut table = mesh.optimize_vertex_fetch_remap().unwrap();
+        table.source_hash = RemapTable::content_hash(source);
+        (mesh, table)
+    }
+
+    #[test]
+    fn reloaded_table_remaps_companion_bytes() {
+        let source = sphere(4);
+        let (simplified, table) = simplified_with_table(&source);
+        assert!(table.destination_vertex_count < table.source_vertex_count);
+
+        let mut bytes = Vec::new();
+        table.save(&mut bytes).unwrap();
+        let loaded = RemapTable::load(bytes.as_slice()).unwrap();
+        assert_eq!(loaded, table);
+        assert!(loaded.matches_source(&source));
+
+        let masks: Vec<u8> = positions(&source).iter().copied().map(mask).collect();
+        let remapped = loaded.apply(&masks).unwrap();
+        assert_eq!(remapped.len(), simplified.count_vertices());
+        let expected: Vec<u8> = positions(&simplified).iter().copied().map(mask).collect();
+        assert_eq!(remapped, expected);
+
+        assert!(matches!(
+            loaded.apply(&masks[1..]),
+            Err(OptError::InvalidVertexCount(_))
+        ));
+        for length in 0..bytes.len() {
+            assert!(RemapTable::load(&bytes[..length]).is_err());
+        }
+    }
+
+    #[test]
+    fn table_of_another_source_is_rejected() {
+        let source = sphere(4);
+        let (_, table) = simplified_with_table(&source);
+        let mut bytes = Vec::new();
+        table.save(&mut bytes).unwrap();
+        let loaded = RemapTable::load(bytes.as_slice()).unwrap();
+
+        // Same vertex count, one vertex moved.
+        let mut edited = source.clone();
+        let Some(VertexAttributeValues::Float32x3(positions)) =
+            edited.attribute_mut(Mesh::ATTRIBUTE_POSITION)
+        else {
+            unreachable!()
+        };
+        positions[0][0] += 0.01;
+        assert!(!loaded.matches_source(&edited));
+        assert!(!loaded.matches_source(&sphere(3)));
+    }
+}
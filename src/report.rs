@@ -0,0 +1,153 @@
+use bevy::{
+    math::Vec2,
+    mesh::{Indices, Mesh},
+};
+
+use meshopt::SimplifyOptions;
+
+use crate::{
+    SimplifyMode, SimplifyParams, SimplifyPath, attributes::uv_weights,
+    double_sided::merge_double_sided, mesh_indices, mesh_positions,
+};
+
+/// Outcome of a simplification run, as returned by
+/// [`MeshExt::simplify_with_report`](crate::MeshExt::simplify_with_report). Combine
+/// [`SimplifyReport::result_error_absolute`] with the camera projection to pick screen-space error
+/// thresholds for LODs.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct SimplifyReport {
+    pub indices_before: usize,
+    pub indices_after: usize,
+    pub vertices_before: usize,
+    /// Vertices referenced by the simplified indices, i.e. what remains once unused vertices are
+    /// compacted away.
+    pub vertices_after: usize,
+    /// Error reported by the simplifier, relative to the mesh extents unless
+    /// `SimplifyOptions::ErrorAbsolute` was used, in world units with
+    /// [`SimplifyParams::world_scale`](crate::SimplifyParams::world_scale).
+    pub result_error: f32,
+    /// Factor turning `result_error` into mesh units, `meshopt::simplify_scale` of the positions or
+    /// `1.0` with `SimplifyOptions::ErrorAbsolute` (`1 / world_scale` with a world scale), see
+    /// [`SimplifyReport::result_error_absolute`].
+    /// Accumulated reports keep the scale of the one with the largest error.
+    pub error_scale: f32,
+    /// Estimated size of the vertex and index buffers in bytes.
+    pub memory_before: usize,
+    /// Estimated size of the vertex and index buffers in bytes, assuming unused vertices are
+    /// compacted away.
+    pub memory_after: usize,
+    /// Triangles removed by [`SimplifyParams::merge_double_sided`](crate::SimplifyParams::merge_double_sided)
+    /// before simplifying, the mesh has to be rendered double-sided when non-zero.
+    pub double_sided_triangles: usize,
+    /// Weights the UV sets were simplified with, see [`UvWeighting::effective_weight`](crate::UvWeighting::effective_weight).
+    pub uv_weights: [Option<Vec2>; 2],
+    /// Run that produced the result, [`SimplifyPath::is_fallback`] when
+    /// [`SimplifyParams::fallback`](crate::SimplifyParams::fallback) engaged. Not accumulated.
+    pub path: SimplifyPath,
+}
+
+impl SimplifyReport {
+    pub(crate) fn new(
+        mesh: &Mesh,
+        params: &SimplifyParams,
+        new_index_count: usize,
+        used_vertices: usize,
+        result_error: f32,
+    ) -> Self {
+        let vertex_size = mesh.get_vertex_size() as usize;
+        let index_size = match mesh.indices() {
+            Some(Indices::U16(_)) => size_of::<u16>(),
+            _ => size_of::<u32>(),
+        };
+        let indices_before = mesh.indices().map_or(0, Indices::len);
+        let vertices_before = mesh.count_vertices();
+
+        SimplifyReport {
+            indices_before,
+            indices_after: new_index_count,
+            vertices_before,
+            vertices_after: used_vertices,
+            result_error,
+            error_scale: error_scale(mesh, params),
+            memory_before: vertices_before * vertex_size + indices_before * index_size,
+            memory_after: used_vertices * vertex_size + new_index_count * index_size,
+            double_sided_triangles: double_sided_triangles(mesh, params),
+            uv_weights: if params.mode != SimplifyMode::Precise {
+                [None; 2]
+            } else {
+                uv_weights(mesh, params)
+            },
+            path: SimplifyPath::Standard,
+        }
+    }
+
+    /// Adds the counts of `other` to this report, keeping the largest error.
+    pub fn accumulate(&mut self, other: &SimplifyReport) {
+        self.indices_before += other.indices_before;
+        self.indices_after += other.indices_after;
+        self.vertices_before += other.vertices_before;
+        self.vertices_after += other.vertices_after;
+        if other.result_error >= self.result_error {
+            self.result_error = other.result_error;
+            self.error_scale = other.error_scale;
+        }
+        self.memory_before += other.memory_before;
+        self.memory_after += other.memory_after;
+        self.double_sided_triangles += other.double_sided_triangles;
+    }
+
+    /// `result_error` in mesh units, i.e. the distance surfaces moved by.
+    pub fn result_error_absolute(&self) -> f32 {
+        self.result_error * self.error_scale
+    }
+
+    pub fn triangles_before(&self) -> usize {
+        self.indices_before / 3
+    }
+
+    pub fn triangles_after(&self) -> usize {
+        self.indices_after / 3
+    }
+}
+
+fn error_scale(mesh: &Mesh, params: &SimplifyParams) -> f32 {
+    if params.options.contains(SimplifyOptions::ErrorAbsolute) {
+        return params.error_units();
+    }
+    mesh_positions(mesh).map_or(0.0, |positions| meshopt::simplify_scale_decoder(positions))
+}
+
+fn double_sided_triangles(mesh: &Mesh, params: &SimplifyParams) -> usize {
+    if !params.merge_double_sided {
+        return 0;
+    }
+    match (mesh_indices(mesh), mesh_positions(mesh)) {
+        (Ok(indices), Ok(positions)) => merge_double_sided(indices, positions, &mut Vec::new()),
+        _ => 0,
+    }
+}
+
+/// Outcome of [`MeshExt::weld_identical_vertices`](crate::MeshExt::weld_identical_vertices).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct WeldReport {
+    pub vertices_before: usize,
+    pub vertices_after: usize,
+    /// Size of the vertex buffers in bytes.
+    pub memory_before: usize,
+    pub memory_after: usize,
+}
+
+impl WeldReport {
+    /// Vertex buffer bytes saved by welding.
+    pub fn memory_saved(&self) -> usize {
+        self.memory_before - self.memory_after
+    }
+
+    /// Adds the counts of `other` to this report.
+    pub fn accumulate(&mut self, other: &WeldReport) {
+        self.vertices_before += other.vertices_before;
+        self.vertices_after += other.vertices_after;
+        self.memory_before += other.memory_before;
+        self.memory_after += other.memory_after;
+    }
+}
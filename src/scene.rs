@@ -0,0 +1,470 @@
+use std::borrow::Cow;
+
+use bevy::{
+    asset::{AssetId, Assets, Handle},
+    ecs::{entity::Entity, hierarchy::Children, message::Messages, world::World},
+    math::Vec3,
+    mesh::{Mesh, Mesh3d, VertexAttributeValues},
+};
+
+use crate::{
+    MeshExt, MeshModified, OptError, SimplifyOverride, SimplifyParams, SimplifyReport,
+    TargetIndices, parallel::par_map, plugin::variant_index,
+};
+
+/// Total triangle count [`simplify_scene`] distributes over a set of meshes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SceneBudget {
+    pub triangles: usize,
+    /// Simplification used for every mesh, `target_index_count` is replaced by the share of the
+    /// budget of each mesh.
+    pub simplify: SimplifyParams,
+    pub weighting: BudgetWeighting,
+}
+
+/// How [`SceneBudget::triangles`] are shared between meshes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum BudgetWeighting {
+    /// In proportion to the triangles of every mesh, every mesh keeps the same fraction.
+    #[default]
+    Triangles,
+    /// In proportion to the triangles of every mesh times the radius of its bounding sphere, so
+    /// big objects keep more detail than small props.
+    TrianglesAndRadius,
+}
+
+/// Outcome of [`simplify_scene`] and [`simplify_hierarchy`].
+#[derive(Debug, Clone, Default)]
+pub struct SceneSimplifyReport {
+    /// One entry per mesh, in the order they were first given.
+    pub meshes: Vec<SceneMeshReport>,
+    pub triangles_before: usize,
+    pub triangles_after: usize,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct SceneMeshReport {
+    pub id: AssetId<Mesh>,
+    /// Triangles the mesh was simplified towards. With [`simplify_scene`] its share of the budget,
+    /// after redistributing the shortfall of the meshes that couldn't reach theirs.
+    pub target_triangles: usize,
+    /// Outcome of the simplification, the mesh is left as it was on errors.
+    pub result: Result<SimplifyReport, OptError>,
+}
+
+impl SceneMeshReport {
+    pub fn reached_target(&self) -> bool {
+        self.result
+            .is_ok_and(|report| report.triangles_after() <= self.target_triangles)
+    }
+}
+
+/// Simplifies the meshes `ids` so that together they keep about `budget.triangles` triangles,
+/// giving each a share of the budget according to [`SceneBudget::weighting`]. Meshes used by
+/// several entities are simplified once however often they are listed, send
+/// [`MeshModified`](crate::MeshModified) for them to refresh the bounds of those entities. The
+/// meshes are simplified in parallel on the `ComputeTaskPool` when it is set up, as it is in Bevy
+/// apps.
+///
+/// Meshes that can't get down to their share, e.g. because of locked borders or the error bound,
+/// are kept as close to it as they got and their shortfall is taken from the others in a second
+/// pass, simplified again from their source. Meshes that fail or aren't loaded are left alone and
+/// count with all of their triangles.
+///
+/// Only assets are given, so entities can't opt out with a [`SimplifyOverride`]: leave their
+/// meshes out of `ids`, or use [`simplify_hierarchy`], which reads the overrides.
+pub fn simplify_scene(
+    meshes: &mut Assets<Mesh>,
+    ids: impl IntoIterator<Item = AssetId<Mesh>>,
+    budget: &SceneBudget,
+) -> SceneSimplifyReport {
+    let mut entries: Vec<Entry> = Vec::new();
+    for id in ids {
+        if entries.iter().any(|entry| entry.id == id) {
+            continue;
+        }
+        let (triangles, weight) = match meshes.get(id) {
+            Some(mesh) => {
+                let triangles = triangle_count(mesh);
+                let radius = match budget.weighting {
+                    BudgetWeighting::Triangles => 1.0,
+                    BudgetWeighting::TrianglesAndRadius => bounding_radius(mesh),
+                };
+                (triangles, triangles as f64 * radius as f64)
+            }
+            None => (0, 0.0),
+        };
+        entries.push(Entry {
+            id,
+            triangles,
+            weight,
+            target: triangles,
+            result: Err(OptError::MissingMesh),
+            simplified: None,
+        });
+    }
+
+    let triangles_before = entries.iter().map(|entry| entry.triangles).sum();
+    allot(&mut entries, budget.triangles);
+    par_map(entries.iter_mut().collect(), |entry| {
+        simplify_entry(meshes, entry, &budget.simplify)
+    });
+
+    // Second pass: the triangles the stuck meshes kept above their share come out of the shares
+    // of the meshes that reached theirs.
+    let shortfall: usize = entries
+        .iter()
+        .map(|entry| entry.kept().saturating_sub(entry.target))
+        .sum();
+    if shortfall > 0 {
+        let reached: Vec<usize> = (0..entries.len())
+            .filter(|&i| entries[i].result.is_ok() && entries[i].kept() <= entries[i].target)
+            .collect();
+        let reached_budget: usize = reached.iter().map(|&i| entries[i].target).sum();
+        let mut reduced = Vec::new();
+        for &i in &reached {
+            let entry = &mut entries[i];
+            let share = entry.target as f64 / reached_budget.max(1) as f64;
+            let target = entry
+                .target
+                .saturating_sub((shortfall as f64 * share).ceil() as usize);
+            if target < entry.target {
+                entry.target = target;
+                reduced.push(i);
+            }
+        }
+        let reduced = entries
+            .iter_mut()
+            .enumerate()
+            .filter(|(i, _)| reduced.contains(i))
+            .map(|(_, entry)| entry)
+            .collect();
+        par_map(reduced, |entry| {
+            simplify_entry(meshes, entry, &budget.simplify)
+        });
+    }
+
+    let mut report = SceneSimplifyReport {
+        triangles_before,
+        ..Default::default()
+    };
+    for entry in entries {
+        report.triangles_after += entry.kept();
+        if let (Some(simplified), Some(mesh)) = (entry.simplified, meshes.get_mut(entry.id)) {
+            *mesh = simplified;
+        }
+        report.meshes.push(SceneMeshReport {
+            id: entry.id,
+            target_triangles: entry.target,
+            result: entry.result,
+        });
+    }
+    report
+}
+
+/// Simplifies the meshes of `root` and all of its descendants in place with `params`, once per
+/// mesh however many entities of the hierarchy use it and in parallel like [`simplify_scene`],
+/// e.g. for a spawned glTF scene. Sends
+/// [`MeshModified`] for every simplified mesh if the message is registered, which
+/// [`MeshBoundsPlugin`](crate::MeshBoundsPlugin) does.
+///
+/// Entities can deviate from `params` with a [`SimplifyOverride`]. A mesh shared by entities with
+/// different overrides is simplified in place for the first of them and into a new asset for each
+/// other one, whose entities are pointed at it. Meshes used by a skipped entity are left alone and
+/// every override of the other entities using them gets a new asset. Reports list meshes once per
+/// override, with the id of the new asset for copies. Meshes that fail or aren't loaded are left alone and count
+/// with all of their triangles, like with [`simplify_scene`]. Queue it as a command to run it from
+/// a system:
+///
+/// ```no_run
+/// use bevy::prelude::*;
+/// use bevy_meshopt::{SimplifyParams, simplify_hierarchy};
+///
+/// fn simplify_scene_root(mut commands: Commands, root: Single<Entity, With<SceneRoot>>) {
+///     let root = *root;
+///     commands.queue(move |world: &mut World| {
+///         let report = simplify_hierarchy(world, root, &SimplifyParams::default());
+///         info!("{} -> {} triangles", report.triangles_before, report.triangles_after);
+///     });
+/// }
+/// ```
+pub fn simplify_hierarchy(
+    world: &mut World,
+    root: Entity,
+    params: &SimplifyParams,
+) -> SceneSimplifyReport {
+    let mut variants: Vec<Option<SimplifyOverride>> = vec![None];
+    // Every entity with its mesh and the index of its override in `variants`.
+    let mut users: Vec<(Entity, AssetId<Mesh>, usize)> = Vec::new();
+    let mut skipped = Vec::new();
+    let mut stack = vec![root];
+    while let Some(entity) = stack.pop() {
+        if let Some(mesh3d) = world.get::<Mesh3d>(entity) {
+            let entity_override = world.get::<SimplifyOverride>(entity);
+            if entity_override == Some(&SimplifyOverride::Skip) {
+                skipped.push(mesh3d.id());
+            } else {
+                let variant = variant_index(&mut variants, entity_override);
+                users.push((entity, mesh3d.id(), variant));
+            }
+        }
+        if let Some(children) = world.get::<Children>(entity) {
+            stack.extend(children.iter().rev());
+        }
+    }
+
+    // Every mesh once per override using it, in the order they were first used. The first one
+    // simplifies the mesh in place unless an entity skips it, the others simplify copies.
+    let mut jobs: Vec<(AssetId<Mesh>, usize, bool)> = Vec::new();
+    for &(_, id, variant) in &users {
+        if !jobs.iter().any(|&(job, v, _)| job == id && v == variant) {
+            let in_place = !skipped.contains(&id) && !jobs.iter().any(|&(job, ..)| job == id);
+            jobs.push((id, variant, in_place));
+        }
+    }
+
+    let meshes = world.get_resource::<Assets<Mesh>>();
+    let copies = jobs
+        .iter()
+        .map(|&(id, variant, _)| {
+            let mesh = meshes.and_then(|meshes| meshes.get(id)).cloned();
+            (variant, mesh)
+        })
+        .collect();
+    let simplified = par_map(copies, |(variant, mesh)| {
+        let params = match variants[variant].as_ref().and_then(|o| o.apply(params)) {
+            Some(overridden) => Cow::Owned(overridden),
+            None => Cow::Borrowed(params),
+        };
+        mesh.map(|mut mesh| {
+            let triangles = triangle_count(&mesh);
+            let result = mesh.simplify_with_report(&params);
+            let target_triangles = params
+                .target_index_count
+                .resolve(triangles * 3, params.min_target_index_count)
+                / 3;
+            (mesh, triangles, target_triangles, result)
+        })
+    });
+
+    let mut report = SceneSimplifyReport::default();
+    let mut modified = Vec::new();
+    // Copies with the mesh and override of the entities to point at them.
+    let mut copied: Vec<(AssetId<Mesh>, usize, Handle<Mesh>)> = Vec::new();
+    let mut meshes = world.get_resource_mut::<Assets<Mesh>>();
+    for ((id, variant, in_place), simplified) in jobs.into_iter().zip(simplified) {
+        let (Some((simplified, triangles, target_triangles, result)), Some(meshes)) =
+            (simplified, meshes.as_mut())
+        else {
+            report.meshes.push(SceneMeshReport {
+                id,
+                target_triangles: 0,
+                result: Err(OptError::MissingMesh),
+            });
+            continue;
+        };
+        report.triangles_before += triangles;
+        report.triangles_after += result.map_or(triangles, |report| report.triangles_after());
+        let mut reported = id;
+        if in_place {
+            if let (Ok(_), Some(mesh)) = (result, meshes.get_mut(id)) {
+                *mesh = simplified;
+                modified.push(id);
+            }
+        } else if result.is_ok() || modified.contains(&id) {
+            // Failed copies are still added when the source was simplified for another override,
+            // so their entities keep the mesh as it was.
+            let handle = meshes.add(simplified);
+            reported = handle.id();
+            modified.push(reported);
+            copied.push((id, variant, handle));
+        }
+        report.meshes.push(SceneMeshReport {
+            id: reported,
+            target_triangles,
+            result,
+        });
+    }
+
+    for (entity, id, variant) in users {
+        let Some((.., handle)) = copied
+            .iter()
+            .find(|&&(source, v, _)| source == id && v == variant)
+        else {
+            continue;
+        };
+        if let Some(mut mesh3d) = world.get_mut::<Mesh3d>(entity) {
+            mesh3d.0 = handle.clone();
+        }
+    }
+
+    if world.contains_resource::<Messages<MeshModified>>() {
+        world.write_message_batch(modified.into_iter().map(MeshModified));
+    }
+    report
+}
+
+struct Entry {
+    id: AssetId<Mesh>,
+    triangles: usize,
+    weight: f64,
+    target: usize,
+    result: Result<SimplifyReport, OptError>,
+    /// Result waiting to replace the source, which later passes simplify from again.
+    simplified: Option<Mesh>,
+}
+
+impl Entry {
+    /// Triangles the mesh ends up with.
+    fn kept(&self) -> usize {
+        self.result
+            .map_or(self.triangles, |report| report.triangles_after())
+    }
+}
+
+/// Shares `budget` out between the entries according to their weight, without giving any of them
+/// more than the triangles it has, and redistributing what those don't use.
+fn allot(entries: &mut [Entry], budget: usize) {
+    let mut open: Vec<usize> = (0..entries.len())
+        .filter(|&i| entries[i].triangles > 0)
+        .collect();
+    let mut remaining = budget;
+    loop {
+        let weight: f64 = open.iter().map(|&i| entries[i].weight).sum();
+        let full: Vec<usize> = open
+            .iter()
+            .copied()
+            .filter(|&i| {
+                weight <= 0.0 || share(remaining, entries[i].weight, weight) >= entries[i].triangles
+            })
+            .collect();
+        if full.is_empty() {
+            for &i in &open {
+                entries[i].target = share(remaining, entries[i].weight, weight);
+            }
+            return;
+        }
+        for i in full {
+            entries[i].target = entries[i].triangles;
+            remaining = remaining.saturating_sub(entries[i].triangles);
+            open.retain(|&open| open != i);
+        }
+    }
+}
+
+fn share(budget: usize, weight: f64, total_weight: f64) -> usize {
+    (budget as f64 * weight / total_weight) as usize
+}
+
+fn simplify_entry(meshes: &Assets<Mesh>, entry: &mut Entry, template: &SimplifyParams) {
+    let Some(source) = meshes.get(entry.id) else {
+        return;
+    };
+    let params = SimplifyParams {
+        target_index_count: TargetIndices::TriangleCount(entry.target),
+        ..template.clone()
+    };
+    let mut mesh = source.clone();
+    entry.result = mesh.simplify_with_report(&params);
+    entry.simplified = entry.result.is_ok().then_some(mesh);
+}
+
+pub(crate) fn triangle_count(mesh: &Mesh) -> usize {
+    mesh.indices()
+        .map_or(mesh.count_vertices(), |indices| indices.len())
+        / 3
+}
+
+/// Radius of a sphere around the center of the bounds of `mesh` enclosing all of its positions.
+fn bounding_radius(mesh: &Mesh) -> f32 {
+    let Some(VertexAttributeValues::Float32x3(positions)) =
+        mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+    else {
+        return 0.0;
+    };
+    let Some(&first) = positions.first() else {
+        return 0.0;
+    };
+    let (min, max) = positions.iter().fold(
+        (Vec3::from(first), Vec3::from(first)),
+        |(min, max), &position| (min.min(position.into()), max.max(position.into())),
+    );
+    let center = (min + max) * 0.5;
+    positions
+        .iter()
+        .map(|&position| center.distance(position.into()))
+        .fold(0.0, f32::max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::hierarchy::ChildOf;
+
+    use crate::test_util::sphere;
+
+    /// Root with one child using the mesh of `mesh` per override.
+    fn spawn_users(world: &mut World, overrides: &[Option<SimplifyOverride>]) -> Vec<Entity> {
+        let handle = world.resource_mut::<Assets<Mesh>>().add(sphere(4));
+        let children = overrides
+            .iter()
+            .map(|entity_override| {
+                let mut child = world.spawn(Mesh3d(handle.clone()));
+                if let Some(entity_override) = entity_override {
+                    child.insert(entity_override.clone());
+                }
+                child.id()
+            })
+            .collect::<Vec<_>>();
+        world.spawn_empty().add_children(&children);
+        children
+    }
+
+    fn quarter() -> SimplifyOverride {
+        SimplifyOverride::Params {
+            target_index_count: Some(TargetIndices::Multiplier(0.25)),
+            max_error: Some(1.0),
+        }
+    }
+
+    fn triangles(world: &World, entity: Entity) -> usize {
+        let id = world.get::<Mesh3d>(entity).unwrap().id();
+        triangle_count(world.resource::<Assets<Mesh>>().get(id).unwrap())
+    }
+
+    #[test]
+    fn hierarchy_simplifies_shared_mesh_per_override() {
+        let mut world = World::new();
+        world.init_resource::<Assets<Mesh>>();
+        let users = spawn_users(&mut world, &[None, Some(quarter()), None]);
+        let root = world.get::<ChildOf>(users[0]).unwrap().parent();
+        let before = triangle_count(&sphere(4));
+        let params = SimplifyParams {
+            target_index_count: TargetIndices::Multiplier(0.5),
+            max_error: 1.0,
+            ..Default::default()
+        };
+
+        let report = simplify_hierarchy(&mut world, root, &params);
+        assert_eq!(report.meshes.len(), 2);
+        assert_eq!(world.resource::<Assets<Mesh>>().len(), 2);
+        let [default, overridden, shared] = [0, 1, 2].map(|i| triangles(&world, users[i]));
+        assert_eq!(default, shared);
+        assert!(default <= before / 2);
+        assert!(overridden <= before / 4);
+        assert!(overridden < default);
+    }
+
+    #[test]
+    fn hierarchy_copies_mesh_of_skipped_entity() {
+        let mut world = World::new();
+        world.init_resource::<Assets<Mesh>>();
+        let users = spawn_users(&mut world, &[Some(SimplifyOverride::Skip), Some(quarter())]);
+        let root = world.get::<ChildOf>(users[0]).unwrap().parent();
+
+        let report = simplify_hierarchy(&mut world, root, &SimplifyParams::default());
+        assert_eq!(report.meshes.len(), 1);
+        assert_eq!(triangles(&world, users[0]), triangle_count(&sphere(4)));
+        assert!(triangles(&world, users[1]) <= triangle_count(&sphere(4)) / 4);
+    }
+}
@@ -0,0 +1,109 @@
+use bevy::{
+    math::{Vec2, Vec3},
+    mesh::{Mesh, VertexAttributeValues},
+    reflect::{Reflect, std_traits::ReflectDefault},
+};
+
+use crate::{OptError, adjacency::TriangleAdjacency, mesh_indices, mesh_positions};
+
+/// Locks the vertices along attribute seams, where exporters split vertices sharing a position
+/// because their UVs or normals differ, so textures don't swim across UV seams when the vertices
+/// next to them collapse. Independent of `SimplifyOptions::LockBorder`, which only locks open
+/// borders and doesn't see seams.
+///
+/// The simplifier already keeps seams from tearing apart, but lets seam vertices slide along the
+/// seam, which pulls the texture on both sides with them. See [`HardEdges`](crate::HardEdges) for
+/// creases that aren't split.
+#[derive(Debug, Copy, Clone, PartialEq, Reflect)]
+#[reflect(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct SeamLocks {
+    /// Seams of `ATTRIBUTE_UV_0`.
+    pub uv_0: bool,
+    /// Seams of `ATTRIBUTE_UV_1`, e.g. the charts of a lightmap.
+    pub uv_1: bool,
+    /// UVs closer than this are the same.
+    pub uv_epsilon: f32,
+    /// Angle in radians above which split normals are a seam, `None` ignores normals.
+    pub normal_angle: Option<f32>,
+}
+
+impl Default for SeamLocks {
+    fn default() -> Self {
+        SeamLocks {
+            uv_0: true,
+            uv_1: false,
+            uv_epsilon: 1e-6,
+            normal_angle: None,
+        }
+    }
+}
+
+impl SeamLocks {
+    pub(crate) fn lock_seam_vertices(
+        &self,
+        mesh: &Mesh,
+        indices: &[u32],
+        positions: &[[f32; 3]],
+        locks: &mut [bool],
+    ) {
+        let welded = TriangleAdjacency::new(indices, positions);
+        let mut welded_locks = vec![false; welded.welded_vertex_count];
+
+        for (attribute, enabled) in [
+            (Mesh::ATTRIBUTE_UV_0, self.uv_0),
+            (Mesh::ATTRIBUTE_UV_1, self.uv_1),
+        ] {
+            if let (true, Some(VertexAttributeValues::Float32x2(uvs))) =
+                (enabled, mesh.attribute(attribute))
+                && uvs.len() == positions.len()
+            {
+                let epsilon_squared = self.uv_epsilon * self.uv_epsilon;
+                flag_splits(&welded.remap, uvs, &mut welded_locks, |a, b| {
+                    Vec2::from(*a).distance_squared(Vec2::from(*b)) > epsilon_squared
+                });
+            }
+        }
+        if let (Some(angle), Some(VertexAttributeValues::Float32x3(normals))) =
+            (self.normal_angle, mesh.attribute(Mesh::ATTRIBUTE_NORMAL))
+            && normals.len() == positions.len()
+        {
+            let cos_threshold = angle.cos();
+            flag_splits(&welded.remap, normals, &mut welded_locks, |a, b| {
+                let [a, b] = [a, b].map(|normal| Vec3::from(*normal).normalize_or_zero());
+                a.dot(b) < cos_threshold
+            });
+        }
+
+        welded.flag_welded(&welded_locks, locks);
+    }
+}
+
+/// Flags the welded vertices whose vertices have values that `differ` from the first one seen.
+fn flag_splits<T>(
+    remap: &[u32],
+    values: &[T],
+    welded_locks: &mut [bool],
+    differ: impl Fn(&T, &T) -> bool,
+) {
+    let mut first: Vec<Option<&T>> = vec![None; welded_locks.len()];
+    for (value, &welded_vertex) in values.iter().zip(remap) {
+        if welded_vertex == u32::MAX {
+            continue;
+        }
+        match first[welded_vertex as usize] {
+            None => first[welded_vertex as usize] = Some(value),
+            Some(first) if differ(first, value) => welded_locks[welded_vertex as usize] = true,
+            Some(_) => {}
+        }
+    }
+}
+
+/// One flag per vertex of `mesh`, set on the vertices [`SeamLocks`] locks.
+pub(crate) fn seam_vertices(mesh: &Mesh, seams: &SeamLocks) -> Result<Vec<bool>, OptError> {
+    let indices = mesh_indices(mesh)?;
+    let positions = mesh_positions(mesh)?;
+    let mut locks = vec![false; positions.len()];
+    seams.lock_seam_vertices(mesh, indices, positions, &mut locks);
+    Ok(locks)
+}
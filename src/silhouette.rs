@@ -0,0 +1,119 @@
+use bevy::{math::Vec3, reflect::Reflect};
+
+use crate::adjacency::TriangleAdjacency;
+
+/// Locks the vertices forming the silhouette of the mesh when seen from a few fixed directions,
+/// see [`SimplifyParams::lock_silhouettes`](crate::SimplifyParams::lock_silhouettes).
+#[derive(Debug, Clone, PartialEq, Reflect)]
+#[reflect(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct SilhouetteLocks {
+    /// Directions the mesh is viewed along, from the camera towards the mesh, in mesh space.
+    pub directions: Vec<Vec3>,
+    /// Faces within this angle in radians of being edge-on count as both front and back facing,
+    /// so the silhouettes of slightly different views get locked as well.
+    pub angle_tolerance: f32,
+}
+
+impl SilhouetteLocks {
+    /// Locks both vertices of every edge whose adjacent faces straddle one of the view directions.
+    /// Vertices are compared by position, so every copy of a vertex along an attribute seam is
+    /// locked.
+    pub(crate) fn lock_silhouette_vertices(
+        &self,
+        indices: &[u32],
+        positions: &[[f32; 3]],
+        locks: &mut [bool],
+    ) {
+        let welded = TriangleAdjacency::new(indices, positions);
+        let tolerance = self.angle_tolerance.max(0.0).sin();
+        let mut welded_locks = vec![false; welded.welded_vertex_count];
+        for ((a, b), faces) in welded.welded_edges() {
+            let faces: Vec<Vec3> = faces
+                .iter()
+                .map(|&triangle| welded.face_normals[triangle as usize])
+                .collect();
+            // Open borders have no second face to compare against, `LockBorder` covers those.
+            if faces.len() < 2 {
+                continue;
+            }
+
+            let silhouette = self.directions.iter().any(|direction| {
+                let direction = direction.normalize_or_zero();
+                let (min, max) =
+                    faces
+                        .iter()
+                        .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), normal| {
+                            let facing = normal.dot(direction);
+                            (min.min(facing), max.max(facing))
+                        });
+                min <= tolerance && max >= -tolerance
+            });
+            if silhouette {
+                welded_locks[a as usize] = true;
+                welded_locks[b as usize] = true;
+            }
+        }
+
+        welded.flag_welded(&welded_locks, locks);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        MeshExt, SimplifyParams, TargetIndices,
+        test_util::{indices, positions, sphere},
+    };
+
+    #[test]
+    fn silhouette_stays_while_the_rest_collapses() {
+        let source = sphere(10);
+        let silhouette = SilhouetteLocks {
+            directions: vec![Vec3::Z, Vec3::X],
+            angle_tolerance: 0.05,
+        };
+        let mut locks = vec![false; source.count_vertices()];
+        silhouette.lock_silhouette_vertices(&indices(&source), positions(&source), &mut locks);
+        let locked: Vec<[f32; 3]> = positions(&source)
+            .iter()
+            .zip(&locks)
+            .filter(|(_, locked)| **locked)
+            .map(|(position, _)| *position)
+            .collect();
+        // The outlines seen along Z and X, the great circles of the sphere around them.
+        assert!(!locked.is_empty());
+        for [x, _, z] in &locked {
+            assert!(z.abs() < 0.2 || x.abs() < 0.2, "{x} {z}");
+        }
+
+        let mut mesh = source.clone();
+        let report = mesh
+            .simplify_with_report(&SimplifyParams {
+                target_index_count: TargetIndices::Multiplier(0.05),
+                max_error: 1.0,
+                silhouette_locks: Some(silhouette),
+                ..Default::default()
+            })
+            .unwrap();
+        assert!(report.triangles_after() < report.triangles_before() / 4);
+        let mut used = indices(&mesh);
+        used.sort_unstable();
+        used.dedup();
+        let kept: Vec<[f32; 3]> = used
+            .into_iter()
+            .map(|vertex| positions(&mesh)[vertex as usize])
+            .collect();
+        for position in &locked {
+            assert!(kept.contains(position), "{position:?}");
+        }
+        // Next to nothing is left of the unlocked vertices.
+        let unlocked = source.count_vertices() - locked.len();
+        assert!(
+            kept.len() - locked.len() < unlocked / 10,
+            "{} vertices",
+            kept.len()
+        );
+    }
+}
@@ -0,0 +1,967 @@
+use std::{cell::RefCell, ops::ControlFlow};
+
+use bevy::{
+    math::Vec3,
+    mesh::{Indices, Mesh, PrimitiveTopology},
+};
+use meshopt::{SimplifyOptions, ffi};
+
+use crate::{
+    OptError, SimplifyMode, SimplifyParams, SimplifyPath, SimplifyReport, SimplifyStrategy,
+    SymmetryMode, TargetIndices,
+    attributes::{VertexAttributes, lock_joint_seam_vertices, vertex_attributes},
+    double_sided::merge_double_sided,
+    fallback::apply_fallback,
+    foliage::remove_cards,
+    manifold::lock_non_manifold_vertices,
+    mesh_indices, mesh_positions,
+    optimize::optimize_vertex_fetch,
+    planar::planar_regions,
+    points::{simplify_point_list, simplify_to_points},
+    refuse_morph_targets, symmetry, take_mesh_indices_mut,
+    vertex::sort_triangles,
+    with_simplified_indices,
+};
+
+/// `meshopt_SimplifyVertex_Lock` and `meshopt_SimplifyVertex_Protect`, which the bindings don't
+/// expose.
+const SIMPLIFY_VERTEX_LOCK: u8 = 1 << 0;
+const SIMPLIFY_VERTEX_PROTECT: u8 = 1 << 1;
+
+/// Buffers reused between simplification runs, so repeatedly simplifying (e.g. dry runs while
+/// scrubbing a slider) doesn't reallocate them every time.
+#[derive(Default)]
+pub(crate) struct SimplifyScratch {
+    /// Output of the last simplification.
+    pub indices: Vec<u32>,
+    /// Run that produced `indices`.
+    pub path: SimplifyPath,
+    pub locks: Vec<bool>,
+    pub seen: Vec<bool>,
+    pub attributes: Vec<f32>,
+    pub attribute_weights: Vec<f32>,
+    /// Source indices sorted by [`SimplifyParams::canonical_order`].
+    pub sorted: Vec<u32>,
+    /// Source indices without degenerate triangles.
+    pub stripped: Vec<u32>,
+    /// Source indices with double-sided faces merged.
+    pub merged: Vec<u32>,
+    /// Cards kept by [`SimplifyStrategy::CardRemoval`] and the triangles that aren't cards.
+    pub cards: Vec<u32>,
+    pub rest: Vec<u32>,
+}
+
+thread_local! {
+    static SCRATCH: RefCell<SimplifyScratch> = RefCell::default();
+}
+
+/// Runs `f` with this thread's scratch buffers.
+pub(crate) fn with_scratch<T>(f: impl FnOnce(&mut SimplifyScratch) -> T) -> T {
+    SCRATCH.with(|scratch| match scratch.try_borrow_mut() {
+        Ok(mut scratch) => f(&mut scratch),
+        // Re-entrant use (e.g. from a callback), fall back to fresh buffers.
+        Err(_) => f(&mut SimplifyScratch::default()),
+    })
+}
+
+/// Simplifies the mesh with `params`, returning the new indices along with the resulting error
+/// and the run that produced them.
+pub(crate) fn simplify_mesh_indices(
+    mesh: &Mesh,
+    params: &SimplifyParams,
+) -> Result<(Vec<u32>, f32, SimplifyPath), OptError> {
+    with_scratch(|scratch| {
+        let error = simplify_mesh_into(mesh, params, scratch)?;
+        Ok((scratch.indices.clone(), error, scratch.path))
+    })
+}
+
+/// Simplifies the mesh in place, reporting the result.
+pub(crate) fn simplify_with_report(
+    mesh: &mut Mesh,
+    params: &SimplifyParams,
+) -> Result<SimplifyReport, OptError> {
+    if params.mode == SimplifyMode::Points {
+        return simplify_to_points(mesh, params);
+    }
+    let (new_indices, error, path) = simplify_mesh_indices(mesh, params)?;
+    Ok(apply_simplified_indices(
+        mesh,
+        params,
+        new_indices,
+        error,
+        path,
+    ))
+}
+
+/// Copy of the mesh simplified in place with `params`, with the vertices no longer used dropped
+/// and the index format of the source.
+pub(crate) fn simplified(mesh: &Mesh, params: &SimplifyParams) -> Result<Mesh, OptError> {
+    refuse_morph_targets(mesh)?;
+    let mut simplified = mesh.clone();
+    if mesh.primitive_topology() == PrimitiveTopology::PointList {
+        simplify_point_list(&mut simplified, params)?;
+        return Ok(simplified);
+    }
+    with_simplified_indices(&mut simplified, params, |mesh| {
+        simplify_with_report(mesh, params)?;
+        // Point clouds only keep the points they use already.
+        if params.mode != SimplifyMode::Points {
+            optimize_vertex_fetch(mesh)?;
+        }
+        Ok(())
+    })?;
+    Ok(simplified)
+}
+
+/// Replaces the indices of the mesh with the result of simplifying it with `params`, unless
+/// nothing is left, and reports the outcome.
+pub(crate) fn apply_simplified_indices(
+    mesh: &mut Mesh,
+    params: &SimplifyParams,
+    new_indices: Vec<u32>,
+    error: f32,
+    path: SimplifyPath,
+) -> SimplifyReport {
+    let used_vertices = with_scratch(|scratch| {
+        count_used_vertices(&new_indices, mesh.count_vertices(), &mut scratch.seen)
+    });
+    let report = SimplifyReport {
+        path,
+        ..SimplifyReport::new(mesh, params, new_indices.len(), used_vertices, error)
+    };
+    if new_indices.len() >= 3 {
+        mesh.insert_indices(Indices::U32(new_indices));
+    }
+    report
+}
+
+/// Simplifies the mesh with `params` into `scratch.indices` and `scratch.path`, returning the
+/// resulting error.
+pub(crate) fn simplify_mesh_into(
+    mesh: &Mesh,
+    params: &SimplifyParams,
+    scratch: &mut SimplifyScratch,
+) -> Result<f32, OptError> {
+    match params.mode {
+        SimplifyMode::Precise => {}
+        SimplifyMode::Sloppy if params.options != SimplifyOptions::None => {
+            return Err(OptError::UnsupportedSimplifyOptions(params.options));
+        }
+        SimplifyMode::Sloppy => {}
+        SimplifyMode::Points => return Err(OptError::UnsupportedSimplifyMode(params.mode)),
+    }
+    let indices = mesh_indices(mesh)?;
+    let positions = mesh_positions(mesh)?;
+    // meshoptimizer's error metrics break down on non-finite positions.
+    if let Some(vertex) = positions
+        .iter()
+        .position(|position| !position.iter().all(|value| value.is_finite()))
+    {
+        return Err(OptError::InvalidPositions(vertex));
+    }
+
+    let SimplifyScratch {
+        indices: out,
+        path,
+        locks,
+        seen,
+        attributes,
+        attribute_weights,
+        sorted,
+        stripped,
+        merged,
+        cards,
+        rest,
+        ..
+    } = scratch;
+    let indices = if params.canonical_order {
+        sorted.clone_from(indices);
+        sort_triangles(sorted);
+        sorted.as_slice()
+    } else {
+        indices.as_slice()
+    };
+    let indices = if params.strip_degenerates {
+        strip_degenerate_triangles(indices, positions, stripped);
+        stripped.as_slice()
+    } else {
+        indices
+    };
+    let sparse_params;
+    let (indices, params) =
+        if params.merge_double_sided && merge_double_sided(indices, positions, merged) > 0 {
+            // Vertices only used by the removed triangles would otherwise look like attribute seams
+            // to the simplifier and keep their twins from collapsing.
+            sparse_params = SimplifyParams {
+                options: params.options | SimplifyOptions::Sparse,
+                ..params.clone()
+            };
+            (merged.as_slice(), &sparse_params)
+        } else {
+            (indices, params)
+        };
+    let mut target_index_count = params
+        .target_index_count
+        .resolve(indices.len(), params.min_target_index_count);
+    let indices = match params.strategy {
+        SimplifyStrategy::EdgeCollapse => {
+            cards.clear();
+            indices
+        }
+        SimplifyStrategy::CardRemoval => {
+            target_index_count = remove_cards(indices, positions, target_index_count, cards, rest);
+            if rest.is_empty() {
+                out.clone_from(cards);
+                *path = SimplifyPath::Standard;
+                return Ok(0.0);
+            }
+            rest.as_slice()
+        }
+    };
+    let locks = resolve_vertex_locks(mesh, indices, positions, params, locks)?;
+    let planar = params
+        .planarity_tolerance
+        .filter(|_| params.mode == SimplifyMode::Precise)
+        .map(|tolerance| planar_regions(indices, positions, tolerance));
+    let input = SimplifyInput {
+        indices,
+        positions,
+        attributes: vertex_attributes(
+            mesh,
+            positions,
+            params,
+            planar.as_ref().map(|planar| planar.interior.as_slice()),
+            attributes,
+            attribute_weights,
+        ),
+        locks,
+        protect: planar.as_ref().map(|planar| planar.boundaries.as_slice()),
+    };
+
+    if let Some(symmetry) = &params.symmetry
+        && symmetry.mode == SymmetryMode::Mirror
+    {
+        let (new_indices, error, mirrored_path) =
+            symmetry::simplify_mirrored(mesh, input, target_index_count, params, symmetry)?;
+        out.clear();
+        out.extend_from_slice(&new_indices);
+        out.extend_from_slice(cards);
+        *path = mirrored_path;
+        return Ok(error);
+    }
+
+    let (error, simplified_path) = match params.target_index_count {
+        TargetIndices::VertexCount(vertices) => {
+            simplify_to_vertex_count(out, input, vertices, params, seen)
+        }
+        _ => simplify_into(out, input, target_index_count, params),
+    };
+    out.extend_from_slice(cards);
+    *path = simplified_path;
+    Ok(error)
+}
+
+/// Simplifications [`TargetIndices::VertexCount`] runs at most to get under its budget.
+const VERTEX_COUNT_ATTEMPTS: usize = 4;
+
+/// Simplifies `input` until the result uses at most `vertices` vertices. The index target starts
+/// at the indices per vertex of `input` and is lowered by the overshoot of every attempt, until the
+/// result fits, the simplifier stops short of the target or the attempts run out.
+fn simplify_to_vertex_count(
+    out: &mut Vec<u32>,
+    input: SimplifyInput,
+    vertices: usize,
+    params: &SimplifyParams,
+    seen: &mut Vec<bool>,
+) -> (f32, SimplifyPath) {
+    let vertex_count = input.positions.len();
+    let resolve = |indices: f64| {
+        TargetIndices::Count(indices as usize)
+            .resolve(input.indices.len(), params.min_target_index_count)
+    };
+    let used = count_used_vertices(input.indices, vertex_count, seen).max(1);
+    let mut target_index_count =
+        resolve(input.indices.len() as f64 * vertices as f64 / used as f64);
+    let mut result = simplify_into(out, input, target_index_count, params);
+    for _ in 1..VERTEX_COUNT_ATTEMPTS {
+        let kept = count_used_vertices(out, vertex_count, seen);
+        if kept <= vertices || out.len() > target_index_count {
+            break;
+        }
+        let lowered = resolve(target_index_count as f64 * vertices as f64 / kept as f64);
+        if lowered >= target_index_count {
+            break;
+        }
+        target_index_count = lowered;
+        result = simplify_into(out, input, target_index_count, params);
+    }
+    result
+}
+
+/// Copies the triangles of `indices` that have an area into `out`, dropping the ones using a
+/// vertex twice or whose vertices are collinear.
+fn strip_degenerate_triangles(indices: &[u32], positions: &[[f32; 3]], out: &mut Vec<u32>) {
+    out.clear();
+    out.extend(
+        indices
+            .chunks_exact(3)
+            .filter(|triangle| {
+                let [a, b, c] = [0, 1, 2].map(|i| Vec3::from(positions[triangle[i] as usize]));
+                (b - a).cross(c - a) != Vec3::ZERO
+            })
+            .flatten(),
+    );
+}
+
+/// Combines the user supplied vertex locks with the locks implied by the rest of `params`, using
+/// `buffer` when they have to be merged.
+pub(crate) fn resolve_vertex_locks<'a>(
+    mesh: &Mesh,
+    indices: &[u32],
+    positions: &[[f32; 3]],
+    params: &'a SimplifyParams,
+    buffer: &'a mut Vec<bool>,
+) -> Result<Option<&'a [bool]>, OptError> {
+    if let Some(locks) = &params.vertex_locks
+        && locks.len() != positions.len()
+    {
+        return Err(OptError::InvalidVertexLockCount(locks.len()));
+    }
+
+    if params.symmetry.is_none()
+        && params.silhouette_locks.is_none()
+        && params.hard_edges.is_none()
+        && params.seam_locks.is_none()
+        && !params.lock_non_manifold
+        && !params.lock_joint_seams
+        && params.locked_vertices.is_none()
+    {
+        return Ok(params.vertex_locks.as_deref());
+    }
+
+    buffer.clear();
+    match &params.vertex_locks {
+        Some(locks) => buffer.extend_from_slice(locks),
+        None => buffer.resize(positions.len(), false),
+    }
+    if let Some(locked_vertices) = &params.locked_vertices {
+        locked_vertices.lock_vertices(mesh, positions, buffer)?;
+    }
+    if let Some(symmetry) = &params.symmetry {
+        symmetry.lock_plane_vertices(positions, buffer);
+    }
+    if let Some(silhouette) = &params.silhouette_locks {
+        silhouette.lock_silhouette_vertices(indices, positions, buffer);
+    }
+    if let Some(hard_edges) = &params.hard_edges {
+        hard_edges.lock_hard_edge_vertices(mesh, indices, positions, buffer);
+    }
+    if let Some(seam_locks) = &params.seam_locks {
+        seam_locks.lock_seam_vertices(mesh, indices, positions, buffer);
+    }
+    if params.lock_non_manifold {
+        lock_non_manifold_vertices(indices, positions, buffer);
+    }
+    if params.lock_joint_seams {
+        lock_joint_seam_vertices(mesh, indices, buffer);
+    }
+    Ok(Some(buffer))
+}
+
+/// Buffers the simplifier runs over.
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct SimplifyInput<'a> {
+    pub indices: &'a [u32],
+    pub positions: &'a [[f32; 3]],
+    pub attributes: Option<VertexAttributes<'a>>,
+    /// One lock per vertex.
+    pub locks: Option<&'a [bool]>,
+    /// Vertices whose attribute discontinuities have to be kept, collapses across the other
+    /// discontinuities are allowed when set.
+    pub protect: Option<&'a [bool]>,
+}
+
+/// Runs the simplifier selected by `params` over `input`, falling back according to
+/// [`SimplifyParams::fallback`], writing the new indices into `out` and returning the resulting
+/// error along with the run that produced them.
+pub(crate) fn simplify_into(
+    out: &mut Vec<u32>,
+    input: SimplifyInput,
+    target_index_count: usize,
+    params: &SimplifyParams,
+) -> (f32, SimplifyPath) {
+    let error = run_simplifier(out, input, target_index_count, params);
+    apply_fallback(out, input, target_index_count, params, error)
+}
+
+/// Runs the simplifier selected by `params` over `input` once, writing the new indices into `out`
+/// and returning the resulting error.
+pub(crate) fn run_simplifier(
+    out: &mut Vec<u32>,
+    input: SimplifyInput,
+    target_index_count: usize,
+    params: &SimplifyParams,
+) -> f32 {
+    let SimplifyInput {
+        indices,
+        positions,
+        attributes,
+        locks,
+        protect,
+    } = input;
+    debug_assert!(locks.is_none_or(|locks| locks.len() == positions.len()));
+    debug_assert!(protect.is_none_or(|protect| protect.len() == positions.len()));
+    debug_assert!(attributes.is_none_or(|attributes| {
+        attributes.values.len() == positions.len() * attributes.stride()
+    }));
+
+    out.clear();
+    out.resize(indices.len(), 0);
+    let mut options = params.options;
+    let vertex_flags: Option<Vec<u8>> = protect.map(|protect| {
+        options |= SimplifyOptions::Permissive;
+        protect
+            .iter()
+            .enumerate()
+            .map(|(vertex, &protect)| {
+                let lock = locks.is_some_and(|locks| locks[vertex]);
+                let mut flags = 0;
+                if lock {
+                    flags |= SIMPLIFY_VERTEX_LOCK;
+                }
+                if protect {
+                    flags |= SIMPLIFY_VERTEX_PROTECT;
+                }
+                flags
+            })
+            .collect()
+    });
+    let lock_ptr = match &vertex_flags {
+        Some(flags) => flags.as_ptr(),
+        None => locks.map_or(std::ptr::null(), |locks| locks.as_ptr().cast::<u8>()),
+    };
+    let target_index_count = target_index_count.min(indices.len());
+    let (attribute_ptr, attribute_weights, attribute_count) =
+        attributes.map_or((std::ptr::null(), std::ptr::null(), 0), |attributes| {
+            (
+                attributes.values.as_ptr(),
+                attributes.weights.as_ptr(),
+                attributes.stride(),
+            )
+        });
+
+    // Absolute errors in world units are handed to the simplifier in mesh units.
+    let error_units = params.error_units();
+    let mut result_error = 0.0;
+    // SAFETY: `out` has room for `indices.len()` indices which is the most the simplifier writes,
+    // positions are tightly packed, `attributes` has `attribute_count` floats per vertex and
+    // `locks` has one entry per vertex.
+    let index_count = unsafe {
+        if params.mode == SimplifyMode::Sloppy {
+            ffi::meshopt_simplifySloppy(
+                out.as_mut_ptr(),
+                indices.as_ptr(),
+                indices.len(),
+                positions.as_ptr().cast(),
+                positions.len(),
+                size_of::<[f32; 3]>(),
+                lock_ptr,
+                target_index_count,
+                params.max_error * error_units,
+                &mut result_error,
+            )
+        } else {
+            ffi::meshopt_simplifyWithAttributes(
+                out.as_mut_ptr(),
+                indices.as_ptr(),
+                indices.len(),
+                positions.as_ptr().cast(),
+                positions.len(),
+                size_of::<[f32; 3]>(),
+                attribute_ptr,
+                attribute_count * size_of::<f32>(),
+                attribute_weights,
+                attribute_count,
+                lock_ptr,
+                target_index_count,
+                params.max_error * error_units,
+                options.bits(),
+                &mut result_error,
+            )
+        }
+    };
+    out.truncate(index_count);
+
+    result_error / error_units
+}
+
+/// Counts the distinct vertices referenced by `indices`.
+pub(crate) fn count_used_vertices(
+    indices: &[u32],
+    vertex_count: usize,
+    seen: &mut Vec<bool>,
+) -> usize {
+    seen.clear();
+    seen.resize(vertex_count, false);
+    let mut used = 0;
+    for &index in indices {
+        if let Some(seen) = seen.get_mut(index as usize)
+            && !*seen
+        {
+            *seen = true;
+            used += 1;
+        }
+    }
+    used
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StepParams {
+    /// Simplification used for every step. `target_index_count` is ignored in favor of `reduction`
+    /// and `max_error` bounds the error accumulated over all steps.
+    pub simplify: SimplifyParams,
+    /// Fraction of the current indices removed per step, e.g. `0.25` for 25% fewer indices every
+    /// iteration.
+    pub reduction: f32,
+    /// Upper bound on the number of steps taken.
+    pub max_steps: u32,
+}
+
+impl Default for StepParams {
+    fn default() -> Self {
+        StepParams {
+            simplify: SimplifyParams::default(),
+            reduction: 0.25,
+            max_steps: 32,
+        }
+    }
+}
+
+/// Simplifies `mesh` step by step until `predicate` breaks, the step limit is reached or the
+/// simplifier stops making progress. A step rejected by `predicate` is rolled back.
+pub(crate) fn simplify_until(
+    mesh: &mut Mesh,
+    step: &StepParams,
+    mut predicate: impl FnMut(&SimplifyReport, &Mesh) -> ControlFlow<()>,
+) -> Result<SimplifyReport, OptError> {
+    let vertex_count = mesh.count_vertices();
+    let indices = mesh_indices(mesh)?;
+    let index_count = indices.len();
+    let used_vertices =
+        with_scratch(|scratch| count_used_vertices(indices, vertex_count, &mut scratch.seen));
+    let base = SimplifyReport::new(mesh, &step.simplify, index_count, used_vertices, 0.0);
+    let reduction = step.reduction.clamp(0.0, 1.0);
+
+    let mut accepted = base;
+    for _ in 0..step.max_steps {
+        let current = accepted.indices_after;
+        let params = SimplifyParams {
+            max_error: (step.simplify.max_error - accepted.result_error).max(0.0),
+            target_index_count: TargetIndices::Count(
+                ((current as f32 * (1.0 - reduction)) as usize / 3 * 3).max(3),
+            ),
+            ..step.simplify.clone()
+        };
+
+        let (new_indices, error, path) = simplify_mesh_indices(mesh, &params)?;
+        if new_indices.len() < 3 || new_indices.len() >= current {
+            break;
+        }
+
+        let used_vertices = with_scratch(|scratch| {
+            count_used_vertices(&new_indices, vertex_count, &mut scratch.seen)
+        });
+        let new_index_count = new_indices.len();
+        let previous = take_mesh_indices_mut(mesh)?;
+        mesh.insert_indices(Indices::U32(new_indices));
+
+        let report = SimplifyReport {
+            indices_before: base.indices_before,
+            vertices_before: base.vertices_before,
+            memory_before: base.memory_before,
+            result_error: accepted.result_error + error,
+            path,
+            ..SimplifyReport::new(mesh, &params, new_index_count, used_vertices, 0.0)
+        };
+
+        if predicate(&report, mesh).is_break() {
+            mesh.insert_indices(Indices::U32(previous));
+            break;
+        }
+        accepted = report;
+    }
+
+    Ok(accepted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::{
+        asset::RenderAssetUsages,
+        math::primitives::Cuboid,
+        mesh::{Meshable, VertexAttributeValues},
+    };
+
+    use crate::{
+        MeshExt,
+        test_util::{grid, indices, positions, sphere, triangle_set, with_u16_indices},
+        vertex::remap_vertices,
+    };
+
+    /// Vertex and index bytes of `mesh`, the snapshot two results are compared by.
+    fn snapshot(mesh: &Mesh) -> Vec<u8> {
+        let mut bytes: Vec<u8> = mesh
+            .attributes()
+            .flat_map(|(_, values)| values.get_bytes().to_vec())
+            .collect();
+        bytes.extend(indices(mesh).into_iter().flat_map(u32::to_le_bytes));
+        bytes
+    }
+
+    /// `mesh` with its vertices and triangles reversed and every triangle rotated by a corner.
+    fn shuffled(mut mesh: Mesh) -> Mesh {
+        let vertex_count = mesh.count_vertices();
+        let remap: Vec<u32> = (0..vertex_count as u32).rev().collect();
+        remap_vertices(&mut mesh, &remap, vertex_count);
+        let triangles = indices(&mesh)
+            .chunks_exact(3)
+            .rev()
+            .flat_map(|corners| {
+                [corners[1], corners[2], corners[0]].map(|index| remap[index as usize])
+            })
+            .collect();
+        mesh.insert_indices(Indices::U32(triangles));
+        mesh
+    }
+
+    #[test]
+    fn target_above_index_count_keeps_triangles() {
+        let mut mesh = sphere(4);
+        let source = indices(&mesh);
+        let params = SimplifyParams {
+            target_index_count: TargetIndices::Count(source.len() * 2),
+            ..Default::default()
+        };
+        let report = mesh.simplify_with_report(&params).unwrap();
+        assert_eq!(report.result_error, 0.0);
+        assert_eq!(indices(&mesh), source);
+    }
+
+    #[test]
+    fn simplification_is_bit_identical_across_runs_and_input_orders() {
+        let params = SimplifyParams {
+            target_index_count: TargetIndices::Multiplier(0.5),
+            max_error: 1.0,
+            canonical_order: true,
+            ..Default::default()
+        };
+        let simplified = |mut mesh: Mesh| {
+            mesh.canonicalize().unwrap();
+            let report = mesh.simplify_with_report(&params).unwrap();
+            assert!(report.triangles_after() <= report.triangles_before() / 2);
+            snapshot(&mesh)
+        };
+
+        let first = simplified(sphere(4));
+        assert_eq!(simplified(sphere(4)), first);
+        assert_ne!(snapshot(&shuffled(sphere(4))), snapshot(&sphere(4)));
+        assert_eq!(simplified(shuffled(sphere(4))), first);
+    }
+
+    /// Row of `quads` unit quads along X as a non-indexed triangle list.
+    fn quad_strip(quads: u32) -> Mesh {
+        let positions: Vec<[f32; 3]> = (0..quads)
+            .flat_map(|quad| {
+                let [x0, x1] = [quad as f32, quad as f32 + 1.0];
+                [
+                    [x0, 0.0, 0.0],
+                    [x0, 0.0, 1.0],
+                    [x1, 0.0, 0.0],
+                    [x1, 0.0, 0.0],
+                    [x0, 0.0, 1.0],
+                    [x1, 0.0, 1.0],
+                ]
+            })
+            .collect();
+        Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::default(),
+        )
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+    }
+
+    fn keep_triangles() -> SimplifyParams {
+        SimplifyParams {
+            target_index_count: TargetIndices::Multiplier(1.0),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn u16_cube_keeps_its_triangles_and_index_format() {
+        let cube = with_u16_indices(Cuboid::default().mesh().into());
+        let mut simplified = cube.clone();
+        simplified.simplify_with_report(&keep_triangles()).unwrap();
+        assert!(matches!(simplified.indices(), Some(Indices::U16(_))));
+        assert_eq!(simplified.count_vertices(), cube.count_vertices());
+        assert_eq!(triangle_set(&simplified), triangle_set(&cube));
+    }
+
+    #[test]
+    fn triangle_soup_is_indexed_or_expanded_back() {
+        let soup = quad_strip(8);
+        let mut indexed = soup.clone();
+        indexed.simplify_with_report(&keep_triangles()).unwrap();
+        assert!(indexed.indices().is_some());
+        assert_eq!(indexed.count_vertices(), 2 * 9);
+        assert_eq!(triangle_set(&indexed), triangle_set(&soup));
+
+        let mut expanded = soup.clone();
+        expanded
+            .simplify_with_report(&SimplifyParams {
+                expand_generated_indices: true,
+                ..keep_triangles()
+            })
+            .unwrap();
+        assert!(expanded.indices().is_none());
+        assert_eq!(expanded.count_vertices(), soup.count_vertices());
+        assert_eq!(triangle_set(&expanded), triangle_set(&soup));
+    }
+
+    #[test]
+    fn line_list_is_refused() {
+        let mut lines = Mesh::new(PrimitiveTopology::LineList, RenderAssetUsages::default())
+            .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, vec![[0.0f32; 3]; 4]);
+        let result = lines.simplify_with_report(&SimplifyParams::default());
+        assert!(matches!(
+            result,
+            Err(OptError::UnsupportedPrimitiveTopology(
+                PrimitiveTopology::LineList
+            ))
+        ));
+    }
+
+    #[test]
+    fn nan_position_fails_without_changing_the_mesh() {
+        let mut mesh = sphere(4);
+        let Some(VertexAttributeValues::Float32x3(positions)) =
+            mesh.attribute_mut(Mesh::ATTRIBUTE_POSITION)
+        else {
+            unreachable!()
+        };
+        positions[7][1] = f32::NAN;
+        let source = indices(&mesh);
+        let result = mesh.simplify_with_report(&SimplifyParams::default());
+        assert!(matches!(result, Err(OptError::InvalidPositions(7))));
+        assert_eq!(indices(&mesh), source);
+    }
+
+    #[test]
+    fn out_of_bounds_index_fails_without_changing_the_mesh() {
+        let mut mesh = sphere(4);
+        let vertex_count = mesh.count_vertices() as u32;
+        let mut source = indices(&mesh);
+        source[4] = vertex_count + 5;
+        mesh.insert_indices(Indices::U32(source.clone()));
+        let result = mesh.simplify_with_report(&SimplifyParams::default());
+        assert!(matches!(
+            result,
+            Err(OptError::IndexOutOfBounds(index)) if index == vertex_count + 5
+        ));
+        assert_eq!(indices(&mesh), source);
+    }
+
+    #[test]
+    fn partial_triangle_fails() {
+        let mut mesh = sphere(4);
+        let mut source = indices(&mesh);
+        source.pop();
+        let count = source.len();
+        mesh.insert_indices(Indices::U32(source));
+        let result = mesh.simplify_with_report(&SimplifyParams::default());
+        assert!(matches!(result, Err(OptError::InvalidIndexCount(c)) if c == count));
+    }
+
+    #[test]
+    fn strip_degenerates_removes_collapsed_triangles() {
+        let mut mesh = sphere(4);
+        let mut source = indices(&mesh);
+        let triangles = source.len() / 3;
+        source.extend([0, 0, 1, 2, 3, 2]);
+        mesh.insert_indices(Indices::U32(source));
+        let params = SimplifyParams {
+            target_index_count: TargetIndices::Multiplier(1.0),
+            strip_degenerates: true,
+            ..Default::default()
+        };
+        mesh.simplify_with_report(&params).unwrap();
+        let simplified = indices(&mesh);
+        assert_eq!(simplified.len() / 3, triangles);
+        assert!(
+            simplified
+                .chunks_exact(3)
+                .all(|t| t[0] != t[1] && t[1] != t[2] && t[2] != t[0])
+        );
+    }
+
+    /// [`grid`] bent over two bones, joint `0` driving the left half and joint `1` the right one,
+    /// blended across the middle.
+    fn two_bone_grid(cells: u32) -> Mesh {
+        let mut mesh = grid(cells);
+        let (joints, weights): (Vec<[u16; 4]>, Vec<[f32; 4]>) = positions(&mesh)
+            .iter()
+            .map(|&[x, _, _]| {
+                let blend = ((x - 0.25) * 2.0).clamp(0.0, 1.0);
+                ([0, 1, 0, 0], [1.0 - blend, blend, 0.0, 0.0])
+            })
+            .unzip();
+        mesh.insert_attribute(
+            Mesh::ATTRIBUTE_JOINT_INDEX,
+            VertexAttributeValues::Uint16x4(joints),
+        );
+        mesh.insert_attribute(Mesh::ATTRIBUTE_JOINT_WEIGHT, weights);
+        mesh
+    }
+
+    #[test]
+    fn skinned_mesh_keeps_joints_in_step_and_its_seam() {
+        // An odd cell count keeps the dominant joint from tying on the middle column.
+        let skinned = two_bone_grid(15);
+        let simplified = skinned
+            .simplified(&SimplifyParams {
+                target_index_count: TargetIndices::Multiplier(0.5),
+                max_error: 1.0,
+                lock_joint_seams: true,
+                ..Default::default()
+            })
+            .unwrap();
+        assert!(indices(&simplified).len() <= indices(&skinned).len() / 2);
+
+        let count = simplified.count_vertices();
+        let Some(VertexAttributeValues::Uint16x4(joints)) =
+            simplified.attribute(Mesh::ATTRIBUTE_JOINT_INDEX)
+        else {
+            unreachable!()
+        };
+        let Some(VertexAttributeValues::Float32x4(weights)) =
+            simplified.attribute(Mesh::ATTRIBUTE_JOINT_WEIGHT)
+        else {
+            unreachable!()
+        };
+        assert_eq!(joints.len(), count);
+        assert_eq!(weights.len(), count);
+        assert!(joints.iter().all(|&joints| joints == [0, 1, 0, 0]));
+        for (weights, &[x, _, _]) in weights.iter().zip(positions(&simplified)) {
+            assert!((weights.iter().sum::<f32>() - 1.0).abs() < 1e-6);
+            assert_eq!(weights[1], ((x - 0.25) * 2.0).clamp(0.0, 1.0));
+        }
+
+        // The columns on either side of the middle have different dominant joints.
+        let kept = positions(&simplified);
+        let seam = positions(&skinned)
+            .iter()
+            .filter(|&[x, _, _]| (x - 0.5).abs() < 0.5 / 15.0 + 1e-6);
+        for position in seam {
+            assert!(kept.contains(position), "{position:?} collapsed");
+        }
+    }
+
+    fn sloppy(options: SimplifyOptions) -> SimplifyParams {
+        SimplifyParams {
+            mode: SimplifyMode::Sloppy,
+            options,
+            target_index_count: TargetIndices::Multiplier(0.25),
+            max_error: 1.0,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn sloppy_mode_rejects_options_it_ignores() {
+        let mut mesh = sphere(4);
+        let source = indices(&mesh);
+        for options in [
+            SimplifyOptions::LockBorder,
+            SimplifyOptions::Sparse,
+            SimplifyOptions::ErrorAbsolute,
+        ] {
+            let result = mesh.simplify_with_report(&sloppy(options));
+            assert!(
+                matches!(result, Err(OptError::UnsupportedSimplifyOptions(rejected)) if rejected == options)
+            );
+            assert_eq!(indices(&mesh), source);
+        }
+    }
+
+    #[test]
+    fn sloppy_mode_reaches_the_target_and_reports_its_error() {
+        let source = sphere(4);
+        let target = indices(&source).len() / 4;
+
+        let mut out = Vec::new();
+        let error = run_simplifier(
+            &mut out,
+            SimplifyInput {
+                indices: &indices(&source),
+                positions: positions(&source),
+                attributes: None,
+                locks: None,
+                protect: None,
+            },
+            target,
+            &sloppy(SimplifyOptions::None),
+        );
+        assert!(!out.is_empty() && out.len() <= target);
+        assert!(error > 0.0);
+
+        let mut mesh = source.clone();
+        let report = mesh
+            .simplify_with_report(&sloppy(SimplifyOptions::None))
+            .unwrap();
+        assert_eq!(report.indices_after, out.len());
+        assert_eq!(report.result_error, error);
+        assert_eq!(indices(&mesh).len(), out.len());
+    }
+
+    #[test]
+    fn u16_mesh_is_simplified_step_by_step() {
+        let source = with_u16_indices(sphere(4));
+        let triangles = indices(&source).len() / 3;
+        let step = StepParams {
+            simplify: SimplifyParams {
+                max_error: 1.0,
+                ..Default::default()
+            },
+            reduction: 0.25,
+            max_steps: 16,
+        };
+
+        let mut mesh = source.clone();
+        let mut steps = 0;
+        let report = mesh
+            .simplify_until(step.clone(), |report, _| {
+                steps += 1;
+                match report.triangles_after() > triangles / 4 {
+                    true => ControlFlow::Continue(()),
+                    false => ControlFlow::Break(()),
+                }
+            })
+            .unwrap();
+        assert!(steps > 1);
+        assert!(matches!(mesh.indices(), Some(Indices::U16(_))));
+        assert_eq!(report.triangles_before(), triangles);
+        assert!(report.triangles_after() > triangles / 4);
+        assert_eq!(indices(&mesh).len(), report.indices_after);
+
+        // Breaking on the first step rolls it back.
+        let mut mesh = source.clone();
+        let report = mesh
+            .simplify_until(step, |_, _| ControlFlow::Break(()))
+            .unwrap();
+        assert_eq!(report.indices_after, report.indices_before);
+        assert!(matches!(mesh.indices(), Some(Indices::U16(_))));
+        assert_eq!(indices(&mesh), indices(&source));
+    }
+}
@@ -0,0 +1,162 @@
+use std::ops::Range;
+
+use bevy::{
+    asset::Assets,
+    camera::primitives::{Aabb, MeshAabb},
+    ecs::prelude::*,
+    mesh::{Indices, Mesh, Mesh3d},
+    transform::components::{GlobalTransform, Transform},
+};
+
+use crate::{MeshletParams, Meshlets, OptError, meshlet::build_meshlets, vertex::gather_vertices};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct MeshletSplitParams {
+    pub meshlets: MeshletParams,
+    /// Triangles per spawned entity, consecutive meshlets are grouped until they reach it.
+    pub partition_triangles: usize,
+}
+
+impl Default for MeshletSplitParams {
+    fn default() -> Self {
+        MeshletSplitParams {
+            meshlets: MeshletParams::default(),
+            partition_triangles: 4096,
+        }
+    }
+}
+
+/// Added to an entity split by [`split_into_meshlet_entities`], holding what is needed to undo it
+/// with [`merge_meshlet_entities`].
+#[derive(Component, Debug, Clone)]
+pub struct MeshletSplit {
+    /// The entity's original mesh, removed while it is split.
+    pub mesh: Mesh3d,
+    /// Child entities, one per partition.
+    pub partitions: Vec<Entity>,
+}
+
+/// Child entity rendering a partition of its parent's meshlets.
+#[derive(Component, Debug, Clone, PartialEq, Eq)]
+pub struct MeshletPartition {
+    /// Meshlets of the parent's mesh in this partition.
+    pub meshlets: Range<usize>,
+}
+
+/// Splits the mesh of `entity` into meshlets, groups them into partitions of roughly
+/// [`MeshletSplitParams::partition_triangles`] and spawns a child entity with its own mesh and
+/// [`Aabb`] per partition, so frustum culling and visibility ranges work per partition.
+///
+/// The children are clones of `entity` without its mesh, transform and hierarchy, so they keep its
+/// material. The original [`Mesh3d`] is moved into a [`MeshletSplit`] to hide it.
+pub fn split_into_meshlet_entities(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    entity: Entity,
+    mesh3d: &Mesh3d,
+    params: &MeshletSplitParams,
+) -> Result<Vec<Entity>, OptError> {
+    let mesh = meshes.get(mesh3d).ok_or(OptError::MissingMesh)?;
+    let meshlets = build_meshlets(mesh, &params.meshlets)?;
+
+    let partition_meshes: Vec<(Range<usize>, Mesh)> = partitions(&meshlets, params)
+        .into_iter()
+        .map(|range| {
+            let partition = partition_mesh(mesh, &meshlets, range.clone());
+            (range, partition)
+        })
+        .collect();
+
+    let partitions: Vec<Entity> = partition_meshes
+        .into_iter()
+        .map(|(range, partition)| {
+            let aabb = partition.compute_aabb().unwrap_or_default();
+            let handle = meshes.add(partition);
+            commands
+                .entity(entity)
+                .clone_and_spawn_with_opt_out(|builder| {
+                    builder.deny::<(
+                        Mesh3d,
+                        Aabb,
+                        Transform,
+                        GlobalTransform,
+                        ChildOf,
+                        Children,
+                        Meshlets,
+                        MeshletSplit,
+                    )>();
+                })
+                .insert((
+                    Mesh3d(handle),
+                    aabb,
+                    Transform::default(),
+                    ChildOf(entity),
+                    MeshletPartition { meshlets: range },
+                ))
+                .id()
+        })
+        .collect();
+
+    commands
+        .entity(entity)
+        .remove::<(Mesh3d, Aabb)>()
+        .insert(MeshletSplit {
+            mesh: mesh3d.clone(),
+            partitions: partitions.clone(),
+        });
+    Ok(partitions)
+}
+
+/// Undoes [`split_into_meshlet_entities`], despawning the partitions and restoring the original
+/// mesh of `entity`.
+pub fn merge_meshlet_entities(commands: &mut Commands, entity: Entity, split: &MeshletSplit) {
+    for &partition in &split.partitions {
+        commands.entity(partition).try_despawn();
+    }
+    commands
+        .entity(entity)
+        .remove::<MeshletSplit>()
+        .insert(split.mesh.clone());
+}
+
+/// Groups consecutive meshlets until they reach the partition's triangle count.
+fn partitions(meshlets: &Meshlets, params: &MeshletSplitParams) -> Vec<Range<usize>> {
+    let mut partitions = Vec::new();
+    let mut start = 0;
+    let mut triangles = 0;
+    for (index, meshlet) in meshlets.meshlets.iter().enumerate() {
+        triangles += meshlet.triangle_count as usize;
+        if triangles >= params.partition_triangles.max(1) {
+            partitions.push(start..index + 1);
+            start = index + 1;
+            triangles = 0;
+        }
+    }
+    if start < meshlets.len() {
+        partitions.push(start..meshlets.len());
+    }
+    partitions
+}
+
+/// Mesh holding only the vertices and triangles of the meshlets in `range`.
+fn partition_mesh(mesh: &Mesh, meshlets: &Meshlets, range: Range<usize>) -> Mesh {
+    let mut sources = Vec::new();
+    let mut remap = vec![u32::MAX; mesh.count_vertices()];
+    let mut indices = Vec::new();
+    for meshlet in range {
+        let vertices = meshlets.meshlet_vertices(meshlet);
+        for &local in meshlets.meshlet_triangles(meshlet) {
+            let vertex = vertices[local as usize];
+            let new_index = &mut remap[vertex as usize];
+            if *new_index == u32::MAX {
+                *new_index = sources.len() as u32;
+                sources.push(vertex);
+            }
+            indices.push(*new_index);
+        }
+    }
+
+    let mut partition = gather_vertices(mesh, &sources);
+    partition.insert_indices(Indices::U32(indices));
+    partition
+}
@@ -0,0 +1,157 @@
+use bevy::mesh::{Indices, Mesh, PrimitiveTopology};
+
+use crate::{
+    OptError,
+    optimize::{indices_keeping_format, narrowed_indices},
+};
+
+/// Strip of the triangle list in `mesh`, restarted at `restart_index` or joined with degenerate
+/// triangles. Bevy only restarts strips at the largest value of the index format, so the restart
+/// index picks the format: `u16::MAX` for `u16` indices, `u32::MAX` for `u32` ones.
+pub(crate) fn to_triangle_strip(mesh: &Mesh, restart_index: Option<u32>) -> Result<Mesh, OptError> {
+    let topology = mesh.primitive_topology();
+    if topology != PrimitiveTopology::TriangleList {
+        return Err(OptError::UnsupportedPrimitiveTopology(topology));
+    }
+    let vertex_count = mesh.count_vertices();
+    let (indices, wide) = if mesh.indices().is_some() {
+        indices_keeping_format(mesh)?
+    } else {
+        if vertex_count == 0 || !vertex_count.is_multiple_of(3) {
+            return Err(OptError::InvalidIndexCount(vertex_count));
+        }
+        ((0..vertex_count as u32).collect(), false)
+    };
+
+    let wide = match restart_index {
+        // Bevy restarts `u16` strips at `u16::MAX` regardless, it can't be a vertex.
+        None => wide || vertex_count > u16::MAX as usize,
+        Some(u32::MAX) => true,
+        // The restart index can't also be a vertex.
+        Some(restart) if restart == u16::MAX as u32 && vertex_count <= restart as usize => false,
+        Some(restart) => return Err(OptError::InvalidRestartIndex(restart)),
+    };
+
+    // SAFETY: `strip` is as large as the bound meshoptimizer computes for the indices, which are
+    // whole triangles all below `vertex_count`.
+    let strip = unsafe {
+        let mut strip = vec![0; meshopt::ffi::meshopt_stripifyBound(indices.len())];
+        let count = meshopt::ffi::meshopt_stripify(
+            strip.as_mut_ptr(),
+            indices.as_ptr(),
+            indices.len(),
+            vertex_count,
+            restart_index.unwrap_or(0),
+        );
+        strip.truncate(count);
+        strip
+    };
+
+    Ok(with_topology(
+        mesh,
+        PrimitiveTopology::TriangleStrip,
+        narrowed_indices(strip, wide),
+    ))
+}
+
+/// Triangle list of the strip in `mesh`, restarting wherever Bevy does and dropping the
+/// degenerate triangles joining strips.
+pub(crate) fn to_triangle_list(mesh: &Mesh) -> Result<Mesh, OptError> {
+    let topology = mesh.primitive_topology();
+    if topology != PrimitiveTopology::TriangleStrip {
+        return Err(OptError::UnsupportedPrimitiveTopology(topology));
+    }
+    let vertex_count = mesh.count_vertices();
+    let (strip, wide, restart_index) = match mesh.indices() {
+        Some(Indices::U16(indices)) => (
+            indices.iter().map(|&index| index as u32).collect(),
+            false,
+            u16::MAX as u32,
+        ),
+        Some(Indices::U32(indices)) => (indices.clone(), true, u32::MAX),
+        // Sequential indices never restart.
+        None => (
+            (0..vertex_count as u32).collect(),
+            vertex_count > u16::MAX as usize + 1,
+            0,
+        ),
+    };
+    if strip.len() < 3 {
+        return Err(OptError::InvalidIndexCount(strip.len()));
+    }
+    if let Some(&index) = strip
+        .iter()
+        .find(|&&index| index != restart_index && index as usize >= vertex_count)
+    {
+        return Err(OptError::IndexOutOfBounds(index));
+    }
+
+    // SAFETY: `list` is as large as the bound meshoptimizer computes for the strip.
+    let list = unsafe {
+        let mut list = vec![0; meshopt::ffi::meshopt_unstripifyBound(strip.len())];
+        let count = meshopt::ffi::meshopt_unstripify(
+            list.as_mut_ptr(),
+            strip.as_ptr(),
+            strip.len(),
+            restart_index,
+        );
+        list.truncate(count);
+        list
+    };
+
+    Ok(with_topology(
+        mesh,
+        PrimitiveTopology::TriangleList,
+        narrowed_indices(list, wide),
+    ))
+}
+
+/// Copy of `mesh` with another topology and index buffer, the vertices are left untouched.
+fn with_topology(mesh: &Mesh, topology: PrimitiveTopology, indices: Indices) -> Mesh {
+    let mut converted = Mesh::new(topology, mesh.asset_usage);
+    for (attribute, values) in mesh.attributes() {
+        converted.insert_attribute(*attribute, values.clone());
+    }
+    converted.insert_indices(indices);
+    if let Some(morph_targets) = mesh.morph_targets() {
+        converted.set_morph_targets(morph_targets.clone());
+    }
+    if let Some(names) = mesh.morph_target_names() {
+        converted.set_morph_target_names(names.to_vec());
+    }
+    converted.enable_raytracing = mesh.enable_raytracing;
+    converted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        MeshExt,
+        test_util::{attribute_bytes, sphere, triangle_set, with_u16_indices},
+    };
+
+    fn assert_round_trips(mesh: &Mesh, restart_index: Option<u32>) {
+        let strip = mesh.to_triangle_strip(restart_index).unwrap();
+        assert_eq!(strip.primitive_topology(), PrimitiveTopology::TriangleStrip);
+        let list = strip.to_triangle_list().unwrap();
+        assert_eq!(list.primitive_topology(), PrimitiveTopology::TriangleList);
+        assert_eq!(triangle_set(&list), triangle_set(mesh));
+        assert_eq!(attribute_bytes(&strip), attribute_bytes(mesh));
+        assert_eq!(attribute_bytes(&list), attribute_bytes(mesh));
+    }
+
+    #[test]
+    fn strip_round_trips_to_the_same_triangles() {
+        assert_round_trips(&sphere(6), None);
+        assert_round_trips(&sphere(6), Some(u32::MAX));
+        assert_round_trips(&with_u16_indices(sphere(6)), None);
+        assert_round_trips(&with_u16_indices(sphere(6)), Some(u16::MAX as u32));
+    }
+
+    #[test]
+    fn restart_index_must_match_bevy() {
+        let result = sphere(2).to_triangle_strip(Some(7));
+        assert!(matches!(result, Err(OptError::InvalidRestartIndex(7))));
+    }
+}
@@ -0,0 +1,108 @@
+use bevy::mesh::{Indices, Mesh};
+
+use crate::{
+    OptError, SimplifyParams, TargetIndices, mesh_indices,
+    simplify::{count_used_vertices, simplify_mesh_into, with_scratch},
+    u32_indexed,
+};
+
+/// Result of simplifying a mesh at one error bound of [`simplify_sweep`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SweepPoint {
+    pub max_error: f32,
+    /// Error reported by the simplifier, accumulated over the previous points when cascading.
+    pub achieved_error: f32,
+    pub triangles: usize,
+    pub vertices: usize,
+}
+
+/// Simplifies `mesh` as far as every error bound in `errors` allows, without modifying it, and
+/// returns the resulting error/triangle count curve ordered by increasing `max_error`.
+/// Non-indexed meshes are measured as indexed by merging identical vertices.
+///
+/// The target index count of `params` is ignored so that only the error bound limits the
+/// simplification. With `cascade` every point is simplified from the previous one with the
+/// remaining error budget, which is faster on large meshes but slightly overestimates the error.
+pub fn simplify_sweep(
+    mesh: &Mesh,
+    params: &SimplifyParams,
+    errors: &[f32],
+    cascade: bool,
+) -> Result<Vec<SweepPoint>, OptError> {
+    let mesh = &*u32_indexed(mesh)?;
+    mesh_indices(mesh)?;
+    let vertex_count = mesh.count_vertices();
+    let mut errors = errors.to_vec();
+    errors.sort_by(f32::total_cmp);
+
+    let mut params = SimplifyParams {
+        target_index_count: TargetIndices::Count(0),
+        min_target_index_count: 0,
+        ..params.clone()
+    };
+    let mut source = cascade.then(|| mesh.clone());
+    let mut previous_error = 0.0;
+    let mut points = Vec::with_capacity(errors.len());
+    for max_error in errors {
+        let point = with_scratch(|scratch| {
+            let achieved_error = match &mut source {
+                Some(source) => {
+                    params.max_error = (max_error - previous_error).max(0.0);
+                    let error = simplify_mesh_into(source, &params, scratch)?;
+                    // Nothing left to cascade from once everything has been simplified away.
+                    if scratch.indices.len() >= 3 {
+                        source.insert_indices(Indices::U32(scratch.indices.clone()));
+                    }
+                    previous_error + error
+                }
+                None => {
+                    params.max_error = max_error;
+                    simplify_mesh_into(mesh, &params, scratch)?
+                }
+            };
+
+            let vertices = count_used_vertices(&scratch.indices, vertex_count, &mut scratch.seen);
+            Ok(SweepPoint {
+                max_error,
+                achieved_error,
+                triangles: scratch.indices.len() / 3,
+                vertices,
+            })
+        })?;
+        previous_error = point.achieved_error;
+        points.push(point);
+    }
+    Ok(points)
+}
+
+/// Index of the point where adding more error stops paying off in triangles, i.e. the point
+/// furthest from the line between the first and last point once both axes are normalized.
+/// `None` for fewer than three points.
+pub fn knee_point(points: &[SweepPoint]) -> Option<usize> {
+    let (first, last) = (points.first()?, points.last()?);
+    if points.len() < 3 {
+        return None;
+    }
+
+    let error_range = (last.max_error - first.max_error).max(f32::EPSILON);
+    let triangle_range = (first.triangles as f32 - last.triangles as f32).max(1.0);
+    let normalized = |point: &SweepPoint| {
+        (
+            (point.max_error - first.max_error) / error_range,
+            (first.triangles as f32 - point.triangles as f32) / triangle_range,
+        )
+    };
+
+    // The normalized curve runs from (0, 0) to (1, 1), its distance to that diagonal is
+    // proportional to `y - x`.
+    points
+        .iter()
+        .enumerate()
+        .map(|(index, point)| {
+            let (x, y) = normalized(point);
+            (index, y - x)
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .filter(|(_, distance)| *distance > 0.0)
+        .map(|(index, _)| index)
+}
@@ -0,0 +1,353 @@
+use std::collections::HashMap;
+
+use bevy::{
+    math::{IVec3, Vec2, Vec3},
+    mesh::{Mesh, VertexAttributeValues},
+    reflect::Reflect,
+};
+
+use crate::{
+    OptError, SimplifyParams, SimplifyPath,
+    simplify::{SimplifyInput, simplify_into},
+};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Reflect)]
+#[reflect(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub enum SymmetryMode {
+    /// Lock the vertices lying on the plane so both halves stay stitched together along it. The
+    /// halves are simplified like the rest of the mesh and can end up differing, see
+    /// [`SymmetryMode::Mirror`] for identical ones.
+    Lock,
+    /// Simplify the half in front of the plane and mirror the result onto the other half, reusing
+    /// the mirrored vertices already present in the mesh. Vertices on the plane are locked.
+    ///
+    /// The mesh has to be split along the plane (no triangle crossing it) and every vertex needs a
+    /// mirrored counterpart, otherwise [`OptError::AsymmetricMesh`] is returned.
+    Mirror,
+}
+
+/// Plane a mesh is authored mirror-symmetric about.
+#[derive(Debug, Copy, Clone, PartialEq, Reflect)]
+#[reflect(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct SymmetryPlane {
+    /// Plane normal, pointing into the half that gets simplified in [`SymmetryMode::Mirror`].
+    pub normal: Vec3,
+    /// Distance of the plane from the origin along `normal`.
+    pub distance: f32,
+    /// Vertices within this distance of the plane are considered to lie on it. This is also the
+    /// tolerance used when matching mirrored vertices.
+    pub epsilon: f32,
+    pub mode: SymmetryMode,
+}
+
+impl Default for SymmetryPlane {
+    fn default() -> Self {
+        SymmetryPlane {
+            normal: Vec3::X,
+            distance: 0.0,
+            epsilon: 1e-5,
+            mode: SymmetryMode::Lock,
+        }
+    }
+}
+
+impl SymmetryPlane {
+    pub fn signed_distance(&self, point: Vec3) -> f32 {
+        let length = self.normal.length();
+        point.dot(self.normal) / length - self.distance
+    }
+
+    /// Reflects `point` across the plane.
+    pub fn mirror(&self, point: Vec3) -> Vec3 {
+        point - 2.0 * self.signed_distance(point) * self.normal.normalize()
+    }
+
+    fn side(&self, point: Vec3) -> i8 {
+        let distance = self.signed_distance(point);
+        if distance > self.epsilon {
+            1
+        } else if distance < -self.epsilon {
+            -1
+        } else {
+            0
+        }
+    }
+
+    /// Marks every vertex lying on the plane as locked.
+    pub(crate) fn lock_plane_vertices(&self, positions: &[[f32; 3]], locks: &mut [bool]) {
+        for (lock, position) in locks.iter_mut().zip(positions) {
+            if self.side(Vec3::from_array(*position)) == 0 {
+                *lock = true;
+            }
+        }
+    }
+}
+
+/// Simplifies the positive half of the mesh and mirrors the resulting triangles onto the negative
+/// half, see [`SymmetryMode::Mirror`].
+pub(crate) fn simplify_mirrored(
+    mesh: &Mesh,
+    input: SimplifyInput,
+    target_index_count: usize,
+    params: &SimplifyParams,
+    plane: &SymmetryPlane,
+) -> Result<(Vec<u32>, f32, SimplifyPath), OptError> {
+    let SimplifyInput {
+        indices, positions, ..
+    } = input;
+    let sides: Vec<i8> = positions
+        .iter()
+        .map(|position| plane.side(Vec3::from_array(*position)))
+        .collect();
+
+    let mut positive = Vec::new();
+    // Triangles lying in the plane are their own mirror image, so they are left untouched.
+    let mut planar = Vec::new();
+    let mut crossing = 0;
+    for triangle in indices.chunks_exact(3) {
+        let front = triangle.iter().any(|&v| sides[v as usize] > 0);
+        let back = triangle.iter().any(|&v| sides[v as usize] < 0);
+        match (front, back) {
+            (true, true) => crossing += 1,
+            (true, false) => positive.extend_from_slice(triangle),
+            // Replaced by the mirrored front half.
+            (false, true) => {}
+            (false, false) => planar.extend_from_slice(triangle),
+        }
+    }
+
+    if crossing > 0 {
+        return Err(OptError::AsymmetricMesh(crossing));
+    }
+
+    // Only the front half is simplified, so every vertex it references needs a mirror behind the
+    // plane for the mirrored triangles.
+    let mirrors = mirror_map(mesh, positions, &sides, plane);
+    let unmatched = positive
+        .iter()
+        .filter(|&&vertex| sides[vertex as usize] > 0 && mirrors[vertex as usize] == u32::MAX)
+        .count();
+    if unmatched > 0 {
+        return Err(OptError::AsymmetricMesh(unmatched));
+    }
+
+    let half_target = (target_index_count.saturating_sub(planar.len()) / 2 / 3 * 3).max(3);
+    let mut half = Vec::new();
+    let (result_error, path) = simplify_into(
+        &mut half,
+        SimplifyInput {
+            indices: &positive,
+            ..input
+        },
+        half_target,
+        params,
+    );
+
+    let mirror_vertex = |vertex: u32| {
+        if sides[vertex as usize] == 0 {
+            vertex
+        } else {
+            mirrors[vertex as usize]
+        }
+    };
+
+    let mut new_indices = Vec::with_capacity(half.len() * 2 + planar.len());
+    new_indices.extend_from_slice(&half);
+    for triangle in half.chunks_exact(3) {
+        // Mirroring flips the winding, swap two corners to restore it.
+        new_indices.push(mirror_vertex(triangle[0]));
+        new_indices.push(mirror_vertex(triangle[2]));
+        new_indices.push(mirror_vertex(triangle[1]));
+    }
+    new_indices.extend_from_slice(&planar);
+
+    Ok((new_indices, result_error, path))
+}
+
+/// For every vertex in front of the plane, finds the vertex behind it at the mirrored position.
+///
+/// Vertices sharing a position (attribute seams) are told apart by comparing mirrored normals and
+/// the first UV channel when the mesh has them.
+fn mirror_map(
+    mesh: &Mesh,
+    positions: &[[f32; 3]],
+    sides: &[i8],
+    plane: &SymmetryPlane,
+) -> Vec<u32> {
+    let normals = match mesh.attribute(Mesh::ATTRIBUTE_NORMAL) {
+        Some(VertexAttributeValues::Float32x3(normals)) if normals.len() == positions.len() => {
+            Some(normals)
+        }
+        _ => None,
+    };
+    let uvs = match mesh.attribute(Mesh::ATTRIBUTE_UV_0) {
+        Some(VertexAttributeValues::Float32x2(uvs)) if uvs.len() == positions.len() => Some(uvs),
+        _ => None,
+    };
+
+    let epsilon = plane.epsilon.max(1e-6);
+    let cell_size = epsilon * 2.0;
+    let cell = |point: Vec3| (point / cell_size).floor().as_ivec3();
+
+    let mut grid: HashMap<IVec3, Vec<u32>> = HashMap::new();
+    for (vertex, position) in positions.iter().enumerate() {
+        if sides[vertex] < 0 {
+            grid.entry(cell(Vec3::from_array(*position)))
+                .or_default()
+                .push(vertex as u32);
+        }
+    }
+
+    let plane_normal = plane.normal.normalize();
+    let mut mirrors = vec![u32::MAX; positions.len()];
+    for (vertex, position) in positions.iter().enumerate() {
+        if sides[vertex] <= 0 {
+            continue;
+        }
+
+        let mirrored = plane.mirror(Vec3::from_array(*position));
+        let mirrored_normal = normals.map(|normals| {
+            let normal = Vec3::from_array(normals[vertex]);
+            normal - 2.0 * normal.dot(plane_normal) * plane_normal
+        });
+
+        let center = cell(mirrored);
+        let mut best = (f32::INFINITY, u32::MAX);
+        for x in -1..=1 {
+            for y in -1..=1 {
+                for z in -1..=1 {
+                    let Some(candidates) = grid.get(&(center + IVec3::new(x, y, z))) else {
+                        continue;
+                    };
+
+                    for &candidate in candidates {
+                        let distance =
+                            Vec3::from_array(positions[candidate as usize]).distance(mirrored);
+                        if distance > epsilon {
+                            continue;
+                        }
+
+                        let mut score = distance / epsilon;
+                        if let (Some(normals), Some(mirrored_normal)) = (normals, mirrored_normal) {
+                            score += 1.0
+                                - Vec3::from_array(normals[candidate as usize])
+                                    .dot(mirrored_normal);
+                        }
+                        if let Some(uvs) = uvs {
+                            score += Vec2::from_array(uvs[candidate as usize])
+                                .distance(Vec2::from_array(uvs[vertex]));
+                        }
+
+                        if score < best.0 {
+                            best = (score, candidate);
+                        }
+                    }
+                }
+            }
+        }
+
+        mirrors[vertex] = best.1;
+    }
+
+    mirrors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::{math::primitives::Sphere, mesh::Meshable};
+
+    use crate::{
+        MeshExt, TargetIndices,
+        test_util::{indices, positions, sphere},
+    };
+
+    fn halved(mesh: &mut Mesh, mode: SymmetryMode) {
+        mesh.simplify_with_report(&SimplifyParams {
+            target_index_count: TargetIndices::Multiplier(0.5),
+            max_error: 1.0,
+            symmetry: Some(SymmetryPlane {
+                mode,
+                ..Default::default()
+            }),
+            ..Default::default()
+        })
+        .unwrap();
+    }
+
+    /// Distinct positions used by the triangles, rounded to a thousandth so the two halves of a
+    /// sphere match.
+    fn used_positions(mesh: &Mesh) -> Vec<IVec3> {
+        let positions = positions(mesh);
+        let mut used: Vec<IVec3> = indices(mesh)
+            .into_iter()
+            .map(|vertex| {
+                (Vec3::from_array(positions[vertex as usize]) * 1000.0)
+                    .round()
+                    .as_ivec3()
+            })
+            .collect();
+        used.sort_unstable_by_key(|position| position.to_array());
+        used.dedup();
+        used
+    }
+
+    /// Sorted `|x|` of the used positions in front of and behind the plane at X=0.
+    fn x_histograms(mesh: &Mesh) -> (Vec<i32>, Vec<i32>) {
+        let used = used_positions(mesh);
+        let side = |front: bool| {
+            let mut side: Vec<i32> = used
+                .iter()
+                .filter(|position| position.x != 0 && (position.x > 0) == front)
+                .map(|position| position.x.abs())
+                .collect();
+            side.sort_unstable();
+            side
+        };
+        (side(true), side(false))
+    }
+
+    #[test]
+    fn mirror_mode_keeps_both_halves_identical() {
+        // Meridians at X=0 split the sphere along the plane.
+        let mut mesh = Sphere::new(1.0).mesh().uv(32, 18);
+        let (front, back) = x_histograms(&mesh);
+        assert_eq!(front, back);
+
+        halved(&mut mesh, SymmetryMode::Mirror);
+        let (simplified_front, simplified_back) = x_histograms(&mesh);
+        assert!(simplified_front.len() < front.len());
+        assert_eq!(simplified_front, simplified_back);
+    }
+
+    #[test]
+    fn lock_mode_keeps_the_vertices_on_the_plane() {
+        let on_plane = |mesh: &Mesh| {
+            let mut on_plane = used_positions(mesh);
+            on_plane.retain(|position| position.x == 0);
+            on_plane
+        };
+        let mut mesh = sphere(8);
+        let before = on_plane(&mesh);
+        assert!(!before.is_empty());
+
+        halved(&mut mesh, SymmetryMode::Lock);
+        assert!(indices(&mesh).len() <= indices(&sphere(8)).len() / 2);
+        assert_eq!(on_plane(&mesh), before);
+    }
+
+    #[test]
+    fn mirror_mode_refuses_triangles_crossing_the_plane() {
+        let mut mesh = sphere(4);
+        let result = mesh.simplify_with_report(&SimplifyParams {
+            symmetry: Some(SymmetryPlane {
+                mode: SymmetryMode::Mirror,
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+        assert!(matches!(result, Err(OptError::AsymmetricMesh(_))));
+    }
+}
@@ -0,0 +1,246 @@
+//! Meshes shared by the unit tests.
+
+use bevy::{
+    asset::RenderAssetUsages,
+    math::{Vec3, primitives::Sphere},
+    mesh::{
+        Indices, Mesh, MeshVertexAttribute, MeshVertexAttributeId, Meshable, PrimitiveTopology,
+        VertexFormat,
+    },
+};
+
+/// Unit icosphere, `u32` indexed with positions, normals and UVs.
+pub(crate) fn sphere(subdivisions: u32) -> Mesh {
+    Sphere::new(1.0).mesh().ico(subdivisions).unwrap()
+}
+
+/// Square of `cells` by `cells` quads on the XZ plane from `0.0` to `1.0`, facing up, `u32`
+/// indexed with positions, normals and UVs.
+pub(crate) fn grid(cells: u32) -> Mesh {
+    let side = cells + 1;
+    let mut positions = Vec::new();
+    let mut uvs = Vec::new();
+    for z in 0..side {
+        for x in 0..side {
+            let uv = [x as f32 / cells as f32, z as f32 / cells as f32];
+            positions.push([uv[0], 0.0, uv[1]]);
+            uvs.push(uv);
+        }
+    }
+    let mut indices = Vec::new();
+    for z in 0..cells {
+        for x in 0..cells {
+            let corner = z * side + x;
+            indices.extend([corner, corner + side, corner + 1]);
+            indices.extend([corner + 1, corner + side, corner + side + 1]);
+        }
+    }
+    Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    )
+    .with_inserted_attribute(
+        Mesh::ATTRIBUTE_NORMAL,
+        vec![[0.0, 1.0, 0.0]; positions.len()],
+    )
+    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+    .with_inserted_indices(Indices::U32(indices))
+}
+
+/// [`grid`] with `Float32x2` positions, like the meshes of 2D games.
+pub(crate) fn planar_grid(cells: u32) -> Mesh {
+    let mut mesh = grid(cells);
+    let positions: Vec<[f32; 2]> = positions(&mesh).iter().map(|&[x, _, z]| [x, z]).collect();
+    // Same id as `Mesh::ATTRIBUTE_POSITION`.
+    let attribute = MeshVertexAttribute::new("Vertex_Position", 0, VertexFormat::Float32x2);
+    mesh.insert_attribute(attribute, positions);
+    mesh
+}
+
+/// `mesh` with its indices narrowed to `u16`.
+pub(crate) fn with_u16_indices(mut mesh: Mesh) -> Mesh {
+    let indices = mesh.indices().unwrap().iter().map(|index| index as u16);
+    mesh.insert_indices(Indices::U16(indices.collect()));
+    mesh
+}
+
+/// `u32` indices of `mesh`, whatever their format.
+pub(crate) fn indices(mesh: &Mesh) -> Vec<u32> {
+    mesh.indices().map_or_else(Vec::new, |indices| {
+        indices.iter().map(|index| index as u32).collect()
+    })
+}
+
+/// Positions of `mesh`, which have to be `Float32x3`.
+pub(crate) fn positions(mesh: &Mesh) -> &[[f32; 3]] {
+    mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+        .and_then(|values| values.as_float3())
+        .unwrap()
+}
+
+/// Id and bytes of every attribute of `mesh`, to compare meshes attribute by attribute.
+pub(crate) fn attribute_bytes(mesh: &Mesh) -> Vec<(MeshVertexAttributeId, Vec<u8>)> {
+    mesh.attributes()
+        .map(|(attribute, values)| (attribute.id, values.get_bytes().to_vec()))
+        .collect()
+}
+
+/// Triangles of `mesh` as the positions of their corners, rotated to start at the smallest one and
+/// sorted, to compare meshes whose triangles or vertices are in a different order or that aren't
+/// indexed.
+pub(crate) fn triangle_set(mesh: &Mesh) -> Vec<[[u32; 3]; 3]> {
+    let positions = positions(mesh);
+    let corner = |index: u32| positions[index as usize].map(f32::to_bits);
+    let indices = match mesh.indices() {
+        Some(_) => indices(mesh),
+        None => (0..positions.len() as u32).collect(),
+    };
+    let mut triangles: Vec<_> = indices
+        .chunks_exact(3)
+        .map(|triangle| {
+            let mut corners = [
+                corner(triangle[0]),
+                corner(triangle[1]),
+                corner(triangle[2]),
+            ];
+            let first = (0..3).min_by_key(|&i| corners[i]).unwrap();
+            corners.rotate_left(first);
+            corners
+        })
+        .collect();
+    triangles.sort_unstable();
+    triangles
+}
+
+/// Cube from `-1.0` to `1.0` with its edges chamfered `bevel` wide, flat shaded with split normals
+/// along the bevels. The faces and bevel strips are `cells` by `cells` quads, the corners single
+/// triangles.
+pub(crate) fn beveled_cube(bevel: f32, cells: u32) -> Mesh {
+    let inner = 1.0 - bevel;
+    let mut positions: Vec<[f32; 3]> = Vec::new();
+    let mut normals: Vec<[f32; 3]> = Vec::new();
+    let mut indices: Vec<u32> = Vec::new();
+    // Point whose coordinate along `axes[i]` is `values[i]`.
+    let point = |axes: [usize; 3], values: [f32; 3]| {
+        let mut point = Vec3::ZERO;
+        for (axis, value) in axes.into_iter().zip(values) {
+            point[axis] = value;
+        }
+        point
+    };
+    // Adds the quads of a flat patch, or a single triangle if the last two corners are the same.
+    let mut patch = |corners: [Vec3; 4], cells: u32| {
+        let mut normal = (corners[1] - corners[0]).cross(corners[2] - corners[0]);
+        let outward = normal.dot(corners.iter().sum::<Vec3>()) > 0.0;
+        if !outward {
+            normal = -normal;
+        }
+        let first = positions.len() as u32;
+        if corners[2] == corners[3] {
+            positions.extend(corners[..3].iter().map(|corner| corner.to_array()));
+            normals.extend([normal.normalize().to_array(); 3]);
+            indices.extend(if outward {
+                [first, first + 1, first + 2]
+            } else {
+                [first, first + 2, first + 1]
+            });
+            return;
+        }
+        for row in 0..=cells {
+            for column in 0..=cells {
+                let [u, v] = [column as f32 / cells as f32, row as f32 / cells as f32];
+                let position = corners[0]
+                    .lerp(corners[1], u)
+                    .lerp(corners[3].lerp(corners[2], u), v);
+                positions.push(position.to_array());
+                normals.push(normal.normalize().to_array());
+            }
+        }
+        for row in 0..cells {
+            for column in 0..cells {
+                let corner = first + row * (cells + 1) + column;
+                let quad = [corner, corner + 1, corner + cells + 2, corner + cells + 1];
+                let triangles = [quad[0], quad[1], quad[2], quad[0], quad[2], quad[3]];
+                if outward {
+                    indices.extend(triangles);
+                } else {
+                    indices.extend(triangles.into_iter().rev());
+                }
+            }
+        }
+    };
+    for axis in 0..3 {
+        let axes = [axis, (axis + 1) % 3, (axis + 2) % 3];
+        for sign in [-1.0, 1.0] {
+            // Face on the side of `axis`.
+            patch(
+                [[-1.0, -1.0], [1.0, -1.0], [1.0, 1.0], [-1.0, 1.0]]
+                    .map(|[u, v]| point(axes, [sign, u * inner, v * inner])),
+                cells,
+            );
+            for other in [-1.0, 1.0] {
+                // Bevel between this face and the one on the side of the next axis.
+                patch(
+                    [
+                        [sign, other * inner, -inner],
+                        [sign * inner, other, -inner],
+                        [sign * inner, other, inner],
+                        [sign, other * inner, inner],
+                    ]
+                    .map(|values| point(axes, values)),
+                    cells,
+                );
+            }
+        }
+    }
+    for x in [-1.0, 1.0] {
+        for y in [-1.0, 1.0] {
+            for z in [-1.0, 1.0] {
+                let corner = Vec3::new(x, y, z) * inner;
+                let [a, b, c] =
+                    [Vec3::X, Vec3::Y, Vec3::Z].map(|axis| corner + axis * corner * bevel / inner);
+                patch([a, b, c, c], 1);
+            }
+        }
+    }
+    Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    )
+    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+    .with_inserted_indices(Indices::U32(indices))
+}
+
+/// Ribbon of `segments` quads winding along the X axis, `length` long and `0.02` wide, coiled
+/// into a helix so every collapse bends it. `u32` indexed with positions only.
+pub(crate) fn ribbon(segments: u32, length: f32) -> Mesh {
+    let mut positions = Vec::new();
+    for segment in 0..=segments {
+        let t = segment as f32 / segments as f32;
+        let angle = t * std::f32::consts::TAU * 8.0;
+        let center = Vec3::new(t * length, angle.sin() * 0.5, angle.cos() * 0.5);
+        let across = Vec3::new(0.0, angle.cos(), -angle.sin()) * 0.01;
+        positions.push((center - across).to_array());
+        positions.push((center + across).to_array());
+    }
+    let mut indices = Vec::new();
+    for segment in 0..segments {
+        let corner = segment * 2;
+        indices.extend([
+            corner,
+            corner + 2,
+            corner + 1,
+            corner + 1,
+            corner + 2,
+            corner + 3,
+        ]);
+    }
+    Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    )
+    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+    .with_inserted_indices(Indices::U32(indices))
+}
@@ -0,0 +1,348 @@
+use bevy::mesh::{Indices, Mesh, VertexAttributeValues, VertexFormat};
+
+use crate::{OptError, WeldReport, take_mesh_indices_mut, validate_indices};
+
+/// Evaluates `$body` with `$values` bound to the `Vec` inside of a [`VertexAttributeValues`],
+/// whatever its format. The `($values, $variant)` form also binds `$variant` to the constructor of
+/// that format, to build new values of the same format. The `(($a, $b), ($values_a, $values_b))`
+/// form binds the `Vec`s of two values of the same format, evaluating `$fallback` if the formats
+/// differ. The `for_format $format, $variant` form binds the constructor for a [`VertexFormat`] and
+/// evaluates to `Some($body)`, or `None` for formats [`VertexAttributeValues`] can't hold.
+macro_rules! with_values {
+    (@formats [$($format:ident),*] one $attribute:expr, $values:ident, $variant:ident, $body:expr) => {
+        match $attribute {
+            $(VertexAttributeValues::$format($values) => {
+                let $variant = VertexAttributeValues::$format;
+                $body
+            })*
+        }
+    };
+    (@formats [$($format:ident),*] pair $pair:expr, $a:ident, $b:ident, $body:expr, $fallback:expr) => {
+        match $pair {
+            $((VertexAttributeValues::$format($a), VertexAttributeValues::$format($b)) => $body,)*
+            _ => $fallback,
+        }
+    };
+    (@formats [$($format:ident),*] format $vertex_format:expr, $variant:ident, $body:expr) => {
+        match $vertex_format {
+            $(VertexFormat::$format => {
+                let $variant = VertexAttributeValues::$format;
+                Some($body)
+            })*
+            _ => None,
+        }
+    };
+    (@each $($rest:tt)*) => {
+        with_values!(@formats [
+            Float32, Sint32, Uint32, Float32x2, Sint32x2, Uint32x2,
+            Float32x3, Sint32x3, Uint32x3, Float32x4, Sint32x4, Uint32x4,
+            Sint16x2, Snorm16x2, Uint16x2, Unorm16x2, Sint16x4, Snorm16x4,
+            Uint16x4, Unorm16x4, Sint8x2, Snorm8x2, Uint8x2, Unorm8x2,
+            Sint8x4, Snorm8x4, Uint8x4, Unorm8x4
+        ] $($rest)*)
+    };
+    (for_format $vertex_format:expr, $variant:ident => $body:expr) => {
+        with_values!(@each format $vertex_format, $variant, $body)
+    };
+    (($a:expr, $b:expr), ($values_a:ident, $values_b:ident) => $body:expr, else $fallback:expr) => {
+        with_values!(@each pair ($a, $b), $values_a, $values_b, $body, $fallback)
+    };
+    ($attribute:expr, $values:ident => $body:expr) => {
+        with_values!($attribute, ($values, _variant) => $body)
+    };
+    ($attribute:expr, ($values:ident, $variant:ident) => $body:expr) => {
+        with_values!(@each one $attribute, $values, $variant, $body)
+    };
+}
+
+/// Appends a copy of every vertex in `sources` to all attributes of the mesh, in order. The copy
+/// of `sources[i]` ends up at index `count_vertices() + i`.
+pub(crate) fn append_vertices(mesh: &mut Mesh, sources: &[u32]) {
+    for (_, attribute) in mesh.attributes_mut() {
+        with_values!(attribute, values => append_copies(values, sources));
+    }
+}
+
+fn append_copies<T: Copy>(values: &mut Vec<T>, sources: &[u32]) {
+    values.reserve(sources.len());
+    for &source in sources {
+        values.push(values[source as usize]);
+    }
+}
+
+/// Moves every vertex `i` to `remap[i]` in all attributes of the mesh, dropping vertices mapped to
+/// `u32::MAX`. The indices have to be remapped separately.
+pub(crate) fn remap_vertices(mesh: &mut Mesh, remap: &[u32], vertex_count: usize) {
+    for (_, attribute) in mesh.attributes_mut() {
+        with_values!(attribute, values => *values = remapped(values, remap, vertex_count));
+    }
+}
+
+fn remapped<T: Copy + Default>(values: &[T], remap: &[u32], vertex_count: usize) -> Vec<T> {
+    let mut remapped = vec![T::default(); vertex_count];
+    for (value, &new_index) in values.iter().zip(remap) {
+        if new_index != u32::MAX {
+            remapped[new_index as usize] = *value;
+        }
+    }
+    remapped
+}
+
+/// New mesh made of a copy of every vertex in `sources`, in order, without any indices.
+pub(crate) fn gather_vertices(mesh: &Mesh, sources: &[u32]) -> Mesh {
+    let mut gathered = Mesh::new(mesh.primitive_topology(), mesh.asset_usage);
+    for (attribute, values) in mesh.attributes() {
+        let values = with_values!(values, (values, variant) => {
+            variant(sources.iter().map(|&source| values[source as usize]).collect())
+        });
+        gathered.insert_attribute(*attribute, values);
+    }
+    gathered
+}
+
+/// Values of `format` read from `bytes` holding one value every `stride` bytes, e.g. decoded
+/// vertex buffers with values padded to a multiple of 4 bytes. `None` for formats
+/// [`VertexAttributeValues`] can't hold or a `stride` smaller than the format.
+pub(crate) fn values_from_bytes(
+    format: VertexFormat,
+    bytes: &[u8],
+    stride: usize,
+) -> Option<VertexAttributeValues> {
+    if stride < format.size() as usize {
+        return None;
+    }
+    with_values!(for_format format, variant => variant(read_strided(bytes, stride)))
+}
+
+fn read_strided<T: Copy>(bytes: &[u8], stride: usize) -> Vec<T> {
+    bytes
+        .chunks_exact(stride)
+        // SAFETY: only called for the formats of `VertexAttributeValues`, arrays of integers or
+        // floats that are valid for any bits, and `stride` covers at least one of them.
+        .map(|chunk| unsafe { chunk.as_ptr().cast::<T>().read_unaligned() })
+        .collect()
+}
+
+/// Replaces the vertices with a copy of the vertex every index refers to and removes the indices,
+/// turning the mesh into a non-indexed one.
+pub(crate) fn expand_indices(mesh: &mut Mesh) {
+    let Some(indices) = mesh.remove_indices() else {
+        return;
+    };
+    let sources: Vec<u32> = indices.iter().map(|index| index as u32).collect();
+    for (_, attribute) in mesh.attributes_mut() {
+        with_values!(attribute, values => {
+            *values = sources.iter().map(|&source| values[source as usize]).collect();
+        });
+    }
+}
+
+/// Appends every vertex of `other` to the mesh, returns `false` without changing anything if the
+/// two meshes don't have the same attributes in the same formats.
+pub(crate) fn append_mesh_vertices(mesh: &mut Mesh, other: &Mesh) -> bool {
+    let same_layout = mesh.attributes().count() == other.attributes().count()
+        && mesh.attributes().all(|(attribute, _)| {
+            other
+                .attributes()
+                .any(|(other, _)| other.id == attribute.id && other.format == attribute.format)
+        });
+    if !same_layout {
+        return false;
+    }
+
+    for (attribute, values) in mesh.attributes_mut() {
+        let other = other.attribute(attribute.id).unwrap();
+        with_values!(
+            (values, other),
+            (values, other) => values.extend_from_slice(other),
+            else unreachable!()
+        );
+    }
+    true
+}
+
+/// Merges vertices that are identical in every attribute and remaps `indices` onto them, vertices
+/// no index refers to are dropped. Meshes with more attributes than meshoptimizer can compare at
+/// once are left as is.
+pub(crate) fn deduplicate_vertices(mesh: &mut Mesh, indices: &mut [u32]) {
+    deduplicate_vertices_by(mesh, indices, None);
+}
+
+/// [`deduplicate_vertices`] comparing `position_cells` instead of the positions when given, one
+/// per vertex.
+fn deduplicate_vertices_by(
+    mesh: &mut Mesh,
+    indices: &mut [u32],
+    position_cells: Option<&[[i32; 3]]>,
+) {
+    const MAX_STREAMS: usize = 16;
+    if mesh.attributes().count() > MAX_STREAMS {
+        return;
+    }
+
+    let vertex_count = mesh.count_vertices();
+    let streams: Vec<meshopt::ffi::meshopt_Stream> = mesh
+        .attributes()
+        .map(|(attribute, values)| match position_cells {
+            Some(cells) if attribute.id == Mesh::ATTRIBUTE_POSITION.id => {
+                meshopt::ffi::meshopt_Stream {
+                    data: cells.as_ptr().cast(),
+                    size: size_of::<[i32; 3]>(),
+                    stride: size_of::<[i32; 3]>(),
+                }
+            }
+            _ => {
+                let bytes = values.get_bytes();
+                meshopt::ffi::meshopt_Stream {
+                    data: bytes.as_ptr().cast(),
+                    size: attribute.format.size() as usize,
+                    stride: attribute.format.size() as usize,
+                }
+            }
+        })
+        .collect();
+    let mut remap = vec![u32::MAX; vertex_count];
+    // SAFETY: every stream holds `vertex_count` elements of its format and every index is below
+    // `vertex_count`.
+    let unique = unsafe {
+        meshopt::ffi::meshopt_generateVertexRemapMulti(
+            remap.as_mut_ptr(),
+            indices.as_ptr(),
+            indices.len(),
+            vertex_count,
+            streams.as_ptr(),
+            streams.len(),
+        )
+    };
+    for index in indices.iter_mut() {
+        *index = remap[*index as usize];
+    }
+    remap_vertices(mesh, &remap, unique);
+}
+
+pub(crate) fn weld_identical_vertices(mesh: &mut Mesh) -> Result<WeldReport, OptError> {
+    let mut indices = take_mesh_indices_mut(mesh)?;
+    let vertex_size = mesh.get_vertex_size() as usize;
+    let vertices_before = mesh.count_vertices();
+    deduplicate_vertices(mesh, &mut indices);
+    mesh.insert_indices(Indices::U32(indices));
+
+    let vertices_after = mesh.count_vertices();
+    Ok(WeldReport {
+        vertices_before,
+        vertices_after,
+        memory_before: vertices_before * vertex_size,
+        memory_after: vertices_after * vertex_size,
+    })
+}
+
+/// Merges vertices that are identical in every attribute, comparing the positions by the point of
+/// a grid with `tolerance` spacing they round to if given. Merged vertices keep the position of
+/// one of them. Indexes non-indexed meshes and narrows `u16` indices back afterwards.
+pub(crate) fn weld_vertices(
+    mesh: &mut Mesh,
+    tolerance: Option<f32>,
+) -> Result<WeldReport, OptError> {
+    let cells = tolerance
+        .map(|tolerance| position_cells(mesh, tolerance))
+        .transpose()?;
+
+    let wide = !matches!(mesh.indices(), Some(Indices::U16(_)));
+    let mut indices = match mesh.indices() {
+        Some(Indices::U16(indices)) => indices.iter().map(|&index| index as u32).collect(),
+        Some(Indices::U32(indices)) => indices.clone(),
+        None => (0..mesh.count_vertices() as u32).collect(),
+    };
+    validate_indices(&indices, mesh.count_vertices())?;
+
+    let vertex_size = mesh.get_vertex_size() as usize;
+    let vertices_before = mesh.count_vertices();
+    deduplicate_vertices_by(mesh, &mut indices, cells.as_deref());
+    mesh.insert_indices(if wide {
+        Indices::U32(indices)
+    } else {
+        // Welding never adds vertices, so they still fit.
+        Indices::U16(indices.iter().map(|&index| index as u16).collect())
+    });
+
+    let vertices_after = mesh.count_vertices();
+    Ok(WeldReport {
+        vertices_before,
+        vertices_after,
+        memory_before: vertices_before * vertex_size,
+        memory_after: vertices_after * vertex_size,
+    })
+}
+
+/// Point of the grid with `tolerance` spacing every position rounds to.
+fn position_cells(mesh: &Mesh, tolerance: f32) -> Result<Vec<[i32; 3]>, OptError> {
+    if !(tolerance > 0.0 && tolerance.is_finite()) {
+        return Err(OptError::InvalidWeldTolerance(tolerance));
+    }
+    let Some(VertexAttributeValues::Float32x3(positions)) =
+        mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+    else {
+        return Err(OptError::MissingPositions);
+    };
+    positions
+        .iter()
+        .enumerate()
+        .map(|(vertex, position)| {
+            if !position.iter().all(|v| v.is_finite()) {
+                return Err(OptError::InvalidPositions(vertex));
+            }
+            Ok(position.map(|v| (v / tolerance).round() as i32))
+        })
+        .collect()
+}
+
+/// Sorts the vertices by their bytes in every attribute, in the order the mesh lists its
+/// attributes, and the triangles with [`sort_triangles`]. Identical vertices keep their relative
+/// order.
+pub(crate) fn canonicalize(mesh: &mut Mesh) -> Result<(), OptError> {
+    let mut indices = take_mesh_indices_mut(mesh)?;
+    let vertex_count = mesh.count_vertices();
+    let streams: Vec<(&[u8], usize)> = mesh
+        .attributes()
+        .map(|(attribute, values)| (values.get_bytes(), attribute.format.size() as usize))
+        .collect();
+    let vertex_bytes = |vertex: u32| {
+        streams
+            .iter()
+            .map(move |(bytes, size)| &bytes[vertex as usize * size..][..*size])
+    };
+    let mut order: Vec<u32> = (0..vertex_count as u32).collect();
+    order.sort_by(|&a, &b| vertex_bytes(a).cmp(vertex_bytes(b)));
+
+    let mut remap = vec![0; vertex_count];
+    for (new, &old) in order.iter().enumerate() {
+        remap[old as usize] = new as u32;
+    }
+    remap_vertices(mesh, &remap, vertex_count);
+    for index in &mut indices {
+        *index = remap[*index as usize];
+    }
+    sort_triangles(&mut indices);
+    mesh.insert_indices(Indices::U32(indices));
+    Ok(())
+}
+
+/// Rotates every triangle to start at its smallest index, keeping its winding, and sorts the
+/// triangles by their indices.
+pub(crate) fn sort_triangles(indices: &mut [u32]) {
+    let mut triangles: Vec<[u32; 3]> = indices
+        .chunks_exact(3)
+        .map(|corners| {
+            let [a, b, c] = [corners[0], corners[1], corners[2]];
+            match a.min(b).min(c) {
+                min if min == a => [a, b, c],
+                min if min == b => [b, c, a],
+                _ => [c, a, b],
+            }
+        })
+        .collect();
+    triangles.sort_unstable();
+    for (corners, triangle) in indices.chunks_exact_mut(3).zip(&triangles) {
+        corners.copy_from_slice(triangle);
+    }
+}
@@ -0,0 +1,134 @@
+use std::{fmt, sync::Arc};
+
+use bevy::{
+    math::Vec3,
+    mesh::{Mesh, MeshVertexAttribute, VertexAttributeValues},
+};
+
+use crate::{OptError, SimplifyParams};
+
+/// Vertices to lock in place during simplification in addition to
+/// [`SimplifyParams::vertex_locks`], e.g. to pin material boundaries or skinning seams so meshes
+/// simplified independently don't crack where they meet. See [`SimplifyParams::locked_vertices`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub enum VertexLocks {
+    /// Bit `i % 64` of word `i / 64` locks vertex `i`, one bit per vertex rounded up to whole
+    /// words.
+    Bitmask(Vec<u64>),
+    /// Locks the vertices whose position, in mesh space, the predicate is true for. Not
+    /// serialized.
+    #[cfg_attr(feature = "serialize", serde(skip))]
+    Predicate(LockPredicate),
+    /// Locks the vertices whose value of a custom `Float32`, `Uint32` or `Sint32` attribute isn't
+    /// zero, e.g. painted in a DCC tool. Not serialized.
+    #[cfg_attr(feature = "serialize", serde(skip))]
+    Attribute(MeshVertexAttribute),
+}
+
+impl VertexLocks {
+    /// [`VertexLocks::Bitmask`] locking `vertices` of a mesh with `vertex_count` vertices.
+    /// Vertices past `vertex_count` are ignored.
+    pub fn from_vertices(vertices: impl IntoIterator<Item = u32>, vertex_count: usize) -> Self {
+        let mut words = vec![0; vertex_count.div_ceil(64)];
+        for vertex in vertices {
+            if let Some(word) = words.get_mut(vertex as usize / 64) {
+                *word |= 1 << (vertex % 64);
+            }
+        }
+        VertexLocks::Bitmask(words)
+    }
+
+    pub fn predicate(predicate: impl Fn(Vec3) -> bool + Send + Sync + 'static) -> Self {
+        VertexLocks::Predicate(LockPredicate(Arc::new(predicate)))
+    }
+
+    /// Sets the locks of the vertices this selects, leaving the others as they are.
+    pub(crate) fn lock_vertices(
+        &self,
+        mesh: &Mesh,
+        positions: &[[f32; 3]],
+        locks: &mut [bool],
+    ) -> Result<(), OptError> {
+        match self {
+            VertexLocks::Bitmask(words) => {
+                if words.len() != positions.len().div_ceil(64) {
+                    return Err(OptError::InvalidVertexLockCount(words.len() * 64));
+                }
+                for (vertex, lock) in locks.iter_mut().enumerate() {
+                    *lock |= words[vertex / 64] & (1 << (vertex % 64)) != 0;
+                }
+            }
+            VertexLocks::Predicate(predicate) => {
+                for (lock, &position) in locks.iter_mut().zip(positions) {
+                    *lock |= (predicate.0)(position.into());
+                }
+            }
+            VertexLocks::Attribute(attribute) => {
+                let error = OptError::InvalidLockAttribute(attribute.name);
+                let values = mesh.attribute(*attribute).ok_or(error)?;
+                if values.len() != positions.len() {
+                    return Err(error);
+                }
+                match values {
+                    VertexAttributeValues::Float32(values) => {
+                        mark(locks, values.iter().map(|&value| value != 0.0));
+                    }
+                    VertexAttributeValues::Uint32(values) => {
+                        mark(locks, values.iter().map(|&value| value != 0));
+                    }
+                    VertexAttributeValues::Sint32(values) => {
+                        mark(locks, values.iter().map(|&value| value != 0));
+                    }
+                    _ => return Err(error),
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn mark(locks: &mut [bool], locked: impl Iterator<Item = bool>) {
+    for (lock, locked) in locks.iter_mut().zip(locked) {
+        *lock |= locked;
+    }
+}
+
+/// Predicate of [`VertexLocks::Predicate`], compared by identity.
+#[derive(Clone)]
+pub struct LockPredicate(pub Arc<dyn Fn(Vec3) -> bool + Send + Sync>);
+
+impl fmt::Debug for LockPredicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("LockPredicate").finish_non_exhaustive()
+    }
+}
+
+impl PartialEq for LockPredicate {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// [`SimplifyParams::vertex_locks`] merged with [`SimplifyParams::locked_vertices`], one lock per
+/// vertex of `mesh`, for code that remaps the vertices before simplifying them.
+pub(crate) fn user_vertex_locks(
+    mesh: &Mesh,
+    positions: &[[f32; 3]],
+    params: &SimplifyParams,
+) -> Result<Option<Vec<bool>>, OptError> {
+    if let Some(locks) = &params.vertex_locks
+        && locks.len() != positions.len()
+    {
+        return Err(OptError::InvalidVertexLockCount(locks.len()));
+    }
+    let Some(locked_vertices) = &params.locked_vertices else {
+        return Ok(params.vertex_locks.clone());
+    };
+    let mut locks = params
+        .vertex_locks
+        .clone()
+        .unwrap_or_else(|| vec![false; positions.len()]);
+    locked_vertices.lock_vertices(mesh, positions, &mut locks)?;
+    Ok(Some(locks))
+}